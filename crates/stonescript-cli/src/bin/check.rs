@@ -0,0 +1,97 @@
+use clap::{Parser, ValueEnum};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use stonescript_cli::check::{Checker, FileReport};
+use stonescript_cli::report;
+use stonescript_cli::scripts::collect_scripts;
+use stonescript_lsp::config::DiagnosticLevel;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Check StoneScript files with the language server's diagnostics
+#[derive(Parser)]
+#[command(name = "stonescript-check", version)]
+struct Args {
+    /// Script files, or directories to search for `.txt` scripts
+    #[arg(default_value = ".")]
+    paths: Vec<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = Format::Human)]
+    format: Format,
+
+    /// Change a diagnostic's severity, e.g. `unknown-game-id=error` or
+    /// `unused-variable=off`
+    #[arg(long = "severity", value_name = "CODE=LEVEL", value_parser = parse_severity)]
+    severities: Vec<(String, DiagnosticLevel)>,
+
+    /// Fail on warnings as well as errors
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Reports with source snippets
+    Human,
+    /// A JSON array of diagnostics
+    Json,
+    /// A SARIF 2.1.0 log
+    Sarif,
+}
+
+fn parse_severity(value: &str) -> Result<(String, DiagnosticLevel), String> {
+    let (code, level) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected CODE=LEVEL, got `{}`", value))?;
+    let level =
+        serde_json::from_value(serde_json::Value::String(level.to_string())).map_err(|_| {
+            format!(
+                "unknown level `{}`; expected error, warning, information, hint or off",
+                level
+            )
+        })?;
+    Ok((code.to_string(), level))
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let scripts = match collect_scripts(&args.paths) {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let checker = Checker::new();
+    checker.set_severities(args.severities.into_iter().collect::<HashMap<_, _>>());
+
+    let mut reports: Vec<FileReport> = Vec::new();
+    let mut unreadable = false;
+    for path in &scripts {
+        match checker.check_file(path) {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                unreadable = true;
+            }
+        }
+    }
+
+    match args.format {
+        Format::Human => print!("{}", report::human(&reports)),
+        Format::Json => println!("{:#}", report::json(&reports)),
+        Format::Sarif => println!("{:#}", report::sarif(&reports)),
+    }
+
+    let failed = reports.iter().any(|report| {
+        report.count(DiagnosticSeverity::ERROR) > 0
+            || (args.deny_warnings && report.count(DiagnosticSeverity::WARNING) > 0)
+    });
+    if failed || unreadable {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,14 @@
+use clap::Parser;
+use std::io::{self, BufReader};
+use stonescript_cli::debug;
+
+/// Debug StoneScript scripts from an editor over the Debug Adapter Protocol,
+/// speaking it on standard input and output
+#[derive(Parser)]
+#[command(name = "stonescript-dap", version)]
+struct Args {}
+
+fn main() {
+    Args::parse();
+    debug::serve(BufReader::new(io::stdin()), io::stdout());
+}
@@ -0,0 +1,97 @@
+use clap::Parser;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use stonescript_cli::format::{self, Formatter};
+use stonescript_cli::scripts::collect_scripts;
+use stonescript_lsp::config::FormattingSettings;
+
+/// Format StoneScript files with the language server's formatter
+#[derive(Parser)]
+#[command(name = "stonescript-fmt", version)]
+struct Args {
+    /// Script files, or directories to search for `.txt` scripts; reads
+    /// stdin and writes stdout when none are given
+    paths: Vec<PathBuf>,
+
+    /// Print a diff instead of writing files, and fail if any would change
+    #[arg(long)]
+    check: bool,
+
+    /// Settings file to use instead of the nearest `stonescript.json`
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = if args.paths.is_empty() {
+        format_stdin(&args)
+    } else {
+        format_files(&args)
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// The settings for a script in `dir`
+fn settings(args: &Args, dir: &Path) -> Result<FormattingSettings, String> {
+    match args.config.clone().or_else(|| format::find_config(dir)) {
+        Some(path) => format::load_settings(&path),
+        None => Ok(FormattingSettings::default()),
+    }
+}
+
+/// Whether stdin was already formatted, when checking
+fn format_stdin(args: &Args) -> Result<bool, String> {
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("could not read stdin: {}", e))?;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let formatted = Formatter::new(&settings(args, &cwd)?).format(&source);
+
+    if args.check {
+        print!("{}", format::diff("<stdin>", &source, &formatted));
+        return Ok(formatted == source);
+    }
+    io::stdout()
+        .write_all(formatted.as_bytes())
+        .map_err(|e| format!("could not write stdout: {}", e))?;
+    Ok(true)
+}
+
+/// Whether every file was already formatted, when checking
+fn format_files(args: &Args) -> Result<bool, String> {
+    let scripts = collect_scripts(&args.paths).map_err(|e| e.to_string())?;
+
+    let mut unchanged = true;
+    for path in &scripts {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let dir = std::path::absolute(path)
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let formatted = Formatter::new(&settings(args, &dir)?).format(&source);
+        if formatted == source {
+            continue;
+        }
+
+        unchanged = false;
+        if args.check {
+            let name = path.display().to_string();
+            print!("{}", format::diff(&name, &source, &formatted));
+        } else {
+            std::fs::write(path, formatted)
+                .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(unchanged || !args.check)
+}
@@ -0,0 +1,119 @@
+use clap::Parser;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
+use stonescript_cli::repl::{self, Session};
+
+/// Try StoneScript interactively, against a game state set with `:set`
+#[derive(Parser)]
+#[command(name = "stonescript-repl", version)]
+struct Args {
+    /// Script to run first; its variables and functions stay defined
+    script: Option<PathBuf>,
+}
+
+/// Completes from the game data and the names the session has defined
+struct Completion {
+    names: Rc<RefCell<Vec<String>>>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(repl::complete(line, pos, &self.names.borrow()))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut session = Session::new();
+
+    if let Some(path) = &args.script {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                return ExitCode::from(2);
+            }
+        };
+        match session.eval(&source) {
+            Ok(out) => print!("{}", out),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+
+    let names = Rc::new(RefCell::new(session.names()));
+    let mut editor = match Editor::<Completion, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    editor.set_helper(Some(Completion {
+        names: names.clone(),
+    }));
+
+    println!(
+        "StoneScript {}; :help for commands",
+        env!("CARGO_PKG_VERSION")
+    );
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { ">>> " } else { "... " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+                if repl::needs_more(&input) {
+                    continue;
+                }
+                let entry = std::mem::take(&mut input);
+                if entry.trim().is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(entry.trim_end());
+                if entry.trim() == ":quit" {
+                    break;
+                }
+                match session.run(&entry) {
+                    Ok(out) => print!("{}", out),
+                    Err(e) => println!("error: {}", e),
+                }
+                *names.borrow_mut() = session.names();
+            }
+            // Ctrl-C drops the entry being typed
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
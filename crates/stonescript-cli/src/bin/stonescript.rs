@@ -0,0 +1,105 @@
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use stonescript_cli::bundle::Bundler;
+use stonescript_cli::minify::Minifier;
+
+/// Tools for StoneScript projects
+#[derive(Parser)]
+#[command(name = "stonescript", version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inline a script's imports and `new` objects into one script that can
+    /// be pasted into the Mind Stone
+    Bundle {
+        /// The script to start from
+        entry: PathBuf,
+
+        /// Write the bundle to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Folder module paths are relative to, usually the game's
+        /// `Stonescript` folder; the entry script's own folder is searched too
+        #[arg(long = "root", value_name = "DIR")]
+        roots: Vec<PathBuf>,
+    },
+    /// Shrink a script by dropping comments and blank lines, joining `^`
+    /// lines and shortening the names it declares
+    Minify {
+        /// The script to minify
+        script: PathBuf,
+
+        /// Write the minified script to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Keep every name as written
+        #[arg(long)]
+        keep_names: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Bundle {
+            entry,
+            output,
+            roots,
+        } => bundle(&entry, output, roots),
+        Command::Minify {
+            script,
+            output,
+            keep_names,
+        } => minify(&script, output, keep_names),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn bundle(entry: &Path, output: Option<PathBuf>, roots: Vec<PathBuf>) -> Result<(), String> {
+    let bundler = Bundler::new();
+    bundler.set_roots(roots);
+    let bundle = bundler.bundle(entry).map_err(|e| e.to_string())?;
+
+    write(output, &bundle.script)?;
+    eprint!("{}", bundle.summary());
+    Ok(())
+}
+
+fn minify(script: &Path, output: Option<PathBuf>, keep_names: bool) -> Result<(), String> {
+    let source = std::fs::read_to_string(script)
+        .map_err(|e| format!("could not read {}: {}", script.display(), e))?;
+    let mut minifier = Minifier::new();
+    minifier.set_rename(!keep_names);
+    let minified = minifier
+        .minify(&source)
+        .map_err(|e| format!("{}: {}", script.display(), e))?;
+
+    write(output, &minified.script)?;
+    eprint!("{}", minified.summary(&source));
+    Ok(())
+}
+
+/// Write a script to a file, or to stdout without one
+fn write(output: Option<PathBuf>, script: &str) -> Result<(), String> {
+    match output {
+        Some(path) => std::fs::write(&path, script)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", script);
+            Ok(())
+        }
+    }
+}
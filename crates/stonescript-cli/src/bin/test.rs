@@ -0,0 +1,43 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use stonescript_cli::golden::{self, Outcome, Runner};
+use stonescript_cli::scripts::collect_tests;
+
+/// Run StoneScript scripts over mocked game states and compare what they
+/// do with their `.test.txt` files
+#[derive(Parser)]
+#[command(name = "stonescript-test", version)]
+struct Args {
+    /// Test files, or directories to search for `.test.txt` files
+    #[arg(default_value = ".")]
+    paths: Vec<PathBuf>,
+
+    /// Folder module paths are relative to, usually the game's
+    /// `Stonescript` folder; each script's own folder is searched too
+    #[arg(long = "root", value_name = "DIR")]
+    roots: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let tests = match collect_tests(&args.paths) {
+        Ok(tests) => tests,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let runner = Runner::new();
+    runner.set_roots(args.roots);
+    let outcomes: Vec<Outcome> = tests.iter().map(|test| runner.run(test)).collect();
+    print!("{}", golden::report(&outcomes));
+
+    if outcomes.iter().all(Outcome::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
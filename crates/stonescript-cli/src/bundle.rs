@@ -0,0 +1,1011 @@
+//! Flattening a multi-file project into one script for the Mind Stone
+//!
+//! Every module is copied into the bundle once per use: the entry script, a
+//! single copy of each `import`ed module at its first `import`, and a copy
+//! of the module for each `var x = new Module`. Script-level names of a copy
+//! are renamed wherever they would clash with another copy, and a `new`
+//! object's members become plain names, so `x.Add(y)` reads `x_Add(y)` and
+//! each object keeps its own variables.
+//!
+//! Objects must stay where the bundler can see them: `new` is only
+//! accepted as the initializer of a `var` outside functions and loops, and
+//! the variable only as `x.member`, not passed around as a value.
+
+use crate::edit::{apply_edits, Edit, LineIndex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use stonescript_lsp::utils::workspace::{module_name, Module};
+use stonescript_lsp::utils::Workspace;
+use stonescript_parser::ast::{Expression, Position, Span, Statement};
+use stonescript_parser::{parse_source, walk_expression, walk_statement, Visitor};
+use tower_lsp::lsp_types::Url;
+
+/// Indentation added for the body of a `new` object that runs only once
+const GUARD_INDENT: &str = "  ";
+
+/// A module that could not be bundled, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleError {
+    pub path: PathBuf,
+    pub position: Option<Position>,
+    pub message: String,
+}
+
+impl BundleError {
+    fn new(path: &Path, position: Option<Position>, message: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            position,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(position) = self.position {
+            write!(f, ":{}:{}", position.line + 1, position.column + 1)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// A self-contained script and what went into it
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub script: String,
+    /// Each bundled module, the entry script first
+    pub modules: Vec<ModuleSize>,
+}
+
+/// How much of a bundle one module takes up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSize {
+    pub name: String,
+    /// Copies of the module: one, or one per `new` object
+    pub copies: usize,
+    /// Characters of all copies, leaving out the modules they contain
+    pub characters: usize,
+}
+
+impl Bundle {
+    /// Size of the script and of each module in it
+    pub fn summary(&self) -> String {
+        let characters = self.script.chars().count();
+        let lines = self.script.lines().count();
+        let mut out = format!(
+            "Bundled {} module{}: {} characters, {} lines\n",
+            self.modules.len(),
+            if self.modules.len() == 1 { "" } else { "s" },
+            characters,
+            lines
+        );
+
+        let labels: Vec<String> = self
+            .modules
+            .iter()
+            .map(|module| match module.copies {
+                1 => module.name.clone(),
+                copies => format!("{} (x{})", module.name, copies),
+            })
+            .collect();
+        let width = labels.iter().map(|label| label.chars().count()).max();
+        for (label, module) in labels.iter().zip(&self.modules) {
+            out.push_str(&format!(
+                "  {:<width$}  {:>6}\n",
+                label,
+                module.characters,
+                width = width.unwrap_or(0)
+            ));
+        }
+        out
+    }
+}
+
+/// Bundles scripts, resolving modules the way the language server does
+pub struct Bundler {
+    workspace: Workspace,
+}
+
+impl Bundler {
+    pub fn new() -> Self {
+        Self {
+            workspace: Workspace::new(),
+        }
+    }
+
+    /// Folders module paths are relative to, before the entry script's own
+    /// folder
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        self.workspace.set_roots(roots);
+    }
+
+    /// Inline everything `entry` imports or creates with `new`
+    pub fn bundle(&self, entry: &Path) -> Result<Bundle, BundleError> {
+        let path = std::path::absolute(entry).unwrap_or_else(|_| entry.to_path_buf());
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let module = match self.workspace.load_file(&path, &name) {
+            Some(module) => module,
+            None => return Err(unloadable(entry, None, &path)),
+        };
+
+        let mut plan = Plan {
+            workspace: &self.workspace,
+            document: Url::from_file_path(&path)
+                .unwrap_or_else(|_| Url::parse("file:///script.txt").unwrap()),
+            copies: Vec::new(),
+            singletons: HashMap::new(),
+        };
+        let root = plan.add(module, CopyKind::Entry, None);
+        plan.plan(root)?;
+
+        let mut names = Names::new(&plan.copies);
+        names.assign(&mut plan.copies);
+
+        let mut emitter = Emitter {
+            copies: &plan.copies,
+            characters: vec![0; plan.copies.len()],
+        };
+        let script = emitter.emit(root, "")?.replace("\r\n", "\n");
+
+        let mut modules: Vec<ModuleSize> = Vec::new();
+        for (copy, characters) in plan.copies.iter().zip(emitter.characters) {
+            match modules.iter_mut().find(|m| m.name == copy.module.name) {
+                Some(size) => {
+                    size.copies += 1;
+                    size.characters += characters;
+                }
+                None => modules.push(ModuleSize {
+                    name: copy.module.name.clone(),
+                    copies: 1,
+                    characters,
+                }),
+            }
+        }
+        Ok(Bundle { script, modules })
+    }
+}
+
+impl Default for Bundler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error for a module file that was found but could not be read or parsed
+fn unloadable(importer: &Path, position: Option<Position>, path: &Path) -> BundleError {
+    let message = match std::fs::read_to_string(path) {
+        Ok(source) => match parse_source(&source) {
+            Err(e) => format!("could not parse {}: {}", path.display(), e),
+            Ok(_) => format!("could not load {}", path.display()),
+        },
+        Err(e) => format!("could not read {}: {}", path.display(), e),
+    };
+    BundleError::new(importer, position, message)
+}
+
+enum CopyKind {
+    Entry,
+    Import,
+    /// A `new` object; `prefix` starts the names of its members
+    Instance {
+        prefix: String,
+    },
+}
+
+/// One copy of a module in the bundle
+struct ModuleCopy {
+    module: Arc<Module>,
+    kind: CopyKind,
+    /// The copy whose `import` or `new` statement this one replaces
+    parent: Option<usize>,
+    /// Script-level names the module declares, with their name in the bundle
+    globals: HashMap<String, String>,
+    /// Variables holding `new` objects, with the object's copy
+    instances: HashMap<String, usize>,
+    /// Copies of the modules this one imports, in order
+    imports: Vec<usize>,
+    /// The copy replacing an `import` or `new` statement, keyed by its span;
+    /// repeated imports are left out of the map and dropped
+    inlined: HashMap<Span, usize>,
+    /// Variable that runs a `new` object's statements only on the first frame
+    once_flag: Option<String>,
+}
+
+/// What a name means at script level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binding {
+    Global(usize),
+    Instance(usize),
+}
+
+/// Finds the copies a bundle needs, starting from the entry script
+struct Plan<'a> {
+    workspace: &'a Workspace,
+    /// The entry script, which every module path is resolved from, as the
+    /// game resolves them from its script folder
+    document: Url,
+    copies: Vec<ModuleCopy>,
+    /// The one copy of each imported module, by file
+    singletons: HashMap<PathBuf, usize>,
+}
+
+impl Plan<'_> {
+    fn add(&mut self, module: Arc<Module>, kind: CopyKind, parent: Option<usize>) -> usize {
+        if !matches!(kind, CopyKind::Instance { .. }) {
+            self.singletons
+                .insert(module.path.clone(), self.copies.len());
+        }
+        self.copies.push(ModuleCopy {
+            module,
+            kind,
+            parent,
+            globals: HashMap::new(),
+            instances: HashMap::new(),
+            imports: Vec::new(),
+            inlined: HashMap::new(),
+            once_flag: None,
+        });
+        self.copies.len() - 1
+    }
+
+    /// Record a copy's names and plan the copies it imports or creates
+    fn plan(&mut self, id: usize) -> Result<(), BundleError> {
+        let module = self.copies[id].module.clone();
+        let mut planner = Planner {
+            plan: self,
+            id,
+            module: &module,
+            context: Context::Script,
+            error: None,
+        };
+        planner.visit_block(&module.ast.statements);
+        planner.error.map_or(Ok(()), Err)
+    }
+
+    /// Names declared outside functions belong to the copy
+    fn declare(&mut self, id: usize, name: &str, context: Context) {
+        if context != Context::Function {
+            let globals = &mut self.copies[id].globals;
+            globals.insert(name.to_string(), name.to_string());
+        }
+    }
+
+    /// Whether a copy is, or is inside a copy of, the module at `path`; such
+    /// a module would create objects without end
+    fn creates(&self, id: usize, path: &Path) -> bool {
+        let mut current = Some(id);
+        while let Some(copy) = current {
+            if self.copies[copy].module.path == path {
+                return true;
+            }
+            current = self.copies[copy].parent;
+        }
+        false
+    }
+
+    fn load(
+        &self,
+        importer: &Module,
+        module_path: &str,
+        position: Position,
+    ) -> Result<Arc<Module>, BundleError> {
+        let Some(path) = self.workspace.resolve_path(&self.document, module_path) else {
+            return Err(BundleError::new(
+                &importer.path,
+                Some(position),
+                format!("cannot find module `{}`", module_name(module_path)),
+            ));
+        };
+        self.workspace
+            .load_file(&path, &module_name(module_path))
+            .ok_or_else(|| unloadable(&importer.path, Some(position), &path))
+    }
+}
+
+/// Plans the statements of one copy
+struct Planner<'p, 'a> {
+    plan: &'p mut Plan<'a>,
+    id: usize,
+    module: &'p Arc<Module>,
+    context: Context,
+    error: Option<BundleError>,
+}
+
+impl Planner<'_, '_> {
+    fn plan_statement(&mut self, statement: &Statement) -> Result<(), BundleError> {
+        let (id, module) = (self.id, self.module);
+        match statement {
+            Statement::Import { path, span, .. } => {
+                let imported = self.plan.load(module, path, span.start)?;
+                match self.plan.singletons.get(&imported.path) {
+                    Some(&existing) => self.plan.copies[id].imports.push(existing),
+                    None => {
+                        let child = self.plan.add(imported, CopyKind::Import, Some(id));
+                        self.plan.copies[id].imports.push(child);
+                        self.plan.copies[id].inlined.insert(*span, child);
+                        self.plan.plan(child)?;
+                    }
+                }
+            }
+            Statement::VarDeclaration {
+                name,
+                initializer:
+                    Some(Expression::New {
+                        path,
+                        span: new_span,
+                        ..
+                    }),
+                span,
+                ..
+            } => {
+                if self.context != Context::Script {
+                    return Err(misplaced_new(module, new_span.start));
+                }
+                let created = self.plan.load(module, path, new_span.start)?;
+                if self.plan.creates(id, &created.path) {
+                    return Err(BundleError::new(
+                        &module.path,
+                        Some(new_span.start),
+                        format!("`{}` creates itself with `new`", module_name(path)),
+                    ));
+                }
+                let prefix = match &self.plan.copies[id].kind {
+                    CopyKind::Instance { prefix } => format!("{}_{}", prefix, name),
+                    _ => name.clone(),
+                };
+                let child = self
+                    .plan
+                    .add(created, CopyKind::Instance { prefix }, Some(id));
+                self.plan.copies[id].instances.insert(name.clone(), child);
+                self.plan.copies[id].inlined.insert(*span, child);
+                self.plan.plan(child)?;
+            }
+            _ => {
+                match statement {
+                    Statement::VarDeclaration { name, .. }
+                    | Statement::FunctionDefinition { name, .. }
+                    | Statement::For { variable: name, .. }
+                    | Statement::ForIn { variable: name, .. } => {
+                        self.plan.declare(id, name, self.context)
+                    }
+                    _ => {}
+                }
+                walk_statement(self, statement);
+            }
+        }
+        Ok(())
+    }
+
+    fn block_in(&mut self, context: Context, statements: &[Statement]) {
+        let outer = std::mem::replace(&mut self.context, context);
+        self.visit_block(statements);
+        self.context = outer;
+    }
+
+    fn loop_body(&mut self, body: &[Statement]) {
+        let context = match self.context {
+            Context::Script => Context::Loop,
+            context => context,
+        };
+        self.block_in(context, body);
+    }
+}
+
+impl Visitor for Planner<'_, '_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if self.error.is_none() {
+            if let Err(error) = self.plan_statement(statement) {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn visit_function_definition(&mut self, _name: &str, _params: &[String], body: &[Statement]) {
+        self.block_in(Context::Function, body);
+    }
+
+    fn visit_for(&mut self, _variable: &str, range: &(Expression, Expression), body: &[Statement]) {
+        self.visit_expression(&range.0);
+        self.visit_expression(&range.1);
+        self.loop_body(body);
+    }
+
+    fn visit_for_in(&mut self, _variable: &str, collection: &Expression, body: &[Statement]) {
+        self.visit_expression(collection);
+        self.loop_body(body);
+    }
+
+    fn visit_while(&mut self, condition: &Expression, body: &[Statement]) {
+        self.visit_expression(condition);
+        self.loop_body(body);
+    }
+
+    /// Only the initializer of a `var` may be `new`, and that one is
+    /// planned without walking it
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::New { span, .. } => {
+                self.error
+                    .get_or_insert_with(|| misplaced_new(self.module, span.start));
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Where in a module a statement is, for what `new` may do there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Script,
+    Loop,
+    Function,
+}
+
+fn misplaced_new(module: &Module, position: Position) -> BundleError {
+    BundleError::new(
+        &module.path,
+        Some(position),
+        "`new` can only be bundled as the initializer of a `var` outside functions and loops"
+            .to_string(),
+    )
+}
+
+/// Picks each copy's names in the bundle
+struct Names {
+    /// Every name written anywhere in the bundled modules; new names avoid
+    /// them so that no local variable can capture a renamed one
+    written: HashSet<String>,
+    /// Names that reach built-in or undeclared variables, which a script
+    /// level name of the same spelling would hide
+    unresolved: HashSet<String>,
+    taken: HashSet<String>,
+}
+
+impl Names {
+    fn new(copies: &[ModuleCopy]) -> Self {
+        let mut names = Self {
+            written: HashSet::new(),
+            unresolved: HashSet::new(),
+            taken: HashSet::new(),
+        };
+        for id in 0..copies.len() {
+            let mut walker = Walker {
+                copies,
+                id,
+                locals: Vec::new(),
+                on_name: &mut |name: &str, local: bool, binding: Option<Binding>| {
+                    names.written.insert(name.to_string());
+                    if !local && binding.is_none() {
+                        names.unresolved.insert(name.to_string());
+                    }
+                },
+            };
+            walker.visit_block(&copies[id].module.ast.statements);
+        }
+        names
+    }
+
+    fn assign(&mut self, copies: &mut [ModuleCopy]) {
+        for copy in copies.iter_mut() {
+            let mut globals: Vec<String> = copy.globals.keys().cloned().collect();
+            globals.sort();
+            for name in globals {
+                let renamed = match &copy.kind {
+                    CopyKind::Entry => name.clone(),
+                    CopyKind::Import => {
+                        if self.taken.contains(&name) || self.unresolved.contains(&name) {
+                            let module = copy.module.name.rsplit('/').next().unwrap_or("");
+                            self.fresh(&format!("{}_{}", module, name))
+                        } else {
+                            name.clone()
+                        }
+                    }
+                    CopyKind::Instance { prefix } => self.fresh(&format!("{}_{}", prefix, name)),
+                };
+                self.taken.insert(renamed.clone());
+                copy.globals.insert(name, renamed);
+            }
+
+            if let CopyKind::Instance { prefix } = &copy.kind {
+                if copy.module.ast.statements.iter().any(runs_once) {
+                    let flag = self.fresh(&format!("{}_init", prefix));
+                    self.taken.insert(flag.clone());
+                    copy.once_flag = Some(flag);
+                }
+            }
+        }
+    }
+
+    /// `base`, or `base2`, `base3`... if the name is already used
+    fn fresh(&self, base: &str) -> String {
+        let free = |name: &String| !self.written.contains(name) && !self.taken.contains(name);
+        let base = base.to_string();
+        if free(&base) {
+            return base;
+        }
+        (2..)
+            .map(|n| format!("{}{}", base, n))
+            .find(free)
+            .unwrap_or(base)
+    }
+}
+
+/// Statements of a `new` object's module that the game runs only when the
+/// object is created, as opposed to declarations
+fn runs_once(statement: &Statement) -> bool {
+    !matches!(
+        statement,
+        Statement::VarDeclaration { .. }
+            | Statement::FunctionDefinition { .. }
+            | Statement::Import { .. }
+            | Statement::Comment(..)
+            | Statement::Empty
+    )
+}
+
+/// What a name refers to in a copy: its own names first, then those of the
+/// modules it imports, directly or through other imports
+fn lookup(copies: &[ModuleCopy], id: usize, name: &str) -> Option<Binding> {
+    let mut queue = VecDeque::from([id]);
+    let mut seen = HashSet::new();
+    while let Some(current) = queue.pop_front() {
+        if !seen.insert(current) {
+            continue;
+        }
+        let copy = &copies[current];
+        if copy.globals.contains_key(name) {
+            return Some(Binding::Global(current));
+        }
+        if let Some(&instance) = copy.instances.get(name) {
+            return Some(Binding::Instance(instance));
+        }
+        queue.extend(&copy.imports);
+    }
+    None
+}
+
+/// Walks the names of one copy, telling apart function locals from script
+/// level names
+struct Walker<'a, F: FnMut(&str, bool, Option<Binding>)> {
+    copies: &'a [ModuleCopy],
+    id: usize,
+    /// Names local to the function being walked
+    locals: Vec<String>,
+    on_name: &'a mut F,
+}
+
+impl<F: FnMut(&str, bool, Option<Binding>)> Walker<'_, F> {
+    fn name(&mut self, name: &str) {
+        if self.locals.iter().any(|local| local == name) {
+            (self.on_name)(name, true, None);
+        } else {
+            let binding = lookup(self.copies, self.id, name);
+            (self.on_name)(name, false, binding);
+        }
+    }
+}
+
+impl<F: FnMut(&str, bool, Option<Binding>)> Visitor for Walker<'_, F> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration { name, .. }
+            | Statement::For { variable: name, .. }
+            | Statement::ForIn { variable: name, .. } => self.name(name),
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_function_definition(&mut self, name: &str, params: &[String], body: &[Statement]) {
+        self.name(name);
+        let outer = std::mem::replace(&mut self.locals, function_locals(params, body));
+        for local in self.locals.clone() {
+            self.name(&local);
+        }
+        self.visit_block(body);
+        self.locals = outer;
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name, _) => self.name(name),
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Parameters and the variables a function declares in its body
+fn function_locals(params: &[String], body: &[Statement]) -> Vec<String> {
+    let mut locals = Locals(params.to_vec());
+    locals.visit_block(body);
+    locals.0
+}
+
+/// Collects the variables a block declares, nested blocks included
+struct Locals(Vec<String>);
+
+impl Visitor for Locals {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration { name, .. }
+            | Statement::For { variable: name, .. }
+            | Statement::ForIn { variable: name, .. } => self.0.push(name.clone()),
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+}
+
+/// Writes each copy with its names replaced and its imports inlined
+struct Emitter<'a> {
+    copies: &'a [ModuleCopy],
+    /// Characters each copy adds, leaving out the copies inside it
+    characters: Vec<usize>,
+}
+
+impl Emitter<'_> {
+    /// The text of a copy, every line but verbatim ones starting with `indent`
+    fn emit(&mut self, id: usize, indent: &str) -> Result<String, BundleError> {
+        let copies = self.copies;
+        let copy = &copies[id];
+        let source = copy
+            .module
+            .source
+            .strip_prefix('\u{FEFF}')
+            .unwrap_or(&copy.module.source);
+        let lines = LineIndex::new(source);
+        let verbatim = verbatim_lines(&copy.module.ast.statements);
+
+        let mut edits = Vec::new();
+        let mut error = None;
+        {
+            let mut rewriter = Rewriter {
+                copies,
+                id,
+                module: &copy.module,
+                lines: &lines,
+                locals: Vec::new(),
+                edits: &mut edits,
+                error: &mut error,
+            };
+            rewriter.visit_block(&copy.module.ast.statements);
+        }
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let mut inner_characters = 0;
+        let mut statements: Vec<(&Span, &usize)> = copy.inlined.iter().collect();
+        statements.sort_by_key(|(span, _)| span.start.line);
+        let mut inlined = HashMap::new();
+        for (span, &child) in statements {
+            let child_indent = format!("{}{}", indent, lines.indent(span.start.line));
+            let text = self.emit(child, &child_indent)?;
+            inner_characters += text.chars().count();
+            let header = match &copies[child].kind {
+                CopyKind::Instance { .. } => {
+                    format!("{}// {}\n", child_indent, lines.text(*span).trim())
+                }
+                _ => format!("{}// {}\n", child_indent, copies[child].module.name),
+            };
+            inlined.insert(span.start.line, format!("{}{}", header, text));
+        }
+        let mut dropper = Dropper {
+            lines: &lines,
+            inlined,
+            replaced: HashSet::new(),
+            edits: &mut edits,
+        };
+        dropper.visit_block(&copy.module.ast.statements);
+        let replaced = dropper.replaced;
+
+        let once = copy.once_flag.as_ref().map(|flag| {
+            guard_once(
+                flag,
+                &copy.module.ast.statements,
+                &lines,
+                &verbatim,
+                &mut edits,
+            )
+        });
+        let guarded = once.unwrap_or_default();
+
+        for line in 0..lines.count() {
+            if verbatim.contains(&line)
+                || replaced.contains(&line)
+                || lines.line(line).trim().is_empty()
+            {
+                continue;
+            }
+            let start = lines.start(line);
+            if !indent.is_empty() {
+                edits.push(Edit {
+                    start,
+                    end: start,
+                    text: indent.to_string(),
+                });
+            }
+            if guarded.contains(&line) {
+                edits.push(Edit {
+                    start,
+                    end: start,
+                    text: GUARD_INDENT.to_string(),
+                });
+            }
+        }
+
+        let mut text = apply_edits(source, edits);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.characters[id] = text.chars().count() - inner_characters;
+        Ok(text)
+    }
+}
+
+/// Replaces each `import` and `new` statement with its inlined copy, or
+/// drops it when the module is already in the bundle, collecting the lines
+/// replaced
+struct Dropper<'a> {
+    lines: &'a LineIndex<'a>,
+    /// Text of the inlined copies, by the line of the statement they replace
+    inlined: HashMap<usize, String>,
+    replaced: HashSet<usize>,
+    edits: &'a mut Vec<Edit>,
+}
+
+impl Visitor for Dropper<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Import { span, .. }
+            | Statement::VarDeclaration {
+                initializer: Some(Expression::New { .. }),
+                span,
+                ..
+            } => {
+                self.edits.push(Edit {
+                    start: self.lines.start(span.start.line),
+                    end: self.lines.start(span.end.line + 1),
+                    text: self.inlined.remove(&span.start.line).unwrap_or_default(),
+                });
+                self.replaced.extend(span.start.line..=span.end.line);
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+}
+
+/// Nest the statements a `new` object runs on creation under `?flag`,
+/// returning the lines to indent
+fn guard_once(
+    flag: &str,
+    statements: &[Statement],
+    lines: &LineIndex,
+    verbatim: &HashSet<usize>,
+    edits: &mut Vec<Edit>,
+) -> HashSet<usize> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    let mut in_run = false;
+    for statement in statements {
+        let Some(span) = statement.span() else {
+            continue;
+        };
+        if !runs_once(statement) {
+            in_run = false;
+        } else if in_run {
+            runs.last_mut().unwrap().1 = span.end.line;
+        } else {
+            runs.push((span.start.line, span.end.line));
+            in_run = true;
+        }
+    }
+
+    edits.push(Edit {
+        start: 0,
+        end: 0,
+        text: format!("var {} = true\n", flag),
+    });
+    let mut guarded = HashSet::new();
+    for &(first, last) in &runs {
+        let start = lines.start(first);
+        edits.push(Edit {
+            start,
+            end: start,
+            text: format!("?{}\n", flag),
+        });
+        guarded.extend((first..=last).filter(|line| !verbatim.contains(line)));
+    }
+    if let Some(&(_, last)) = runs.last() {
+        let end = lines.start(last + 1);
+        let newline = if lines.has_newline(last) { "" } else { "\n" };
+        edits.push(Edit {
+            start: end,
+            end,
+            text: format!("{}{}{} = false\n", newline, GUARD_INDENT, flag),
+        });
+    }
+    guarded
+}
+
+/// Records the renames in one copy
+struct Rewriter<'a> {
+    copies: &'a [ModuleCopy],
+    id: usize,
+    module: &'a Module,
+    lines: &'a LineIndex<'a>,
+    locals: Vec<String>,
+    edits: &'a mut Vec<Edit>,
+    error: &'a mut Option<BundleError>,
+}
+
+/// What a name or member chain evaluates to
+enum Target {
+    /// A script-level name, with its name in the bundle
+    Name(String),
+    Object(usize),
+}
+
+impl Rewriter<'_> {
+    fn fail(&mut self, position: Position, message: String) {
+        if self.error.is_none() {
+            *self.error = Some(BundleError::new(&self.module.path, Some(position), message));
+        }
+    }
+
+    fn replace(&mut self, span: Span, text: &str) {
+        self.edits.push(Edit {
+            start: self.lines.offset(span.start),
+            end: self.lines.offset(span.end),
+            text: text.to_string(),
+        });
+    }
+
+    /// Rename a declared name when it is script level
+    fn declaration(&mut self, name: &str, span: Span) {
+        if self.locals.iter().any(|local| local == name) {
+            return;
+        }
+        if let Some(renamed) = self.copies[self.id].globals.get(name) {
+            if renamed != name {
+                let renamed = renamed.clone();
+                self.replace(span, &renamed);
+            }
+        }
+    }
+
+    /// Resolve a name, or a member of a `new` object
+    fn resolve(&mut self, expression: &Expression) -> Option<Target> {
+        match expression {
+            Expression::Identifier(name, _) => {
+                if self.locals.iter().any(|local| local == name) {
+                    return None;
+                }
+                match lookup(self.copies, self.id, name)? {
+                    Binding::Global(owner) => {
+                        let renamed = &self.copies[owner].globals[name];
+                        Some(Target::Name(renamed.clone())).filter(|_| renamed != name)
+                    }
+                    Binding::Instance(instance) => Some(Target::Object(instance)),
+                }
+            }
+            Expression::Property {
+                object, property, ..
+            } => {
+                let Target::Object(instance) = self.resolve(object)? else {
+                    return None;
+                };
+                let copy = &self.copies[instance];
+                if let Some(renamed) = copy.globals.get(property) {
+                    Some(Target::Name(renamed.clone()))
+                } else if let Some(&nested) = copy.instances.get(property) {
+                    Some(Target::Object(nested))
+                } else {
+                    let message = format!("`{}` has no member `{}`", copy.module.name, property);
+                    self.fail(expression.span().start, message);
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Visitor for Rewriter<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Import { .. }
+            | Statement::VarDeclaration {
+                initializer: Some(Expression::New { .. }),
+                ..
+            } => {}
+            Statement::FunctionDefinition {
+                name,
+                name_span,
+                params,
+                body,
+                ..
+            } => {
+                self.declaration(name, *name_span);
+                let outer = std::mem::replace(&mut self.locals, function_locals(params, body));
+                self.visit_block(body);
+                self.locals = outer;
+            }
+            _ => {
+                match statement {
+                    Statement::VarDeclaration {
+                        name, name_span, ..
+                    } => self.declaration(name, *name_span),
+                    Statement::For {
+                        variable,
+                        variable_span,
+                        ..
+                    }
+                    | Statement::ForIn {
+                        variable,
+                        variable_span,
+                        ..
+                    } => self.declaration(variable, *variable_span),
+                    _ => {}
+                }
+                walk_statement(self, statement);
+            }
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(..) | Expression::Property { .. } => {
+                match self.resolve(expression) {
+                    Some(Target::Name(renamed)) => self.replace(expression.span(), &renamed),
+                    Some(Target::Object(_)) => self.fail(
+                        expression.span().start,
+                        "an object created with `new` can only be bundled when used as \
+                         `object.member`"
+                            .to_string(),
+                    ),
+                    None => {
+                        if let Expression::Property { object, .. } = expression {
+                            self.visit_expression(object);
+                        }
+                    }
+                }
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Lines inside multi-line strings, such as `ascii` art, and block
+/// comments, which must not be indented
+fn verbatim_lines(statements: &[Statement]) -> HashSet<usize> {
+    let mut verbatim = VerbatimLines(HashSet::new());
+    verbatim.visit_block(statements);
+    verbatim.0
+}
+
+struct VerbatimLines(HashSet<usize>);
+
+impl Visitor for VerbatimLines {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::Comment(_, span) = statement {
+            self.0.extend(span.start.line + 1..=span.end.line);
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::String(_, span) | Expression::Interpolation(_, span) = expression {
+            self.0.extend(span.start.line + 1..=span.end.line);
+        }
+        walk_expression(self, expression);
+    }
+}
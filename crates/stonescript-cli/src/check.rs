@@ -0,0 +1,98 @@
+//! Running the language server's diagnostics over script files
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use stonescript_lsp::config::DiagnosticLevel;
+use stonescript_lsp::providers::DiagnosticsProvider;
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, Url};
+
+/// Code for scripts the parser rejects outright
+pub const PARSE_ERROR: &str = "parse-error";
+
+/// The diagnostics found in one script
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    /// The path as the script was found, for display
+    pub path: PathBuf,
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl FileReport {
+    pub fn count(&self, severity: DiagnosticSeverity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| self::severity(diagnostic) == severity)
+            .count()
+    }
+}
+
+/// The severity a diagnostic is reported with; the LSP leaves a missing
+/// one to the client, and here it counts as an error
+pub fn severity(diagnostic: &Diagnostic) -> DiagnosticSeverity {
+    diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR)
+}
+
+/// Checks scripts with the same rules the language server uses
+pub struct Checker {
+    diagnostics: DiagnosticsProvider,
+    workspace: Workspace,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: DiagnosticsProvider::new(),
+            workspace: Workspace::new(),
+        }
+    }
+
+    /// Change the severity of diagnostics by code, as `diagnosticSeverities`
+    /// does in the editor
+    pub fn set_severities(&self, severities: HashMap<String, DiagnosticLevel>) {
+        self.diagnostics.set_severities(severities);
+    }
+
+    pub fn check_file(&self, path: &Path) -> std::io::Result<FileReport> {
+        let source = std::fs::read_to_string(path)?;
+        Ok(self.check_source(path, source))
+    }
+
+    /// Check a script's text; `path` resolves its imports
+    pub fn check_source(&self, path: &Path, source: String) -> FileReport {
+        let mut diagnostics = match parse_source(&source) {
+            Ok(ast) => {
+                let mut scope = ScopeAnalyzer::new();
+                scope.analyze_ast(&ast);
+                let uri = std::path::absolute(path)
+                    .ok()
+                    .and_then(|path| Url::from_file_path(path).ok())
+                    .unwrap_or_else(|| Url::parse("file:///script.txt").unwrap());
+                self.diagnostics
+                    .provide_diagnostics(&ast, &source, &scope, &uri, &self.workspace)
+            }
+            Err(e) => vec![Diagnostic {
+                range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(PARSE_ERROR.to_string())),
+                message: format!("Parse error: {}", e),
+                ..Default::default()
+            }],
+        };
+        diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+
+        FileReport {
+            path: path.to_path_buf(),
+            source,
+            diagnostics,
+        }
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
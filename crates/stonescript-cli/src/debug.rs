@@ -0,0 +1,677 @@
+//! A Debug Adapter Protocol server that steps through a script as it runs
+//! over scripted game-state frames
+//!
+//! The script runs the way `stonescript-test` runs it, with each frame's
+//! game state taken from a `.test.txt` file. Breakpoints go on the lines
+//! statements start on; stepping follows function calls, imports and `new`
+//! objects. Besides the standard requests, the server answers `nextFrame`,
+//! which runs on to the first statement of the next frame.
+
+use serde_json::{json, Value as Json};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use stonescript_interpreter::{Call, Debugger, Interpreter, Object, Value};
+use stonescript_lsp::utils::Workspace;
+use stonescript_parser::{parse_source, walk_statement, Program, Statement, Visitor};
+
+use crate::golden::{self, document_url, FrameTest, ProjectHost};
+use crate::scripts::TEST_SUFFIX;
+
+/// Frames run when there is no test file to say how many
+const DEFAULT_FRAMES: u64 = 1;
+
+/// The only thread a script has
+const THREAD_ID: i64 = 1;
+
+/// Read a message: `Content-Length` headers, a blank line and a JSON body.
+/// `None` at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            let value = value.trim();
+            length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| invalid(format!("invalid Content-Length `{}`", value)))?,
+            );
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// Write a message with its `Content-Length` header
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serve one debugging session, reading requests from `input` and writing
+/// responses and events to `output`, until the client disconnects
+pub fn serve(input: impl BufRead + Send + 'static, output: impl Write + 'static) {
+    let (sender, requests) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut input = input;
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let adapter = Rc::new(RefCell::new(Adapter::new(requests, Box::new(output))));
+    let launch = loop {
+        let mut adapter = adapter.borrow_mut();
+        if adapter.configured && adapter.launch.is_some() {
+            break adapter.launch.take();
+        }
+        match adapter.next_request() {
+            Some(request) => {
+                adapter.handle(&request);
+                if adapter.closed {
+                    return;
+                }
+            }
+            None => return,
+        }
+    };
+    if let Some(launch) = launch {
+        run(&adapter, launch);
+    }
+
+    let mut adapter = adapter.borrow_mut();
+    if !adapter.closed {
+        adapter.event("exited", json!({ "exitCode": 0 }));
+        adapter.event("terminated", json!({}));
+    }
+    while !adapter.closed {
+        match adapter.next_request() {
+            Some(request) => {
+                adapter.handle(&request);
+            }
+            None => break,
+        }
+    }
+}
+
+/// Run the script frame by frame, sending what each frame did as output
+fn run(adapter: &Rc<RefCell<Adapter>>, launch: Launch) {
+    let workspace = adapter.borrow().workspace.clone();
+    let mut host = ProjectHost::new(&workspace, &launch.program);
+    let mut interpreter = Interpreter::new(launch.ast);
+    interpreter.set_debugger(Some(Box::new(Hook(adapter.clone()))));
+    if launch.stop_on_entry {
+        adapter.borrow_mut().mode = Mode::Entry;
+    }
+
+    for number in 1..=launch.count {
+        if adapter.borrow().closed {
+            return;
+        }
+        host.advance(&launch.frames, number);
+        let result = interpreter.run_frame(&mut host);
+        let mut adapter = adapter.borrow_mut();
+        match result {
+            Ok(frame) => {
+                for event in &frame.events {
+                    adapter.output("stdout", &format!("{}\n", event));
+                }
+            }
+            Err(e) => {
+                adapter.output("stderr", &format!("{}\n", e));
+                let stack = [Call {
+                    name: String::new(),
+                    module: e.module.clone(),
+                    span: e.span,
+                    scope: interpreter.main().clone(),
+                    locals: None,
+                }];
+                adapter.stop("exception", Some(e.message), number - 1, &stack);
+                return;
+            }
+        }
+    }
+}
+
+/// What the client asked to debug
+struct Launch {
+    program: PathBuf,
+    ast: Program,
+    frames: Vec<FrameTest>,
+    count: u64,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    /// Read the `launch` arguments: `program`, and optionally `frames` (a
+    /// test file; by default the one next to the program), `frameCount`,
+    /// `stopOnEntry` and `roots`
+    fn from_args(args: &Json, workspace: &Workspace) -> Result<Self, String> {
+        let program = args["program"]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or("`program` must be the path of the script to debug")?;
+        let source = std::fs::read_to_string(&program)
+            .map_err(|e| format!("could not read {}: {}", program.display(), e))?;
+        let ast = parse_source(&source)
+            .map_err(|e| format!("{} does not parse: {}", program.display(), e))?;
+
+        let test = match args["frames"].as_str() {
+            Some(path) => Some(PathBuf::from(path)),
+            None => {
+                let stem = program.file_stem().unwrap_or_default().to_string_lossy();
+                let sibling = program.with_file_name(format!("{}{}", stem, TEST_SUFFIX));
+                sibling.is_file().then_some(sibling)
+            }
+        };
+        let frames = match &test {
+            Some(test) => {
+                let text = std::fs::read_to_string(test)
+                    .map_err(|e| format!("could not read {}: {}", test.display(), e))?;
+                golden::parse(&text).map_err(|e| format!("{}: {}", test.display(), e))?
+            }
+            None => Vec::new(),
+        };
+        let count = args["frameCount"]
+            .as_u64()
+            .or_else(|| frames.last().map(|frame| frame.number))
+            .unwrap_or(DEFAULT_FRAMES);
+
+        if let Some(roots) = args["roots"].as_array() {
+            let roots = roots.iter().filter_map(Json::as_str).map(PathBuf::from);
+            workspace.set_roots(roots.collect());
+        }
+
+        Ok(Self {
+            program: canonical(&program),
+            ast,
+            frames,
+            count,
+            stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+/// When the script should next pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Run,
+    Entry,
+    Pause,
+    StepIn,
+    /// Step over: pause with this many calls or fewer under way
+    StepOver(usize),
+    /// Step out: pause with fewer calls than this under way
+    StepOut(usize),
+    /// Pause in the first frame after this one
+    NextFrame(u64),
+}
+
+/// What a variable reference shows the client
+enum Container {
+    Variables(Vec<(String, Value)>),
+    Object(Rc<RefCell<Object>>),
+    Array(Rc<RefCell<Vec<Value>>>),
+}
+
+/// The session's state, shared by the request loop and the interpreter
+struct Adapter {
+    requests: Receiver<Json>,
+    /// Requests read while the script ran, to answer once it pauses
+    pending: VecDeque<Json>,
+    output: Box<dyn Write>,
+    seq: i64,
+    workspace: Rc<Workspace>,
+    launch: Option<Launch>,
+    program: PathBuf,
+    configured: bool,
+    closed: bool,
+    mode: Mode,
+    /// Lines to pause on, by file
+    breakpoints: HashMap<PathBuf, BTreeSet<usize>>,
+    /// Files of the modules the script has reached
+    modules: HashMap<String, Option<PathBuf>>,
+    /// Where the paused script is
+    frame: u64,
+    stack: Vec<Call>,
+    /// What each variable reference handed out while paused stands for
+    containers: Vec<Container>,
+}
+
+impl Adapter {
+    fn new(requests: Receiver<Json>, output: Box<dyn Write>) -> Self {
+        Self {
+            requests,
+            pending: VecDeque::new(),
+            output,
+            seq: 0,
+            workspace: Rc::new(Workspace::new()),
+            launch: None,
+            program: PathBuf::new(),
+            configured: false,
+            closed: false,
+            mode: Mode::Run,
+            breakpoints: HashMap::new(),
+            modules: HashMap::new(),
+            frame: 0,
+            stack: Vec::new(),
+            containers: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, mut message: Json) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        // A client that has gone away is noticed when its input ends
+        let _ = write_message(&mut self.output, &message);
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    fn fail(&mut self, request: &Json, message: String) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn output(&mut self, category: &str, text: &str) {
+        self.event("output", json!({ "category": category, "output": text }));
+    }
+
+    /// The next request to answer, waiting for one if need be; `None` once
+    /// the client's input has ended
+    fn next_request(&mut self) -> Option<Json> {
+        self.pending
+            .pop_front()
+            .or_else(|| self.requests.recv().ok())
+    }
+
+    /// Take in requests that arrived while the script ran: those that act
+    /// on a running script are answered now, the rest when it pauses
+    fn poll(&mut self) {
+        loop {
+            match self.requests.try_recv() {
+                Ok(request) => match request["command"].as_str() {
+                    Some("pause" | "setBreakpoints" | "threads" | "disconnect" | "terminate") => {
+                        self.handle(&request);
+                    }
+                    _ => self.pending.push_back(request),
+                },
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    if self.pending.is_empty() {
+                        self.closed = true;
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Answer a request; returns whether it lets a paused script go on
+    fn handle(&mut self, request: &Json) -> bool {
+        let args = &request["arguments"];
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                self.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                );
+                self.event("initialized", json!({}));
+            }
+            "launch" => match Launch::from_args(args, &self.workspace) {
+                Ok(launch) => {
+                    self.program = launch.program.clone();
+                    self.launch = Some(launch);
+                    self.respond(request, json!({}));
+                }
+                Err(e) => self.fail(request, e),
+            },
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(args);
+                self.respond(request, body);
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, json!({}));
+            }
+            "threads" => self.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "script" }] }),
+            ),
+            "stackTrace" => {
+                let body = self.stack_trace();
+                self.respond(request, body);
+            }
+            "scopes" => {
+                let body = self.scopes(args["frameId"].as_u64().unwrap_or_default() as usize);
+                self.respond(request, body);
+            }
+            "variables" => {
+                let reference = args["variablesReference"].as_u64().unwrap_or_default();
+                let body = self.variables(reference as usize);
+                self.respond(request, body);
+            }
+            "pause" => {
+                self.mode = Mode::Pause;
+                self.respond(request, json!({}));
+            }
+            "continue" | "next" | "stepIn" | "stepOut" | "nextFrame" => {
+                let depth = self.stack.len();
+                self.mode = match request["command"].as_str().unwrap_or_default() {
+                    "next" => Mode::StepOver(depth),
+                    "stepIn" => Mode::StepIn,
+                    // Stepping out of the script leaves it for the next frame
+                    "stepOut" if depth > 1 => Mode::StepOut(depth),
+                    "stepOut" | "nextFrame" => Mode::NextFrame(self.frame),
+                    _ => Mode::Run,
+                };
+                self.respond(request, json!({ "allThreadsContinued": true }));
+                return true;
+            }
+            "disconnect" | "terminate" => {
+                self.closed = true;
+                self.respond(request, json!({}));
+                return true;
+            }
+            command => self.fail(request, format!("unsupported request `{}`", command)),
+        }
+        false
+    }
+
+    /// Pause the script at the top of `stack` until the client lets it go
+    fn stop(&mut self, reason: &str, text: Option<String>, frame: u64, stack: &[Call]) {
+        self.mode = Mode::Run;
+        self.frame = frame;
+        self.stack = stack.to_vec();
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(text) = text {
+            body["text"] = json!(text);
+        }
+        self.event("stopped", body);
+
+        loop {
+            match self.next_request() {
+                Some(request) if self.handle(&request) => break,
+                Some(_) => {}
+                None => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        self.stack.clear();
+        self.containers.clear();
+    }
+
+    /// The file a module runs from; the program for the main script
+    fn path_of(&mut self, module: &str) -> Option<PathBuf> {
+        if module.is_empty() {
+            return Some(self.program.clone());
+        }
+        if let Some(path) = self.modules.get(module) {
+            return path.clone();
+        }
+        let path = self
+            .workspace
+            .resolve_path(&document_url(&self.program), module)
+            .map(|path| canonical(&path));
+        self.modules.insert(module.to_string(), path.clone());
+        path
+    }
+
+    fn at_breakpoint(&mut self, call: &Call) -> bool {
+        let line = call.span.start.line + 1;
+        self.path_of(&call.module)
+            .and_then(|path| self.breakpoints.get(&path))
+            .is_some_and(|lines| lines.contains(&line))
+    }
+
+    /// Move each breakpoint to the first line at or after it that a
+    /// statement starts on
+    fn set_breakpoints(&mut self, args: &Json) -> Json {
+        let Some(path) = args["source"]["path"].as_str().map(Path::new) else {
+            return json!({ "breakpoints": [] });
+        };
+        let lines = statement_lines(path);
+        let mut set = BTreeSet::new();
+        let mut breakpoints = Vec::new();
+        for requested in args["breakpoints"].as_array().into_iter().flatten() {
+            let line = requested["line"].as_u64().unwrap_or_default() as usize;
+            match lines.range(line..).next() {
+                Some(&line) => {
+                    set.insert(line);
+                    breakpoints.push(json!({ "verified": true, "line": line }));
+                }
+                None => breakpoints.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "no statement on or after this line",
+                })),
+            }
+        }
+        self.breakpoints.insert(canonical(path), set);
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn stack_trace(&mut self) -> Json {
+        let stack = self.stack.clone();
+        let frames: Vec<Json> = stack
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(id, call)| {
+                let path = self.path_of(&call.module);
+                let file = path
+                    .as_deref()
+                    .and_then(Path::file_name)
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| call.module.clone());
+                let name = if call.name.is_empty() {
+                    format!("{} (frame {})", file, self.frame + 1)
+                } else {
+                    call.name.clone()
+                };
+                json!({
+                    "id": id,
+                    "name": name,
+                    "source": { "name": file, "path": path },
+                    "line": call.span.start.line + 1,
+                    "column": call.span.start.column + 1,
+                })
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    /// A function's locals, the module or object it belongs to, and the
+    /// script's globals
+    fn scopes(&mut self, frame_id: usize) -> Json {
+        let Some(call) = self.stack.get(frame_id).cloned() else {
+            return json!({ "scopes": [] });
+        };
+        let mut scopes = Vec::new();
+        if let Some(locals) = &call.locals {
+            let mut locals: Vec<(String, Value)> = locals.clone().into_iter().collect();
+            locals.sort_by(|a, b| a.0.cmp(&b.0));
+            let reference = self.reference(Container::Variables(locals));
+            scopes.push(json!({
+                "name": "Locals",
+                "presentationHint": "locals",
+                "variablesReference": reference,
+            }));
+        }
+        if !call.module.is_empty() {
+            let reference = self.reference(Container::Object(call.scope.clone()));
+            scopes.push(json!({ "name": call.module, "variablesReference": reference }));
+        }
+        if let Some(main) = self.stack.first().map(|call| call.scope.clone()) {
+            let reference = self.reference(Container::Object(main));
+            scopes.push(json!({ "name": "Globals", "variablesReference": reference }));
+        }
+        json!({ "scopes": scopes })
+    }
+
+    fn variables(&mut self, reference: usize) -> Json {
+        let entries: Vec<(String, Value)> = match reference
+            .checked_sub(1)
+            .and_then(|i| self.containers.get(i))
+        {
+            Some(Container::Variables(entries)) => entries.clone(),
+            Some(Container::Object(object)) => object
+                .borrow()
+                .fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            Some(Container::Array(items)) => items
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (format!("[{}]", i), value.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+        let variables: Vec<Json> = entries
+            .into_iter()
+            .map(|(name, value)| {
+                let reference = match &value {
+                    Value::Array(items) if !items.borrow().is_empty() => {
+                        self.reference(Container::Array(items.clone()))
+                    }
+                    Value::Object(object) => self.reference(Container::Object(object.clone())),
+                    _ => 0,
+                };
+                json!({
+                    "name": name,
+                    "value": show(&value),
+                    "type": value.type_name(),
+                    "variablesReference": reference,
+                })
+            })
+            .collect();
+        json!({ "variables": variables })
+    }
+
+    /// Hand out a variable reference for `container`, good until the
+    /// script goes on
+    fn reference(&mut self, container: Container) -> usize {
+        self.containers.push(container);
+        self.containers.len()
+    }
+}
+
+/// The interpreter's view of the session: decides at each statement
+/// whether to pause
+struct Hook(Rc<RefCell<Adapter>>);
+
+impl Debugger for Hook {
+    fn statement(&mut self, frame: u64, stack: &[Call]) {
+        let mut adapter = self.0.borrow_mut();
+        adapter.poll();
+        let Some(top) = stack.last() else {
+            return;
+        };
+        if adapter.closed {
+            return;
+        }
+        let depth = stack.len();
+        let reason = match adapter.mode {
+            Mode::Run => None,
+            Mode::Entry => Some("entry"),
+            Mode::Pause => Some("pause"),
+            Mode::StepIn => Some("step"),
+            Mode::StepOver(calls) => (depth <= calls).then_some("step"),
+            Mode::StepOut(calls) => (depth < calls).then_some("step"),
+            Mode::NextFrame(last) => (frame > last).then_some("frame"),
+        };
+        let reason = reason.or_else(|| adapter.at_breakpoint(top).then_some("breakpoint"));
+        if let Some(reason) = reason {
+            adapter.stop(reason, None, frame, stack);
+        }
+    }
+}
+
+/// Lines, counting from 1, that statements in the script at `path` start on
+fn statement_lines(path: &Path) -> BTreeSet<usize> {
+    struct Lines(BTreeSet<usize>);
+
+    impl Visitor for Lines {
+        fn visit_statement(&mut self, statement: &Statement) {
+            if let (Some(span), false) = (
+                statement.span(),
+                matches!(statement, Statement::Comment(..)),
+            ) {
+                self.0.insert(span.start.line + 1);
+            }
+            walk_statement(self, statement);
+        }
+    }
+
+    let mut lines = Lines(BTreeSet::new());
+    if let Some(program) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|source| parse_source(&source).ok())
+    {
+        lines.visit_program(&program);
+    }
+    lines.0
+}
+
+/// A path in one spelling, so breakpoints and modules can be matched
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A value as the client shows it, with strings quoted
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    }
+}
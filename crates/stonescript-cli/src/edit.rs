@@ -0,0 +1,77 @@
+//! Rewriting scripts by editing the text the parser's spans point into
+
+use stonescript_parser::ast::{Position, Span};
+
+/// A text replacement in a script, by byte offsets
+#[derive(Clone)]
+pub(crate) struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Byte offsets of line starts, for turning spans into text ranges
+pub(crate) struct LineIndex<'s> {
+    source: &'s str,
+    starts: Vec<usize>,
+}
+
+impl<'s> LineIndex<'s> {
+    pub fn new(source: &'s str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(at, _)| at + 1));
+        if starts.len() > 1 && starts.last() == Some(&source.len()) {
+            starts.pop();
+        }
+        Self { source, starts }
+    }
+
+    pub fn count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Start of a line, or the end of the text past the last line
+    pub fn start(&self, line: usize) -> usize {
+        self.starts.get(line).copied().unwrap_or(self.source.len())
+    }
+
+    /// Whether a line break follows the line
+    pub fn has_newline(&self, line: usize) -> bool {
+        line + 1 < self.starts.len() || self.source.ends_with('\n')
+    }
+
+    pub fn line(&self, line: usize) -> &'s str {
+        self.source[self.start(line)..self.start(line + 1)].trim_end_matches(['\n', '\r'])
+    }
+
+    pub fn indent(&self, line: usize) -> &'s str {
+        let text = self.line(line);
+        &text[..text.len() - text.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// Byte offset of a position, whose column counts characters
+    pub fn offset(&self, position: Position) -> usize {
+        let start = self.start(position.line);
+        let line = &self.source[start..self.start(position.line + 1)];
+        start
+            + line
+                .char_indices()
+                .nth(position.column)
+                .map_or(line.len(), |(at, _)| at)
+    }
+
+    pub fn text(&self, span: Span) -> &'s str {
+        &self.source[self.offset(span.start)..self.offset(span.end)]
+    }
+}
+
+/// Apply edits that do not overlap; insertions at the same offset keep
+/// their order
+pub(crate) fn apply_edits(source: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    let mut text = source.to_string();
+    for edit in edits.iter().rev() {
+        text.replace_range(edit.start..edit.end, &edit.text);
+    }
+    text
+}
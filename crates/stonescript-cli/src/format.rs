@@ -0,0 +1,61 @@
+//! Formatting scripts with the language server's formatter
+
+use similar::TextDiff;
+use std::path::{Path, PathBuf};
+use stonescript_lsp::config::{FormattingSettings, Settings};
+use stonescript_lsp::providers::formatting::{format_source, Indent};
+
+/// Project settings file, holding the same options the editor sends
+pub const CONFIG_FILE: &str = "stonescript.json";
+
+/// The nearest `stonescript.json` in `dir` or one of its parents
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Read the formatter options from a settings file
+pub fn load_settings(path: &Path) -> Result<FormattingSettings, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let value =
+        serde_json::from_str(&text).map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+    Settings::try_from_value(value)
+        .map(|settings| settings.formatting)
+        .map_err(|e| format!("invalid {}: {}", path.display(), e))
+}
+
+/// Formats scripts the way the editor does without tab options of its own
+pub struct Formatter {
+    indent: Indent,
+}
+
+impl Formatter {
+    pub fn new(settings: &FormattingSettings) -> Self {
+        Self {
+            indent: Indent::resolve(settings, None),
+        }
+    }
+
+    pub fn format(&self, source: &str) -> String {
+        format_source(source, self.indent)
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new(&FormattingSettings::default())
+    }
+}
+
+/// A unified diff from `source` to `formatted`, empty when they match
+pub fn diff(name: &str, source: &str, formatted: &str) -> String {
+    if source == formatted {
+        return String::new();
+    }
+    TextDiff::from_lines(source, formatted)
+        .unified_diff()
+        .header(name, name)
+        .to_string()
+}
@@ -0,0 +1,496 @@
+//! Golden-frame tests: running a script over mocked game states and
+//! comparing what it did with what a test file expects
+//!
+//! A test file sits next to the script it tests, `Boss.test.txt` for
+//! `Boss.txt`:
+//!
+//! ```text
+//! # Swap to the sword for bosses
+//! frame 1: loc=rocky, foe=none, hp=10
+//! commands:
+//!   equip vigor crossbow
+//! frame 2: foe=boss
+//! commands:
+//!   equip vigor sword
+//! storage:
+//!   bosses = 1
+//! frame 5: hp=3
+//! screen:
+//!   |
+//!   |LOW HP
+//! ```
+//!
+//! Each `frame` line runs the script up to that frame, with the game state
+//! it sets; state carries over to later frames, and `time` and `totaltime`
+//! count up by one a frame. The sections below list what the frame must
+//! have done: all of its commands and `>` prints in order, storage values
+//! by key (`none` for a missing key), or the screen's rows from the top.
+//! Sections a frame leaves out are not checked.
+
+use similar::TextDiff;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use stonescript_interpreter::{Fixture, Host, Interpreter, Screen, Value};
+use stonescript_lsp::utils::Workspace;
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::Url;
+
+use crate::scripts::TEST_SUFFIX;
+
+/// One `frame` line of a test file and the sections below it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTest {
+    /// Frame to check, counting from 1
+    pub number: u64,
+    /// Line of the test file the frame starts on, counting from 1
+    pub line: usize,
+    pub state: Vec<(String, Value)>,
+    pub expectations: Vec<Expectation>,
+}
+
+/// What a frame must have done
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expectation {
+    Commands(Vec<String>),
+    Prints(Vec<String>),
+    Storage(Vec<(String, String)>),
+    Screen(Vec<String>),
+}
+
+impl Expectation {
+    fn name(&self) -> &'static str {
+        match self {
+            Expectation::Commands(_) => "commands",
+            Expectation::Prints(_) => "prints",
+            Expectation::Storage(_) => "storage",
+            Expectation::Screen(_) => "screen",
+        }
+    }
+}
+
+/// A test file that could not be read, with the line at fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Read the frames of a test file
+pub fn parse(text: &str) -> Result<Vec<FrameTest>, SyntaxError> {
+    let mut frames: Vec<FrameTest> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| SyntaxError { line, message };
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indented = raw.starts_with([' ', '\t']);
+        if indented {
+            let Some(frame) = frames.last_mut() else {
+                return Err(error("expected a `frame` line first".to_string()));
+            };
+            let Some(section) = frame.expectations.last_mut() else {
+                return Err(error("expected a section such as `commands:`".to_string()));
+            };
+            match section {
+                Expectation::Commands(lines) | Expectation::Prints(lines) => {
+                    lines.push(trimmed.to_string())
+                }
+                Expectation::Storage(entries) => {
+                    let (key, value) = trimmed.split_once('=').ok_or_else(|| {
+                        error(format!("expected `key = value`, got `{}`", trimmed))
+                    })?;
+                    entries.push((key.trim().to_string(), unquote(value.trim()).to_string()));
+                }
+                Expectation::Screen(rows) => {
+                    let row = trimmed.strip_prefix('|').ok_or_else(|| {
+                        error("screen rows start with `|`, to keep their spaces".to_string())
+                    })?;
+                    rows.push(row.trim_end().to_string());
+                }
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("frame") {
+            let (number, state) = rest.split_once(':').unwrap_or((rest, ""));
+            let next = frames.last().map_or(1, |frame| frame.number + 1);
+            let number = match number.trim() {
+                "" => next,
+                number => number
+                    .parse()
+                    .map_err(|_| error(format!("expected a frame number, got `{}`", number)))?,
+            };
+            if number < next {
+                return Err(error(format!(
+                    "frame {} comes after frame {}",
+                    number,
+                    next - 1
+                )));
+            }
+            frames.push(FrameTest {
+                number,
+                line,
+                state: parse_state(state).map_err(error)?,
+                expectations: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(frame) = frames.last_mut() else {
+            return Err(error("expected a `frame` line first".to_string()));
+        };
+        let section = match trimmed {
+            "commands:" => Expectation::Commands(Vec::new()),
+            "prints:" => Expectation::Prints(Vec::new()),
+            "storage:" => Expectation::Storage(Vec::new()),
+            "screen:" => Expectation::Screen(Vec::new()),
+            _ => {
+                return Err(error(format!(
+                    "expected `frame`, `commands:`, `prints:`, `storage:` or `screen:`, got `{}`",
+                    trimmed
+                )))
+            }
+        };
+        frame.expectations.push(section);
+    }
+    Ok(frames)
+}
+
+/// `loc=rocky, foe=none, hp=10` as values to set; `none` clears a query
+fn parse_state(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut state = Vec::new();
+    for pair in split_pairs(text) {
+        let (path, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected `query=value`, got `{}`", pair))?;
+        let value = match value.trim() {
+            "none" => Value::from(""),
+            value if value.starts_with('"') => Value::from(unquote(value)),
+            value => Value::from_text(value),
+        };
+        state.push((path.trim().to_string(), value));
+    }
+    Ok(state)
+}
+
+/// The comma-separated pairs of `text`, leaving commas in quotes alone
+fn split_pairs(text: &str) -> Vec<&str> {
+    let mut pairs = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                pairs.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pairs.push(&text[start..]);
+    pairs
+        .into_iter()
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .collect()
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+/// The script a test file tests: `Boss.txt` for `Boss.test.txt`
+pub fn script_for(test: &Path) -> PathBuf {
+    let name = test
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = name.strip_suffix(TEST_SUFFIX).unwrap_or(&name);
+    test.with_file_name(format!("{}.txt", stem))
+}
+
+/// A check that did not pass, or why the test could not run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The frame it happened on, if the test got that far
+    pub frame: Option<u64>,
+    pub message: String,
+    /// Unified diff from the expected lines to the actual ones
+    pub diff: String,
+}
+
+impl Failure {
+    fn new(frame: Option<u64>, message: String) -> Self {
+        Self {
+            frame,
+            message,
+            diff: String::new(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(frame) = self.frame {
+            write!(f, "frame {}: ", frame)?;
+        }
+        writeln!(f, "{}", self.message)?;
+        write!(f, "{}", self.diff)
+    }
+}
+
+/// How one test file went
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub test: PathBuf,
+    pub failures: Vec<Failure>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Runs test files, loading modules the way the language server does
+pub struct Runner {
+    workspace: Workspace,
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self {
+            workspace: Workspace::new(),
+        }
+    }
+
+    /// Folders module paths are relative to, before the script's own
+    /// folder
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        self.workspace.set_roots(roots);
+    }
+
+    pub fn run(&self, test: &Path) -> Outcome {
+        let failures = match std::fs::read_to_string(test) {
+            Ok(text) => self.run_text(test, &text),
+            Err(e) => vec![Failure::new(None, format!("could not read test: {}", e))],
+        };
+        Outcome {
+            test: test.to_path_buf(),
+            failures,
+        }
+    }
+
+    /// Run the frames of a test file's `text`; `test` locates the script
+    pub fn run_text(&self, test: &Path, text: &str) -> Vec<Failure> {
+        let frames = match parse(text) {
+            Ok(frames) => frames,
+            Err(e) => return vec![Failure::new(None, format!("invalid test: {}", e))],
+        };
+        let script = script_for(test);
+        let source = match std::fs::read_to_string(&script) {
+            Ok(source) => source,
+            Err(e) => {
+                let message = format!("could not read {}: {}", script.display(), e);
+                return vec![Failure::new(None, message)];
+            }
+        };
+        let program = match parse_source(&source) {
+            Ok(program) => program,
+            Err(e) => {
+                let message = format!("{} does not parse: {}", script.display(), e);
+                return vec![Failure::new(None, message)];
+            }
+        };
+
+        let mut host = ProjectHost::new(&self.workspace, &script);
+        let mut interpreter = Interpreter::new(program);
+        let mut failures = Vec::new();
+        for test in &frames {
+            loop {
+                let number = interpreter.frames() + 1;
+                host.advance(&frames, number);
+                let frame = match interpreter.run_frame(&mut host) {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        // Errors in imported modules already name the module
+                        let message = if e.module.is_empty() {
+                            format!("{}:{}", script.display(), e)
+                        } else {
+                            e.to_string()
+                        };
+                        failures.push(Failure::new(Some(number), message));
+                        return failures;
+                    }
+                };
+                if number < test.number {
+                    continue;
+                }
+
+                for expectation in &test.expectations {
+                    let (expected, actual) = match expectation {
+                        Expectation::Commands(expected) => (expected.clone(), frame.commands()),
+                        Expectation::Prints(expected) => (expected.clone(), frame.prints()),
+                        Expectation::Storage(entries) => {
+                            let line = |key: &str, value: &str| format!("{} = {}", key, value);
+                            let expected = entries.iter().map(|(k, v)| line(k, v)).collect();
+                            let actual = entries
+                                .iter()
+                                .map(|(key, _)| match interpreter.storage().get(key) {
+                                    Some(value) => line(key, &value.to_string()),
+                                    None => line(key, "none"),
+                                })
+                                .collect();
+                            (expected, actual)
+                        }
+                        Expectation::Screen(expected) => {
+                            let mut screen = Screen::new();
+                            if host.fixture.state("foe").is_some_and(|foe| foe.is_truthy()) {
+                                let (width, height) = (screen.width(), screen.height());
+                                screen.set_foe(Some((width as i64 * 3 / 4, height as i64 / 2)));
+                            }
+                            screen.render(&frame.events);
+                            let text = screen.to_text();
+                            let mut actual: Vec<String> = text.lines().map(String::from).collect();
+                            let mut expected = expected.clone();
+                            for rows in [&mut actual, &mut expected] {
+                                while rows.last().is_some_and(|row| row.is_empty()) {
+                                    rows.pop();
+                                }
+                            }
+                            (expected, actual)
+                        }
+                    };
+                    if expected != actual {
+                        failures.push(Failure {
+                            frame: Some(number),
+                            message: format!("{} differ (line {})", expectation.name(), test.line),
+                            diff: diff(&expected, &actual),
+                        });
+                    }
+                }
+                break;
+            }
+        }
+        failures
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A unified diff from the expected lines to the actual ones
+fn diff(expected: &[String], actual: &[String]) -> String {
+    let lines = |lines: &[String]| lines.iter().map(|line| format!("{}\n", line)).collect();
+    let (expected, actual): (String, String) = (lines(expected), lines(actual));
+    TextDiff::from_lines(&expected, &actual)
+        .unified_diff()
+        .header("expected", "actual")
+        .to_string()
+}
+
+/// The fixture a test sets up, with modules read from the project
+pub(crate) struct ProjectHost<'a> {
+    pub(crate) fixture: Fixture,
+    workspace: &'a Workspace,
+    document: Url,
+}
+
+impl<'a> ProjectHost<'a> {
+    /// A fresh fixture for `script`, whose imports `workspace` resolves
+    pub(crate) fn new(workspace: &'a Workspace, script: &Path) -> Self {
+        Self {
+            fixture: Fixture::new(),
+            workspace,
+            document: document_url(script),
+        }
+    }
+
+    /// Get the game ready for frame `number`: the clock moves on by one
+    /// frame, and the state a test gives that frame is set
+    pub(crate) fn advance(&mut self, frames: &[FrameTest], number: u64) {
+        if number > 1 {
+            for path in ["time", "totaltime"] {
+                let time = self.fixture.get(path).and_then(Value::as_i64).unwrap_or(0);
+                self.fixture.set(path, time + 1);
+            }
+        }
+        for test in frames.iter().filter(|test| test.number == number) {
+            for (path, value) in &test.state {
+                self.fixture.set(path, value.clone());
+            }
+        }
+    }
+}
+
+/// The URL the language server's workspace knows `script` by
+pub(crate) fn document_url(script: &Path) -> Url {
+    std::path::absolute(script)
+        .ok()
+        .and_then(|path| Url::from_file_path(path).ok())
+        .unwrap_or_else(|| Url::parse("file:///script.txt").unwrap())
+}
+
+impl Host for ProjectHost<'_> {
+    fn state(&self, path: &str) -> Option<Value> {
+        self.fixture.state(path)
+    }
+
+    fn matches(&self, path: &str, word: &str) -> bool {
+        self.fixture.matches(path, word)
+    }
+
+    fn call(&mut self, function: &str, args: &[Value]) -> Option<Value> {
+        self.fixture.call(function, args)
+    }
+
+    fn module(&self, path: &str) -> Option<String> {
+        let file = self.workspace.resolve_path(&self.document, path)?;
+        std::fs::read_to_string(file).ok()
+    }
+}
+
+/// Results in the style of `cargo test`: a line per test, then the
+/// failures with their diffs and a count
+pub fn report(outcomes: &[Outcome]) -> String {
+    let mut out = String::new();
+    for outcome in outcomes {
+        let status = if outcome.passed() { "ok" } else { "FAILED" };
+        out.push_str(&format!("test {} ... {}\n", outcome.test.display(), status));
+    }
+
+    let failed: Vec<&Outcome> = outcomes.iter().filter(|o| !o.passed()).collect();
+    if !failed.is_empty() {
+        out.push_str("\nfailures:\n");
+        for outcome in &failed {
+            out.push_str(&format!("\n---- {} ----\n", outcome.test.display()));
+            for failure in &outcome.failures {
+                out.push_str(&failure.to_string());
+            }
+        }
+    }
+
+    let result = if failed.is_empty() { "ok" } else { "FAILED" };
+    out.push_str(&format!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        result,
+        outcomes.len() - failed.len(),
+        failed.len()
+    ));
+    out
+}
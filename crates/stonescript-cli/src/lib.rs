@@ -0,0 +1,12 @@
+//! Command-line tools for StoneScript projects
+
+pub mod bundle;
+pub mod check;
+pub mod debug;
+mod edit;
+pub mod format;
+pub mod golden;
+pub mod minify;
+pub mod repl;
+pub mod report;
+pub mod scripts;
@@ -0,0 +1,401 @@
+//! Shrinking a script to fewer characters without changing what it does
+//!
+//! The script's tree is printed back without its comments and blank lines,
+//! with `^` continuations joined onto the line they continue and blocks
+//! indented by a single space per level of nesting. Names the script
+//! declares itself are shortened, the most used first. `ascii` art and
+//! strings are copied exactly as written.
+//!
+//! Only names the script is known to own are shortened: function locals
+//! whose every use resolves to a declaration, and script-level names
+//! unless the script imports modules, which share them. A script that
+//! other scripts import should be bundled before it is minified.
+
+use crate::edit::LineIndex;
+use std::collections::{HashMap, HashSet};
+use stonescript_lsp::data::native_functions::ALL_FUNCTIONS;
+use stonescript_lsp::data::{GAME_STATE_QUERIES, KEYWORDS};
+use stonescript_lsp::utils::ScopeAnalyzer;
+use stonescript_parser::ast::{Expression, Program, Span, Statement};
+use stonescript_parser::{
+    parse, walk_expression, walk_expression_mut, walk_statement, walk_statement_mut, Printer,
+    Visitor, VisitorMut,
+};
+
+/// Game-provided names the language server has no table for, which a
+/// shortened name must not take
+const RESERVED: &[&str] = &[
+    "ai", "ascii", "asciiend", "bighead", "draw", "event", "face", "false", "in", "int", "item",
+    "key", "player", "pos", "res", "rng", "rngf", "storage", "string", "summon", "sys", "te",
+    "true", "utc",
+];
+
+/// A minified script and the names shortened in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minified {
+    pub script: String,
+    /// Each shortened name and what it became, the most used first
+    pub renames: Vec<(String, String)>,
+}
+
+impl Minified {
+    /// How much smaller the script got than `source`
+    pub fn summary(&self, source: &str) -> String {
+        let before = source.chars().count();
+        let after = self.script.chars().count();
+        let saved = before.saturating_sub(after);
+        let percent = (saved * 100).checked_div(before).unwrap_or(0);
+        format!(
+            "Minified {} to {} characters ({}% smaller), {} names shortened\n",
+            before,
+            after,
+            percent,
+            self.renames.len()
+        )
+    }
+}
+
+/// Minifies scripts
+pub struct Minifier {
+    rename: bool,
+}
+
+impl Minifier {
+    pub fn new() -> Self {
+        Self { rename: true }
+    }
+
+    /// Whether to shorten the script's own names; on by default
+    pub fn set_rename(&mut self, rename: bool) {
+        self.rename = rename;
+    }
+
+    pub fn minify(&self, source: &str) -> Result<Minified, String> {
+        let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+        let crlf = source.contains("\r\n");
+        let source = source.replace("\r\n", "\n");
+
+        // Spans point into the text with continuations already joined
+        let (text, joins) = join_continuations(&source);
+        let mut program = parse(&text).map_err(|e| format!("the script does not parse: {}", e))?;
+        let mut survey = Survey::default();
+        survey.visit_program(&program);
+
+        let renames = if self.rename {
+            shorten_names(&program, &survey)
+        } else {
+            Vec::new()
+        };
+        let mut shrink = Shrink {
+            renames: renames.iter().cloned().collect(),
+        };
+        shrink.visit_program_mut(&mut program);
+
+        let mut written = program.clone();
+        let lines = LineIndex::new(&text);
+        let mut art = Art {
+            source: &source,
+            lines: &lines,
+            joins: &joins,
+        };
+        art.visit_program_mut(&mut written);
+
+        let mut printer = Printer::new();
+        printer.set_indent(1);
+        printer.set_line_width(None);
+        let mut script = printer.print(&written);
+        if !parse(&script).is_ok_and(|minified| minified.eq_ignoring_spans(&program)) {
+            return Err("the minified script would not do the same".to_string());
+        }
+        if crlf {
+            script = script.replace('\n', "\r\n");
+        }
+        Ok(Minified { script, renames })
+    }
+}
+
+impl Default for Minifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Join `^` lines onto the line above as the parser does, returning the
+/// joined text and, for each join, where in it bytes were taken out and
+/// how many
+fn join_continuations(source: &str) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::with_capacity(source.len());
+    let mut joins = Vec::new();
+    let mut after_comment = false;
+    let mut lines = source.lines().peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        match trimmed.strip_prefix('^') {
+            Some(rest) if !after_comment => {
+                let newline = usize::from(text.ends_with('\n'));
+                text.truncate(text.len() - newline);
+                joins.push((text.len(), newline + indent + 1));
+                text.push_str(rest);
+                after_comment = false;
+            }
+            // The game starts a new line after a comment, still without `^`
+            Some(rest) => {
+                joins.push((text.len(), indent + 1));
+                text.push_str(rest);
+                after_comment = rest.trim_start().starts_with("//");
+            }
+            None => {
+                text.push_str(line);
+                after_comment = trimmed.starts_with("//");
+            }
+        }
+        if lines.peek().is_some() || source.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    (text, joins)
+}
+
+/// Offset in the source of an offset in the joined text; `inclusive` counts
+/// bytes taken out right at the offset as before it
+fn original_offset(joins: &[(usize, usize)], offset: usize, inclusive: bool) -> usize {
+    offset
+        + joins
+            .iter()
+            .filter(|&&(at, _)| at < offset || (inclusive && at == offset))
+            .map(|&(_, removed)| removed)
+            .sum::<usize>()
+}
+
+/// Where a script declares and uses names, and what else it holds that
+/// minifying has to work around
+#[derive(Default)]
+struct Survey {
+    /// Every identifier written, declarations included
+    names: Vec<(String, Span)>,
+    /// Names declared outside functions
+    script_level: HashSet<String>,
+    /// Names declared inside functions, parameters included
+    local: HashSet<String>,
+    imports: bool,
+    function_depth: usize,
+}
+
+impl Survey {
+    fn declare(&mut self, name: &str, span: Span) {
+        if self.function_depth > 0 {
+            self.local.insert(name.to_string());
+        } else {
+            self.script_level.insert(name.to_string());
+        }
+        self.names.push((name.to_string(), span));
+    }
+}
+
+impl Visitor for Survey {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::FunctionDefinition {
+                name,
+                name_span,
+                params,
+                param_spans,
+                body,
+                ..
+            } => {
+                self.declare(name, *name_span);
+                self.function_depth += 1;
+                for (param, span) in params.iter().zip(param_spans) {
+                    self.declare(param, *span);
+                }
+                self.visit_block(body);
+                self.function_depth -= 1;
+                return;
+            }
+            Statement::VarDeclaration {
+                name, name_span, ..
+            } => self.declare(name, *name_span),
+            Statement::For {
+                variable,
+                variable_span,
+                ..
+            }
+            | Statement::ForIn {
+                variable,
+                variable_span,
+                ..
+            } => self.declare(variable, *variable_span),
+            Statement::Import { .. } => self.imports = true,
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name, span) = expression {
+            self.names.push((name.clone(), *span));
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// Drops comments and blank lines and shortens names
+struct Shrink {
+    renames: HashMap<String, String>,
+}
+
+impl Shrink {
+    fn rename(&self, name: &mut String) {
+        if let Some(short) = self.renames.get(name.as_str()) {
+            *name = short.clone();
+        }
+    }
+}
+
+impl VisitorMut for Shrink {
+    fn visit_block_mut(&mut self, statements: &mut Vec<Statement>) {
+        statements
+            .retain(|statement| !matches!(statement, Statement::Comment(..) | Statement::Empty));
+        for statement in statements {
+            self.visit_statement_mut(statement);
+        }
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::FunctionDefinition { name, params, .. } => {
+                self.rename(name);
+                for param in params {
+                    self.rename(param);
+                }
+            }
+            Statement::VarDeclaration { name, .. }
+            | Statement::For { variable: name, .. }
+            | Statement::ForIn { variable: name, .. } => self.rename(name),
+            _ => {}
+        }
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if let Expression::Identifier(name, _) = expression {
+            self.rename(name);
+        }
+        walk_expression_mut(self, expression);
+    }
+}
+
+/// Puts back the `^` lines of `ascii` art that the parser joined, so that
+/// the art is printed as written
+struct Art<'a> {
+    source: &'a str,
+    /// Lines of the source with continuations joined
+    lines: &'a LineIndex<'a>,
+    joins: &'a [(usize, usize)],
+}
+
+impl VisitorMut for Art<'_> {
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if let Expression::String(art, span) = expression {
+            let start = original_offset(self.joins, self.lines.offset(span.start), true);
+            let end = original_offset(self.joins, self.lines.offset(span.end), false);
+            let written = self.source[start..end]
+                .strip_prefix("ascii\n")
+                .and_then(|written| written.strip_suffix("asciiend"));
+            if let Some(written) = written {
+                *art = written.to_string();
+            }
+        }
+        walk_expression_mut(self, expression);
+    }
+}
+
+/// Pick short names for the names the script owns, most used first
+fn shorten_names(program: &Program, survey: &Survey) -> Vec<(String, String)> {
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(program);
+
+    let mut builtin: HashSet<String> = RESERVED.iter().map(|name| name.to_string()).collect();
+    builtin.extend(KEYWORDS.iter().map(|keyword| keyword.name.to_lowercase()));
+    builtin.extend(
+        GAME_STATE_QUERIES
+            .iter()
+            .map(|query| query.name.to_lowercase()),
+    );
+    for function in ALL_FUNCTIONS.iter().flat_map(|functions| functions.iter()) {
+        builtin.insert(function.namespace.to_lowercase());
+        builtin.insert(function.name.to_lowercase());
+    }
+    let mut taken = builtin.clone();
+    taken.extend(survey.names.iter().map(|(name, _)| name.to_lowercase()));
+
+    let mut uses: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (index, (name, _)) in survey.names.iter().enumerate() {
+        uses.entry(name.as_str()).or_insert((0, index)).0 += 1;
+    }
+    let owned = |name: &str| {
+        if builtin.contains(&name.to_lowercase()) {
+            return false;
+        }
+        if survey.script_level.contains(name) {
+            return !survey.imports;
+        }
+        survey.local.contains(name)
+            && survey
+                .names
+                .iter()
+                .filter(|(used, _)| used == name)
+                .all(|(_, span)| {
+                    scope
+                        .find_variable_at(name, span.start)
+                        .is_some_and(|variable| variable.definition_span.is_some())
+                })
+    };
+    let mut names: Vec<(&str, (usize, usize))> =
+        uses.into_iter().filter(|(name, _)| owned(name)).collect();
+    names.sort_by_key(|&(_, (count, first))| (std::cmp::Reverse(count), first));
+
+    let mut fresh = ShortNames::default();
+    let mut renames = Vec::new();
+    for (name, _) in names {
+        let short = loop {
+            let candidate = fresh.peek();
+            if !taken.contains(&candidate) {
+                break candidate;
+            }
+            fresh.advance();
+        };
+        if short.len() < name.len() {
+            fresh.advance();
+            renames.push((name.to_string(), short));
+        }
+    }
+    renames
+}
+
+/// `a` to `z`, then `aa` to `zz`, and so on
+#[derive(Default)]
+struct ShortNames {
+    next: usize,
+}
+
+impl ShortNames {
+    fn peek(&self) -> String {
+        let mut n = self.next;
+        let mut name = Vec::new();
+        loop {
+            name.push(b'a' + (n % 26) as u8);
+            if n < 26 {
+                break;
+            }
+            n = n / 26 - 1;
+        }
+        name.reverse();
+        String::from_utf8(name).unwrap_or_default()
+    }
+
+    fn advance(&mut self) {
+        self.next += 1;
+    }
+}
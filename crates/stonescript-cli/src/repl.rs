@@ -0,0 +1,277 @@
+//! An interactive session for trying StoneScript without the game
+//!
+//! Code typed at the prompt runs as part of one script that lives as long
+//! as the session, so variables and functions stay defined. Lines starting
+//! with `:` and a name are session commands, such as `:set hp 5`.
+
+use std::fmt::Write;
+use stonescript_interpreter::{Fixture, Interpreter, Value};
+use stonescript_lsp::data::game_state::get_properties;
+use stonescript_lsp::data::native_functions::GLOBAL_FUNCTIONS;
+use stonescript_lsp::data::{
+    get_functions_in_namespace, ARRAY_METHODS, FOES, GAME_STATE_QUERIES, KEYWORDS, LOCATIONS,
+};
+use stonescript_parser::parse_source;
+
+/// What `:help` shows
+pub const HELP: &str = "\
+Type StoneScript to run it; a block such as `?hp < 5` or `func F()` ends
+at an empty line. Prints, commands and the value of a final expression are
+shown after each entry.
+
+:set QUERY VALUE   set game state, e.g. `:set hp 5`, `:set foe none`
+:unset QUERY       make a game state query have no value
+:vars              list the script's variables and functions
+:storage           list what `storage.Set` has saved
+:reset             forget variables, functions and storage
+:help              show this help
+:quit              leave
+";
+
+/// Session commands, as completed after `:`
+const COMMANDS: &[&str] = &["help", "quit", "reset", "set", "storage", "unset", "vars"];
+
+/// Native namespaces whose functions complete after a `.`
+const NAMESPACES: &[&str] = &["math", "music", "storage", "string", "ui"];
+
+/// A script being written one entry at a time, and the game state it sees
+pub struct Session {
+    interpreter: Interpreter,
+    fixture: Fixture,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            interpreter: empty_script(),
+            fixture: Fixture::new(),
+        }
+    }
+
+    pub fn fixture(&self) -> &Fixture {
+        &self.fixture
+    }
+
+    /// Whether `input` is a session command rather than code; a lone `:`
+    /// or `:?` is the start of an else branch
+    pub fn is_command(input: &str) -> bool {
+        input
+            .trim_start()
+            .strip_prefix(':')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    /// Run code or a session command, returning what to show
+    pub fn run(&mut self, input: &str) -> Result<String, String> {
+        if Self::is_command(input) {
+            self.command(input.trim())
+        } else {
+            self.eval(input)
+        }
+    }
+
+    /// Run code as the next part of the script
+    pub fn eval(&mut self, source: &str) -> Result<String, String> {
+        let program = parse_source(source).map_err(|e| format!("parse error: {}", e))?;
+        let (frame, value) = self
+            .interpreter
+            .run_snippet(program, &mut self.fixture)
+            .map_err(|e| e.to_string())?;
+
+        let mut out = String::new();
+        for event in &frame.events {
+            let _ = writeln!(out, "{}", event);
+        }
+        match value {
+            None | Some(Value::Null) => {}
+            Some(value) => {
+                let _ = writeln!(out, "= {}", show(&value));
+            }
+        }
+        Ok(out)
+    }
+
+    fn command(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line[1..].splitn(3, char::is_whitespace);
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.map(str::trim).filter(|w| !w.is_empty()).collect();
+        match (name, args.as_slice()) {
+            ("set", [path, value]) => {
+                let value = match *value {
+                    "none" => Value::from(""),
+                    value if value.starts_with('"') && value.ends_with('"') && value.len() > 1 => {
+                        Value::from(&value[1..value.len() - 1])
+                    }
+                    value => Value::from_text(value),
+                };
+                self.fixture.set(path, value);
+                Ok(String::new())
+            }
+            ("set", _) => Err("usage: :set QUERY VALUE".to_string()),
+            ("unset", [path]) => {
+                self.fixture.remove(path);
+                Ok(String::new())
+            }
+            ("unset", _) => Err("usage: :unset QUERY".to_string()),
+            ("vars", []) => {
+                let main = self.interpreter.main().borrow();
+                let mut out = String::new();
+                for (name, value) in &main.fields {
+                    let _ = writeln!(out, "var {} = {}", name, show(value));
+                }
+                for function in main.functions.values() {
+                    let _ = writeln!(
+                        out,
+                        "func {}({})",
+                        function.name,
+                        function.params.join(", ")
+                    );
+                }
+                Ok(out)
+            }
+            ("storage", []) => {
+                let mut out = String::new();
+                for (key, value) in self.interpreter.storage() {
+                    let _ = writeln!(out, "{} = {}", key, show(value));
+                }
+                Ok(out)
+            }
+            ("reset", []) => {
+                self.interpreter = empty_script();
+                Ok(String::new())
+            }
+            ("help", []) => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command `{}`; see :help", line)),
+        }
+    }
+
+    /// Names the script has defined, for completion
+    pub fn names(&self) -> Vec<String> {
+        let main = self.interpreter.main().borrow();
+        main.fields
+            .keys()
+            .chain(main.functions.keys())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An interpreter for a script with nothing in it yet
+fn empty_script() -> Interpreter {
+    Interpreter::new(parse_source("").expect("an empty script parses"))
+}
+
+/// A value as the REPL shows it, with strings quoted
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    }
+}
+
+/// Whether `input` needs more lines: `ascii` art runs to its `asciiend`,
+/// and a condition, function or loop to an empty line
+pub fn needs_more(input: &str) -> bool {
+    let ascii = input.lines().any(|line| line.trim_end().ends_with("ascii"))
+        && !input
+            .lines()
+            .any(|line| line.trim_start().starts_with("asciiend"));
+    let first = input.lines().next().unwrap_or_default().trim();
+    let word = first.split_whitespace().next().unwrap_or_default();
+    let opens = first.starts_with('?') || matches!(word, "func" | "for");
+    ascii || opens && !input.ends_with("\n\n")
+}
+
+/// Completions for the word before `pos` in `line`: the position the word
+/// starts at, and what it could be. `names` are the script's own names.
+pub fn complete(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '.')
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let word = &before[start..];
+    let context = before[..start].trim_start();
+
+    let mut candidates: Vec<String> = Vec::new();
+    if context == ":" {
+        candidates.extend(COMMANDS.iter().map(|c| c.to_string()));
+    } else if let Some(command) = context.strip_prefix(':') {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        match args.as_slice() {
+            ["set" | "unset"] => candidates.extend(game_state_paths()),
+            ["set", "loc"] => candidates.extend(LOCATIONS.iter().map(|s| s.to_string())),
+            ["set", "foe"] => candidates.extend(FOES.iter().map(|s| s.to_string())),
+            _ => {}
+        }
+    } else if let Some(query) = ["loc", "foe"].into_iter().find(|query| {
+        context
+            .trim_end()
+            .strip_suffix(['=', '!'])
+            .is_some_and(|rest| rest.trim_end().ends_with(query))
+    }) {
+        let ids = if query == "loc" { LOCATIONS } else { FOES };
+        candidates.extend(ids.iter().map(|s| s.to_string()));
+    } else if let Some((object, _)) = word.rsplit_once('.') {
+        let members: Vec<String> = if NAMESPACES.contains(&object) {
+            get_functions_in_namespace(object)
+                .iter()
+                .map(|f| f.name.to_string())
+                .collect()
+        } else if let Some(properties) = get_properties(object) {
+            properties.iter().map(|p| p.name.to_string()).collect()
+        } else if names.iter().any(|name| name == object) {
+            ARRAY_METHODS.iter().map(|f| f.name.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        candidates.extend(members.into_iter().map(|m| format!("{}.{}", object, m)));
+    } else {
+        let keywords = KEYWORDS
+            .iter()
+            .map(|k| k.name)
+            .filter(|name| name.starts_with(|c: char| c.is_alphabetic()));
+        let queries = GAME_STATE_QUERIES.iter().map(|q| q.name);
+        let functions = GLOBAL_FUNCTIONS.iter().map(|f| f.name);
+        candidates.extend(
+            keywords
+                .chain(queries)
+                .chain(NAMESPACES.iter().copied())
+                .chain(functions)
+                .map(str::to_string)
+                .chain(names.iter().cloned()),
+        );
+    }
+
+    let prefix = word.to_lowercase();
+    let mut found: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.to_lowercase().starts_with(&prefix))
+        .collect();
+    found.sort();
+    found.dedup();
+    (start, found)
+}
+
+/// Every game state query and its properties, such as `loc` and `loc.stars`
+fn game_state_paths() -> Vec<String> {
+    GAME_STATE_QUERIES
+        .iter()
+        .flat_map(|query| {
+            let properties = query.properties.unwrap_or_default();
+            std::iter::once(query.name.to_string()).chain(
+                properties
+                    .iter()
+                    .map(move |property| format!("{}.{}", query.name, property.name)),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,221 @@
+//! Rendering check results for people, scripts and code scanning tools
+
+use crate::check::{severity, FileReport};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Tabs are drawn this wide in source snippets
+const TAB_WIDTH: usize = 4;
+
+/// rustc-style reports with the offending line underlined
+pub fn human(reports: &[FileReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        let lines: Vec<&str> = report.source.lines().collect();
+        for diagnostic in &report.diagnostics {
+            write_diagnostic(&mut out, report, &lines, diagnostic);
+        }
+    }
+
+    let errors: usize = reports
+        .iter()
+        .map(|report| report.count(DiagnosticSeverity::ERROR))
+        .sum();
+    let warnings: usize = reports
+        .iter()
+        .map(|report| report.count(DiagnosticSeverity::WARNING))
+        .sum();
+    let _ = writeln!(
+        out,
+        "Checked {}: {}, {}",
+        plural(reports.len(), "file"),
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    );
+    out
+}
+
+fn write_diagnostic(
+    out: &mut String,
+    report: &FileReport,
+    lines: &[&str],
+    diagnostic: &Diagnostic,
+) {
+    let start = diagnostic.range.start;
+    let end = diagnostic.range.end;
+    let line_number = (start.line + 1).to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    let _ = write!(out, "{}", label(severity(diagnostic)));
+    if let Some(code) = code(diagnostic) {
+        let _ = write!(out, "[{}]", code);
+    }
+    let _ = writeln!(out, ": {}", diagnostic.message);
+    let _ = writeln!(
+        out,
+        "{}--> {}:{}:{}",
+        gutter,
+        report.path.display(),
+        start.line + 1,
+        start.character + 1
+    );
+
+    let Some(line) = lines.get(start.line as usize) else {
+        out.push('\n');
+        return;
+    };
+    // Past the end of the first line, underline the rest of it
+    let line_length = line.chars().count() as u32;
+    let underline_end = if end.line == start.line {
+        end.character.min(line_length)
+    } else {
+        line_length
+    };
+    let column = display_width(line.chars().take(start.character as usize));
+    let width = display_width(
+        line.chars()
+            .skip(start.character as usize)
+            .take(underline_end.saturating_sub(start.character) as usize),
+    );
+
+    let _ = writeln!(out, "{} |", gutter);
+    let _ = writeln!(
+        out,
+        "{} | {}",
+        line_number,
+        line.replace('\t', &" ".repeat(TAB_WIDTH))
+    );
+    let _ = writeln!(
+        out,
+        "{} | {}{}",
+        gutter,
+        " ".repeat(column),
+        "^".repeat(width.max(1))
+    );
+    out.push('\n');
+}
+
+fn display_width(chars: impl Iterator<Item = char>) -> usize {
+    chars.map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+fn label(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "info",
+        _ => "hint",
+    }
+}
+
+fn code(diagnostic: &Diagnostic) -> Option<String> {
+    match diagnostic.code.as_ref()? {
+        NumberOrString::String(code) => Some(code.clone()),
+        NumberOrString::Number(code) => Some(code.to_string()),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// One diagnostic in `--format json`, with 1-based lines and columns
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonDiagnostic {
+    file: String,
+    line: u32,
+    column: u32,
+    end_line: u32,
+    end_column: u32,
+    severity: &'static str,
+    code: Option<String>,
+    message: String,
+}
+
+/// Every diagnostic as a flat JSON array
+pub fn json(reports: &[FileReport]) -> Value {
+    let diagnostics: Vec<JsonDiagnostic> = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| JsonDiagnostic {
+                file: file_name(report),
+                line: diagnostic.range.start.line + 1,
+                column: diagnostic.range.start.character + 1,
+                end_line: diagnostic.range.end.line + 1,
+                end_column: diagnostic.range.end.character + 1,
+                severity: label(severity(diagnostic)),
+                code: code(diagnostic),
+                message: diagnostic.message.clone(),
+            })
+        })
+        .collect();
+    serde_json::to_value(diagnostics).unwrap_or(Value::Null)
+}
+
+/// A SARIF 2.1.0 log, as read by code scanning services
+pub fn sarif(reports: &[FileReport]) -> Value {
+    let rules: BTreeSet<String> = reports
+        .iter()
+        .flat_map(|report| report.diagnostics.iter().filter_map(code))
+        .collect();
+    let results: Vec<Value> = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| {
+                let range = diagnostic.range;
+                json!({
+                    "ruleId": code(diagnostic),
+                    "level": sarif_level(severity(diagnostic)),
+                    "message": { "text": diagnostic.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": file_name(report) },
+                            "region": {
+                                "startLine": range.start.line + 1,
+                                "startColumn": range.start.character + 1,
+                                "endLine": range.end.line + 1,
+                                "endColumn": range.end.character + 1,
+                            }
+                        }
+                    }]
+                })
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "stonescript-check",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn sarif_level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        _ => "note",
+    }
+}
+
+/// The script's path with `/` separators, as SARIF URIs expect
+fn file_name(report: &FileReport) -> String {
+    report.path.to_string_lossy().replace('\\', "/")
+}
@@ -0,0 +1,60 @@
+//! Finding the scripts a command was pointed at
+
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Ending of the golden-frame test files that sit next to scripts, which
+/// are not scripts themselves
+pub const TEST_SUFFIX: &str = ".test.txt";
+
+/// Scripts named on the command line: files as given, and every `.txt`
+/// file below a directory, skipping hidden folders such as `.git`
+pub fn collect_scripts(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    collect(paths, is_script)
+}
+
+/// Test files named on the command line: files as given, and every
+/// `.test.txt` file below a directory
+pub fn collect_tests(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    collect(paths, is_test)
+}
+
+fn collect(paths: &[PathBuf], wanted: fn(&Path) -> bool) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            // Reading it reports a missing file
+            found.push(path.clone());
+            continue;
+        }
+
+        let walker = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()));
+        for entry in walker {
+            let entry = entry?;
+            if entry.file_type().is_file() && wanted(entry.path()) {
+                found.push(entry.into_path());
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("txt") && !is_test(path)
+}
+
+fn is_test(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(TEST_SUFFIX))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
@@ -0,0 +1,196 @@
+//! Test bundling projects into one script
+
+use std::path::Path;
+use std::process::Command;
+use stonescript_cli::bundle::{Bundle, BundleError, Bundler, ModuleSize};
+use stonescript_parser::parse_source;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bundle");
+
+fn bundle(entry: &str) -> Result<Bundle, BundleError> {
+    Bundler::new().bundle(&Path::new(FIXTURES).join(entry))
+}
+
+#[test]
+fn test_imports_and_objects() {
+    let bundle = bundle("Main.txt").unwrap();
+    assert_eq!(
+        bundle.script,
+        "\
+// Draws two points and a menu
+// UI/Menu
+// UI/Colors
+var color = \"#red\"
+
+var Menu_x = 3
+
+func Draw()
+  >o@Menu_x@,0,@color@Menu
+
+// var a = new Components/Vector
+// Vector
+var a_x = 0
+var a_y = 0
+
+func a_Init(_x, _y)
+  a_x = _x
+  a_y = _y
+
+func a_ToString()
+  return \"[\" + a_x + \", \" + a_y + \"]\"
+// var b = new Components/Vector
+// Vector
+var b_x = 0
+var b_y = 0
+
+func b_Init(_x, _y)
+  b_x = _x
+  b_y = _y
+
+func b_ToString()
+  return \"[\" + b_x + \", \" + b_y + \"]\"
+var x = 1
+
+?loc.begin
+  a_Init(1, 2)
+  b_Init(x, 5)
+>@a_ToString()@ @b_x@
+Draw()
+"
+    );
+    assert!(parse_source(&bundle.script).is_ok());
+}
+
+#[test]
+fn test_object_statements_run_once() {
+    let bundle = bundle("Counters.txt").unwrap();
+    assert_eq!(
+        bundle.script,
+        "\
+// var c = new UI/Counter
+var c_init = true
+var c_count = 0
+?c_init
+  c_count++
+  >Created
+  c_init = false
+?c_count > 0
+  c_count = c_count + 1
+"
+    );
+}
+
+#[test]
+fn test_nested_import_is_indented() {
+    let dir = std::env::temp_dir().join(format!("stonescript-bundle-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("UI")).unwrap();
+    std::fs::write(dir.join("Main.txt"), "?loc.begin\n  import UI/Art\n").unwrap();
+    std::fs::write(
+        dir.join("UI/Art.txt"),
+        "var art = ascii\n/\\\n\\/\nasciiend\n>`0,0,@art@\n",
+    )
+    .unwrap();
+
+    let bundle = Bundler::new().bundle(&dir.join("Main.txt")).unwrap();
+    assert_eq!(
+        bundle.script,
+        "?loc.begin\n  // UI/Art\n  var art = ascii\n/\\\n\\/\nasciiend\n  >`0,0,@art@\n"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_errors() {
+    // The scripts in `Errors` use modules from the folder above
+    let bundler = Bundler::new();
+    bundler.set_roots(vec![FIXTURES.into()]);
+    let error = |entry: &str| {
+        let e = bundler
+            .bundle(&Path::new(FIXTURES).join(entry))
+            .unwrap_err();
+        let position = e.position.map(|p| (p.line + 1, p.column + 1));
+        (
+            e.path.file_name().unwrap().to_str().unwrap().to_string(),
+            position,
+            e.message,
+        )
+    };
+
+    assert_eq!(
+        error("Errors/Missing.txt"),
+        (
+            "Missing.txt".to_string(),
+            Some((1, 1)),
+            "cannot find module `UI/Nowhere`".to_string()
+        )
+    );
+    assert_eq!(
+        error("Errors/Passed.txt").1,
+        Some((2, 12)),
+        "an object can only be used through its members"
+    );
+    assert_eq!(
+        error("Errors/InLoop.txt").2,
+        "`new` can only be bundled as the initializer of a `var` outside functions and loops"
+    );
+}
+
+#[test]
+fn test_summary() {
+    let bundle = bundle("Main.txt").unwrap();
+    let vector = bundle
+        .modules
+        .iter()
+        .find(|module| module.name == "Components/Vector")
+        .unwrap();
+    assert_eq!(vector.copies, 2);
+
+    let total: usize = bundle.modules.iter().map(|module| module.characters).sum();
+    assert_eq!(total, bundle.script.chars().count());
+
+    let summary = Bundle {
+        script: "?a\n  >b\n".to_string(),
+        modules: vec![
+            ModuleSize {
+                name: "Main".to_string(),
+                copies: 1,
+                characters: 5,
+            },
+            ModuleSize {
+                name: "UI/Vector".to_string(),
+                copies: 2,
+                characters: 3,
+            },
+        ],
+    }
+    .summary();
+    assert_eq!(
+        summary,
+        "Bundled 2 modules: 8 characters, 2 lines\n  Main                 5\n  UI/Vector (x2)       3\n"
+    );
+}
+
+#[test]
+fn test_bundle_command() {
+    let output =
+        std::env::temp_dir().join(format!("stonescript-bundle-{}.txt", std::process::id()));
+    let run = |entry: &str| {
+        Command::new(env!("CARGO_BIN_EXE_stonescript"))
+            .args(["bundle", entry, "-o", output.to_str().unwrap()])
+            .output()
+            .unwrap()
+    };
+
+    let result = run(&format!("{}/Counters.txt", FIXTURES));
+    assert_eq!(result.status.code(), Some(0));
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .starts_with("Bundled 2 modules"));
+    assert!(std::fs::read_to_string(&output)
+        .unwrap()
+        .starts_with("// var c = new UI/Counter\n"));
+
+    let result = run(&format!("{}/Errors/Missing.txt", FIXTURES));
+    assert_eq!(result.status.code(), Some(1));
+    std::fs::remove_file(output).unwrap();
+}
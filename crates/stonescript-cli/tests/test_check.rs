@@ -0,0 +1,92 @@
+//! Test stonescript-check's reports and exit status
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stonescript_cli::check::{Checker, FileReport};
+use stonescript_cli::report;
+use stonescript_cli::scripts::collect_scripts;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/check");
+
+fn check(source: &str) -> FileReport {
+    Checker::new().check_source(Path::new("Scripts/Low.txt"), source.to_string())
+}
+
+#[test]
+fn test_human_report() {
+    let report = check("?hp < 5\n\t?loc = deadwod\n\t\t>low\n");
+    assert_eq!(
+        report::human(&[report]),
+        "\
+warning[unknown-game-id]: Unknown location `deadwod`
+ --> Scripts/Low.txt:2:9
+  |
+2 |     ?loc = deadwod
+  |            ^^^^^^^
+
+Checked 1 file: 0 errors, 1 warning
+"
+    );
+}
+
+#[test]
+fn test_json_report() {
+    let json = report::json(&[check("equip sword *12\n")]);
+    let diagnostic = &json[0];
+    assert_eq!(diagnostic["file"], "Scripts/Low.txt");
+    assert_eq!(diagnostic["code"], "invalid-command-argument");
+    assert_eq!(diagnostic["severity"], "warning");
+    assert_eq!(diagnostic["line"], 1);
+    assert_eq!(diagnostic["column"], 13);
+    assert_eq!(diagnostic["endColumn"], 16);
+}
+
+#[test]
+fn test_sarif_report() {
+    let sarif = report::sarif(&[check("?loc = deadwod\n  >a\n")]);
+    assert_eq!(sarif["version"], "2.1.0");
+
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "stonescript-check");
+    assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "unknown-game-id");
+
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "unknown-game-id");
+    assert_eq!(result["level"], "warning");
+    let region = &result["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(region["startLine"], 1);
+    assert_eq!(region["startColumn"], 8);
+}
+
+#[test]
+fn test_collect_scripts() {
+    let scripts = collect_scripts(&[PathBuf::from(FIXTURES)]).unwrap();
+    let names: Vec<_> = scripts
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Clean.txt", "Typos.txt"]);
+}
+
+#[test]
+fn test_exit_status() {
+    let status = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_stonescript-check"))
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let clean = format!("{}/Clean.txt", FIXTURES);
+
+    // Warnings alone pass unless denied
+    assert_eq!(status(&[FIXTURES]), Some(0));
+    assert_eq!(status(&["--deny-warnings", FIXTURES]), Some(1));
+    assert_eq!(status(&["--deny-warnings", &clean]), Some(0));
+    assert_eq!(
+        status(&["--severity", "unknown-game-id=error", FIXTURES]),
+        Some(1)
+    );
+    assert_eq!(status(&["--format", "sarif", FIXTURES]), Some(0));
+}
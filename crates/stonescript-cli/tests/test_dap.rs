@@ -0,0 +1,257 @@
+//! Test stonescript-dap's message framing and a debugging session
+
+use serde_json::{json, Value as Json};
+use std::io::{BufReader, Cursor};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use stonescript_cli::debug::{read_message, write_message};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/debug");
+
+/// An editor talking to the adapter over its standard input and output
+struct Client {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    seq: i64,
+    events: Vec<Json>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_stonescript-dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Self {
+            input: child.stdin.take().unwrap(),
+            output: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+            events: Vec::new(),
+        }
+    }
+
+    fn receive(&mut self) -> Json {
+        read_message(&mut self.output)
+            .unwrap()
+            .expect("the adapter ended the session")
+    }
+
+    /// Send a request and return its response
+    fn send(&mut self, command: &str, arguments: Json) -> Json {
+        self.seq += 1;
+        let request = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        write_message(&mut self.input, &request).unwrap();
+        loop {
+            let message = self.receive();
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// Send a request that should succeed and return its response's body
+    fn request(&mut self, command: &str, arguments: Json) -> Json {
+        let response = self.send(command, arguments);
+        assert_eq!(response["success"], true, "{}", response);
+        response["body"].clone()
+    }
+
+    /// Wait for an event and return its body
+    fn event(&mut self, name: &str) -> Json {
+        if let Some(i) = self.events.iter().position(|e| e["event"] == name) {
+            return self.events.remove(i)["body"].clone();
+        }
+        loop {
+            let message = self.receive();
+            if message["event"] == name {
+                return message["body"].clone();
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// Wait for the script to pause; returns the reason and, for the
+    /// innermost frame, its name, file and line
+    fn stopped(&mut self) -> (String, String, String, u64) {
+        let reason = self.event("stopped")["reason"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        let top = &trace["stackFrames"][0];
+        (
+            reason,
+            top["name"].as_str().unwrap().to_string(),
+            top["source"]["name"].as_str().unwrap().to_string(),
+            top["line"].as_u64().unwrap(),
+        )
+    }
+
+    /// Name and value of each variable behind a reference
+    fn variables(&mut self, reference: &Json) -> Vec<(String, String)> {
+        let body = self.request("variables", json!({ "variablesReference": reference }));
+        body["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                let name = v["name"].as_str().unwrap().to_string();
+                (name, v["value"].as_str().unwrap().to_string())
+            })
+            .collect()
+    }
+}
+
+fn pair(name: &str, value: &str) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
+
+#[test]
+fn test_framing() {
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &json!({ "seq": 1 })).unwrap();
+    write_message(&mut buffer, &json!({ "seq": 2 })).unwrap();
+    assert!(buffer.starts_with(b"Content-Length: 9\r\n\r\n{\"seq\":1}"));
+
+    let mut input = Cursor::new(buffer);
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "seq": 1 })));
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "seq": 2 })));
+    assert_eq!(read_message(&mut input).unwrap(), None);
+}
+
+#[test]
+fn test_session() {
+    let main = format!("{}/Main.txt", FIXTURES);
+    let mut client = Client::start();
+    client.request("initialize", json!({ "adapterID": "stonescript" }));
+    client.event("initialized");
+
+    // Breakpoints move down to the next statement, past the comment
+    let set = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": main }, "breakpoints": [{ "line": 1 }] }),
+    );
+    assert_eq!(set["breakpoints"][0]["line"], 2);
+    let set = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": main }, "breakpoints": [{ "line": 6 }, { "line": 50 }] }),
+    );
+    assert_eq!(
+        set["breakpoints"][0],
+        json!({ "verified": true, "line": 6 })
+    );
+    assert_eq!(set["breakpoints"][1]["verified"], false);
+
+    client.request("launch", json!({ "program": main, "stopOnEntry": true }));
+    client.request("configurationDone", json!({}));
+    assert_eq!(
+        client.stopped(),
+        (
+            "entry".into(),
+            "Main.txt (frame 1)".into(),
+            "Main.txt".into(),
+            2
+        )
+    );
+
+    // Into the imported module and back out of it
+    client.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(
+        client.stopped(),
+        ("step".into(), "Counter".into(), "Counter.txt".into(), 1)
+    );
+    client.request("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped().3, 3);
+
+    // Over `new` and the function definition
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped().3, 4);
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped().3, 5);
+
+    // The foe turns up in frame 2, which calls the function
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(
+        client.stopped(),
+        ("breakpoint".into(), "Hit".into(), "Main.txt".into(), 6)
+    );
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["stackFrames"][1]["name"], "Main.txt (frame 2)");
+    assert_eq!(trace["stackFrames"][1]["line"], 9);
+
+    let scopes = client.request(
+        "scopes",
+        json!({ "frameId": trace["stackFrames"][0]["id"] }),
+    );
+    let scopes = scopes["scopes"].as_array().unwrap().clone();
+    assert_eq!(scopes[0]["name"], "Locals");
+    assert_eq!(
+        client.variables(&scopes[0]["variablesReference"]),
+        [pair("amount", "2")]
+    );
+    assert_eq!(scopes[1]["name"], "Globals");
+    let globals = client.request(
+        "variables",
+        json!({ "variablesReference": scopes[1]["variablesReference"] }),
+    );
+    let globals = globals["variables"].as_array().unwrap().clone();
+    assert_eq!(globals[0]["name"], "hits");
+    assert_eq!(globals[1]["name"], "v");
+    assert_eq!(
+        client.variables(&globals[1]["variablesReference"]),
+        [pair("x", "1"), pair("y", "2")]
+    );
+
+    client.request("nextFrame", json!({ "threadId": 1 }));
+    assert_eq!(
+        client.stopped(),
+        (
+            "frame".into(),
+            "Main.txt (frame 3)".into(),
+            "Main.txt".into(),
+            2
+        )
+    );
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped().0, "breakpoint");
+    client.request("continue", json!({ "threadId": 1 }));
+    client.event("terminated");
+
+    let prints: Vec<Json> = client
+        .events
+        .iter()
+        .filter(|e| e["event"] == "output")
+        .map(|e| e["body"]["output"].clone())
+        .collect();
+    assert!(prints.contains(&json!(">hits 2\n")), "{:?}", prints);
+
+    client.request("disconnect", json!({}));
+    drop(client.input);
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn test_launch_errors() {
+    let mut client = Client::start();
+    client.request("initialize", json!({}));
+    let response = client.send(
+        "launch",
+        json!({ "program": format!("{}/Missing.txt", FIXTURES) }),
+    );
+    assert_eq!(response["success"], false);
+    assert!(response["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("could not read"));
+
+    client.request("disconnect", json!({}));
+    assert!(client.child.wait().unwrap().success());
+}
@@ -0,0 +1,95 @@
+//! Test stonescript-fmt's config lookup, diffs and exit status
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use stonescript_cli::format::{self, find_config, load_settings, Formatter};
+use stonescript_lsp::config::IndentStyle;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fmt");
+
+/// A scratch copy of the fixtures, so files can be formatted in place
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stonescript-fmt-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("Tabs")).unwrap();
+    for file in ["Messy.txt", "Tabs/stonescript.json", "Tabs/Messy.txt"] {
+        std::fs::copy(format!("{}/{}", FIXTURES, file), dir.join(file)).unwrap();
+    }
+    dir
+}
+
+fn fmt(args: &[&str], stdin: &str) -> (Option<i32>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stonescript-fmt"))
+        .args(args)
+        .current_dir(FIXTURES)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_project_config() {
+    let tabs = PathBuf::from(FIXTURES).join("Tabs");
+    let config = find_config(&tabs).unwrap();
+    assert_eq!(config, tabs.join("stonescript.json"));
+
+    let settings = load_settings(&config).unwrap();
+    assert_eq!(settings.indent_style, Some(IndentStyle::Tabs));
+    assert_eq!(Formatter::new(&settings).format("?a\n  >b\n"), "?a\n\t>b\n");
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(format::diff("A.txt", "?a\n>b\n", "?a\n>b\n"), "");
+    assert_eq!(
+        format::diff("A.txt", "?a\n    >b\n", "?a\n  >b\n"),
+        "--- A.txt\n+++ A.txt\n@@ -1,2 +1,2 @@\n ?a\n-    >b\n+  >b\n"
+    );
+}
+
+#[test]
+fn test_stdin() {
+    assert_eq!(fmt(&[], "?a\n    >b"), (Some(0), "?a\n  >b\n".to_string()));
+
+    let (status, diff) = fmt(&["--check"], "?a\n    >b\n");
+    assert_eq!(status, Some(1));
+    assert!(diff.contains("-    >b\n+  >b\n"));
+    assert_eq!(fmt(&["--check"], "?a\n  >b\n"), (Some(0), String::new()));
+}
+
+#[test]
+fn test_files() {
+    let dir = scratch("files");
+    let dir_arg = dir.to_str().unwrap();
+
+    let (status, diff) = fmt(&["--check", dir_arg], "");
+    assert_eq!(status, Some(1));
+    assert!(diff.contains("Tabs/Messy.txt"));
+    assert!(diff.contains("+\t>b"));
+
+    assert_eq!(fmt(&[dir_arg], "").0, Some(0));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("Messy.txt")).unwrap(),
+        "?a\n  >b\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("Tabs/Messy.txt")).unwrap(),
+        "?a\n\t>b\n"
+    );
+    assert_eq!(fmt(&["--check", dir_arg], ""), (Some(0), String::new()));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
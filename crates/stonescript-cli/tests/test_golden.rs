@@ -0,0 +1,139 @@
+//! Test stonescript-test's file format, discovery and reports
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stonescript_cli::golden::{self, Expectation, Runner};
+use stonescript_cli::scripts::{collect_scripts, collect_tests};
+use stonescript_interpreter::Value;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/golden");
+
+fn test_binary(path: &str) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_stonescript-test"))
+        .arg(path)
+        .current_dir(FIXTURES)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_parse() {
+    let frames = golden::parse(
+        "\
+# A comment
+frame 1: loc=caves, foe=none, hp=5, foe.name=\"Big, bad\"
+commands:
+  equip vigor sword
+
+frame 3
+storage:
+  runs = \"2\"
+screen:
+  |  x
+prints:
+",
+    )
+    .unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!((frames[0].number, frames[0].line), (1, 2));
+    assert_eq!(
+        frames[0].state,
+        vec![
+            ("loc".to_string(), Value::from("caves")),
+            ("foe".to_string(), Value::from("")),
+            ("hp".to_string(), Value::Int(5)),
+            ("foe.name".to_string(), Value::from("Big, bad")),
+        ]
+    );
+    assert_eq!(
+        frames[0].expectations,
+        vec![Expectation::Commands(vec!["equip vigor sword".to_string()])]
+    );
+    assert_eq!(frames[1].number, 3);
+    assert_eq!(
+        frames[1].expectations,
+        vec![
+            Expectation::Storage(vec![("runs".to_string(), "2".to_string())]),
+            Expectation::Screen(vec!["  x".to_string()]),
+            Expectation::Prints(vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |text: &str| golden::parse(text).unwrap_err().to_string();
+    assert_eq!(
+        error("commands:\n  a"),
+        "line 1: expected a `frame` line first"
+    );
+    assert_eq!(
+        error("frame 2\nframe 1"),
+        "line 2: frame 1 comes after frame 2"
+    );
+    assert_eq!(
+        error("frame: hp"),
+        "line 1: expected `query=value`, got `hp`"
+    );
+    assert_eq!(
+        error("frame\nscreen:\n  x"),
+        "line 3: screen rows start with `|`, to keep their spaces"
+    );
+    assert!(error("frame\nexpect:").starts_with("line 2: expected `frame`"));
+}
+
+#[test]
+fn test_discovery() {
+    let pass = PathBuf::from(FIXTURES).join("Pass");
+    assert_eq!(
+        golden::script_for(&pass.join("Boss.test.txt")),
+        pass.join("Boss.txt")
+    );
+    assert_eq!(
+        collect_tests(std::slice::from_ref(&pass)).unwrap(),
+        vec![pass.join("Boss.test.txt")]
+    );
+    // Test files are not scripts to check or format
+    assert_eq!(
+        collect_scripts(std::slice::from_ref(&pass)).unwrap(),
+        vec![pass.join("Boss.txt"), pass.join("UI/Warning.txt")]
+    );
+}
+
+#[test]
+fn test_passing_project() {
+    let outcome = Runner::new().run(&Path::new(FIXTURES).join("Pass/Boss.test.txt"));
+    assert_eq!(outcome.failures, vec![]);
+
+    let (code, stdout) = test_binary("Pass");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "test Pass/Boss.test.txt ... ok\n\ntest result: ok. 1 passed; 0 failed\n"
+    );
+}
+
+#[test]
+fn test_failing_project() {
+    let (code, stdout) = test_binary("Fail");
+    assert_eq!(code, Some(1));
+    assert!(
+        stdout.contains(
+            "frame 2: commands differ (line 4)\n--- expected\n+++ actual\n@@ -1 +1 @@\n\
+             -equip vigor axe\n+equip vigor sword\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("-bosses = 2\n+bosses = 1\n"), "{}", stdout);
+    assert!(
+        stdout.contains("could not read Fail/Missing.txt"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("test result: FAILED. 0 passed; 2 failed\n"));
+}
@@ -0,0 +1,117 @@
+//! Test stonescript-repl's sessions, block input and completion
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use stonescript_cli::repl::{complete, needs_more, Session};
+use stonescript_interpreter::Value;
+
+fn run(session: &mut Session, input: &str) -> String {
+    session
+        .run(input)
+        .unwrap_or_else(|e| panic!("{}: {}", input, e))
+}
+
+#[test]
+fn test_session() {
+    let mut session = Session::new();
+    assert_eq!(run(&mut session, "var x = 3\n"), "");
+    assert_eq!(run(&mut session, "math.Pow(2, x)\n"), "= 8\n");
+    assert_eq!(
+        run(&mut session, "string.Format(\"{0}/{1}\", x, 10)\n"),
+        "= \"3/10\"\n"
+    );
+    assert_eq!(run(&mut session, "storage.Set(\"k\", x)\n"), "");
+
+    assert_eq!(run(&mut session, ":set hp 2"), "");
+    assert_eq!(session.fixture().get("hp"), Some(&Value::Int(2)));
+    assert_eq!(run(&mut session, ":set foe \"big boss\""), "");
+    assert_eq!(
+        run(
+            &mut session,
+            "?hp < 5 & foe = boss\n  >low @hp@\n  equip sword\n\n"
+        ),
+        ">low 2\nequip sword\n"
+    );
+    assert_eq!(run(&mut session, ":set foe none"), "");
+    assert_eq!(session.fixture().get("foe"), Some(&Value::from("")));
+
+    run(&mut session, "func Add(a, b)\n  return a + b\n\n");
+    assert_eq!(run(&mut session, "Add(x, 4)"), "= 7\n");
+    assert_eq!(run(&mut session, ":vars"), "var x = 3\nfunc Add(a, b)\n");
+    assert_eq!(run(&mut session, ":storage"), "k = 3\n");
+
+    run(&mut session, ":reset");
+    assert_eq!(run(&mut session, ":vars"), "");
+    assert_eq!(session.names(), Vec::<String>::new());
+}
+
+#[test]
+fn test_errors() {
+    let mut session = Session::new();
+    assert_eq!(
+        session.run("x = 1").unwrap_err(),
+        "1:1: `x` is not declared; declare it with `var`"
+    );
+    assert_eq!(
+        session.run(":set hp").unwrap_err(),
+        "usage: :set QUERY VALUE"
+    );
+    assert_eq!(
+        session.run(":bogus").unwrap_err(),
+        "unknown command `:bogus`; see :help"
+    );
+    // A lone `:` is an else branch, not a command
+    assert!(!Session::is_command(":"));
+    assert!(!Session::is_command(":?hp < 5"));
+}
+
+#[test]
+fn test_needs_more() {
+    assert!(!needs_more("var x = 1\n"));
+    assert!(needs_more("?hp < 5\n"));
+    assert!(needs_more("?hp < 5\n  >low\n"));
+    assert!(!needs_more("?hp < 5\n  >low\n\n"));
+    assert!(needs_more("func F()\n"));
+    assert!(needs_more("for i = 1..3\n"));
+    assert!(needs_more("var art = ascii\n##\n\n"));
+    assert!(!needs_more("var art = ascii\n##\n\nasciiend\n"));
+}
+
+#[test]
+fn test_complete() {
+    let names = vec!["total".to_string()];
+    let words = |line: &str| complete(line, line.len(), &names).1;
+
+    assert_eq!(
+        complete("x = math.Po", 11, &names),
+        (4, vec!["math.Pow".to_string()])
+    );
+    assert!(words("?lo").contains(&"loc".to_string()));
+    assert!(words("?lo").contains(&"loadout".to_string()));
+    assert_eq!(words("to"), vec!["total", "totaltime"]);
+    assert!(words("total.").contains(&"total.Add".to_string()));
+    assert!(words("loc.st").contains(&"loc.stars".to_string()));
+    assert!(words("?loc = ca").contains(&"cave".to_string()));
+    assert_eq!(complete(":se", 3, &names), (1, vec!["set".to_string()]));
+    assert!(words(":set fo").contains(&"foe.hp".to_string()));
+    assert!(words(":set loc ro").contains(&"rocky".to_string()));
+}
+
+#[test]
+fn test_binary() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stonescript-repl"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"var x = 2\n?x > 1\n  >big\n\nmath.Max(x, 5)\n:quit\nmath.Max(1, 0)\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.ends_with(">big\n= 5\n"), "{:?}", stdout);
+}
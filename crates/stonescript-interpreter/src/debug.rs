@@ -0,0 +1,46 @@
+//! Watching a script run, statement by statement
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use stonescript_parser::Span;
+
+use crate::value::{Object, Value};
+
+/// Told about each statement before it runs, to pause or step through a
+/// script. A debugger pauses simply by not returning until it should go on.
+pub trait Debugger {
+    /// `stack` holds the code running, outermost first: the script, then
+    /// each function call, import and `new` object under way
+    fn statement(&mut self, frame: u64, stack: &[Call]);
+}
+
+/// Code under way: the script itself, a function call, or a module being
+/// imported or made into an object
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// Function name or module path; empty for the script itself
+    pub name: String,
+    /// Path of the module the code is in; empty for the main script
+    pub module: String,
+    /// Statement being run; for callers, the one making the call
+    pub span: Span,
+    /// The script, module or `new` object whose variables the code sees
+    pub scope: Rc<RefCell<Object>>,
+    /// Parameters and variables of a function call
+    pub locals: Option<HashMap<String, Value>>,
+}
+
+impl Call {
+    pub(crate) fn new(name: &str, scope: Rc<RefCell<Object>>, span: Span) -> Self {
+        let module = scope.borrow().path.clone();
+        Self {
+            name: name.to_string(),
+            module,
+            span,
+            scope,
+            locals: None,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! What a frame of a script did to the game
+
+use std::fmt;
+
+use stonescript_parser::{OutputKind, Span};
+
+use crate::value::Value;
+
+/// Something a script asked the game to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A command such as `equip vigor crossbow *8` or `play buy 200`, with
+    /// its arguments as the game receives them
+    Command {
+        name: String,
+        args: Vec<String>,
+        span: Span,
+    },
+    /// A `>` print, with its offset, color and text evaluated
+    Print {
+        kind: OutputKind,
+        position: Option<(i64, i64)>,
+        color: Option<String>,
+        text: String,
+        span: Span,
+    },
+    /// A call to a native function that acts on the game, such as
+    /// `music.Play` or `ui.ShowBanner`
+    Call {
+        function: String,
+        args: Vec<Value>,
+        span: Span,
+    },
+}
+
+impl Event {
+    pub fn span(&self) -> Span {
+        match self {
+            Event::Command { span, .. } | Event::Print { span, .. } | Event::Call { span, .. } => {
+                *span
+            }
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    /// The event written the way a script would ask for it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Command { name, args, .. } => {
+                write!(f, "{}", name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                Ok(())
+            }
+            Event::Print {
+                kind,
+                position,
+                color,
+                text,
+                ..
+            } => {
+                write!(f, ">{}", kind.prefix())?;
+                if let Some((x, y)) = position {
+                    write!(f, "{},{},", x, y)?;
+                }
+                if let Some(color) = color {
+                    write!(f, "{},", color)?;
+                }
+                write!(f, "{}", text)
+            }
+            Event::Call { function, args, .. } => {
+                write!(f, "{}(", function)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! The game a script runs in
+
+use std::collections::HashMap;
+
+use crate::value::Value;
+
+/// Width of the game's screen in characters
+pub const SCREEN_WIDTH: i64 = 80;
+
+/// Height of the game's screen in characters
+pub const SCREEN_HEIGHT: i64 = 30;
+
+/// What the interpreter asks of the game: its state, the functions it
+/// provides, and the modules scripts import
+pub trait Host {
+    /// Value of a game-state query such as `hp`, `loc.stars` or
+    /// `foe.distance`, if the game has one
+    fn state(&self, path: &str) -> Option<Value>;
+
+    /// Whether a query matches the word it is compared with, as in
+    /// `?loc = rocky` or `?foe = boss`. The game matches foes by any of
+    /// their names and tags, so by default a word of the value is enough.
+    fn matches(&self, path: &str, word: &str) -> bool {
+        match self.state(path) {
+            Some(Value::String(text)) => text == word || text.split_whitespace().any(|w| w == word),
+            Some(value) => value.to_string() == word,
+            None => false,
+        }
+    }
+
+    /// Result of calling a game function such as `item.GetCount(...)`, or
+    /// `None` if the game has no such function
+    fn call(&mut self, _function: &str, _args: &[Value]) -> Option<Value> {
+        None
+    }
+
+    /// Source of the module at `path`, as written after `import` or `new`
+    fn module(&self, _path: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Game state set up by hand, for running scripts in tests
+///
+/// A new fixture stands at the start of Rocky Plateau with full health and
+/// no foe; tests change what they need with [`Fixture::set`].
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    state: HashMap<String, Value>,
+    results: HashMap<String, Value>,
+    modules: HashMap<String, String>,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let mut fixture = Self {
+            state: HashMap::new(),
+            results: HashMap::new(),
+            modules: HashMap::new(),
+        };
+        let defaults: &[(&str, Value)] = &[
+            ("loc", "rocky".into()),
+            ("loc.name", "Rocky Plateau".into()),
+            ("loc.stars", 0.into()),
+            ("loc.begin", false.into()),
+            ("loc.loop", false.into()),
+            ("loc.gp", 0.into()),
+            ("foe", "".into()),
+            ("foe.name", "".into()),
+            ("foe.hp", 0.into()),
+            ("foe.maxhp", 0.into()),
+            ("foe.armor", 0.into()),
+            ("foe.distance", 0.into()),
+            ("foe.damage", 0.into()),
+            ("foe.count", 0.into()),
+            ("foe.state", 0.into()),
+            ("foe.maxarmor", 0.into()),
+            ("foe.buffs.string", "".into()),
+            ("foe.debuffs.string", "".into()),
+            ("hp", 10.into()),
+            ("maxhp", 10.into()),
+            ("armor", 0.into()),
+            ("buffs", "".into()),
+            ("buffs.string", "".into()),
+            ("debuffs", "".into()),
+            ("debuffs.string", "".into()),
+            ("item.left", "".into()),
+            ("item.left.state", 0.into()),
+            ("item.right", "".into()),
+            ("item.right.state", 0.into()),
+            ("ai.walking", false.into()),
+            ("ai.paused", false.into()),
+            ("sys.isPC", true.into()),
+            ("sys.isMobile", false.into()),
+            ("rng", 0.into()),
+            ("rngf", 0.0.into()),
+            ("pos.x", 0.into()),
+            ("pos.y", 0.into()),
+            ("pos.z", 0.into()),
+            ("input.x", 0.into()),
+            ("input.y", 0.into()),
+            ("time", 0.into()),
+            ("totaltime", 0.into()),
+            ("screen.w", SCREEN_WIDTH.into()),
+            ("screen.h", SCREEN_HEIGHT.into()),
+            ("music", "".into()),
+        ];
+        for (path, value) in defaults {
+            fixture.set(path, value.clone());
+        }
+        fixture
+    }
+
+    /// Set a query's value; setting `loc`, `foe` or a hand's item also
+    /// sets its `id`
+    pub fn set(&mut self, path: &str, value: impl Into<Value>) {
+        let value = value.into();
+        if matches!(path, "loc" | "foe" | "item.left" | "item.right") {
+            self.state.insert(format!("{}.id", path), value.clone());
+        }
+        self.state.insert(path.to_string(), value);
+    }
+
+    /// Make a query have no value
+    pub fn remove(&mut self, path: &str) {
+        self.state.remove(path);
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        self.state.get(path)
+    }
+
+    /// What calls to the game function `function` return
+    pub fn set_result(&mut self, function: &str, value: impl Into<Value>) {
+        self.results.insert(function.to_string(), value.into());
+    }
+
+    /// Make `source` importable as `path`
+    pub fn add_module(&mut self, path: &str, source: &str) {
+        self.modules.insert(path.to_string(), source.to_string());
+    }
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Host for Fixture {
+    fn state(&self, path: &str) -> Option<Value> {
+        self.state.get(path).cloned()
+    }
+
+    fn call(&mut self, function: &str, _args: &[Value]) -> Option<Value> {
+        self.results.get(function).cloned()
+    }
+
+    fn module(&self, path: &str) -> Option<String> {
+        self.modules.get(path).cloned()
+    }
+}
@@ -0,0 +1,1128 @@
+//! Running a script one frame at a time
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use stonescript_parser::{
+    parse_source, AssignmentOperator, BinaryOperator, CommandArgs, CommandValue, Expression,
+    InterpolationPart, ItemCriterion, Position, Program, Span, Statement, UnaryOperator,
+};
+
+use crate::debug::{Call, Debugger};
+use crate::event::Event;
+use crate::host::Host;
+use crate::natives;
+use crate::value::{Function, Object, Value};
+
+/// Deepest function calls and `new` objects may nest
+const MAX_DEPTH: usize = 64;
+
+/// Most times a `for` or `while` loop may run in one frame
+const MAX_ITERATIONS: usize = 1_000_000;
+
+/// An error that stops a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    /// Module the error is in; empty for the main script
+    pub module: String,
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.module.is_empty() {
+            write!(f, "{}:", self.module)?;
+        }
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.start.line + 1,
+            self.span.start.column + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// What one frame of a script did
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    /// Frames run before this one
+    pub number: u64,
+    pub events: Vec<Event>,
+}
+
+impl Frame {
+    /// Commands issued, written as in a script
+    pub fn commands(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, Event::Command { .. }))
+            .map(Event::to_string)
+            .collect()
+    }
+
+    /// Text of the `>` prints
+    pub fn prints(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Print { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// How a statement left the block it is in
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// Where names are looked up while running code
+struct Context {
+    /// Script, module or object the code belongs to
+    scope: Rc<RefCell<Object>>,
+    /// Parameters and variables of the function being run, if any
+    locals: Option<HashMap<String, Value>>,
+}
+
+impl Context {
+    fn new(scope: Rc<RefCell<Object>>) -> Self {
+        Self {
+            scope,
+            locals: None,
+        }
+    }
+
+    fn error(&self, span: Span, message: impl Into<String>) -> RuntimeError {
+        RuntimeError {
+            module: self.scope.borrow().path.clone(),
+            span,
+            message: message.into(),
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.locals.as_ref().and_then(|locals| locals.get(name)) {
+            return Some(value.clone());
+        }
+        self.scope.borrow().fields.get(name).cloned()
+    }
+
+    /// Create or overwrite a variable of the function, or of the scope
+    /// outside functions
+    fn declare(&mut self, name: &str, value: Value) {
+        match &mut self.locals {
+            Some(locals) => {
+                locals.insert(name.to_string(), value);
+            }
+            None => {
+                self.scope
+                    .borrow_mut()
+                    .fields
+                    .insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+/// Runs a script frame by frame, as the game does
+///
+/// Every frame runs the whole script again. Variables keep their values
+/// between frames: a `var` sets its variable only the first time it runs.
+/// Imported modules run each frame in scopes of their own, and `new`
+/// objects run their module once, when they are made.
+pub struct Interpreter {
+    statements: Rc<Vec<Statement>>,
+    main: Rc<RefCell<Object>>,
+    /// Statements of each module loaded, by path
+    modules: HashMap<String, Rc<Vec<Statement>>>,
+    /// Scope of each imported module, by path
+    imports: HashMap<String, Rc<RefCell<Object>>>,
+    storage: BTreeMap<String, Value>,
+    /// The panel `ui.root` holds, unless the host has one
+    ui_root: Rc<RefCell<Object>>,
+    frames: u64,
+    /// Events of the frame being run
+    events: Vec<Event>,
+    /// Modules already imported this frame
+    imported: HashSet<String>,
+    depth: usize,
+    /// Code under way, for the debugger
+    calls: Vec<Call>,
+    debugger: Option<Box<dyn Debugger>>,
+}
+
+impl Interpreter {
+    pub fn new(program: Program) -> Self {
+        Self {
+            statements: Rc::new(program.statements),
+            main: Rc::new(RefCell::new(Object::default())),
+            modules: HashMap::new(),
+            imports: HashMap::new(),
+            storage: BTreeMap::new(),
+            ui_root: Rc::new(RefCell::new(Object::default())),
+            frames: 0,
+            events: Vec::new(),
+            imported: HashSet::new(),
+            depth: 0,
+            calls: Vec::new(),
+            debugger: None,
+        }
+    }
+
+    /// Have `debugger` told about each statement before it runs
+    pub fn set_debugger(&mut self, debugger: Option<Box<dyn Debugger>>) {
+        self.debugger = debugger;
+    }
+
+    /// Run the script once with the game in the state `host` gives
+    pub fn run_frame(&mut self, host: &mut dyn Host) -> Result<Frame, RuntimeError> {
+        self.start();
+        let statements = self.statements.clone();
+        let mut cx = Context::new(self.main.clone());
+        let result = self.run_module(&statements, &mut cx, host);
+        let frame = Frame {
+            number: self.frames,
+            events: std::mem::take(&mut self.events),
+        };
+        self.frames += 1;
+        result.map(|()| frame)
+    }
+
+    /// Run more code as part of the main script, the way a REPL does: it
+    /// sees and sets the script's variables and functions, and the value
+    /// of a final expression such as `math.Pow(2, 10)` is returned
+    pub fn run_snippet(
+        &mut self,
+        program: Program,
+        host: &mut dyn Host,
+    ) -> Result<(Frame, Option<Value>), RuntimeError> {
+        self.start();
+        let mut statements = program.statements;
+        let last = match statements.last() {
+            Some(Statement::ExpressionStatement { expression, .. })
+                if !matches!(expression, Expression::Identifier(name, _)
+                    if name == "break" || name == "continue") =>
+            {
+                statements.pop()
+            }
+            _ => None,
+        };
+        let mut cx = Context::new(self.main.clone());
+        let mut result = self.run_module(&statements, &mut cx, host);
+        let mut value = None;
+        if let (Ok(()), Some(Statement::ExpressionStatement { expression, .. })) = (&result, last) {
+            match self.eval(&expression, &mut cx, host) {
+                Ok(v) => value = Some(v),
+                Err(e) => result = Err(e),
+            }
+        }
+        let frame = Frame {
+            number: self.frames,
+            events: std::mem::take(&mut self.events),
+        };
+        result.map(|()| (frame, value))
+    }
+
+    /// Get ready to run the main script from the top
+    fn start(&mut self) {
+        self.imported.clear();
+        self.depth = 0;
+        let top = Span::new(Position::new(0, 0), Position::new(0, 0));
+        self.calls = vec![Call::new("", self.main.clone(), top)];
+    }
+
+    /// Frames run so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Value of a variable of the main script
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.main.borrow().fields.get(name).cloned()
+    }
+
+    /// Variables and functions of the main script
+    pub fn main(&self) -> &Rc<RefCell<Object>> {
+        &self.main
+    }
+
+    /// What scripts have saved with `storage.Set`, which outlives frames
+    pub fn storage(&self) -> &BTreeMap<String, Value> {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut BTreeMap<String, Value> {
+        &mut self.storage
+    }
+
+    /// Run a script or module body: its functions are defined first, so
+    /// they can be called from above their definition
+    fn run_module(
+        &mut self,
+        statements: &[Statement],
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<(), RuntimeError> {
+        for statement in statements {
+            define(statement, cx);
+        }
+        self.exec_block(statements, cx, host).map(|_| ())
+    }
+
+    fn exec_block(
+        &mut self,
+        statements: &[Statement],
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<Flow, RuntimeError> {
+        for statement in statements {
+            match self.exec(statement, cx, host)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn exec(
+        &mut self,
+        statement: &Statement,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<Flow, RuntimeError> {
+        if let (Some(span), true) = (statement.span(), self.debugger.is_some()) {
+            if !matches!(statement, Statement::Comment(..)) {
+                self.pause(span, cx);
+            }
+        }
+        match statement {
+            Statement::Condition {
+                condition,
+                then_block,
+                else_ifs,
+                else_block,
+                ..
+            } => {
+                if self.eval(condition, cx, host)?.is_truthy() {
+                    return self.exec_block(then_block, cx, host);
+                }
+                for else_if in else_ifs {
+                    if self.eval(&else_if.condition, cx, host)?.is_truthy() {
+                        return self.exec_block(&else_if.block, cx, host);
+                    }
+                }
+                if let Some(else_block) = else_block {
+                    return self.exec_block(else_block, cx, host);
+                }
+            }
+            Statement::Command {
+                name,
+                args,
+                structured,
+                span,
+            } => {
+                let args = match structured {
+                    Some(structured) => self.command_args(structured, cx, host)?,
+                    None => args
+                        .iter()
+                        .map(|arg| self.eval(arg, cx, host).map(|value| value.to_string()))
+                        .collect::<Result<_, _>>()?,
+                };
+                self.events.push(Event::Command {
+                    name: name.clone(),
+                    args,
+                    span: *span,
+                });
+            }
+            Statement::VarDeclaration {
+                name, initializer, ..
+            } => {
+                if cx.locals.is_none() && cx.scope.borrow().fields.contains_key(name) {
+                    return Ok(Flow::Next);
+                }
+                let value = match initializer {
+                    Some(initializer) => self.eval(initializer, cx, host)?,
+                    None => Value::Null,
+                };
+                cx.declare(name, value);
+            }
+            Statement::Assignment {
+                target,
+                op,
+                value,
+                span,
+            } => {
+                let mut value = self.eval(value, cx, host)?;
+                if let Some(op) = compound_operator(*op) {
+                    let current = self.eval(target, cx, host)?;
+                    value = binary(op, current, value).map_err(|e| cx.error(*span, e))?;
+                }
+                self.assign(target, value, cx, host)?;
+            }
+            Statement::Output {
+                kind,
+                position,
+                color,
+                text,
+                span,
+                ..
+            } => {
+                let position = match position {
+                    Some((x, y)) => Some((
+                        self.eval_int(x, cx, host, "print offset")?,
+                        self.eval_int(y, cx, host, "print offset")?,
+                    )),
+                    None => None,
+                };
+                let color = match color {
+                    Some(color) => Some(self.eval(color, cx, host)?.to_string()),
+                    None => None,
+                };
+                let text = self.eval(text, cx, host)?.to_string();
+                self.events.push(Event::Print {
+                    kind: *kind,
+                    position,
+                    color,
+                    text,
+                    span: *span,
+                });
+            }
+            Statement::ExpressionStatement { expression, .. } => {
+                if let Expression::Identifier(name, _) = expression {
+                    if cx.variable(name).is_none() {
+                        match name.as_str() {
+                            "break" => return Ok(Flow::Break),
+                            "continue" => return Ok(Flow::Continue),
+                            _ => {}
+                        }
+                    }
+                }
+                self.eval(expression, cx, host)?;
+            }
+            Statement::FunctionDefinition { .. } => define(statement, cx),
+            Statement::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.eval(value, cx, host)?,
+                    None => Value::Null,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Statement::For {
+                variable,
+                range,
+                body,
+                span,
+                ..
+            } => {
+                let start = self.eval_int(&range.0, cx, host, "range")?;
+                let end = self.eval_int(&range.1, cx, host, "range")?;
+                let step = if start <= end { 1 } else { -1 };
+                let mut i = start;
+                let mut iterations = 0;
+                loop {
+                    iterations += 1;
+                    if iterations > MAX_ITERATIONS {
+                        return Err(cx.error(*span, "the loop does not end"));
+                    }
+                    cx.declare(variable, Value::Int(i));
+                    match self.exec_block(body, cx, host)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                    if i == end {
+                        break;
+                    }
+                    i += step;
+                }
+            }
+            Statement::ForIn {
+                variable,
+                collection,
+                body,
+                ..
+            } => {
+                let elements = match self.eval(collection, cx, host)? {
+                    Value::Array(elements) => elements.borrow().clone(),
+                    Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+                    other => {
+                        return Err(cx.error(
+                            collection.span(),
+                            format!("cannot loop over a {}", other.type_name()),
+                        ))
+                    }
+                };
+                for element in elements {
+                    cx.declare(variable, element);
+                    match self.exec_block(body, cx, host)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Statement::While {
+                condition,
+                body,
+                span,
+            } => {
+                let mut iterations = 0;
+                while self.eval(condition, cx, host)?.is_truthy() {
+                    iterations += 1;
+                    if iterations > MAX_ITERATIONS {
+                        return Err(cx.error(*span, "the loop does not end"));
+                    }
+                    match self.exec_block(body, cx, host)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Statement::Import { path, span, .. } => {
+                if self.imported.insert(path.clone()) {
+                    let statements = self.load(path, *span, cx, host)?;
+                    let scope = self
+                        .imports
+                        .entry(path.clone())
+                        .or_insert_with(|| Rc::new(RefCell::new(Object::new(path))))
+                        .clone();
+                    self.push_call(path, scope.clone(), *span, cx);
+                    let result = self.run_module(&statements, &mut Context::new(scope), host);
+                    self.calls.pop();
+                    result?;
+                }
+            }
+            Statement::Comment(..) | Statement::Empty => {}
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Arguments of `equip`, `loadout`, `brew` and `activate` as the game
+    /// reads them
+    fn command_args(
+        &mut self,
+        structured: &CommandArgs,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<Vec<String>, RuntimeError> {
+        let mut args = Vec::new();
+        match structured {
+            CommandArgs::Equip(criteria) => {
+                for criterion in criteria {
+                    args.push(match criterion {
+                        ItemCriterion::Stars(n, _) => format!("*{}", n),
+                        ItemCriterion::Enchant(n, _) => format!("+{}", n),
+                        ItemCriterion::Value(value) => self.command_value(value, cx, host)?,
+                    });
+                }
+            }
+            CommandArgs::Loadout(values)
+            | CommandArgs::Brew(values)
+            | CommandArgs::Activate(values) => {
+                for value in values {
+                    args.push(self.command_value(value, cx, host)?);
+                }
+            }
+        }
+        Ok(args)
+    }
+
+    fn command_value(
+        &mut self,
+        value: &CommandValue,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<String, RuntimeError> {
+        Ok(match value {
+            CommandValue::Number(n, _) => n.to_string(),
+            CommandValue::Word(word, _) => word.clone(),
+            CommandValue::Interpolated(expression) => self.eval(expression, cx, host)?.to_string(),
+        })
+    }
+
+    /// Statements of the module at `path`, parsing it the first time
+    fn load(
+        &mut self,
+        path: &str,
+        span: Span,
+        cx: &Context,
+        host: &dyn Host,
+    ) -> Result<Rc<Vec<Statement>>, RuntimeError> {
+        if let Some(statements) = self.modules.get(path) {
+            return Ok(statements.clone());
+        }
+        let source = host
+            .module(path)
+            .ok_or_else(|| cx.error(span, format!("cannot find module `{}`", path)))?;
+        let program = parse_source(&source)
+            .map_err(|e| cx.error(span, format!("module `{}` does not parse: {}", path, e)))?;
+        let statements = Rc::new(program.statements);
+        self.modules.insert(path.to_string(), statements.clone());
+        Ok(statements)
+    }
+
+    fn eval_int(
+        &mut self,
+        expression: &Expression,
+        cx: &mut Context,
+        host: &mut dyn Host,
+        what: &str,
+    ) -> Result<i64, RuntimeError> {
+        let value = self.eval(expression, cx, host)?;
+        value.as_i64().ok_or_else(|| {
+            cx.error(
+                expression.span(),
+                format!("{} must be a number, not a {}", what, value.type_name()),
+            )
+        })
+    }
+
+    /// The game-state query an expression reads, such as `foe.hp`, if its
+    /// leftmost name is not a variable
+    fn game_path(&self, expression: &Expression, cx: &Context) -> Option<String> {
+        match expression {
+            Expression::Identifier(name, _) => {
+                if cx.variable(name).is_some() || natives::NAMESPACES.contains(&name.as_str()) {
+                    None
+                } else {
+                    Some(name.clone())
+                }
+            }
+            Expression::Property {
+                object, property, ..
+            } => self
+                .game_path(object, cx)
+                .map(|path| format!("{}.{}", path, property)),
+            _ => None,
+        }
+    }
+
+    fn eval(
+        &mut self,
+        expression: &Expression,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<Value, RuntimeError> {
+        Ok(match expression {
+            Expression::Integer(i, _) => Value::Int(*i),
+            Expression::Float(f, _) => Value::Float(*f),
+            Expression::Boolean(b, _) => Value::Bool(*b),
+            Expression::String(s, _) => Value::String(s.clone()),
+            Expression::Identifier(name, _) => match cx.variable(name) {
+                Some(value) => value,
+                // Words the game does not know, as in `?loc = rocky`, are
+                // strings
+                None => host
+                    .state(name)
+                    .unwrap_or_else(|| Value::String(name.clone())),
+            },
+            Expression::Property {
+                object,
+                property,
+                span,
+            } => {
+                if let Expression::Identifier(namespace, _) = object.as_ref() {
+                    if cx.variable(namespace).is_none() {
+                        if let Some(value) = natives::constant(namespace, property) {
+                            return Ok(value);
+                        }
+                    }
+                }
+                if let Some(path) = self.game_path(expression, cx) {
+                    return match host.state(&path) {
+                        Some(value) => Ok(value),
+                        None if path == "ui.root" => Ok(Value::Object(self.ui_root.clone())),
+                        None => Err(cx.error(*span, format!("game state `{}` is not set", path))),
+                    };
+                }
+                match self.eval(object, cx, host)? {
+                    Value::Object(object) => object
+                        .borrow()
+                        .fields
+                        .get(property)
+                        .cloned()
+                        .ok_or_else(|| {
+                            cx.error(*span, format!("the object has no variable `{}`", property))
+                        })?,
+                    other => {
+                        return Err(cx.error(
+                            *span,
+                            format!("a {} has no property `{}`", other.type_name(), property),
+                        ))
+                    }
+                }
+            }
+            Expression::FunctionCall {
+                function,
+                args,
+                span,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, cx, host))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(function, args, *span, cx, host)?
+            }
+            Expression::BinaryOp {
+                left,
+                op,
+                right,
+                span,
+            } => match op {
+                BinaryOperator::And => Value::Bool(
+                    self.eval(left, cx, host)?.is_truthy()
+                        && self.eval(right, cx, host)?.is_truthy(),
+                ),
+                BinaryOperator::Or => Value::Bool(
+                    self.eval(left, cx, host)?.is_truthy()
+                        || self.eval(right, cx, host)?.is_truthy(),
+                ),
+                BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                    let a = self.eval(left, cx, host)?;
+                    let b = self.eval(right, cx, host)?;
+                    let equal = match (self.game_path(left, cx), &a, &b) {
+                        (Some(path), Value::String(_), Value::String(word)) => {
+                            host.matches(&path, word)
+                        }
+                        _ => a == b,
+                    };
+                    Value::Bool(equal == (*op == BinaryOperator::Equal))
+                }
+                _ => {
+                    let a = self.eval(left, cx, host)?;
+                    let b = self.eval(right, cx, host)?;
+                    binary(*op, a, b).map_err(|e| cx.error(*span, e))?
+                }
+            },
+            Expression::UnaryOp { op, operand, span } => {
+                let value = self.eval(operand, cx, host)?;
+                match op {
+                    UnaryOperator::Not => Value::Bool(!value.is_truthy()),
+                    UnaryOperator::Negate => match value {
+                        Value::Int(i) => Value::Int(i.wrapping_neg()),
+                        Value::Float(f) => Value::Float(-f),
+                        other => {
+                            return Err(
+                                cx.error(*span, format!("cannot negate a {}", other.type_name()))
+                            )
+                        }
+                    },
+                    UnaryOperator::Increment | UnaryOperator::Decrement => {
+                        let step = if *op == UnaryOperator::Increment {
+                            BinaryOperator::Add
+                        } else {
+                            BinaryOperator::Subtract
+                        };
+                        if value.as_f64().is_none() {
+                            return Err(cx.error(
+                                *span,
+                                format!("cannot {} a {}", op_verb(*op), value.type_name()),
+                            ));
+                        }
+                        let value =
+                            binary(step, value, Value::Int(1)).map_err(|e| cx.error(*span, e))?;
+                        self.assign(operand, value.clone(), cx, host)?;
+                        value
+                    }
+                }
+            }
+            Expression::Interpolation(parts, _) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(s, _) => text.push_str(s),
+                        InterpolationPart::Expression(expression) => {
+                            text.push_str(&self.eval(expression, cx, host)?.to_string())
+                        }
+                    }
+                }
+                Value::String(text)
+            }
+            Expression::New { path, span, .. } => {
+                let statements = self.load(path, *span, cx, host)?;
+                let object = Rc::new(RefCell::new(Object::new(path)));
+                self.enter(*span, cx)?;
+                self.push_call(path, object.clone(), *span, cx);
+                let result = self.run_module(&statements, &mut Context::new(object.clone()), host);
+                self.calls.pop();
+                self.depth -= 1;
+                result?;
+                Value::Object(object)
+            }
+            Expression::Array { elements, .. } => Value::array(
+                elements
+                    .iter()
+                    .map(|element| self.eval(element, cx, host))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expression::IndexAccess {
+                object,
+                index,
+                span,
+            } => {
+                let value = self.eval(object, cx, host)?;
+                let i = self.eval_int(index, cx, host, "index")?;
+                match value {
+                    Value::Array(elements) => {
+                        let elements = elements.borrow();
+                        usize::try_from(i)
+                            .ok()
+                            .and_then(|i| elements.get(i))
+                            .cloned()
+                            .ok_or_else(|| cx.error(*span, out_of_range(i, elements.len())))?
+                    }
+                    Value::String(s) => usize::try_from(i)
+                        .ok()
+                        .and_then(|i| s.chars().nth(i))
+                        .map(|c| Value::String(c.to_string()))
+                        .ok_or_else(|| cx.error(*span, out_of_range(i, s.chars().count())))?,
+                    other => {
+                        return Err(cx.error(*span, format!("cannot index a {}", other.type_name())))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Let the debugger see the statement at `span` before it runs
+    #[inline(never)]
+    fn pause(&mut self, span: Span, cx: &Context) {
+        if let Some(call) = self.calls.last_mut() {
+            call.span = span;
+            call.locals = cx.locals.clone();
+        }
+        if let Some(mut debugger) = self.debugger.take() {
+            debugger.statement(self.frames, &self.calls);
+            self.debugger = Some(debugger);
+        }
+    }
+
+    /// Start code that `cx`, at `span`, is calling, importing or making
+    #[inline(never)]
+    fn push_call(&mut self, name: &str, scope: Rc<RefCell<Object>>, span: Span, cx: &Context) {
+        if let Some(caller) = self.calls.last_mut() {
+            caller.span = span;
+            if self.debugger.is_some() {
+                caller.locals = cx.locals.clone();
+            }
+        }
+        self.calls.push(Call::new(name, scope, span));
+    }
+
+    /// Count a nested function call or `new` object
+    fn enter(&mut self, span: Span, cx: &Context) -> Result<(), RuntimeError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(cx.error(span, "calls nest too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        function: &Expression,
+        args: Vec<Value>,
+        span: Span,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<Value, RuntimeError> {
+        let unknown =
+            |cx: &Context, name: &str| cx.error(span, format!("unknown function `{}`", name));
+        match function {
+            Expression::Identifier(name, _) => {
+                let defined = cx.scope.borrow().functions.get(name).cloned();
+                if let Some(defined) = defined {
+                    let owner = cx.scope.clone();
+                    return self.invoke(&defined, owner, args, span, cx, host);
+                }
+                if let Some(result) = natives::global(name, &args) {
+                    return result.map_err(|e| cx.error(span, e));
+                }
+                host.call(name, &args).ok_or_else(|| unknown(cx, name))
+            }
+            Expression::Property {
+                object, property, ..
+            } => {
+                if let Expression::Identifier(namespace, _) = object.as_ref() {
+                    if cx.variable(namespace).is_none() {
+                        let result = match namespace.as_str() {
+                            "math" => natives::math(property, &args),
+                            "string" => natives::string(property, &args),
+                            "storage" => natives::storage(&mut self.storage, property, &args),
+                            "int" => natives::int_namespace(property, &args),
+                            _ => None,
+                        };
+                        if let Some(result) = result {
+                            return result.map_err(|e| cx.error(span, e));
+                        }
+                    }
+                }
+                let path = match object.as_ref() {
+                    Expression::Identifier(namespace, _)
+                        if natives::NAMESPACES.contains(&namespace.as_str()) =>
+                    {
+                        Some(namespace.clone())
+                    }
+                    _ => self.game_path(object, cx),
+                };
+                if let Some(path) = path {
+                    let name = format!("{}.{}", path, property);
+                    if let Some(value) = host.call(&name, &args) {
+                        return Ok(value);
+                    }
+                    let namespace = path.split('.').next().unwrap_or_default();
+                    if natives::EVENT_NAMESPACES.contains(&namespace) {
+                        // UI components are objects scripts set fields of
+                        let result = if property.starts_with("Add") {
+                            Value::Object(Rc::new(RefCell::new(Object::default())))
+                        } else {
+                            Value::Null
+                        };
+                        self.events.push(Event::Call {
+                            function: name,
+                            args,
+                            span,
+                        });
+                        return Ok(result);
+                    }
+                    return Err(unknown(cx, &name));
+                }
+                match self.eval(object, cx, host)? {
+                    Value::Object(object) => {
+                        let defined = object.borrow().functions.get(property).cloned();
+                        match defined {
+                            Some(defined) => self.invoke(&defined, object, args, span, cx, host),
+                            None => Err(unknown(cx, property)),
+                        }
+                    }
+                    Value::Array(elements) => natives::array(&elements, property, &args)
+                        .ok_or_else(|| unknown(cx, property))?
+                        .map_err(|e| cx.error(span, e)),
+                    value if property.eq_ignore_ascii_case("ToString") => {
+                        Ok(Value::String(value.to_string()))
+                    }
+                    value => Err(cx.error(
+                        span,
+                        format!("a {} has no function `{}`", value.type_name(), property),
+                    )),
+                }
+            }
+            _ => Err(cx.error(span, "only functions can be called")),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        function: &Function,
+        owner: Rc<RefCell<Object>>,
+        args: Vec<Value>,
+        span: Span,
+        cx: &Context,
+        host: &mut dyn Host,
+    ) -> Result<Value, RuntimeError> {
+        if args.len() > function.params.len() {
+            return Err(cx.error(
+                span,
+                format!(
+                    "`{}` takes {} arguments but {} were given",
+                    function.name,
+                    function.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        let mut args = args.into_iter();
+        let locals = function
+            .params
+            .iter()
+            .map(|param| (param.clone(), args.next().unwrap_or(Value::Null)))
+            .collect();
+        self.enter(span, cx)?;
+        self.push_call(&function.name, owner.clone(), span, cx);
+        let mut inner = Context {
+            scope: owner,
+            locals: Some(locals),
+        };
+        let flow = self.exec_block(&function.body, &mut inner, host);
+        self.calls.pop();
+        self.depth -= 1;
+        Ok(match flow? {
+            Flow::Return(value) => value,
+            _ => Value::Null,
+        })
+    }
+
+    fn assign(
+        &mut self,
+        target: &Expression,
+        value: Value,
+        cx: &mut Context,
+        host: &mut dyn Host,
+    ) -> Result<(), RuntimeError> {
+        match target {
+            Expression::Identifier(name, span) => {
+                if let Some(slot) = cx.locals.as_mut().and_then(|locals| locals.get_mut(name)) {
+                    *slot = value;
+                    return Ok(());
+                }
+                let mut scope = cx.scope.borrow_mut();
+                match scope.fields.get_mut(name) {
+                    Some(slot) => *slot = value,
+                    None => {
+                        drop(scope);
+                        return Err(cx.error(
+                            *span,
+                            format!("`{}` is not declared; declare it with `var`", name),
+                        ));
+                    }
+                }
+            }
+            Expression::Property {
+                object,
+                property,
+                span,
+            } => match self.eval(object, cx, host)? {
+                Value::Object(object) => {
+                    object.borrow_mut().fields.insert(property.clone(), value);
+                }
+                other => {
+                    return Err(cx.error(
+                        *span,
+                        format!("cannot set `{}` of a {}", property, other.type_name()),
+                    ))
+                }
+            },
+            Expression::IndexAccess {
+                object,
+                index,
+                span,
+            } => {
+                let array = self.eval(object, cx, host)?;
+                let i = self.eval_int(index, cx, host, "index")?;
+                let Value::Array(elements) = array else {
+                    return Err(cx.error(
+                        *span,
+                        format!("cannot set an element of a {}", array.type_name()),
+                    ));
+                };
+                let mut elements = elements.borrow_mut();
+                let len = elements.len();
+                match usize::try_from(i).ok().and_then(|i| elements.get_mut(i)) {
+                    Some(slot) => *slot = value,
+                    None => return Err(cx.error(*span, out_of_range(i, len))),
+                }
+            }
+            other => return Err(cx.error(other.span(), "cannot assign to this expression")),
+        }
+        Ok(())
+    }
+}
+
+/// Define the function `statement` declares, if it is a `func`
+fn define(statement: &Statement, cx: &Context) {
+    if let Statement::FunctionDefinition {
+        name, params, body, ..
+    } = statement
+    {
+        let function = Function {
+            name: name.clone(),
+            params: params.clone(),
+            body: body.clone(),
+        };
+        cx.scope
+            .borrow_mut()
+            .functions
+            .insert(name.clone(), Rc::new(function));
+    }
+}
+
+/// The operator `+=` and the like apply before assigning
+fn compound_operator(op: AssignmentOperator) -> Option<BinaryOperator> {
+    match op {
+        AssignmentOperator::Assign => None,
+        AssignmentOperator::AddAssign => Some(BinaryOperator::Add),
+        AssignmentOperator::SubtractAssign => Some(BinaryOperator::Subtract),
+        AssignmentOperator::MultiplyAssign => Some(BinaryOperator::Multiply),
+        AssignmentOperator::DivideAssign => Some(BinaryOperator::Divide),
+        AssignmentOperator::ModuloAssign => Some(BinaryOperator::Modulo),
+    }
+}
+
+fn op_verb(op: UnaryOperator) -> &'static str {
+    match op {
+        UnaryOperator::Increment => "increment",
+        UnaryOperator::Decrement => "decrement",
+        UnaryOperator::Not => "invert",
+        UnaryOperator::Negate => "negate",
+    }
+}
+
+fn out_of_range(index: i64, len: usize) -> String {
+    format!("index {} is out of range for {} elements", index, len)
+}
+
+/// Arithmetic and ordering; `&`, `|`, `=` and `!=` are handled by the
+/// interpreter
+fn binary(op: BinaryOperator, a: Value, b: Value) -> Result<Value, String> {
+    use BinaryOperator::*;
+    if op == Add && (matches!(a, Value::String(_)) || matches!(b, Value::String(_))) {
+        return Ok(Value::String(format!("{}{}", a, b)));
+    }
+    if let (Value::String(a), Value::String(b)) = (&a, &b) {
+        let ordering = a.cmp(b);
+        return match op {
+            Less => Ok(Value::Bool(ordering.is_lt())),
+            LessEqual => Ok(Value::Bool(ordering.is_le())),
+            Greater => Ok(Value::Bool(ordering.is_gt())),
+            GreaterEqual => Ok(Value::Bool(ordering.is_ge())),
+            _ => Err(format!("cannot apply `{}` to strings", op)),
+        };
+    }
+    if let (Value::Int(x), Value::Int(y)) = (&a, &b) {
+        let (x, y) = (*x, *y);
+        return match op {
+            Add => Ok(Value::Int(x.wrapping_add(y))),
+            Subtract => Ok(Value::Int(x.wrapping_sub(y))),
+            Multiply => Ok(Value::Int(x.wrapping_mul(y))),
+            Divide | Modulo if y == 0 => Err("division by zero".to_string()),
+            Divide => Ok(Value::Int(x.wrapping_div(y))),
+            Modulo => Ok(Value::Int(x.wrapping_rem(y))),
+            Less => Ok(Value::Bool(x < y)),
+            LessEqual => Ok(Value::Bool(x <= y)),
+            Greater => Ok(Value::Bool(x > y)),
+            GreaterEqual => Ok(Value::Bool(x >= y)),
+            Equal | NotEqual | And | Or => unreachable!("handled by the interpreter"),
+        };
+    }
+    let (Some(x), Some(y)) = (a.as_f64(), b.as_f64()) else {
+        return Err(format!(
+            "cannot apply `{}` to a {} and a {}",
+            op,
+            a.type_name(),
+            b.type_name()
+        ));
+    };
+    Ok(match op {
+        Add => Value::Float(x + y),
+        Subtract => Value::Float(x - y),
+        Multiply => Value::Float(x * y),
+        Divide => Value::Float(x / y),
+        Modulo => Value::Float(x % y),
+        Less => Value::Bool(x < y),
+        LessEqual => Value::Bool(x <= y),
+        Greater => Value::Bool(x > y),
+        GreaterEqual => Value::Bool(x >= y),
+        Equal | NotEqual | And | Or => unreachable!("handled by the interpreter"),
+    })
+}
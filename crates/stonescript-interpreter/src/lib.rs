@@ -0,0 +1,21 @@
+//! StoneScript Interpreter
+//!
+//! Runs scripts off-device, frame by frame as the game does, against a
+//! game state the caller supplies through [`Host`]. Commands, prints and
+//! calls that act on the game are recorded as [`Event`]s, and a frame's
+//! prints can be drawn onto a [`Screen`].
+
+pub mod debug;
+pub mod event;
+pub mod host;
+pub mod interpreter;
+mod natives;
+pub mod screen;
+pub mod value;
+
+pub use debug::{Call, Debugger};
+pub use event::Event;
+pub use host::{Fixture, Host, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use interpreter::{Frame, Interpreter, RuntimeError};
+pub use screen::{Cell, Color, Screen};
+pub use value::{Function, Object, Value};
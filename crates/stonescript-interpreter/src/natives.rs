@@ -0,0 +1,369 @@
+//! The `math`, `string`, `storage` and `int` namespaces, and the methods of
+//! arrays
+//!
+//! Function names are matched ignoring case, as the game does. Each
+//! function returns `None` when the namespace has no function of that
+//! name, so the interpreter can pass the call on to the host.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::value::Value;
+
+type Native = Option<Result<Value, String>>;
+
+/// Namespaces the interpreter provides itself
+pub const NAMESPACES: &[&str] = &["math", "string", "storage", "int"];
+
+/// Namespaces whose functions act on the game and are recorded as events
+pub const EVENT_NAMESPACES: &[&str] = &["music", "ambient", "ui", "draw"];
+
+fn number(args: &[Value], index: usize) -> Result<f64, String> {
+    match args.get(index) {
+        Some(value) => value
+            .as_f64()
+            .ok_or_else(|| format!("expected a number, found {}", value.type_name())),
+        None => Err(format!("missing argument {}", index + 1)),
+    }
+}
+
+fn int(args: &[Value], index: usize) -> Result<i64, String> {
+    number(args, index).map(|n| n as i64)
+}
+
+fn text(args: &[Value], index: usize) -> Result<String, String> {
+    match args.get(index) {
+        Some(Value::Null) | None => Err(format!("missing argument {}", index + 1)),
+        Some(value) => Ok(value.to_string()),
+    }
+}
+
+/// Whether all numbers given are ints, so the result can stay one
+fn all_ints(args: &[Value]) -> bool {
+    args.iter().all(|arg| matches!(arg, Value::Int(_)))
+}
+
+/// `math.pi` and `math.e`
+pub fn constant(namespace: &str, name: &str) -> Option<Value> {
+    match (namespace, name.to_ascii_lowercase().as_str()) {
+        ("math", "pi") => Some(Value::Float(std::f64::consts::PI)),
+        ("math", "e") => Some(Value::Float(std::f64::consts::E)),
+        _ => None,
+    }
+}
+
+pub fn math(name: &str, args: &[Value]) -> Native {
+    let float = |f: fn(f64) -> f64| number(args, 0).map(|x| Value::Float(f(x)));
+    let to_int = |f: fn(f64) -> f64| number(args, 0).map(|x| Value::Int(f(x) as i64));
+    Some(match name.to_ascii_lowercase().as_str() {
+        "abs" => match args.first() {
+            Some(Value::Int(i)) => Ok(Value::Int(i.wrapping_abs())),
+            _ => float(f64::abs),
+        },
+        "sign" => float(|x| if x == 0.0 { 0.0 } else { x.signum() }),
+        "sqrt" => float(f64::sqrt),
+        "floor" => float(f64::floor),
+        "ceil" => float(f64::ceil),
+        "round" => float(f64::round),
+        "floortoint" => to_int(f64::floor),
+        "ceiltoint" => to_int(f64::ceil),
+        "roundtoint" => to_int(f64::round),
+        "sin" => float(f64::sin),
+        "cos" => float(f64::cos),
+        "tan" => float(f64::tan),
+        "exp" => float(f64::exp),
+        "log" => number(args, 0).and_then(|x| {
+            Ok(Value::Float(match args.get(1) {
+                Some(_) => x.log(number(args, 1)?),
+                None => x.ln(),
+            }))
+        }),
+        "torad" => float(f64::to_radians),
+        "todeg" => float(f64::to_degrees),
+        "pow" => number(args, 0).and_then(|base| Ok(Value::Float(base.powf(number(args, 1)?)))),
+        "max" | "min" => {
+            let max = name.eq_ignore_ascii_case("max");
+            if all_ints(args) {
+                int(args, 0).and_then(|a| {
+                    let b = int(args, 1)?;
+                    Ok(Value::Int(if max { a.max(b) } else { a.min(b) }))
+                })
+            } else {
+                number(args, 0).and_then(|a| {
+                    let b = number(args, 1)?;
+                    Ok(Value::Float(if max { a.max(b) } else { a.min(b) }))
+                })
+            }
+        }
+        "clamp" => {
+            if all_ints(args) {
+                (|| {
+                    let (x, low, high) = (int(args, 0)?, int(args, 1)?, int(args, 2)?);
+                    Ok(Value::Int(x.max(low).min(high)))
+                })()
+            } else {
+                (|| {
+                    let (x, low, high) = (number(args, 0)?, number(args, 1)?, number(args, 2)?);
+                    Ok(Value::Float(x.max(low).min(high)))
+                })()
+            }
+        }
+        "lerp" => (|| {
+            let (a, b, t) = (number(args, 0)?, number(args, 1)?, number(args, 2)?);
+            Ok(Value::Float(a + (b - a) * t))
+        })(),
+        _ => return None,
+    })
+}
+
+pub fn string(name: &str, args: &[Value]) -> Native {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "size" => text(args, 0).map(|s| Value::Int(s.chars().count() as i64)),
+        "sub" => (|| {
+            let chars: Vec<char> = text(args, 0)?.chars().collect();
+            let start = (int(args, 1)?.max(0) as usize).min(chars.len());
+            let end = match args.get(2) {
+                Some(_) => (start + int(args, 2)?.max(0) as usize).min(chars.len()),
+                None => chars.len(),
+            };
+            Ok(Value::String(chars[start..end].iter().collect()))
+        })(),
+        "indexof" => (|| {
+            let s = text(args, 0)?;
+            let needle = text(args, 1)?;
+            let start = match args.get(2) {
+                Some(_) => int(args, 2)?.max(0) as usize,
+                None => 0,
+            };
+            let skipped: usize = s.chars().take(start).map(char::len_utf8).sum();
+            Ok(Value::Int(match s[skipped..].find(&needle) {
+                Some(byte) => (start + s[skipped..skipped + byte].chars().count()) as i64,
+                None => -1,
+            }))
+        })(),
+        "contains" => (|| Ok(Value::Bool(text(args, 0)?.contains(&text(args, 1)?))))(),
+        "equals" => (|| Ok(Value::Bool(text(args, 0)? == text(args, 1)?)))(),
+        "tolower" => text(args, 0).map(|s| Value::String(s.to_lowercase())),
+        "toupper" => text(args, 0).map(|s| Value::String(s.to_uppercase())),
+        "capitalize" => text(args, 0).map(|s| {
+            let mut chars = s.chars();
+            Value::String(match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            })
+        }),
+        "format" => text(args, 0).map(|format| {
+            let mut result = format;
+            for (i, arg) in args.iter().enumerate().skip(1) {
+                result = result.replace(&format!("{{{}}}", i - 1), &arg.to_string());
+            }
+            Value::String(result)
+        }),
+        "split" => (|| {
+            let s = text(args, 0)?;
+            let mut separators = Vec::new();
+            let mut discard_empty = false;
+            for arg in &args[1..] {
+                match arg {
+                    Value::Bool(b) => discard_empty = *b,
+                    other => separators.push(other.to_string()),
+                }
+            }
+            let mut parts = vec![s];
+            if separators.is_empty() {
+                parts = parts[0].split_whitespace().map(str::to_string).collect();
+            }
+            for separator in separators.iter().filter(|s| !s.is_empty()) {
+                parts = parts
+                    .iter()
+                    .flat_map(|part| part.split(separator.as_str()))
+                    .map(str::to_string)
+                    .collect();
+            }
+            Ok(Value::array(
+                parts
+                    .into_iter()
+                    .filter(|part| !discard_empty || !part.is_empty())
+                    .map(Value::String)
+                    .collect(),
+            ))
+        })(),
+        "join" => {
+            let (separator, array) = match (args.first(), args.get(1)) {
+                (Some(Value::Array(array)), separator) | (separator, Some(Value::Array(array))) => {
+                    (separator.map(Value::to_string).unwrap_or_default(), array)
+                }
+                _ => return Some(Err("expected an array to join".to_string())),
+            };
+            let joined: Vec<String> = array.borrow().iter().map(Value::to_string).collect();
+            Ok(Value::String(joined.join(&separator)))
+        }
+        "break" => (|| {
+            let s = text(args, 0)?;
+            let width = int(args, 1)?.max(1) as usize;
+            Ok(Value::array(
+                break_lines(&s, width)
+                    .into_iter()
+                    .map(Value::String)
+                    .collect(),
+            ))
+        })(),
+        _ => return None,
+    })
+}
+
+/// Lines of at most `width` characters, broken at spaces where possible
+fn break_lines(s: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in s.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut word: Vec<char> = word.chars().collect();
+            let used = line.chars().count();
+            if used > 0 && used + 1 + word.len() <= width {
+                line.push(' ');
+            } else if used > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn storage(storage: &mut BTreeMap<String, Value>, name: &str, args: &[Value]) -> Native {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "get" => text(args, 0).map(|key| {
+            storage
+                .get(&key)
+                .or_else(|| args.get(1))
+                .cloned()
+                .unwrap_or(Value::Null)
+        }),
+        "set" => (|| {
+            let value = args.get(1).cloned().ok_or("missing argument 2")?;
+            storage.insert(text(args, 0)?, value);
+            Ok(Value::Null)
+        })(),
+        "has" => text(args, 0).map(|key| Value::Bool(storage.contains_key(&key))),
+        "delete" => text(args, 0).map(|key| {
+            storage.remove(&key);
+            Value::Null
+        }),
+        "incr" => (|| {
+            let key = text(args, 0)?;
+            let amount = match args.get(1) {
+                Some(_) => int(args, 1)?,
+                None => 1,
+            };
+            let current = match storage.get(&key) {
+                Some(value) => value
+                    .as_i64()
+                    .ok_or_else(|| format!("storage key `{}` does not hold a number", key))?,
+                None => 0,
+            };
+            storage.insert(key, Value::Int(current + amount));
+            Ok(Value::Int(current + amount))
+        })(),
+        "keys" => {
+            let prefix = args.first().map(Value::to_string).unwrap_or_default();
+            Ok(Value::array(
+                storage
+                    .keys()
+                    .filter(|key| key.starts_with(&prefix))
+                    .map(|key| Value::String(key.clone()))
+                    .collect(),
+            ))
+        }
+        _ => return None,
+    })
+}
+
+/// `int.Parse`
+pub fn int_namespace(name: &str, args: &[Value]) -> Native {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "parse" => text(args, 0).and_then(|s| {
+            s.trim()
+                .parse()
+                .map(Value::Int)
+                .map_err(|_| format!("`{}` is not a whole number", s))
+        }),
+        _ => return None,
+    })
+}
+
+/// Functions called without a namespace
+pub fn global(name: &str, args: &[Value]) -> Native {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "type" => match args.first() {
+            Some(value) => Ok(Value::String(value.type_name().to_string())),
+            None => Err("missing argument 1".to_string()),
+        },
+        _ => return None,
+    })
+}
+
+/// Methods of an array value, such as `a.Add(1)`
+pub fn array(array: &RefCell<Vec<Value>>, name: &str, args: &[Value]) -> Native {
+    let index = |i: usize, len: usize| -> Result<usize, String> {
+        let index = int(args, i)?;
+        if index < 0 || index as usize >= len {
+            return Err(format!(
+                "index {} is out of range for {} elements",
+                index, len
+            ));
+        }
+        Ok(index as usize)
+    };
+    let value = |i: usize| {
+        args.get(i)
+            .cloned()
+            .ok_or(format!("missing argument {}", i + 1))
+    };
+    let mut elements = array.borrow_mut();
+    Some(match name.to_ascii_lowercase().as_str() {
+        "add" => value(0).map(|v| {
+            elements.push(v);
+            Value::Null
+        }),
+        "clear" => {
+            elements.clear();
+            Ok(Value::Null)
+        }
+        "contains" => value(0).map(|v| Value::Bool(elements.contains(&v))),
+        "count" => Ok(Value::Int(elements.len() as i64)),
+        "indexof" => value(0).map(|v| {
+            Value::Int(
+                elements
+                    .iter()
+                    .position(|e| *e == v)
+                    .map_or(-1, |i| i as i64),
+            )
+        }),
+        "emplace" => (|| {
+            let i = index(0, elements.len())?;
+            elements[i] = value(1)?;
+            Ok(Value::Null)
+        })(),
+        "insert" => (|| {
+            let i = index(0, elements.len() + 1)?;
+            elements.insert(i, value(1)?);
+            Ok(Value::Null)
+        })(),
+        "removeat" => index(0, elements.len()).map(|i| elements.remove(i)),
+        "sort" => {
+            elements.sort_by(|a, b| match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => a.to_string().cmp(&b.to_string()),
+            });
+            Ok(Value::Null)
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,324 @@
+//! Drawing a frame's `>` prints onto a character grid the size of the
+//! game's screen
+
+use std::fmt::Write;
+
+use stonescript_parser::OutputKind;
+
+use crate::event::Event;
+use crate::host::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Color of a character on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Rgb(u8, u8, u8),
+    /// `#rainbow`: the hue changes along the line
+    Rainbow,
+}
+
+impl Color {
+    /// White, what prints without a color use
+    pub const DEFAULT: Color = Color::Rgb(0xff, 0xff, 0xff);
+
+    /// Read a color as written in a print: `#red`, `#f80` or `#ff8800`,
+    /// with or without the `#`
+    pub fn parse(text: &str) -> Option<Color> {
+        let name = text.trim().trim_start_matches('#').to_ascii_lowercase();
+        let named = match name.as_str() {
+            "white" => Some((0xff, 0xff, 0xff)),
+            "black" => Some((0x00, 0x00, 0x00)),
+            "red" => Some((0xff, 0x00, 0x00)),
+            "green" => Some((0x00, 0xff, 0x00)),
+            "blue" => Some((0x00, 0x00, 0xff)),
+            "yellow" => Some((0xff, 0xff, 0x00)),
+            "cyan" => Some((0x00, 0xff, 0xff)),
+            "magenta" => Some((0xff, 0x00, 0xff)),
+            "gray" | "grey" => Some((0x80, 0x80, 0x80)),
+            _ => None,
+        };
+        if let Some((r, g, b)) = named {
+            return Some(Color::Rgb(r, g, b));
+        }
+        if name.starts_with("rain") {
+            return Some(Color::Rainbow);
+        }
+        let digits: Vec<u8> = name
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?;
+        match digits[..] {
+            [r, g, b] => Some(Color::Rgb(r * 17, g * 17, b * 17)),
+            [r1, r2, g1, g2, b1, b2] => Some(Color::Rgb(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+            _ => None,
+        }
+    }
+
+    /// The color a character in column `x` is drawn in
+    fn at(self, x: usize) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Rainbow => {
+                const HUES: [(u8, u8, u8); 6] = [
+                    (0xff, 0x00, 0x00),
+                    (0xff, 0xff, 0x00),
+                    (0x00, 0xff, 0x00),
+                    (0x00, 0xff, 0xff),
+                    (0x00, 0x00, 0xff),
+                    (0xff, 0x00, 0xff),
+                ];
+                HUES[x % HUES.len()]
+            }
+        }
+    }
+}
+
+/// One character on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub color: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            color: Color::DEFAULT,
+        }
+    }
+}
+
+/// The game's screen as a grid of characters
+///
+/// Prints are drawn where the game puts them: `` >` `` from the top-left
+/// corner, `>c` from the center, `>o` and `>h` from the player and `>f`
+/// from the targeted foe. Spaces are transparent, so art drawn later only
+/// covers what its other characters land on. `>h` prints are on the HUD,
+/// above everything else.
+#[derive(Debug, Clone)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    player: (i64, i64),
+    foe: Option<(i64, i64)>,
+}
+
+impl Screen {
+    /// An empty screen of the game's size
+    pub fn new() -> Self {
+        Self::with_size(SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+            player: (width as i64 / 4, height as i64 / 2),
+            foe: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Where `>o` and `>h` prints are placed from
+    pub fn set_player(&mut self, x: i64, y: i64) {
+        self.player = (x, y);
+    }
+
+    /// Where `>f` prints are placed from; without a foe they are not drawn
+    pub fn set_foe(&mut self, foe: Option<(i64, i64)>) {
+        self.foe = foe;
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        (x < self.width && y < self.height).then(|| self.cells[y * self.width + x])
+    }
+
+    /// Clear the screen, as at the start of a frame
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::default());
+    }
+
+    /// Draw the prints among a frame's events, HUD prints last
+    pub fn render(&mut self, events: &[Event]) {
+        let prints = |hud: bool| {
+            events.iter().filter(move |event| {
+                matches!(event, Event::Print { kind, .. } if (*kind == OutputKind::Hud) == hud)
+            })
+        };
+        for event in prints(false).chain(prints(true)) {
+            self.draw(event);
+        }
+    }
+
+    /// Draw one print; other events are ignored
+    pub fn draw(&mut self, event: &Event) {
+        let Event::Print {
+            kind,
+            position,
+            color,
+            text,
+            ..
+        } = event
+        else {
+            return;
+        };
+        let (dx, dy) = position.unwrap_or((0, 0));
+        let origin = match kind {
+            OutputKind::Plain | OutputKind::Screen => (0, 0),
+            OutputKind::Center => (self.width as i64 / 2, self.height as i64 / 2),
+            OutputKind::Overlay | OutputKind::Hud => self.player,
+            OutputKind::Foe => match self.foe {
+                Some(foe) => foe,
+                None => return,
+            },
+        };
+        let color = color
+            .as_deref()
+            .and_then(Color::parse)
+            .unwrap_or(Color::DEFAULT);
+        self.text(origin.0 + dx, origin.1 + dy, text, color);
+    }
+
+    /// Write `text` with its top-left corner at `x`, `y`, following its
+    /// `[color=#...]` tags
+    fn text(&mut self, x: i64, y: i64, text: &str, color: Color) {
+        let mut colors = vec![color];
+        for (row, line) in text.split('\n').enumerate() {
+            let y = y + row as i64;
+            let mut column = x;
+            let mut rest = line.trim_end_matches('\r');
+            while let Some(c) = rest.chars().next() {
+                if c == '[' {
+                    if let Some(tag_end) = rest.find(']') {
+                        let tag = &rest[1..tag_end];
+                        if let Some(value) = tag.strip_prefix("color=") {
+                            colors.push(Color::parse(value).unwrap_or(color));
+                            rest = &rest[tag_end + 1..];
+                            continue;
+                        }
+                        if tag == "/color" {
+                            if colors.len() > 1 {
+                                colors.pop();
+                            }
+                            rest = &rest[tag_end + 1..];
+                            continue;
+                        }
+                    }
+                }
+                if c != ' ' && (0..self.width as i64).contains(&column) {
+                    if let Ok(y) = usize::try_from(y) {
+                        if y < self.height {
+                            self.cells[y * self.width + column as usize] = Cell {
+                                ch: c,
+                                color: *colors.last().unwrap_or(&color),
+                            };
+                        }
+                    }
+                }
+                column += 1;
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// The characters, one line per row, without trailing spaces
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows() {
+            let line: String = row.iter().map(|cell| cell.ch).collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The screen for a terminal, colored with 24-bit ANSI escapes
+    pub fn to_ansi(&self) -> String {
+        let mut ansi = String::new();
+        for row in self.rows() {
+            let end = row
+                .iter()
+                .rposition(|cell| cell.ch != ' ')
+                .map_or(0, |i| i + 1);
+            let mut current = None;
+            for (x, cell) in row[..end].iter().enumerate() {
+                let rgb = cell.color.at(x);
+                if cell.ch != ' ' && current != Some(rgb) {
+                    let (r, g, b) = rgb;
+                    let _ = write!(ansi, "\x1b[38;2;{};{};{}m", r, g, b);
+                    current = Some(rgb);
+                }
+                ansi.push(cell.ch);
+            }
+            if current.is_some() {
+                ansi.push_str("\x1b[0m");
+            }
+            ansi.push('\n');
+        }
+        ansi
+    }
+
+    /// A standalone HTML page showing the screen as the game would, white
+    /// on black
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n\
+             pre { background: #000; color: #fff; font-family: monospace; \
+             line-height: 1.2; padding: 1ch; display: inline-block; }\n\
+             </style>\n</head>\n<body>\n<pre>",
+        );
+        for row in self.rows() {
+            let mut x = 0;
+            while x < row.len() {
+                let rgb = row[x].color.at(x);
+                let start = x;
+                while x < row.len() && (row[x].ch == ' ' || row[x].color.at(x) == rgb) {
+                    x += 1;
+                }
+                let run: String = row[start..x]
+                    .iter()
+                    .map(|cell| match cell.ch {
+                        '<' => "&lt;".to_string(),
+                        '>' => "&gt;".to_string(),
+                        '&' => "&amp;".to_string(),
+                        c => c.to_string(),
+                    })
+                    .collect();
+                let text = run.trim_end_matches(' ');
+                if rgb == (0xff, 0xff, 0xff) || text.is_empty() {
+                    html.push_str(text);
+                } else {
+                    let (r, g, b) = rgb;
+                    let _ = write!(
+                        html,
+                        "<span style=\"color: #{:02x}{:02x}{:02x}\">{}</span>",
+                        r, g, b, text
+                    );
+                }
+                html.push_str(&run[text.len()..]);
+            }
+            html.push('\n');
+        }
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,292 @@
+//! Running scripts frame by frame against a fixture
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use stonescript_interpreter::{Call, Debugger, Event, Fixture, Frame, Interpreter, Value};
+use stonescript_parser::parse_source;
+
+fn interpreter(source: &str) -> Interpreter {
+    Interpreter::new(parse_source(source).unwrap())
+}
+
+/// Run `source` for one frame with the default fixture
+fn run(source: &str) -> Frame {
+    interpreter(source).run_frame(&mut Fixture::new()).unwrap()
+}
+
+fn error(source: &str) -> String {
+    interpreter(source)
+        .run_frame(&mut Fixture::new())
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn test_vars_persist_between_frames() {
+    let mut interpreter = interpreter("var count = 0\ncount++\n>@count@");
+    let mut fixture = Fixture::new();
+    let prints: Vec<Vec<String>> = (0..3)
+        .map(|_| interpreter.run_frame(&mut fixture).unwrap().prints())
+        .collect();
+    assert_eq!(prints, vec![vec!["1"], vec!["2"], vec!["3"]]);
+    assert_eq!(interpreter.global("count"), Some(Value::Int(3)));
+    assert_eq!(interpreter.frames(), 3);
+}
+
+#[test]
+fn test_conditions_read_the_host() {
+    let source = "\
+?loc = caves
+  equip shovel
+:?foe = boss & hp < 5
+  activate potion
+:?foe.count > 2
+  loadout 2
+:
+  equip vigor crossbow *8 +5
+";
+    let mut interpreter = interpreter(source);
+    let mut fixture = Fixture::new();
+    let mut commands = || interpreter.run_frame(&mut fixture).unwrap().commands();
+    assert_eq!(commands(), vec!["equip vigor crossbow *8 +5"]);
+
+    let mut fixture = Fixture::new();
+    fixture.set("foe", "big boss");
+    fixture.set("hp", 3);
+    let mut interpreter = self::interpreter(source);
+    assert_eq!(
+        interpreter.run_frame(&mut fixture).unwrap().commands(),
+        vec!["activate potion"]
+    );
+    fixture.set("hp", 10);
+    fixture.set("foe.count", 3);
+    assert_eq!(
+        interpreter.run_frame(&mut fixture).unwrap().commands(),
+        vec!["loadout 2"]
+    );
+    fixture.set("loc", "caves");
+    assert_eq!(
+        interpreter.run_frame(&mut fixture).unwrap().commands(),
+        vec!["equip shovel"]
+    );
+}
+
+#[test]
+fn test_commands_are_recorded() {
+    let frame = run("var n = 3\nplay buy 200\nbrew tar+bronze\nequip @n@ sword\n");
+    assert_eq!(
+        frame.commands(),
+        vec!["play buy 200", "brew tar bronze", "equip 3 sword"]
+    );
+}
+
+#[test]
+fn test_prints() {
+    let frame = run("var x = 2\n>hello\n>o@x@,-1,#red,x is @x@\n>`0,0,[@x@]\n");
+    let prints: Vec<String> = frame.events.iter().map(Event::to_string).collect();
+    assert_eq!(prints, vec![">hello", ">o2,-1,#red,x is 2", ">`0,0,[2]"]);
+}
+
+#[test]
+fn test_functions_and_loops() {
+    let frame = run("\
+func Fib(n)
+  ?n < 2
+    return n
+  return Fib(n - 1) + Fib(n - 2)
+var total = 0
+for i = 0..10
+  total += Fib(i)
+var down = []
+for i = 3..1
+  down.Add(i)
+var words = \"\"
+for w : [\"a\", \"b\", \"c\"]
+  ?w = \"b\"
+    continue
+  words += w
+var n = 0
+for i = 1..100
+  n = i
+  ?n = 7
+    break
+>@total@ @down@ @words@ @n@
+");
+    assert_eq!(frame.prints(), vec!["143 [3, 2, 1] ac 7"]);
+}
+
+#[test]
+fn test_arithmetic() {
+    let frame = run(">@7 / 2@ @7.0 / 2@ @-7 % 3@ @1 + 2 * 3@ @\"a\" + 1@ @!(1 < 2)@");
+    assert_eq!(frame.prints(), vec!["3 3.5 -1 7 a1 false"]);
+}
+
+#[test]
+fn test_imports_and_new_objects() {
+    let mut fixture = Fixture::new();
+    fixture.add_module(
+        "Games/Counter",
+        "var value = 0\nfunc Add(n)\n  value += n\n  return value\n",
+    );
+    fixture.add_module("Hud/Ticker", "var ticks = 0\nticks++\n>ticks @ticks@\n");
+    let mut interpreter = interpreter(
+        "\
+import Hud/Ticker
+var a = new Games/Counter
+var b = new Games/Counter
+a.Add(5)
+b.Add(1)
+>@a.value@ @b.value@ @a.Add(0)@
+",
+    );
+    interpreter.run_frame(&mut fixture).unwrap();
+    let frame = interpreter.run_frame(&mut fixture).unwrap();
+    assert_eq!(frame.prints(), vec!["ticks 2", "10 2 10"]);
+}
+
+#[test]
+fn test_storage_outlives_frames() {
+    let mut interpreter = interpreter(
+        "\
+?!storage.Has(\"runs\")
+  storage.Set(\"runs\", 0)
+storage.Incr(\"runs\")
+>@storage.Get(\"runs\")@ @storage.Get(\"missing\", \"none\")@
+",
+    );
+    let mut fixture = Fixture::new();
+    interpreter.run_frame(&mut fixture).unwrap();
+    let frame = interpreter.run_frame(&mut fixture).unwrap();
+    assert_eq!(frame.prints(), vec!["2 none"]);
+    assert_eq!(interpreter.storage().get("runs"), Some(&Value::Int(2)));
+}
+
+#[test]
+fn test_game_functions_and_events() {
+    let mut fixture = Fixture::new();
+    fixture.set_result("item.GetCount", 4);
+    let mut interpreter = interpreter(
+        "\
+var t = ui.AddText()
+t.text = \"hi\"
+music.Play(temple_0)
+>@item.GetCount(\"arrow\")@ @t.text@
+",
+    );
+    let frame = interpreter.run_frame(&mut fixture).unwrap();
+    let events: Vec<String> = frame.events.iter().map(Event::to_string).collect();
+    assert_eq!(
+        events,
+        vec!["ui.AddText()", "music.Play(temple_0)", ">4 hi"]
+    );
+}
+
+#[test]
+fn test_runtime_errors() {
+    assert_eq!(
+        error("x = 1"),
+        "1:1: `x` is not declared; declare it with `var`"
+    );
+    assert_eq!(
+        error("var a = [1]\n>@a[3]@"),
+        "2:3: index 3 is out of range for 1 elements"
+    );
+    assert_eq!(error("var a = 1 / 0"), "1:9: division by zero");
+    assert_eq!(error("Missing()"), "1:1: unknown function `Missing`");
+    assert_eq!(
+        error(">@loc.nothing@"),
+        "1:3: game state `loc.nothing` is not set"
+    );
+    assert_eq!(error("import Nowhere"), "1:1: cannot find module `Nowhere`");
+    assert_eq!(error("func F()\n  F()\nF()"), "2:3: calls nest too deeply");
+    assert_eq!(
+        error("for i = 0..2000000\n  var x = i"),
+        "1:1: the loop does not end"
+    );
+}
+
+#[test]
+fn test_snippets_share_the_main_scope() {
+    let mut interpreter = interpreter("var total = 1");
+    let mut fixture = Fixture::new();
+    interpreter.run_frame(&mut fixture).unwrap();
+
+    let mut snippet = |source: &str| {
+        let program = parse_source(source).unwrap();
+        interpreter.run_snippet(program, &mut fixture).unwrap()
+    };
+    let (_, value) = snippet("func Double(n)\n  return n * 2\ntotal += 4");
+    assert_eq!(value, None);
+    let (frame, value) = snippet(">total is @total@\nequip sword\nDouble(total)");
+    assert_eq!(value, Some(Value::Int(10)));
+    assert_eq!(frame.prints(), vec!["total is 5"]);
+    assert_eq!(frame.commands(), vec!["equip sword"]);
+    assert_eq!(interpreter.frames(), 1);
+}
+
+/// Where each statement ran: frame, the names on the stack, and line
+type Trace = Rc<RefCell<Vec<(u64, Vec<String>, usize)>>>;
+
+struct Recorder {
+    trace: Trace,
+    locals: Rc<RefCell<Vec<String>>>,
+}
+
+impl Debugger for Recorder {
+    fn statement(&mut self, frame: u64, stack: &[Call]) {
+        let names = stack.iter().map(|call| call.name.clone()).collect();
+        let top = stack.last().unwrap();
+        self.trace
+            .borrow_mut()
+            .push((frame, names, top.span.start.line + 1));
+        if let Some(locals) = &top.locals {
+            let mut names: Vec<String> = locals
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            names.sort();
+            self.locals.borrow_mut().extend(names);
+        }
+    }
+}
+
+#[test]
+fn test_debugger_sees_each_statement() {
+    let mut interpreter = interpreter(
+        "\
+// Calls, imports and objects each add to the stack
+func Double(n)
+  var d = n * 2
+  return d
+var x = Double(2)
+import Lib
+var v = new Vec
+",
+    );
+    let trace = Trace::default();
+    let locals = Rc::new(RefCell::new(Vec::new()));
+    interpreter.set_debugger(Some(Box::new(Recorder {
+        trace: trace.clone(),
+        locals: locals.clone(),
+    })));
+    let mut fixture = Fixture::new();
+    fixture.add_module("Lib", ">lib");
+    fixture.add_module("Vec", "var x = 1");
+    interpreter.run_frame(&mut fixture).unwrap();
+
+    let main = || String::new();
+    assert_eq!(
+        *trace.borrow(),
+        vec![
+            (0, vec![main()], 2),
+            (0, vec![main()], 5),
+            (0, vec![main(), "Double".to_string()], 3),
+            (0, vec![main(), "Double".to_string()], 4),
+            (0, vec![main()], 6),
+            (0, vec![main(), "Lib".to_string()], 1),
+            (0, vec![main()], 7),
+            (0, vec![main(), "Vec".to_string()], 1),
+        ]
+    );
+    assert_eq!(*locals.borrow(), vec!["n=2", "d=4", "n=2"]);
+}
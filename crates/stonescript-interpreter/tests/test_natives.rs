@@ -0,0 +1,83 @@
+//! The `math`, `string` and `storage` namespaces and array methods
+
+use stonescript_interpreter::{Fixture, Interpreter};
+use stonescript_parser::parse_source;
+
+/// What `expression` prints as
+fn eval(expression: &str) -> String {
+    let program = parse_source(&format!(">@{}@", expression)).unwrap();
+    let frame = Interpreter::new(program)
+        .run_frame(&mut Fixture::new())
+        .unwrap_or_else(|e| panic!("{}: {}", expression, e));
+    frame.prints().remove(0)
+}
+
+#[test]
+fn test_math() {
+    assert_eq!(eval("math.Abs(-3)"), "3");
+    assert_eq!(eval("math.Sqrt(16)"), "4");
+    assert_eq!(eval("math.Pow(2, 10)"), "1024");
+    assert_eq!(eval("math.Max(3, 8)"), "8");
+    assert_eq!(eval("math.min(3, 1.5)"), "1.5");
+    assert_eq!(eval("math.Clamp(12, 0, 10)"), "10");
+    assert_eq!(eval("math.FloorToInt(2.7)"), "2");
+    assert_eq!(eval("math.RoundToInt(2.5)"), "3");
+    assert_eq!(eval("math.Lerp(0, 10, 0.25)"), "2.5");
+    assert_eq!(eval("math.Round(math.Sin(math.pi / 2))"), "1");
+}
+
+#[test]
+fn test_string() {
+    assert_eq!(eval("string.Size(\"héllo\")"), "5");
+    assert_eq!(eval("string.Sub(\"stonescript\", 5)"), "script");
+    assert_eq!(eval("string.Sub(\"stonescript\", 0, 5)"), "stone");
+    assert_eq!(eval("string.IndexOf(\"a,b,c\", \",\", 2)"), "3");
+    assert_eq!(eval("string.Split(\"a,b,,c\", \",\", true)"), "[a, b, c]");
+    assert_eq!(eval("string.Join(\"-\", [1, 2, 3])"), "1-2-3");
+    assert_eq!(eval("string.Format(\"{0}/{1}\", 3, 10)"), "3/10");
+    assert_eq!(eval("string.Equals(\"a\", \"a\")"), "true");
+    assert_eq!(
+        eval("string.Break(\"one two three\", 7)"),
+        "[one two, three]"
+    );
+    assert_eq!(eval("string.Capitalize(\"rocky\")"), "Rocky");
+    assert_eq!(eval("int.Parse(\"42\") + 1"), "43");
+    assert_eq!(eval("Type(1.5)"), "float");
+}
+
+#[test]
+fn test_storage() {
+    let program = parse_source(
+        "\
+storage.Set(\"b\", 2)
+storage.Set(\"a\", 1)
+storage.Set(\"gone\", 0)
+storage.Delete(\"gone\")
+>@storage.Keys()@ @storage.Has(\"gone\")@ @storage.Incr(\"a\", 5)@
+",
+    )
+    .unwrap();
+    let mut interpreter = Interpreter::new(program);
+    let frame = interpreter.run_frame(&mut Fixture::new()).unwrap();
+    assert_eq!(frame.prints(), vec!["[a, b] false 6"]);
+}
+
+#[test]
+fn test_array_methods() {
+    let program = parse_source(
+        "\
+var a = [3, 1, 2]
+a.Add(0)
+a.Sort()
+a.Insert(1, 9)
+var removed = a.RemoveAt(0)
+a.Emplace(0, 8)
+>@a@ @removed@ @a.Count()@ @a.IndexOf(3)@ @a.Contains(7)@
+",
+    )
+    .unwrap();
+    let frame = Interpreter::new(program)
+        .run_frame(&mut Fixture::new())
+        .unwrap();
+    assert_eq!(frame.prints(), vec!["[8, 1, 2, 3] 0 4 3 false"]);
+}
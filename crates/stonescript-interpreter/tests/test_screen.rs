@@ -0,0 +1,83 @@
+//! Drawing prints onto the virtual screen
+
+use stonescript_interpreter::{Color, Fixture, Interpreter, Screen};
+use stonescript_parser::parse_source;
+
+/// A small screen with one frame of `source` drawn on it
+fn screen(source: &str) -> Screen {
+    let frame = Interpreter::new(parse_source(source).unwrap())
+        .run_frame(&mut Fixture::new())
+        .unwrap();
+    let mut screen = Screen::with_size(12, 6);
+    screen.set_player(2, 3);
+    screen.set_foe(Some((8, 3)));
+    screen.render(&frame.events);
+    screen
+}
+
+#[test]
+fn test_anchors() {
+    let screen = screen(">`0,0,top\n>c-1,0,C\n>o0,-1,P\n>f1,0,F\n>h0,1,hi");
+    assert_eq!(screen.to_text(), "top\n\n  P\n     C   F\n  hi\n\n");
+    assert_eq!(Screen::new().to_text().lines().count(), 30);
+}
+
+#[test]
+fn test_foe_prints_need_a_foe() {
+    let frame = Interpreter::new(parse_source(">f0,0,F").unwrap())
+        .run_frame(&mut Fixture::new())
+        .unwrap();
+    let mut screen = Screen::with_size(4, 2);
+    screen.render(&frame.events);
+    assert_eq!(screen.to_text(), "\n\n");
+}
+
+#[test]
+fn test_spaces_are_transparent_and_hud_is_on_top() {
+    let screen = screen(">h0,0,H\n>`0,3,ab cd\n>`0,3,x   y\n");
+    assert_eq!(screen.to_text().lines().nth(3), Some("xbHcy"));
+}
+
+#[test]
+fn test_clipping_and_multiline() {
+    let screen = screen(">`10,5,abc\n>`-1,0,xyz\n>`0,1,one\ntwo");
+    let text = screen.to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "yz");
+    assert_eq!(lines[5], "          ab");
+}
+
+#[test]
+fn test_colors() {
+    let screen = screen(">`0,0,#f80,ab[color=#00ff00]c[/color]d");
+    let colors: Vec<Color> = (0..4).map(|x| screen.cell(x, 0).unwrap().color).collect();
+    assert_eq!(
+        colors,
+        vec![
+            Color::Rgb(0xff, 0x88, 0x00),
+            Color::Rgb(0xff, 0x88, 0x00),
+            Color::Rgb(0x00, 0xff, 0x00),
+            Color::Rgb(0xff, 0x88, 0x00),
+        ]
+    );
+    assert_eq!(Color::parse("#red"), Some(Color::Rgb(0xff, 0, 0)));
+    assert_eq!(Color::parse("#rainbow"), Some(Color::Rainbow));
+    assert_eq!(Color::parse("#nope"), None);
+}
+
+#[test]
+fn test_ansi_and_html() {
+    let screen = screen(">`0,0,#red,<a>\n>`0,1,b");
+    let ansi = screen.to_ansi();
+    assert!(
+        ansi.starts_with("\x1b[38;2;255;0;0m<a>\x1b[0m\n"),
+        "{:?}",
+        ansi
+    );
+    assert!(ansi.contains("\x1b[38;2;255;255;255mb\x1b[0m\n"));
+
+    let html = screen.to_html();
+    assert!(html.contains("<span style=\"color: #ff0000\">&lt;a&gt;</span>"));
+    assert!(html.contains("\nb   "));
+    assert!(html.ends_with("</pre>\n</body>\n</html>\n"));
+}
@@ -0,0 +1,131 @@
+//! Client-provided server settings
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Settings sent by the client as initialization options or through
+/// `workspace/didChangeConfiguration`, either bare or under a `stonescript` key
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Extra snippets offered alongside the built-in ones
+    pub snippets: Vec<UserSnippet>,
+    /// Severity overrides keyed by diagnostic code, e.g.
+    /// `{ "string-number-comparison": "off" }`
+    pub diagnostic_severities: HashMap<String, DiagnosticLevel>,
+    /// Indentation used by the formatter
+    pub formatting: FormattingSettings,
+}
+
+/// Formatter options; unset ones follow the editor's tab settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormattingSettings {
+    pub indent_style: Option<IndentStyle>,
+    /// Spaces per level when indenting with spaces
+    pub indent_width: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    Spaces,
+    Tabs,
+}
+
+/// Severity a diagnostic is reported with, or `Off` to hide it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Information,
+    Hint,
+    Off,
+}
+
+impl DiagnosticLevel {
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            DiagnosticLevel::Error => Some(DiagnosticSeverity::ERROR),
+            DiagnosticLevel::Warning => Some(DiagnosticSeverity::WARNING),
+            DiagnosticLevel::Information => Some(DiagnosticSeverity::INFORMATION),
+            DiagnosticLevel::Hint => Some(DiagnosticSeverity::HINT),
+            DiagnosticLevel::Off => None,
+        }
+    }
+}
+
+/// A snippet defined in the user's configuration
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UserSnippet {
+    pub label: String,
+    /// Body in LSP snippet syntax
+    pub body: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Settings {
+    /// Read settings from a client payload, falling back to defaults for
+    /// anything missing or malformed
+    pub fn from_value(value: Option<Value>) -> Self {
+        let Some(value) = value else {
+            return Self::default();
+        };
+        Self::try_from_value(value).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid settings: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Read settings, bare or under a `stonescript` key, reporting anything
+    /// malformed
+    pub fn try_from_value(mut value: Value) -> Result<Self, serde_json::Error> {
+        if let Some(nested) = value.get_mut("stonescript") {
+            value = nested.take();
+        }
+        serde_json::from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_settings_from_value() {
+        let settings = Settings::from_value(Some(json!({
+            "stonescript": {
+                "snippets": [{ "label": "hp", "body": "?hp < ${1:5}\n  $0" }]
+            }
+        })));
+        assert_eq!(settings.snippets.len(), 1);
+        assert_eq!(settings.snippets[0].label, "hp");
+        assert_eq!(settings.snippets[0].description, None);
+
+        assert!(Settings::from_value(None).snippets.is_empty());
+        assert!(settings.diagnostic_severities.is_empty());
+        assert!(Settings::from_value(Some(json!({ "snippets": 3 })))
+            .snippets
+            .is_empty());
+    }
+
+    #[test]
+    fn test_diagnostic_severities() {
+        let settings = Settings::from_value(Some(json!({
+            "diagnosticSeverities": { "bool-arithmetic": "error", "unused-variable": "off" }
+        })));
+        assert_eq!(
+            settings.diagnostic_severities["bool-arithmetic"].severity(),
+            Some(DiagnosticSeverity::ERROR)
+        );
+        assert_eq!(
+            settings.diagnostic_severities["unused-variable"].severity(),
+            None
+        );
+    }
+}
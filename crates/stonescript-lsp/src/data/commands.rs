@@ -0,0 +1,25 @@
+//! Argument values of the equip, loadout, brew and activate commands
+
+/// Highest star level of an item, as in `equip sword *10`
+pub const MAX_STAR_LEVEL: i64 = 10;
+
+/// Highest enchantment level of an item, as in `equip sword +10`
+pub const MAX_ENCHANT_LEVEL: i64 = 10;
+
+/// Ingredients `brew` refills the potion with
+pub const BREW_INGREDIENTS: &[(&str, &str)] = &[
+    ("stone", "Stone"),
+    ("wood", "Wood"),
+    ("tar", "Tar"),
+    ("bronze", "Bronze"),
+];
+
+/// What `activate` can use besides an ability ID
+pub const ACTIVATE_SLOTS: &[(&str, &str)] = &[
+    ("potion", "Drinks the potion"),
+    ("P", "Drinks the potion"),
+    ("left", "Activates the left hand item"),
+    ("L", "Activates the left hand item"),
+    ("right", "Activates the right hand item"),
+    ("R", "Activates the right hand item"),
+];
@@ -193,6 +193,34 @@ pub const KEYWORDS: &[KeywordInfo] = &[
         examples: &[">o-6,3,#red,Let's go!"],
         category: KeywordCategory::Print,
     },
+    KeywordInfo {
+        name: ">h",
+        description: "Advanced print relative to player, drawn on the HUD layer.",
+        usage: ">hX,Y,[#color,]<text>",
+        examples: &[">h-4,-3,#white,ascii\n(o.o)\nasciiend"],
+        category: KeywordCategory::Print,
+    },
+    KeywordInfo {
+        name: ">c",
+        description: "Advanced print relative to the center of the screen.",
+        usage: ">cX,Y,[#color,]<text>",
+        examples: &[">c0,-5,#yellow,Boss incoming!"],
+        category: KeywordCategory::Print,
+    },
+    KeywordInfo {
+        name: ">f",
+        description: "Advanced print relative to the targeted foe.",
+        usage: ">fX,Y,[#color,]<text>",
+        examples: &[">f0,-3,#red,@foe.hp@"],
+        category: KeywordCategory::Print,
+    },
+    KeywordInfo {
+        name: ">`",
+        description: "Advanced print relative to the upper-left corner of the screen.",
+        usage: ">`X,Y,[#color,]<text>",
+        examples: &[">`0,0,#cyan,Gold: @loc.gp@"],
+        category: KeywordCategory::Print,
+    },
 ];
 
 /// Get keyword info by name
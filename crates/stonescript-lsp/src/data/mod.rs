@@ -1,6 +1,7 @@
 //! StoneScript data modules
 
 pub mod abilities;
+pub mod commands;
 pub mod filters;
 pub mod foes;
 pub mod game_state;
@@ -8,10 +9,12 @@ pub mod keywords;
 pub mod locations;
 pub mod music;
 pub mod native_functions;
+pub mod snippets;
 pub mod sounds;
 pub mod ui;
 
 pub use abilities::ABILITY_IDS;
+pub use commands::{ACTIVATE_SLOTS, BREW_INGREDIENTS, MAX_ENCHANT_LEVEL, MAX_STAR_LEVEL};
 pub use filters::{ALL_FILTERS, FOE_FILTERS, ITEM_FILTERS};
 pub use foes::{get_foe_name, FOES, FOE_NAMES};
 pub use game_state::{get_game_state, GameStateQuery, Type, GAME_STATE_QUERIES};
@@ -19,8 +22,9 @@ pub use keywords::{get_keyword, KeywordCategory, KeywordInfo, KEYWORDS};
 pub use locations::{get_location_name, LOCATIONS, LOCATION_NAMES};
 pub use music::MUSIC_TRACKS;
 pub use native_functions::{
-    get_function, get_functions_in_namespace, FunctionSignature, Parameter, MATH_FUNCTIONS,
-    MUSIC_FUNCTIONS, STORAGE_FUNCTIONS, STRING_FUNCTIONS, UI_FUNCTIONS,
+    get_function, get_functions_in_namespace, FunctionSignature, Parameter, ARRAY_METHODS,
+    MATH_FUNCTIONS, MUSIC_FUNCTIONS, STORAGE_FUNCTIONS, STRING_FUNCTIONS, UI_FUNCTIONS,
 };
+pub use snippets::{SnippetInfo, SNIPPETS};
 pub use sounds::SOUND_EFFECTS;
-pub use ui::{UI_COMPONENTS, UI_METHODS, UI_PROPERTIES};
+pub use ui::{component_methods, component_properties, UI_COMPONENTS, UI_METHODS, UI_PROPERTIES};
@@ -131,6 +131,231 @@ pub const MATH_FUNCTIONS: &[FunctionSignature] = &[
         return_type: Type::Float,
         description: "Rounds to nearest integer",
     },
+    FunctionSignature {
+        namespace: "math",
+        name: "Sin",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the sine of an angle in radians",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Cos",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the cosine of an angle in radians",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Tan",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the tangent of an angle in radians",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Sign",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns -1, 0 or 1 for the sign of x",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Clamp",
+        parameters: &[
+            Parameter {
+                name: "x",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "min",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "max",
+                typ: Type::Float,
+                optional: false,
+            },
+        ],
+        return_type: Type::Float,
+        description: "Limits x to the range from min to max",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "RoundToInt",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Int,
+        description: "Rounds to the nearest integer, as an Int",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "FloorToInt",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Int,
+        description: "Rounds down to the nearest integer, as an Int",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "CeilToInt",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Int,
+        description: "Rounds up to the nearest integer, as an Int",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Lerp",
+        parameters: &[
+            Parameter {
+                name: "a",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "b",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "t",
+                typ: Type::Float,
+                optional: false,
+            },
+        ],
+        return_type: Type::Float,
+        description: "Interpolates from a to b by the fraction t",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Log",
+        parameters: &[
+            Parameter {
+                name: "x",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "base",
+                typ: Type::Float,
+                optional: true,
+            },
+        ],
+        return_type: Type::Float,
+        description: "Returns the logarithm of x, natural unless a base is given",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Exp",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns e raised to x",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "ToDeg",
+        parameters: &[Parameter {
+            name: "radians",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Converts an angle from radians to degrees",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "ToRad",
+        parameters: &[Parameter {
+            name: "degrees",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Converts an angle from degrees to radians",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Asin",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the angle in radians whose sine is x",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Acos",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the angle in radians whose cosine is x",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Atan",
+        parameters: &[Parameter {
+            name: "x",
+            typ: Type::Float,
+            optional: false,
+        }],
+        return_type: Type::Float,
+        description: "Returns the angle in radians whose tangent is x",
+    },
+    FunctionSignature {
+        namespace: "math",
+        name: "Atan2",
+        parameters: &[
+            Parameter {
+                name: "y",
+                typ: Type::Float,
+                optional: false,
+            },
+            Parameter {
+                name: "x",
+                typ: Type::Float,
+                optional: false,
+            },
+        ],
+        return_type: Type::Float,
+        description: "Returns the angle in radians of the point (x, y)",
+    },
 ];
 
 /// String namespace functions
@@ -311,6 +536,13 @@ pub const UI_FUNCTIONS: &[FunctionSignature] = &[
         return_type: Type::Object("Anim"),
         description: "Adds an Anim object to the root Panel",
     },
+    FunctionSignature {
+        namespace: "ui",
+        name: "AddCanvas",
+        parameters: &[],
+        return_type: Type::Object("Canvas"),
+        description: "Adds a Canvas object to the root Panel",
+    },
     FunctionSignature {
         namespace: "ui",
         name: "AddStyle",
@@ -363,8 +595,127 @@ pub const UI_FUNCTIONS: &[FunctionSignature] = &[
     },
 ];
 
+/// Methods called on array values, e.g. `a.Add(1)`
+pub const ARRAY_METHODS: &[FunctionSignature] = &[
+    FunctionSignature {
+        namespace: "Array",
+        name: "Add",
+        parameters: &[Parameter {
+            name: "value",
+            typ: Type::Unknown,
+            optional: false,
+        }],
+        return_type: Type::Unknown,
+        description: "Appends a value to the end of the array",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Clear",
+        parameters: &[],
+        return_type: Type::Unknown,
+        description: "Removes all elements",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Contains",
+        parameters: &[Parameter {
+            name: "value",
+            typ: Type::Unknown,
+            optional: false,
+        }],
+        return_type: Type::Bool,
+        description: "Returns true if the array contains the value",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Count",
+        parameters: &[],
+        return_type: Type::Int,
+        description: "Returns the number of elements",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Emplace",
+        parameters: &[
+            Parameter {
+                name: "index",
+                typ: Type::Int,
+                optional: false,
+            },
+            Parameter {
+                name: "value",
+                typ: Type::Unknown,
+                optional: false,
+            },
+        ],
+        return_type: Type::Unknown,
+        description: "Replaces the element at the index",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "IndexOf",
+        parameters: &[Parameter {
+            name: "value",
+            typ: Type::Unknown,
+            optional: false,
+        }],
+        return_type: Type::Int,
+        description: "Returns the index of the value, or -1 if not found",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Insert",
+        parameters: &[
+            Parameter {
+                name: "index",
+                typ: Type::Int,
+                optional: false,
+            },
+            Parameter {
+                name: "value",
+                typ: Type::Unknown,
+                optional: false,
+            },
+        ],
+        return_type: Type::Unknown,
+        description: "Inserts a value at the index",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "RemoveAt",
+        parameters: &[Parameter {
+            name: "index",
+            typ: Type::Int,
+            optional: false,
+        }],
+        return_type: Type::Unknown,
+        description: "Removes the element at the index and returns it",
+    },
+    FunctionSignature {
+        namespace: "Array",
+        name: "Sort",
+        parameters: &[],
+        return_type: Type::Unknown,
+        description: "Sorts the array in ascending order",
+    },
+];
+
+/// Functions called without a namespace
+pub const GLOBAL_FUNCTIONS: &[FunctionSignature] = &[FunctionSignature {
+    namespace: "",
+    name: "Type",
+    parameters: &[Parameter {
+        name: "value",
+        typ: Type::Unknown,
+        optional: false,
+    }],
+    return_type: Type::String,
+    description: "Returns the name of a value's type, e.g. \"int\" or \"string\"",
+}];
+
 /// All native functions combined
 pub const ALL_FUNCTIONS: &[&[FunctionSignature]] = &[
+    GLOBAL_FUNCTIONS,
     MATH_FUNCTIONS,
     STRING_FUNCTIONS,
     STORAGE_FUNCTIONS,
@@ -0,0 +1,65 @@
+//! Snippet templates for common StoneScript constructs
+
+/// A completion snippet in LSP snippet syntax (`${1:placeholder}`, `$0`)
+#[derive(Debug, Clone)]
+pub struct SnippetInfo {
+    pub label: &'static str,
+    pub description: &'static str,
+    pub body: &'static str,
+}
+
+/// Built-in snippets, offered wherever a statement may start
+pub const SNIPPETS: &[SnippetInfo] = &[
+    SnippetInfo {
+        label: "func",
+        description: "Function with parameters and body",
+        body: "func ${1:Name}(${2:a}, ${3:b})\n  ${4:return a}\n$0",
+    },
+    SnippetInfo {
+        label: "for",
+        description: "Loop over a range of numbers",
+        body: "for ${1:i} = ${2:0}..${3:10}\n  $0",
+    },
+    SnippetInfo {
+        label: "for :",
+        description: "Loop over the elements of an array",
+        body: "for ${1:e} : ${2:array}\n  $0",
+    },
+    SnippetInfo {
+        label: "?",
+        description: "Condition",
+        body: "?${1:condition}\n  $0",
+    },
+    SnippetInfo {
+        label: "? :",
+        description: "Condition with an else branch",
+        body: "?${1:condition}\n  ${2}\n:\n  $0",
+    },
+    SnippetInfo {
+        label: "? :? :",
+        description: "Condition chain with else-if and else branches",
+        body: "?${1:condition}\n  ${2}\n:?${3:condition}\n  ${4}\n:\n  $0",
+    },
+    SnippetInfo {
+        label: "ascii",
+        description: "Multiline ascii art block",
+        body: "var ${1:art} = ascii\n${0}\nasciiend",
+    },
+    SnippetInfo {
+        label: "ui.AddButton",
+        description: "Button with position, size, text and press handler",
+        body: "var ${1:button} = ui.AddButton()\n\
+               ${1:button}.x = ${2:0}\n\
+               ${1:button}.y = ${3:0}\n\
+               ${1:button}.w = ${4:10}\n\
+               ${1:button}.h = ${5:3}\n\
+               ${1:button}.text = \"${6:Press}\"\n\
+               ${1:button}.SetPressed(${7:OnPressed})\n\
+               $0",
+    },
+    SnippetInfo {
+        label: "new",
+        description: "Variable holding a new instance of a script",
+        body: "var ${1:name} = new ${2:Components/Vector}\n$0",
+    },
+];
@@ -33,6 +33,31 @@ pub const SOUND_EFFECTS: &[&str] = &[
     // Common game sounds
     "success", "failure", "warning",
     "notification", "achievement",
+    
+    // Played by community scripts
+    "ant_walk", "bat_wing", "bat_wing_small", "bearer4_talk_evolving",
+    "bearer5_talk", "bearer_attack", "bearer_attack_hit",
+    "bell_ringer_attack", "blade_drag", "blade_pallas_attack",
+    "bomb_cart_explosion", "bomb_cart_fuse", "bomb_cart_move", "boo_voice",
+    "booklet_close", "booklet_turn_page", "bronze_guardian_attack2",
+    "controller_whip_attack", "controller_whip_hit", "cross_deadwood_row",
+    "cross_deadwood_splash", "crossbow_cast", "crossbow_hit", "dog_bark",
+    "fire_elemental_attack_hit", "fire_geyser", "flying_serpent_loop",
+    "frog", "grappling_hit", "grappling_idle", "hammer_hit",
+    "haunted_gate_key_bounce_1", "haunted_gate_point_lost",
+    "haunted_gate_shuffle", "haunted_gate_shuffle_fast", "ki_slerper_walk",
+    "life_gain", "mindstone_off", "mine_walker_attack_a",
+    "mine_walker_attack_b", "mine_walker_attack_hit", "mosquito_death",
+    "open_note", "perfected_defense", "pickup_bronze", "pickup_success",
+    "player_kick", "player_punch", "quest_stone_jump", "scorpion_death",
+    "scotty_out_of_treasure", "scout_arrives", "scout_wing",
+    "serpent_slither", "smithy_hammer", "smithy_hammer_fail", "snail_walk",
+    "soul_stone", "spider_boss_attack", "spider_boss_awake",
+    "spider_eggs_spawn", "stone_throw_hit", "sword_hit",
+    "treasure_item_pop", "treasure_item_show", "tree_boss_spike",
+    "ui_starnew", "ui_starold", "ui_starold2", "unequip", "uulaa_voice",
+    "wand_aether_cast", "wand_aether_hit", "wand_fire_cast", "wand_hit",
+    "xp_tick",
 ];
 
 // Note: Manual lists ~500 sounds. This is a representative subset.
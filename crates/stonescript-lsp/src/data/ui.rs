@@ -9,48 +9,92 @@ pub const UI_COMPONENTS: &[&str] = &[
     "Canvas",
 ];
 
-/// Common UI properties
-pub const UI_PROPERTIES: &[&str] = &[
+/// UI properties, with the component kinds that have them
+pub const UI_PROPERTIES: &[(&str, &[&str])] = &[
     // Component base properties
-    "x", "y", "w", "h",
-    "anchor", "dock",
-    "ax", "ay", "dx", "dy",
-    "visible",
-    "absoluteX", "absoluteY",
-    "parent",
-    
+    ("x", UI_COMPONENTS),
+    ("y", UI_COMPONENTS),
+    ("w", UI_COMPONENTS),
+    ("h", UI_COMPONENTS),
+    ("anchor", UI_COMPONENTS),
+    ("dock", UI_COMPONENTS),
+    ("ax", UI_COMPONENTS),
+    ("ay", UI_COMPONENTS),
+    ("dx", UI_COMPONENTS),
+    ("dy", UI_COMPONENTS),
+    ("visible", UI_COMPONENTS),
+    ("absoluteX", UI_COMPONENTS),
+    ("absoluteY", UI_COMPONENTS),
+    ("parent", UI_COMPONENTS),
     // Panel specific
-    "children", "clip", "color", "style",
-    
+    ("children", &["Panel"]),
+    ("clip", &["Panel"]),
+    ("color", &["Panel", "Text", "Button", "Anim"]),
+    ("style", &["Panel"]),
     // Text specific
-    "align", "lines", "text",
-    
+    ("align", &["Text"]),
+    ("lines", &["Text"]),
+    ("text", &["Text", "Button"]),
     // Button specific
-    "tcolor", "bcolor", "hcolor", "sound",
-    
+    ("tcolor", &["Button"]),
+    ("bcolor", &["Button"]),
+    ("hcolor", &["Button"]),
+    ("sound", &["Button"]),
     // Anim specific
-    "duration", "flipX", "flipY", "frame",
-    "gamePause", "loop", "playing", "paused",
-    "pivotX", "pivotY", "playOnStart",
-    
+    ("duration", &["Anim"]),
+    ("flipX", &["Anim"]),
+    ("flipY", &["Anim"]),
+    ("frame", &["Anim"]),
+    ("gamePause", &["Anim"]),
+    ("loop", &["Anim"]),
+    ("playing", &["Anim"]),
+    ("paused", &["Anim"]),
+    ("pivotX", &["Anim"]),
+    ("pivotY", &["Anim"]),
+    ("playOnStart", &["Anim"]),
     // Canvas specific
-    "blend",
+    ("blend", &["Canvas"]),
 ];
 
-/// UI methods
-pub const UI_METHODS: &[&str] = &[
+/// UI methods, with the component kinds that have them
+pub const UI_METHODS: &[(&str, &[&str])] = &[
     // Component
-    "Recycle",
-    
+    ("Recycle", UI_COMPONENTS),
     // Panel
-    "Add", "Clear", "Remove",
-    
+    ("Add", &["Panel"]),
+    ("Clear", &["Panel"]),
+    ("Remove", &["Panel"]),
     // Button
-    "SetPressed", "SetDown", "SetUp",
-    
+    ("SetPressed", &["Button"]),
+    ("SetDown", &["Button"]),
+    ("SetUp", &["Button"]),
     // Anim
-    "AddLayer", "Load", "Pause", "Play", "Stop",
-    
+    ("AddLayer", &["Anim"]),
+    ("Load", &["Anim"]),
+    ("Pause", &["Anim"]),
+    ("Play", &["Anim"]),
+    ("Stop", &["Anim"]),
     // Canvas
-    "Get", "Set", "SetFG", "SetBG",
+    ("Get", &["Canvas"]),
+    ("Set", &["Canvas"]),
+    ("SetFG", &["Canvas"]),
+    ("SetBG", &["Canvas"]),
 ];
+
+/// Properties available on a component of the given kind
+pub fn component_properties(kind: &str) -> Vec<&'static str> {
+    members_of(UI_PROPERTIES, kind)
+}
+
+/// Methods available on a component of the given kind
+pub fn component_methods(kind: &str) -> Vec<&'static str> {
+    members_of(UI_METHODS, kind)
+}
+
+fn members_of(table: &[(&'static str, &[&str])], kind: &str) -> Vec<&'static str> {
+    table
+        .iter()
+        .filter(|(_, kinds)| kinds.contains(&kind))
+        .map(|(name, _)| *name)
+        .collect()
+}
@@ -1,5 +1,6 @@
 //! StoneScript LSP Library
 
+pub mod config;
 pub mod data;
 pub mod server;
 pub mod utils;
@@ -0,0 +1,265 @@
+//! Code action provider (quick fixes for diagnostics)
+
+use crate::providers::conditions::condition_refactorings;
+use crate::providers::diagnostics::{
+    for_each_statement, to_range, UNDECLARED_VARIABLE, UNKNOWN_FUNCTION, UNKNOWN_GAME_ID,
+    UNKNOWN_PROPERTY, UNUSED_VARIABLE, WRONG_ARGUMENT_COUNT,
+};
+use crate::providers::refactoring::{
+    contains_call, extract_function, extract_variable, inline_variable,
+};
+use crate::utils::{ScopeAnalyzer, Workspace};
+use serde_json::Value;
+use std::collections::HashMap;
+use stonescript_parser::{Program, Statement};
+use tower_lsp::lsp_types::*;
+
+pub struct CodeActionProvider;
+
+impl CodeActionProvider {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Quick fixes for the diagnostics the client sent along with the
+    /// request, followed by the refactorings available for the selection
+    #[allow(clippy::too_many_arguments)]
+    pub fn provide_code_actions(
+        &self,
+        ast: &Program,
+        source: &str,
+        scope: &ScopeAnalyzer,
+        uri: &Url,
+        range: Range,
+        diagnostics: &[Diagnostic],
+        workspace: &Workspace,
+    ) -> Vec<CodeActionOrCommand> {
+        let mut actions = Vec::new();
+
+        for diagnostic in diagnostics {
+            let Some(NumberOrString::String(code)) = &diagnostic.code else {
+                continue;
+            };
+            let data = diagnostic.data.as_ref().unwrap_or(&Value::Null);
+
+            match code.as_str() {
+                UNKNOWN_PROPERTY | UNKNOWN_FUNCTION | UNKNOWN_GAME_ID => {
+                    let suggestions = strings(&data["suggestions"]);
+                    let preferred = suggestions.len() == 1;
+                    for suggestion in suggestions {
+                        actions.push(quick_fix(
+                            format!("Did you mean `{}`?", suggestion),
+                            diagnostic,
+                            uri,
+                            vec![TextEdit::new(diagnostic.range, suggestion)],
+                            preferred,
+                        ));
+                    }
+
+                    if let Some(name) = data["name"].as_str() {
+                        let position = import_position(ast);
+                        for module in workspace.modules_defining(uri, name) {
+                            actions.push(quick_fix(
+                                format!("Add `import {}`", module),
+                                diagnostic,
+                                uri,
+                                vec![TextEdit::new(
+                                    Range::new(position, position),
+                                    format!("import {}\n", module),
+                                )],
+                                false,
+                            ));
+                        }
+                    }
+                }
+                UNDECLARED_VARIABLE if data["declare"].as_bool() == Some(true) => {
+                    let start = diagnostic.range.start;
+                    actions.push(quick_fix(
+                        "Declare variable with `var`".to_string(),
+                        diagnostic,
+                        uri,
+                        vec![TextEdit::new(Range::new(start, start), "var ".to_string())],
+                        true,
+                    ));
+                }
+                UNUSED_VARIABLE => {
+                    if let Some((name, edit)) = remove_declaration(ast, diagnostic.range) {
+                        actions.push(quick_fix(
+                            format!("Remove unused variable `{}`", name),
+                            diagnostic,
+                            uri,
+                            vec![edit],
+                            true,
+                        ));
+                    }
+                }
+                WRONG_ARGUMENT_COUNT => {
+                    let missing = strings(&data["missing"]);
+                    let Some(position) = closing_paren(source, diagnostic.range) else {
+                        continue;
+                    };
+                    if missing.is_empty() {
+                        continue;
+                    }
+
+                    // Placeholders named after the parameters, before the `)`
+                    let mut text = missing.join(", ");
+                    if data["given"].as_u64().unwrap_or(0) > 0 {
+                        text.insert_str(0, ", ");
+                    }
+                    actions.push(quick_fix(
+                        if missing.len() == 1 {
+                            "Add missing argument".to_string()
+                        } else {
+                            "Add missing arguments".to_string()
+                        },
+                        diagnostic,
+                        uri,
+                        vec![TextEdit::new(Range::new(position, position), text)],
+                        true,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let refactorings = [
+            extract_function(ast, source, scope, uri, range),
+            extract_variable(ast, source, scope, uri, range),
+            inline_variable(ast, source, scope, uri, range),
+        ];
+        actions.extend(
+            refactorings
+                .into_iter()
+                .flatten()
+                .chain(condition_refactorings(ast, source, uri, range))
+                .map(CodeActionOrCommand::CodeAction),
+        );
+
+        actions
+    }
+}
+
+/// Position of the `)` closing the call that starts at `range`, found by
+/// matching brackets in the document from there on, through any `^`
+/// continuation lines and past strings and comments
+fn closing_paren(source: &str, range: Range) -> Option<Position> {
+    let mut depth = 0;
+    for (index, text) in source.lines().enumerate().skip(range.start.line as usize) {
+        let line = index as u32;
+        let skip = if line == range.start.line {
+            range.start.character as usize
+        } else if text.trim_start().starts_with('^') {
+            0
+        } else {
+            return None;
+        };
+        let mut chars = text.chars().enumerate().skip(skip).peekable();
+
+        let mut in_string = false;
+        while let Some((column, c)) = chars.next() {
+            match c {
+                '"' => in_string = !in_string,
+                _ if in_string => {}
+                '/' if chars.peek().is_some_and(|&(_, next)| next == '/') => break,
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(Position::new(line, column as u32));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+impl Default for CodeActionProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn quick_fix(
+    title: String,
+    diagnostic: &Diagnostic,
+    uri: &Url,
+    edits: Vec<TextEdit>,
+    preferred: bool,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        is_preferred: Some(preferred),
+        ..Default::default()
+    })
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// New imports go below the existing top-level ones, or at the top
+fn import_position(ast: &Program) -> Position {
+    let line = ast
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Import { span, .. } => Some(span.end.line as u32 + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    Position::new(line, 0)
+}
+
+/// Edit removing the declaration whose name is at `range`. An initializer
+/// that calls a function is kept as a statement of its own, since the call
+/// may have side effects.
+fn remove_declaration(ast: &Program, range: Range) -> Option<(String, TextEdit)> {
+    let mut found = None;
+    for_each_statement(&ast.statements, &mut |statement| {
+        if let Statement::VarDeclaration {
+            name,
+            name_span,
+            initializer,
+            span,
+        } = statement
+        {
+            if found.is_none() && to_range(name_span) == range {
+                found = Some((name.clone(), initializer.clone(), *span));
+            }
+        }
+    });
+    let (name, initializer, span) = found?;
+
+    let edit = match initializer {
+        Some(value) if contains_call(&value) => TextEdit::new(
+            Range::new(to_range(&span).start, to_range(&value.span()).start),
+            String::new(),
+        ),
+        _ => TextEdit::new(
+            Range::new(
+                Position::new(span.start.line as u32, 0),
+                Position::new(span.end.line as u32 + 1, 0),
+            ),
+            String::new(),
+        ),
+    };
+    Some((name, edit))
+}
@@ -1,44 +1,31 @@
 //! Completion provider
 
+use crate::config::UserSnippet;
 use crate::data::abilities::ABILITY_IDS;
 use crate::data::filters::{FOE_FILTERS, ITEM_FILTERS};
 use crate::data::foes::FOES;
 use crate::data::locations::LOCATIONS;
 use crate::data::music::MUSIC_TRACKS;
-use crate::data::native_functions::{
-    MATH_FUNCTIONS, MUSIC_FUNCTIONS, STORAGE_FUNCTIONS, STRING_FUNCTIONS, UI_FUNCTIONS,
-};
+use crate::data::native_functions::ALL_FUNCTIONS;
 use crate::data::sounds::SOUND_EFFECTS;
-use crate::data::ui::{UI_METHODS, UI_PROPERTIES};
+use crate::data::ui::UI_COMPONENTS;
 use crate::data::*;
-use crate::utils::ScopeAnalyzer;
-use regex;
-use stonescript_parser::{Expression, Program, Statement};
+use crate::utils::workspace::module_preview;
+use crate::utils::{
+    completion_context, infer_type_with_scope, Module, ModuleEntry, ScopeAnalyzer, Variable,
+    Workspace,
+};
+use regex::Regex;
+use std::sync::{Arc, LazyLock, RwLock};
+use stonescript_parser::{Expression, Program};
 use tower_lsp::lsp_types::*;
 
-#[derive(Debug)]
-pub enum CompletionContext {
-    /// Top-level statement context
-    TopLevel,
-    /// After a dot (member access)
-    MemberAccess(String), // object name
-    /// Inside function call
-    FunctionCall,
-    /// Inside specific function call (namespace, function_name)
-    InsideFunctionCall(String, String),
-    /// After keywords
-    AfterKeyword(String),
-    /// Variable/function reference
-    Identifier,
-    /// Binary expression with identifier (e.g., loc=, foe=)
-    BinaryWithIdentifier(String),
-    /// After equip/equipL/equipR keywords (for item filters)
-    AfterEquip,
-}
+pub use crate::utils::CompletionContext;
 
 pub struct CompletionProvider {
     keywords: &'static [KeywordInfo],
     game_state: &'static [GameStateQuery],
+    user_snippets: RwLock<Vec<UserSnippet>>,
 }
 
 impl CompletionProvider {
@@ -46,6 +33,14 @@ impl CompletionProvider {
         Self {
             keywords: KEYWORDS,
             game_state: GAME_STATE_QUERIES,
+            user_snippets: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Replace the snippets loaded from the user's configuration
+    pub fn set_user_snippets(&self, snippets: Vec<UserSnippet>) {
+        if let Ok(mut current) = self.user_snippets.write() {
+            *current = snippets;
         }
     }
 
@@ -55,125 +50,117 @@ impl CompletionProvider {
         position: Position,
         source: &str,
         scope: &ScopeAnalyzer,
+        uri: &Url,
+        workspace: &Workspace,
     ) -> Vec<CompletionItem> {
-        // Determine context from AST
-        let context = self.determine_context(ast, position, source);
-
-        let mut items = match context {
-            CompletionContext::TopLevel => self.complete_top_level(scope),
-            CompletionContext::MemberAccess(object) => self.complete_member_access(&object, source),
-            CompletionContext::AfterKeyword(keyword) => self.complete_after_keyword(&keyword),
-            CompletionContext::AfterEquip => self.complete_after_equip(),
-            CompletionContext::FunctionCall => self.complete_function_call(scope),
-            CompletionContext::InsideFunctionCall(namespace, func) => {
-                self.complete_inside_function_call(&namespace, &func)
+        match completion_context(source, position) {
+            CompletionContext::Statement => {
+                let mut items = self.complete_top_level(scope);
+                items.extend(self.snippet_items());
+                items
             }
-            CompletionContext::Identifier => self.complete_identifier(scope),
-            CompletionContext::BinaryWithIdentifier(identifier) => {
-                self.complete_binary_identifier(&identifier, scope)
+            CompletionContext::Expression => self.complete_expression(scope),
+            CompletionContext::MemberAccess(object) => {
+                let objects = ObjectLookup {
+                    ast,
+                    scope,
+                    uri,
+                    workspace,
+                };
+                self.complete_member_access(&object, &objects)
             }
-        };
-
-        // If no items found, try fallback strategies
-        if items.is_empty() {
-            // Try text-based detection one more time with simpler patterns
-            items = self.fallback_completion(position, source, scope);
+            CompletionContext::Argument { function, index } => {
+                self.complete_argument(&function, index, scope)
+            }
+            CompletionContext::Comparison(other) => self.complete_comparison(&other, scope),
+            CompletionContext::CommandArgument { command, .. } => {
+                self.complete_command_argument(&command, scope)
+            }
+            CompletionContext::ImportPath(prefix) | CompletionContext::NewPath(prefix) => {
+                self.complete_module_path(&prefix, uri, workspace)
+            }
+            CompletionContext::NoCompletion => Vec::new(),
         }
+    }
 
-        items
+    /// Built-in and user snippets, for places where a statement may start
+    fn snippet_items(&self) -> Vec<CompletionItem> {
+        let builtin = SNIPPETS
+            .iter()
+            .map(|snippet| (snippet.label, Some(snippet.description), snippet.body));
+        let user = self
+            .user_snippets
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_default();
+        let user = user.iter().map(|snippet| {
+            (
+                snippet.label.as_str(),
+                snippet.description.as_deref(),
+                snippet.body.as_str(),
+            )
+        });
+
+        builtin
+            .chain(user)
+            .map(|(label, description, body)| CompletionItem {
+                label: label.to_string(),
+                kind: Some(CompletionItemKind::SNIPPET),
+                detail: description.map(str::to_string),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```stonescript\n{}\n```", snippet_preview(body)),
+                })),
+                insert_text: Some(body.to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                insert_text_mode: Some(InsertTextMode::ADJUST_INDENTATION),
+                // Keep keywords of the same name above their snippets
+                sort_text: Some(format!("~{}", label)),
+                ..Default::default()
+            })
+            .collect()
     }
 
-    fn determine_context(
+    /// Next segment of an `import` or `new` path, from the script folders
+    fn complete_module_path(
         &self,
-        _ast: &Program,
-        position: Position,
-        source: &str,
-    ) -> CompletionContext {
-        // First, try text-based detection for incomplete expressions
-        // This handles cases like "?loc=" where the AST might not be complete
-        let line_start = source
-            .lines()
-            .take(position.line as usize)
-            .map(|line| line.len() + 1)
-            .sum::<usize>();
-        let line_end = line_start
-            + source
-                .lines()
-                .nth(position.line as usize)
-                .map(|l| l.len())
-                .unwrap_or(0);
-
-        if line_end > line_start {
-            let line_text = &source[line_start..line_end];
-            let cursor_col = position.character as usize;
-
-            // Check for patterns like "?loc=" or "?foe=" with cursor after =
-            if let Some(text_before_cursor) = line_text.get(..cursor_col.min(line_text.len())) {
-                // Pattern 1: ?identifier= or ?identifier=partial
-                // Matches: "?loc=", "?loc= ", "?loc=r", etc.
-                if let Some(caps) = regex::Regex::new(r"\?(\w+)\s*=\s*\w*$")
-                    .ok()
-                    .and_then(|re| re.captures(text_before_cursor))
-                {
-                    if let Some(ident) = caps.get(1) {
-                        let ident_str = ident.as_str();
-                        // Check if this is a known game state identifier
-                        if matches!(ident_str, "loc" | "foe" | "item" | "ai") {
-                            return CompletionContext::BinaryWithIdentifier(ident_str.to_string());
-                        }
-                    }
-                }
-
-                // Pattern 2: identifier= (without ?) for nested contexts
-                // Matches: " loc=", " foe=", etc.
-                if let Some(caps) = regex::Regex::new(r"[\s\(](\w+)\s*=\s*\w*$")
-                    .ok()
-                    .and_then(|re| re.captures(text_before_cursor))
-                {
-                    if let Some(ident) = caps.get(1) {
-                        let ident_str = ident.as_str();
-                        // Only trigger for known game state identifiers
-                        if matches!(ident_str, "loc" | "foe" | "item" | "ai") {
-                            return CompletionContext::BinaryWithIdentifier(ident_str.to_string());
-                        }
-                    }
-                }
-
-                // Pattern 3: Check for member access with dot
-                // Matches: "loc.", "foe.", etc.
-                if let Some(caps) = regex::Regex::new(r"(\w+)\.\s*$")
-                    .ok()
-                    .and_then(|re| re.captures(text_before_cursor))
-                {
-                    if let Some(ident) = caps.get(1) {
-                        return CompletionContext::MemberAccess(ident.as_str().to_string());
-                    }
-                }
-
-                // Pattern 4: Check for keywords followed by space
-                // Matches: "play ", "activate ", "equip ", "equipL ", "equipR "
-                if let Some(caps) = regex::Regex::new(r"^\s*(play|activate)\s+\w*$")
-                    .ok()
-                    .and_then(|re| re.captures(text_before_cursor))
-                {
-                    if let Some(keyword) = caps.get(1) {
-                        return CompletionContext::AfterKeyword(keyword.as_str().to_string());
-                    }
-                }
-
-                // Pattern 5: Check for equip commands
-                if let Some(_caps) = regex::Regex::new(r"^\s*(equip|equipL|equipR)\s+")
-                    .ok()
-                    .and_then(|re| re.captures(text_before_cursor))
-                {
-                    return CompletionContext::AfterEquip;
-                }
-            }
-        }
-
-        // Default to top-level context
-        // AST-based context detection could be added here in the future
-        CompletionContext::TopLevel
+        prefix: &str,
+        uri: &Url,
+        workspace: &Workspace,
+    ) -> Vec<CompletionItem> {
+        // Keep the separator style the path was started with
+        let separator = if prefix.contains('\\') { '\\' } else { '/' };
+
+        workspace
+            .list_modules(uri, prefix)
+            .into_iter()
+            .map(|entry| match entry {
+                ModuleEntry::Folder(name) => CompletionItem {
+                    label: name.clone(),
+                    kind: Some(CompletionItemKind::FOLDER),
+                    insert_text: Some(format!("{}{}", name, separator)),
+                    // Continue with the folder's contents right away
+                    command: Some(Command {
+                        title: "Suggest".to_string(),
+                        command: "editor.action.triggerSuggest".to_string(),
+                        arguments: None,
+                    }),
+                    ..Default::default()
+                },
+                ModuleEntry::File { name, path } => CompletionItem {
+                    detail: Some(format!("{}{}", prefix, name)),
+                    label: name,
+                    kind: Some(CompletionItemKind::FILE),
+                    documentation: module_preview(&path).map(|preview| {
+                        Documentation::MarkupContent(MarkupContent {
+                            kind: MarkupKind::PlainText,
+                            value: preview,
+                        })
+                    }),
+                    ..Default::default()
+                },
+            })
+            .collect()
     }
 
     fn complete_top_level(&self, scope: &ScopeAnalyzer) -> Vec<CompletionItem> {
@@ -231,203 +218,206 @@ impl CompletionProvider {
             });
         }
 
-        // Note: StoneScript doesn't support user-defined functions
+        items.extend(self.function_items(scope));
 
         items
     }
 
-    fn complete_member_access(&self, object: &str, source: &str) -> Vec<CompletionItem> {
+    fn complete_member_access(
+        &self,
+        object: &Expression,
+        objects: &ObjectLookup,
+    ) -> Vec<CompletionItem> {
+        let scope = objects.scope;
         let mut items = Vec::new();
 
-        // Helper to check if a variable is a UI component
-        let is_ui_component = |var_name: &str| -> bool {
-            let pattern = format!(
-                r"var\s+{}\s*=\s*ui\.(AddPanel|AddButton|AddText|AddAnim|root)",
-                regex::escape(var_name)
-            );
-            regex::Regex::new(&pattern)
-                .ok()
-                .and_then(|re| re.find(source))
-                .is_some()
-        };
+        // Namespaces and game state objects are referenced by name
+        if let Expression::Identifier(name, _) = object {
+            if find_variable(scope, name).is_none() {
+                for func in get_functions_in_namespace(name) {
+                    items.push(self.function_item(func));
+                }
 
-        // Find game state object
-        if let Some(query) = self.game_state.iter().find(|q| q.name == object) {
-            if let Some(properties) = query.properties {
-                for prop in properties {
-                    items.push(CompletionItem {
-                        label: prop.name.to_string(),
-                        kind: Some(CompletionItemKind::PROPERTY),
-                        detail: Some(format!("{}: {}", prop.name, prop.typ)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\n{}.{}: {}\n```\n\n{}",
-                                object, prop.name, prop.typ, prop.description
-                            ),
-                        })),
-                        ..Default::default()
-                    });
+                if name == "cooldown" {
+                    // Add ability IDs for cooldown queries
+                    for ability in ABILITY_IDS {
+                        items.push(CompletionItem {
+                            label: ability.to_string(),
+                            kind: Some(CompletionItemKind::PROPERTY),
+                            detail: Some(format!("Ability cooldown for {}", ability)),
+                            ..Default::default()
+                        });
+                    }
+                    return items;
                 }
             }
         }
 
-        // Check for namespace functions
-        match object {
-            "math" => {
-                for func in MATH_FUNCTIONS {
-                    let params = func
-                        .parameters
-                        .iter()
-                        .map(|p| format!("{}: {}", p.name, p.typ))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    items.push(CompletionItem {
-                        label: func.name.to_string(),
-                        kind: Some(CompletionItemKind::FUNCTION),
-                        detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\nmath.{}({}) -> {}\n```\n\n{}",
-                                func.name, params, func.return_type, func.description
-                            ),
-                        })),
-                        ..Default::default()
-                    });
-                }
-            }
-            "string" => {
-                for func in STRING_FUNCTIONS {
-                    let params = func
-                        .parameters
-                        .iter()
-                        .map(|p| format!("{}: {}", p.name, p.typ))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    items.push(CompletionItem {
-                        label: func.name.to_string(),
-                        kind: Some(CompletionItemKind::FUNCTION),
-                        detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\nstring.{}({}) -> {}\n```\n\n{}",
-                                func.name, params, func.return_type, func.description
-                            ),
-                        })),
-                        ..Default::default()
-                    });
-                }
-            }
-            "storage" => {
-                for func in STORAGE_FUNCTIONS {
-                    let params = func
-                        .parameters
-                        .iter()
-                        .map(|p| format!("{}: {}", p.name, p.typ))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    items.push(CompletionItem {
-                        label: func.name.to_string(),
-                        kind: Some(CompletionItemKind::FUNCTION),
-                        detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\nstorage.{}({}) -> {}\n```\n\n{}",
-                                func.name, params, func.return_type, func.description
-                            ),
-                        })),
-                        ..Default::default()
-                    });
-                }
-            }
-            "music" => {
-                for func in MUSIC_FUNCTIONS {
-                    let params = func
-                        .parameters
-                        .iter()
-                        .map(|p| format!("{}: {}", p.name, p.typ))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    items.push(CompletionItem {
-                        label: func.name.to_string(),
-                        kind: Some(CompletionItemKind::FUNCTION),
-                        detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\nmusic.{}({}) -> {}\n```\n\n{}",
-                                func.name, params, func.return_type, func.description
-                            ),
-                        })),
-                        ..Default::default()
-                    });
-                }
-            }
-            "ui" => {
-                for func in UI_FUNCTIONS {
-                    let params = func
-                        .parameters
-                        .iter()
-                        .map(|p| format!("{}: {}", p.name, p.typ))
-                        .collect::<Vec<_>>()
-                        .join(", ");
+        // Objects created with `new` expose the funcs and vars of their file
+        if let Some(module) = objects.module_for(object) {
+            items.extend(self.module_member_items(&module));
+            return items;
+        }
+
+        match receiver_type(object, scope) {
+            Type::Object(kind) if UI_COMPONENTS.contains(&kind) => {
+                for prop in component_properties(kind) {
                     items.push(CompletionItem {
-                        label: func.name.to_string(),
-                        kind: Some(CompletionItemKind::FUNCTION),
-                        detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: format!(
-                                "```stonescript\nui.{}({}) -> {}\n```\n\n{}",
-                                func.name, params, func.return_type, func.description
-                            ),
-                        })),
+                        label: prop.to_string(),
+                        kind: Some(CompletionItemKind::PROPERTY),
+                        detail: Some(format!("{} property", kind)),
                         ..Default::default()
                     });
                 }
-            }
-            "cooldown" => {
-                // Add ability IDs for cooldown queries
-                for ability in ABILITY_IDS {
+                for method in component_methods(kind) {
                     items.push(CompletionItem {
-                        label: ability.to_string(),
-                        kind: Some(CompletionItemKind::PROPERTY),
-                        detail: Some(format!("Ability cooldown for {}", ability)),
+                        label: method.to_string(),
+                        kind: Some(CompletionItemKind::METHOD),
+                        detail: Some(format!("{} method", kind)),
                         ..Default::default()
                     });
                 }
             }
-            _ => {
-                // Check if this is a UI component variable
-                if is_ui_component(object) {
-                    // Add UI properties
-                    for prop in UI_PROPERTIES {
+            Type::Object(type_name) => {
+                // Find the game state object of this type
+                let query = self
+                    .game_state
+                    .iter()
+                    .find(|q| q.return_type == Type::Object(type_name));
+                if let Some(properties) = query.and_then(|q| q.properties) {
+                    let object_name = query.map(|q| q.name).unwrap_or(type_name);
+                    for prop in properties {
                         items.push(CompletionItem {
-                            label: prop.to_string(),
+                            label: prop.name.to_string(),
                             kind: Some(CompletionItemKind::PROPERTY),
-                            detail: Some("UI property".to_string()),
+                            detail: Some(format!("{}: {}", prop.name, prop.typ)),
+                            documentation: Some(Documentation::MarkupContent(MarkupContent {
+                                kind: MarkupKind::Markdown,
+                                value: format!(
+                                    "```stonescript\n{}.{}: {}\n```\n\n{}",
+                                    object_name, prop.name, prop.typ, prop.description
+                                ),
+                            })),
                             ..Default::default()
                         });
                     }
-                    // Add UI methods
-                    for method in UI_METHODS {
-                        items.push(CompletionItem {
-                            label: method.to_string(),
-                            kind: Some(CompletionItemKind::METHOD),
-                            detail: Some("UI method".to_string()),
-                            ..Default::default()
-                        });
+                }
+            }
+            Type::Array(_) => {
+                for method in ARRAY_METHODS {
+                    items.push(self.method_item(method.name, method.parameters, method));
+                }
+            }
+            Type::String => {
+                // string.X(s, ...) functions called on the string itself
+                for func in STRING_FUNCTIONS {
+                    if let Some((first, rest)) = func.parameters.split_first() {
+                        if first.typ == Type::String {
+                            items.push(self.method_item("String", rest, func));
+                        }
                     }
                 }
             }
+            _ => {}
+        }
+
+        items
+    }
+
+    /// Functions and top-level variables of a module file
+    fn module_member_items(&self, module: &Module) -> Vec<CompletionItem> {
+        let owner = module.name.rsplit('/').next().unwrap_or(&module.name);
+        let functions = module.scope.get_functions();
+        let mut items = Vec::new();
+
+        for func in &functions {
+            items.push(CompletionItem {
+                label: func.name.clone(),
+                kind: Some(CompletionItemKind::METHOD),
+                detail: Some(format!(
+                    "func {}.{}({})",
+                    owner,
+                    func.name,
+                    func.parameters.join(", ")
+                )),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: match &func.doc {
+                        Some(doc) => format!("{}\n\nDefined in `{}`", doc, module.name),
+                        None => format!("Defined in `{}`", module.name),
+                    },
+                })),
+                ..Default::default()
+            });
+        }
+
+        for var in module.scope.get_variables_in_scope(0) {
+            if functions.iter().any(|func| func.name == var.name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: var.name.clone(),
+                kind: Some(CompletionItemKind::FIELD),
+                detail: Some(format!("var {}: {}", var.name, var.inferred_type)),
+                ..Default::default()
+            });
         }
 
         items
     }
 
+    /// Method called on a value, `parameters` excluding the receiver
+    fn method_item(
+        &self,
+        receiver: &str,
+        parameters: &[Parameter],
+        func: &FunctionSignature,
+    ) -> CompletionItem {
+        let params = parameters
+            .iter()
+            .map(|p| match p.typ {
+                Type::Unknown => p.name.to_string(),
+                _ => format!("{}: {}", p.name, p.typ),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        CompletionItem {
+            label: func.name.to_string(),
+            kind: Some(CompletionItemKind::METHOD),
+            detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
+            documentation: Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!(
+                    "```stonescript\n{}.{}({}) -> {}\n```\n\n{}",
+                    receiver, func.name, params, func.return_type, func.description
+                ),
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn function_item(&self, func: &FunctionSignature) -> CompletionItem {
+        let params = func
+            .parameters
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.typ))
+            .collect::<Vec<_>>()
+            .join(", ");
+        CompletionItem {
+            label: func.name.to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some(format!("{}({}) -> {}", func.name, params, func.return_type)),
+            documentation: Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!(
+                    "```stonescript\n{}.{}({}) -> {}\n```\n\n{}",
+                    func.namespace, func.name, params, func.return_type, func.description
+                ),
+            })),
+            ..Default::default()
+        }
+    }
+
     fn complete_after_keyword(&self, keyword: &str) -> Vec<CompletionItem> {
         let mut items = Vec::new();
 
@@ -498,9 +488,119 @@ impl CompletionProvider {
         items
     }
 
-    fn complete_function_call(&self, scope: &ScopeAnalyzer) -> Vec<CompletionItem> {
-        // Complete function names and variables
-        self.complete_identifier(scope)
+    fn complete_command_argument(
+        &self,
+        command: &str,
+        scope: &ScopeAnalyzer,
+    ) -> Vec<CompletionItem> {
+        match command {
+            "play" | "activate" => self.complete_after_keyword(command),
+            "equip" | "equipL" | "equipR" => self.complete_after_equip(),
+            _ => self.complete_expression(scope),
+        }
+    }
+
+    fn complete_argument(
+        &self,
+        function: &Expression,
+        index: usize,
+        scope: &ScopeAnalyzer,
+    ) -> Vec<CompletionItem> {
+        if let Expression::Property {
+            object, property, ..
+        } = function
+        {
+            if let Expression::Identifier(namespace, _) = object.as_ref() {
+                if index == 0 {
+                    let items = self.complete_inside_function_call(namespace, property);
+                    if !items.is_empty() {
+                        return items;
+                    }
+                }
+            }
+        }
+
+        self.complete_expression(scope)
+    }
+
+    /// Values to compare against, e.g. location IDs after `?loc =`
+    fn complete_comparison(
+        &self,
+        other: &Expression,
+        scope: &ScopeAnalyzer,
+    ) -> Vec<CompletionItem> {
+        let subject = match other {
+            Expression::Identifier(name, _) => Some(name),
+            Expression::Property {
+                object, property, ..
+            } if property == "id" => match object.as_ref() {
+                Expression::Identifier(name, _) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(name) = subject {
+            if matches!(name.as_str(), "loc" | "foe" | "item")
+                && scope.find_variable(name).is_none()
+            {
+                return self.complete_binary_identifier(name, scope);
+            }
+        }
+
+        let mut items = Vec::new();
+        if infer_type_with_scope(other, Some(scope)) == Type::Bool {
+            for value in ["true", "false"] {
+                items.push(CompletionItem {
+                    label: value.to_string(),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    sort_text: Some(format!("00_{}", value)),
+                    ..Default::default()
+                });
+            }
+        }
+        items.extend(self.complete_expression(scope));
+        items
+    }
+
+    /// Anything that can start an expression
+    fn complete_expression(&self, scope: &ScopeAnalyzer) -> Vec<CompletionItem> {
+        let mut items = self.complete_identifier(scope);
+
+        // Game state queries, used without `?` inside expressions
+        for query in self.game_state {
+            items.push(CompletionItem {
+                label: query.name.to_string(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(format!("{}: {}", query.name, query.return_type)),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: query.description.to_string(),
+                })),
+                ..Default::default()
+            });
+        }
+
+        // Native function namespaces
+        let mut namespaces: Vec<&str> = ALL_FUNCTIONS
+            .iter()
+            .filter_map(|funcs| funcs.first().map(|f| f.namespace))
+            .filter(|namespace| !namespace.is_empty())
+            .collect();
+        namespaces.dedup();
+        for namespace in namespaces {
+            if items.iter().any(|item| item.label == namespace) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: namespace.to_string(),
+                kind: Some(CompletionItemKind::MODULE),
+                detail: Some(format!("{} functions", namespace)),
+                ..Default::default()
+            });
+        }
+
+        items
     }
 
     fn complete_inside_function_call(
@@ -539,11 +639,35 @@ impl CompletionProvider {
             });
         }
 
-        // Note: StoneScript doesn't support user-defined functions
+        items.extend(self.function_items(scope));
 
         items
     }
 
+    /// User-defined functions declared with `func`
+    fn function_items(&self, scope: &ScopeAnalyzer) -> Vec<CompletionItem> {
+        scope
+            .get_functions()
+            .into_iter()
+            .map(|func| CompletionItem {
+                label: func.name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(format!(
+                    "func {}({})",
+                    func.name,
+                    func.parameters.join(", ")
+                )),
+                documentation: func.doc.map(|doc| {
+                    Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: doc,
+                    })
+                }),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     fn complete_binary_identifier(
         &self,
         identifier: &str,
@@ -620,43 +744,63 @@ impl CompletionProvider {
 
         items
     }
+}
 
-    fn fallback_completion(
-        &self,
-        position: Position,
-        source: &str,
-        scope: &ScopeAnalyzer,
-    ) -> Vec<CompletionItem> {
-        let line_start = source
-            .lines()
-            .take(position.line as usize)
-            .map(|line| line.len() + 1)
-            .sum::<usize>();
-        let line_end = line_start
-            + source
-                .lines()
-                .nth(position.line as usize)
-                .map(|l| l.len())
-                .unwrap_or(0);
-
-        if line_end > line_start {
-            let line_text = &source[line_start..line_end];
-
-            // Very aggressive pattern matching - check for loc or foe anywhere in line
-            if line_text.contains("loc") && line_text.contains("=") {
-                return self.complete_binary_identifier("loc", scope);
-            }
-            if line_text.contains("foe") && line_text.contains("=") {
-                return self.complete_binary_identifier("foe", scope);
-            }
-            if line_text.contains("item") && line_text.contains("=") {
-                return self.complete_binary_identifier("item", scope);
+/// What member completion needs to resolve `new` objects
+struct ObjectLookup<'a> {
+    ast: &'a Program,
+    scope: &'a ScopeAnalyzer,
+    uri: &'a Url,
+    workspace: &'a Workspace,
+}
+
+impl ObjectLookup<'_> {
+    /// Module of an object created with `new`, following chains such as
+    /// `game.board` where `board` is itself a `new` object of `game`'s file
+    fn module_for(&self, object: &Expression) -> Option<Arc<Module>> {
+        match object {
+            Expression::Identifier(name, _) => self
+                .workspace
+                .module_for_variable(self.uri, self.ast, self.scope, name),
+            Expression::Property {
+                object, property, ..
+            } => {
+                let owner = self.module_for(object)?;
+                self.workspace
+                    .module_for_variable(self.uri, &owner.ast, &owner.scope, property)
             }
+            _ => None,
         }
+    }
+}
 
-        // Last resort - return top level completion
-        self.complete_top_level(scope)
+/// Tab stops in a snippet body: `${1:text}`, `${1}` and `$1`
+static TAB_STOP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{\d+:([^}]*)\}|\$\{\d+\}|\$\d+").unwrap());
+
+/// Snippet body with its tab stops replaced by their placeholder text
+fn snippet_preview(body: &str) -> String {
+    TAB_STOP.replace_all(body, "$1").trim_end().to_string()
+}
+
+/// A variable by name, including ones declared inside function bodies
+fn find_variable<'a>(scope: &'a ScopeAnalyzer, name: &str) -> Option<&'a Variable> {
+    scope.find_variable(name).or_else(|| {
+        scope
+            .get_all_variables()
+            .into_iter()
+            .find(|var| var.name == name)
+    })
+}
+
+/// Type of the receiver of a member access
+fn receiver_type(object: &Expression, scope: &ScopeAnalyzer) -> Type {
+    if let Expression::Identifier(name, _) = object {
+        if let Some(var) = find_variable(scope, name) {
+            return var.inferred_type.clone();
+        }
     }
+    infer_type_with_scope(object, Some(scope))
 }
 
 #[cfg(test)]
@@ -0,0 +1,473 @@
+//! Refactorings of conditions: converting between else-if chains and nested
+//! conditions, inverting a condition and merging nested conditions
+
+use crate::providers::diagnostics::for_each_statement;
+use crate::providers::refactoring::{indentation, line_range, refactor, text_of};
+use stonescript_parser::ast::{BinaryOperator, UnaryOperator};
+use stonescript_parser::{Expression, Program, Statement};
+use tower_lsp::lsp_types::*;
+
+/// Precedence of anything that is not a binary operation
+const ATOM: u8 = u8::MAX;
+
+/// Rewrites available for the condition whose `?` or `:?` line holds the
+/// start of `range`
+pub fn condition_refactorings(
+    ast: &Program,
+    source: &str,
+    uri: &Url,
+    range: Range,
+) -> Vec<CodeAction> {
+    let lines: Vec<&str> = source.lines().collect();
+    let Some(statement) = condition_at(ast, range.start.line as usize) else {
+        return Vec::new();
+    };
+    let Some(chain) = Chain::read(&statement, &lines) else {
+        return Vec::new();
+    };
+    let Statement::Condition {
+        condition,
+        then_block,
+        else_ifs,
+        else_block,
+        ..
+    } = &statement
+    else {
+        return Vec::new();
+    };
+
+    let mut rewrites = Vec::new();
+    let reindents = !chain.contains_ascii();
+
+    if !else_ifs.is_empty() && reindents {
+        rewrites.push(("Convert to nested conditions", chain.render_nested()));
+    }
+
+    if let Some(flat) = else_block
+        .as_deref()
+        .and_then(single_condition)
+        .filter(|_| reindents)
+        .and_then(|_| Chain::flatten(&statement, &lines))
+    {
+        rewrites.push(("Convert to else-if chain", flat.render()));
+    }
+
+    if else_ifs.is_empty() {
+        if let Some(otherwise) = &chain.otherwise {
+            let (negated, _) = negate(condition, &lines);
+            let inverted = Chain {
+                branches: vec![Branch {
+                    condition: negated,
+                    comment: chain.branches[0].comment.clone(),
+                    lines: otherwise.lines.clone(),
+                }],
+                otherwise: Some(Branch {
+                    lines: chain.branches[0].lines.clone(),
+                    ..otherwise.clone()
+                }),
+                ..chain.clone()
+            };
+            rewrites.push(("Invert condition", inverted.render()));
+        }
+    }
+
+    if else_ifs.is_empty() && else_block.is_none() && reindents {
+        if let Some(merged) =
+            single_condition(then_block).and_then(|inner| chain.merge(condition, inner, &lines))
+        {
+            rewrites.push(("Merge nested conditions", merged.render()));
+        }
+    }
+
+    let (first, last) = chain.lines;
+    let replaced = Range::new(
+        Position::new(first as u32, 0),
+        Position::new(last as u32, lines[last].chars().count() as u32),
+    );
+    rewrites
+        .into_iter()
+        .map(|(title, text)| {
+            refactor(
+                title,
+                CodeActionKind::REFACTOR_REWRITE,
+                uri,
+                vec![TextEdit::new(replaced, text.join("\n"))],
+            )
+        })
+        .collect()
+}
+
+/// A condition statement as read from the source, with each block's lines
+/// at their original indentation
+#[derive(Clone)]
+struct Chain {
+    indent: String,
+    /// Indentation each block adds
+    unit: String,
+    branches: Vec<Branch>,
+    otherwise: Option<Branch>,
+    /// First and last line of the statement
+    lines: (usize, usize),
+}
+
+#[derive(Clone)]
+struct Branch {
+    /// Condition source; empty for the else branch
+    condition: String,
+    /// Rest of the header line, such as a trailing comment
+    comment: String,
+    lines: Vec<String>,
+}
+
+impl Chain {
+    fn read(statement: &Statement, lines: &[&str]) -> Option<Chain> {
+        let Statement::Condition {
+            condition,
+            else_ifs,
+            else_block,
+            ..
+        } = statement
+        else {
+            return None;
+        };
+        let (first, mut last) = line_range(statement)?;
+        while last > first && lines.get(last)?.trim().is_empty() {
+            last -= 1;
+        }
+
+        let mut headers = vec![(first, Some(condition))];
+        for else_if in else_ifs {
+            let line = else_if.condition.span().start.line;
+            if !lines.get(line)?.trim_start().starts_with(":?") {
+                return None;
+            }
+            headers.push((line, Some(&else_if.condition)));
+        }
+        if let Some(block) = else_block {
+            let block_line = block.iter().find_map(line_range)?.0;
+            let previous = headers.last()?.0;
+            let line = (previous + 1..block_line).rev().find(|&line| {
+                let text = lines[line].trim_start();
+                text.starts_with(':') && !text.starts_with(":?")
+            })?;
+            headers.push((line, None));
+        }
+
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        for (i, (line, condition)) in headers.iter().enumerate() {
+            let end = headers.get(i + 1).map_or(last, |(next, _)| next - 1);
+            let mut block: Vec<String> = lines
+                .get(line + 1..=end)?
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            while block.last().is_some_and(|line| line.trim().is_empty()) {
+                block.pop();
+            }
+            if block.is_empty() {
+                return None;
+            }
+
+            let header = lines[*line];
+            match condition {
+                Some(condition) => {
+                    let span = condition.span();
+                    if span.end.line != *line {
+                        return None;
+                    }
+                    branches.push(Branch {
+                        condition: text_of(lines, &span),
+                        comment: header.chars().skip(span.end.column).collect(),
+                        lines: block,
+                    });
+                }
+                None => {
+                    let colon = header.find(':')?;
+                    otherwise = Some(Branch {
+                        condition: String::new(),
+                        comment: header[colon + 1..].to_string(),
+                        lines: block,
+                    });
+                }
+            }
+        }
+
+        let indent = indentation(lines[first]).to_string();
+        let unit = indentation(&branches.first()?.lines[0])
+            .strip_prefix(indent.as_str())
+            .filter(|unit| !unit.is_empty())
+            .unwrap_or("  ")
+            .to_string();
+
+        Some(Chain {
+            indent,
+            unit,
+            branches,
+            otherwise,
+            lines: (first, last),
+        })
+    }
+
+    /// Read a condition whose else block holds just another condition as
+    /// one chain, following such else blocks all the way down
+    fn flatten(statement: &Statement, lines: &[&str]) -> Option<Chain> {
+        let mut chain = Chain::read(statement, lines)?;
+        let Statement::Condition { else_block, .. } = statement else {
+            return None;
+        };
+
+        if let Some(inner) = else_block.as_deref().and_then(single_condition) {
+            let inner = Chain::flatten(inner, lines)?;
+            if inner.indent != format!("{}{}", chain.indent, chain.unit) {
+                return None;
+            }
+            let unit = chain.unit.clone();
+            chain.branches.extend(
+                inner
+                    .branches
+                    .into_iter()
+                    .map(|branch| branch.dedent(&unit)),
+            );
+            chain.otherwise = inner.otherwise.map(|branch| branch.dedent(&unit));
+        }
+        Some(chain)
+    }
+
+    /// Combine this condition, testing `outer_condition`, with the one
+    /// forming its whole block
+    fn merge(
+        &self,
+        outer_condition: &Expression,
+        inner: &Statement,
+        lines: &[&str],
+    ) -> Option<Chain> {
+        let Statement::Condition {
+            condition: inner_condition,
+            else_ifs,
+            else_block,
+            ..
+        } = inner
+        else {
+            return None;
+        };
+        let inner_chain = Chain::read(inner, lines)?;
+        if !else_ifs.is_empty() || else_block.is_some() {
+            return None;
+        }
+        // Don't lose a comment on the inner condition's line
+        let inner_branch = inner_chain.branches.into_iter().next()?;
+        if !inner_branch.comment.trim().is_empty() {
+            return None;
+        }
+
+        let and = BinaryOperator::And.precedence();
+        let condition = format!(
+            "{} & {}",
+            parenthesize(source(outer_condition, lines), and, false),
+            parenthesize(source(inner_condition, lines), and, true)
+        );
+
+        Some(Chain {
+            branches: vec![Branch {
+                condition,
+                comment: self.branches[0].comment.clone(),
+                lines: inner_branch.dedent(&self.unit).lines,
+            }],
+            ..self.clone()
+        })
+    }
+
+    fn contains_ascii(&self) -> bool {
+        self.branches
+            .iter()
+            .chain(&self.otherwise)
+            .flat_map(|branch| &branch.lines)
+            .any(|line| line.contains("asciiend"))
+    }
+
+    /// `?a`, `:?b`, `:` at one level
+    fn render(&self) -> Vec<String> {
+        let mut text = Vec::new();
+        for (i, branch) in self.branches.iter().enumerate() {
+            let marker = if i == 0 { "?" } else { ":?" };
+            text.push(format!(
+                "{}{}{}{}",
+                self.indent, marker, branch.condition, branch.comment
+            ));
+            text.extend(branch.lines.iter().cloned());
+        }
+        if let Some(otherwise) = &self.otherwise {
+            text.push(format!("{}:{}", self.indent, otherwise.comment));
+            text.extend(otherwise.lines.iter().cloned());
+        }
+        text
+    }
+
+    /// Each else-if as a condition nested in the previous branch's else
+    fn render_nested(&self) -> Vec<String> {
+        let (first, rest) = self.branches.split_first().expect("a branch");
+        let mut text = Chain {
+            branches: vec![first.clone()],
+            otherwise: None,
+            ..self.clone()
+        }
+        .render();
+
+        if rest.is_empty() {
+            if let Some(otherwise) = &self.otherwise {
+                text.push(format!("{}:{}", self.indent, otherwise.comment));
+                text.extend(otherwise.lines.iter().cloned());
+            }
+            return text;
+        }
+
+        text.push(format!("{}:", self.indent));
+        let nested = Chain {
+            branches: rest.to_vec(),
+            ..self.clone()
+        }
+        .render_nested();
+        text.extend(nested.into_iter().map(|line| indent_line(line, &self.unit)));
+        text
+    }
+}
+
+impl Branch {
+    fn dedent(mut self, unit: &str) -> Branch {
+        for line in &mut self.lines {
+            if let Some(rest) = line.strip_prefix(unit) {
+                *line = rest.to_string();
+            }
+        }
+        self
+    }
+}
+
+fn indent_line(line: String, unit: &str) -> String {
+    if line.trim().is_empty() {
+        line
+    } else {
+        format!("{}{}", unit, line)
+    }
+}
+
+/// The condition with a `?` or `:?` on `line`
+fn condition_at(ast: &Program, line: usize) -> Option<Statement> {
+    let mut found = None;
+    for_each_statement(&ast.statements, &mut |statement| {
+        if let Statement::Condition { else_ifs, .. } = statement {
+            let on_header = line_range(statement).is_some_and(|(first, _)| first == line)
+                || else_ifs
+                    .iter()
+                    .any(|else_if| else_if.condition.span().start.line == line);
+            if on_header && found.is_none() {
+                found = Some(statement.clone());
+            }
+        }
+    });
+    found
+}
+
+/// The condition a block consists of, if it holds nothing else
+fn single_condition(block: &[Statement]) -> Option<&Statement> {
+    let mut statements = block
+        .iter()
+        .filter(|statement| !matches!(statement, Statement::Empty));
+    match (statements.next(), statements.next()) {
+        (Some(statement @ Statement::Condition { .. }), None) => Some(statement),
+        _ => None,
+    }
+}
+
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::BinaryOp { op, .. } => op.precedence(),
+        _ => ATOM,
+    }
+}
+
+/// Source text of an expression with the precedence of its outermost operator
+fn source(expression: &Expression, lines: &[&str]) -> (String, u8) {
+    (text_of(lines, &expression.span()), precedence(expression))
+}
+
+/// Parentheses around an operand binding looser than its operator. Operators
+/// associate to the left, so a right operand also needs them on a tie.
+fn parenthesize((text, precedence): (String, u8), parent: u8, right: bool) -> String {
+    if precedence < parent || (right && precedence == parent) {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Text of the negation of `expression`, flipping comparisons and applying
+/// De Morgan's laws to `&` and `|`
+fn negate(expression: &Expression, lines: &[&str]) -> (String, u8) {
+    match expression {
+        Expression::BinaryOp {
+            left, op, right, ..
+        } => match op {
+            BinaryOperator::And | BinaryOperator::Or => {
+                let flipped = if *op == BinaryOperator::And {
+                    BinaryOperator::Or
+                } else {
+                    BinaryOperator::And
+                };
+                let parent = flipped.precedence();
+                (
+                    format!(
+                        "{} {} {}",
+                        parenthesize(negate(left, lines), parent, false),
+                        flipped,
+                        parenthesize(negate(right, lines), parent, true)
+                    ),
+                    parent,
+                )
+            }
+            _ => match negated(*op) {
+                Some(negated) => {
+                    let parent = op.precedence();
+                    (
+                        format!(
+                            "{} {} {}",
+                            parenthesize(source(left, lines), parent, false),
+                            negated,
+                            parenthesize(source(right, lines), parent, true)
+                        ),
+                        parent,
+                    )
+                }
+                None => not(expression, lines),
+            },
+        },
+        Expression::UnaryOp {
+            op: UnaryOperator::Not,
+            operand,
+            ..
+        } => source(operand, lines),
+        Expression::Boolean(value, _) => ((!value).to_string(), ATOM),
+        _ => not(expression, lines),
+    }
+}
+
+/// The comparison testing the opposite, e.g. `>=` for `<`
+fn negated(op: BinaryOperator) -> Option<BinaryOperator> {
+    match op {
+        BinaryOperator::Equal => Some(BinaryOperator::NotEqual),
+        BinaryOperator::NotEqual => Some(BinaryOperator::Equal),
+        BinaryOperator::Less => Some(BinaryOperator::GreaterEqual),
+        BinaryOperator::LessEqual => Some(BinaryOperator::Greater),
+        BinaryOperator::Greater => Some(BinaryOperator::LessEqual),
+        BinaryOperator::GreaterEqual => Some(BinaryOperator::Less),
+        _ => None,
+    }
+}
+
+fn not(expression: &Expression, lines: &[&str]) -> (String, u8) {
+    (
+        format!("!{}", parenthesize(source(expression, lines), ATOM, false)),
+        ATOM,
+    )
+}
@@ -113,12 +113,12 @@ impl DefinitionProvider {
         for stmt in &ast.statements {
             if let Statement::FunctionDefinition {
                 name: func_name,
-                span,
+                name_span,
                 ..
             } = stmt
             {
                 if func_name == name {
-                    return Some(*span);
+                    return Some(*name_span);
                 }
             }
         }
@@ -132,12 +132,12 @@ impl DefinitionProvider {
     ) -> Option<stonescript_parser::ast::Span> {
         for stmt in &ast.statements {
             match stmt {
-                Statement::Assignment { target, span, .. } => {
-                    if let Expression::Identifier(var_name, _) = target {
-                        if var_name == name {
-                            return Some(*span);
-                        }
-                    }
+                Statement::VarDeclaration {
+                    name: var_name,
+                    name_span,
+                    ..
+                } if var_name == name => {
+                    return Some(*name_span);
                 }
                 Statement::Command {
                     name: cmd_name,
@@ -188,7 +188,7 @@ impl DefinitionProvider {
                         Statement::FunctionDefinition {
                             body,
                             params,
-                            name: func_name,
+                            param_spans,
                             ..
                         } => {
                             // Search within the function body first
@@ -199,11 +199,8 @@ impl DefinitionProvider {
                             }
 
                             // Check function parameters
-                            for param in params {
-                                if param == name {
-                                    // Return the function definition span
-                                    return Some(span);
-                                }
+                            if let Some(index) = params.iter().position(|param| param == name) {
+                                return param_spans.get(index).copied();
                             }
 
                             // Not found in function body or params
@@ -256,9 +253,28 @@ impl DefinitionProvider {
                                 }
                             }
                         }
-                        Statement::For { body, .. }
-                        | Statement::While { body, .. }
-                        | Statement::ForIn { body, .. } => {
+                        Statement::For {
+                            body,
+                            variable,
+                            variable_span,
+                            ..
+                        }
+                        | Statement::ForIn {
+                            body,
+                            variable,
+                            variable_span,
+                            ..
+                        } => {
+                            if let Some(def_span) =
+                                self.find_variable_in_statements_at_position(body, name, pos)
+                            {
+                                return Some(def_span);
+                            }
+                            if variable == name {
+                                return Some(*variable_span);
+                            }
+                        }
+                        Statement::While { body, .. } => {
                             if let Some(def_span) =
                                 self.find_variable_in_statements_at_position(body, name, pos)
                             {
@@ -309,7 +325,8 @@ impl DefinitionProvider {
     /// Check if a statement contains the position (including nested statements)
     fn statement_contains_position(&self, stmt: &Statement, pos: AstPosition) -> bool {
         match stmt {
-            Statement::Assignment { span, .. }
+            Statement::VarDeclaration { span, .. }
+            | Statement::Assignment { span, .. }
             | Statement::Command { span, .. }
             | Statement::ExpressionStatement { span, .. }
             | Statement::FunctionDefinition { span, .. }
@@ -345,13 +362,9 @@ impl DefinitionProvider {
                 }
 
                 // Check function parameters
-                for param in params {
-                    if param == name {
-                        // Return the function definition span as a fallback
-                        // In a better implementation, we'd track parameter spans
-                        if let Statement::FunctionDefinition { span, .. } = stmt {
-                            return Some(*span);
-                        }
+                if let Some(index) = params.iter().position(|param| param == name) {
+                    if let Statement::FunctionDefinition { param_spans, .. } = stmt {
+                        return param_spans.get(index).copied();
                     }
                 }
 
@@ -463,12 +476,13 @@ impl DefinitionProvider {
             // Check if this statement defines the variable
             // IMPORTANT: Do NOT recurse into FunctionDefinition - variables in other functions are not visible
             match stmt {
-                Statement::Assignment { target, span, .. } => {
-                    if let Expression::Identifier(var_name, _) = target {
-                        if var_name == name {
-                            last_match = Some(*span);
-                        }
-                    }
+                // Assignments only mutate, the declaration is the definition
+                Statement::VarDeclaration {
+                    name: var_name,
+                    name_span,
+                    ..
+                } if var_name == name => {
+                    last_match = Some(*name_span);
                 }
                 Statement::Command {
                     name: cmd_name,
@@ -498,7 +512,8 @@ impl DefinitionProvider {
     /// Get the span of a statement
     fn get_statement_span(&self, stmt: &Statement) -> Option<stonescript_parser::ast::Span> {
         match stmt {
-            Statement::Assignment { span, .. }
+            Statement::VarDeclaration { span, .. }
+            | Statement::Assignment { span, .. }
             | Statement::Command { span, .. }
             | Statement::ExpressionStatement { span, .. }
             | Statement::FunctionDefinition { span, .. }
@@ -533,6 +548,17 @@ impl DefinitionProvider {
         pos: AstPosition,
     ) -> Option<&'a Expression> {
         match stmt {
+            Statement::VarDeclaration {
+                initializer: Some(value),
+                span,
+                ..
+            } => {
+                if span.contains_position(pos) && value.span().contains_position(pos) {
+                    self.find_deepest_expression(value, pos)
+                } else {
+                    None
+                }
+            }
             Statement::Assignment {
                 target,
                 value,
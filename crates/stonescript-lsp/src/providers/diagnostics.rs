@@ -1,40 +1,1355 @@
 //! Diagnostics provider
 
-use crate::data::{native_functions, Type};
-use crate::utils::ScopeAnalyzer;
+use crate::config::DiagnosticLevel;
+use crate::data::native_functions::get_function_in_namespace;
+use crate::data::{
+    get_functions_in_namespace, get_game_state, native_functions, Type, ABILITY_IDS,
+    ACTIVATE_SLOTS, ALL_FILTERS, BREW_INGREDIENTS, FOES, FOE_NAMES, LOCATIONS, MAX_ENCHANT_LEVEL,
+    MAX_STAR_LEVEL, MUSIC_TRACKS, SOUND_EFFECTS,
+};
+use crate::providers::lints::lint;
+use crate::utils::workspace::import_paths;
+use crate::utils::{
+    infer_type_with_scope, suggest, Module, ScopeAnalyzer, VariableUsage, Workspace,
+};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use stonescript_parser::ast::Position as AstPosition;
+use stonescript_parser::{
+    walk_expression, walk_statement, AssignmentOperator, BinaryOperator, CommandArgs, CommandValue,
+    Expression, ItemCriterion, Program, Span, Statement, UnaryOperator, Visitor,
+};
 use tower_lsp::lsp_types::*;
-use stonescript_parser::{Program, Visitor, Statement, Expression};
 
-pub struct DiagnosticsProvider;
+/// Unknown property of a game object; `data.suggestions` holds replacements
+/// for the whole property access
+pub const UNKNOWN_PROPERTY: &str = "unknown-property";
+/// Call to a function that is neither defined nor imported;
+/// `data.suggestions` holds replacements for the called name, and `data.name`
+/// is set for plain calls that an `import` could fix
+pub const UNKNOWN_FUNCTION: &str = "unknown-function";
+/// Assignment to a variable without a `var` declaration;
+/// `data.declare` tells whether prefixing `var` fixes it
+pub const UNDECLARED_VARIABLE: &str = "undeclared-variable";
+/// Function local that is declared but never read
+pub const UNUSED_VARIABLE: &str = "unused-variable";
+/// Call to a user function with the wrong number of arguments;
+/// `data.missing` names the parameters left without an argument and
+/// `data.given` counts the arguments passed
+pub const WRONG_ARGUMENT_COUNT: &str = "wrong-argument-count";
+/// A String compared with a number, which StoneScript silently coerces
+pub const STRING_NUMBER_COMPARISON: &str = "string-number-comparison";
+/// Arithmetic with a Bool operand
+pub const BOOL_ARITHMETIC: &str = "bool-arithmetic";
+/// A numeric variable passed where a `string.*` function expects a String
+pub const STRING_FUNCTION_NUMBER: &str = "string-function-number";
+/// A variable assigned a String and used as a number elsewhere, reported at
+/// its declaration; the related information points at the first String
+/// assigned and at such a use
+pub const STRING_ASSIGNED_TO_NUMBER: &str = "string-assigned-to-number";
+/// A location, foe, ability, sound or music ID close to a known one, or
+/// an unknown `brew` ingredient; `data.suggestions` holds replacements
+pub const UNKNOWN_GAME_ID: &str = "unknown-game-id";
+/// Statements after a `return` in the same block
+pub const UNREACHABLE_CODE: &str = "unreachable-code";
+/// A condition whose value is known without running the script
+pub const CONSTANT_CONDITION: &str = "constant-condition";
+/// A `:?` branch with the same condition as an earlier branch of the chain
+pub const DUPLICATE_CONDITION: &str = "duplicate-condition";
+/// A `for` loop whose literal start is above its end
+pub const REVERSED_RANGE: &str = "reversed-range";
+/// A malformed `equip` filter, an out-of-range star or enchantment level,
+/// or the wrong number of `loadout`, `brew` or `activate` arguments
+pub const INVALID_COMMAND_ARGUMENT: &str = "invalid-command-argument";
+
+/// Namespaces whose functions are all in the native function tables
+const CHECKED_NAMESPACES: &[&str] = &["math", "music", "ui"];
+
+pub struct DiagnosticsProvider {
+    /// Per-code severity overrides from the user's configuration
+    severities: RwLock<HashMap<String, DiagnosticLevel>>,
+}
 
 impl DiagnosticsProvider {
     pub fn new() -> Self {
-        Self
+        Self {
+            severities: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the severity overrides loaded from the user's configuration
+    pub fn set_severities(&self, severities: HashMap<String, DiagnosticLevel>) {
+        if let Ok(mut current) = self.severities.write() {
+            *current = severities;
+        }
     }
 
     pub fn provide_diagnostics(
         &self,
-        _ast: &Program,
+        ast: &Program,
         _source: &str,
-        _scope: &ScopeAnalyzer,
+        scope: &ScopeAnalyzer,
+        uri: &Url,
+        workspace: &Workspace,
     ) -> Vec<Diagnostic> {
-        // Simplified diagnostics - parse errors are already handled by server.rs
-        // TODO: Implement AST-based semantic analysis:
-        // - Walk AST to find undefined variable references
-        // - Check function call types (would need updated type_inference)
-        // For now, relying on parse error reporting only
-        
-        vec![]
+        // Parse errors are reported by server.rs before we get here
+        let imports_resolved = import_paths(&ast.statements)
+            .iter()
+            .all(|path| workspace.resolve_path(uri, path).is_some());
+        let modules = workspace.imported_modules(uri, ast);
+
+        let mut declarations = Declarations(HashSet::new());
+        declarations.visit_program(ast);
+        let mut declared = declarations.0;
+        for module in &modules {
+            declared.extend(
+                module
+                    .scope
+                    .get_all_variables()
+                    .into_iter()
+                    .map(|var| var.name.clone()),
+            );
+        }
+
+        let mut assignments = Assignments {
+            scope,
+            types: HashMap::new(),
+            strings: HashMap::new(),
+            type_tested: HashSet::new(),
+        };
+        assignments.visit_program(ast);
+
+        let mut numeric_uses = NumericUses {
+            scope,
+            types: &assignments.types,
+            uses: HashMap::new(),
+        };
+        numeric_uses.visit_program(ast);
+        let numeric_uses = numeric_uses.uses;
+
+        let mut checker = Checker {
+            scope,
+            uri,
+            modules,
+            declared,
+            imports_resolved,
+            assignments,
+            numeric_uses,
+            diagnostics: Vec::new(),
+        };
+        checker.visit_program(ast);
+        checker.check_string_assignments();
+
+        let mut diagnostics = checker.diagnostics;
+        diagnostics.extend(lint(ast));
+        self.apply_severities(&mut diagnostics);
+        diagnostics
+    }
+
+    /// Change or drop diagnostics whose code has a configured severity
+    fn apply_severities(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let Ok(severities) = self.severities.read() else {
+            return;
+        };
+        diagnostics.retain_mut(|diagnostic| {
+            let Some(NumberOrString::String(code)) = &diagnostic.code else {
+                return true;
+            };
+            match severities.get(code) {
+                Some(level) => {
+                    diagnostic.severity = level.severity();
+                    diagnostic.severity.is_some()
+                }
+                None => true,
+            }
+        });
+    }
+}
+
+impl Default for DiagnosticsProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Walks a program collecting semantic diagnostics
+struct Checker<'a> {
+    scope: &'a ScopeAnalyzer,
+    uri: &'a Url,
+    modules: Vec<Arc<Module>>,
+    /// Variables, parameters and functions declared in the document or its imports
+    declared: HashSet<String>,
+    /// Whether every `import` could be found, so unknown names are really unknown
+    imports_resolved: bool,
+    /// What each variable is assigned across the document
+    assignments: Assignments<'a>,
+    /// Where each variable is first used as a number
+    numeric_uses: HashMap<VariableKey, Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Command {
+                name,
+                args,
+                structured,
+                span,
+            } => {
+                if let (true, Some(sound)) = (name == "play", args.first()) {
+                    self.check_game_id(sound, GameIds::Sound);
+                }
+                if let Some(structured) = structured {
+                    self.check_command_arguments(name, structured, *span);
+                }
+            }
+            Statement::Assignment {
+                target: Expression::Identifier(name, span),
+                op,
+                value,
+                ..
+            } => {
+                if !self.declared.contains(name) && get_game_state(name).is_none() {
+                    self.push(
+                        *span,
+                        DiagnosticSeverity::WARNING,
+                        UNDECLARED_VARIABLE,
+                        format!("Variable `{}` is assigned without being declared", name),
+                        Some(json!({ "declare": *op == AssignmentOperator::Assign })),
+                    );
+                }
+                // The variable itself is written, not read
+                self.visit_expression(value);
+                return;
+            }
+            Statement::FunctionDefinition { body, .. } => self.check_unused_locals(body),
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Property {
+                object,
+                property,
+                span,
+            } => self.check_game_property(object, property, *span),
+            Expression::FunctionCall {
+                function,
+                args,
+                span,
+            } => match function.as_ref() {
+                Expression::Identifier(name, name_span) => {
+                    self.check_call(name, *name_span, args.len(), *span)
+                }
+                Expression::Property {
+                    object,
+                    property,
+                    span: callee_span,
+                } => {
+                    self.check_native_call(object, property, *callee_span);
+                    self.check_string_arguments(object, property, args);
+                    if let (Some(domain), Some(id)) =
+                        (self.id_parameter(object, property), args.first())
+                    {
+                        self.check_game_id(id, domain);
+                    }
+                    // The callee names a function rather than a property
+                    self.visit_expression(object);
+                    for arg in args {
+                        self.visit_expression(arg);
+                    }
+                    return;
+                }
+                _ => {}
+            },
+            Expression::BinaryOp {
+                left,
+                op,
+                right,
+                span,
+            } => {
+                self.check_operand_types(left, *op, right, *span);
+                if matches!(op, BinaryOperator::Equal | BinaryOperator::NotEqual) {
+                    if let Some(domain) = self.compared_ids(left) {
+                        self.check_game_id(right, domain);
+                    } else if let Some(domain) = self.compared_ids(right) {
+                        self.check_game_id(left, domain);
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_expression(self, expression);
+    }
+}
+
+impl Checker<'_> {
+    /// `foe.hpp`: only reported when a known property is a likely fix, since
+    /// the property tables don't cover everything the game exposes
+    fn check_game_property(&mut self, object: &Expression, property: &str, span: Span) {
+        let Expression::Identifier(object_name, _) = object else {
+            return;
+        };
+        if self.declared.contains(object_name) {
+            return;
+        }
+        // Cooldowns are looked up by ability ID: `cooldown.bardiche`
+        if object_name == "cooldown" {
+            let start = span.end.column.saturating_sub(property.chars().count());
+            let name_span = Span::new(AstPosition::new(span.end.line, start), span.end);
+            self.check_id(property, name_span, GameIds::Ability);
+            return;
+        }
+        let Some(properties) = get_game_state(object_name).and_then(|query| query.properties)
+        else {
+            return;
+        };
+        if properties
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(property))
+        {
+            return;
+        }
+
+        let suggestions = suggest(property, properties.iter().map(|p| p.name));
+        if suggestions.is_empty() {
+            return;
+        }
+        self.push(
+            span,
+            DiagnosticSeverity::WARNING,
+            UNKNOWN_PROPERTY,
+            format!("Unknown property `{}` on `{}`", property, object_name),
+            Some(json!({
+                "suggestions": suggestions
+                    .iter()
+                    .map(|s| format!("{}.{}", object_name, s))
+                    .collect::<Vec<_>>()
+            })),
+        );
+    }
+
+    /// `ui.AddButon()`: a misspelled function of a native namespace
+    fn check_native_call(&mut self, object: &Expression, name: &str, span: Span) {
+        let Expression::Identifier(namespace, _) = object else {
+            return;
+        };
+        if self.declared.contains(namespace) {
+            return;
+        }
+        // Other namespaces have functions the tables do not list yet
+        if !CHECKED_NAMESPACES.contains(&namespace.as_str()) {
+            return;
+        }
+        let functions = get_functions_in_namespace(namespace);
+        if functions.iter().any(|f| f.name.eq_ignore_ascii_case(name)) {
+            return;
+        }
+
+        let suggestions = suggest(name, functions.iter().map(|f| f.name));
+        if suggestions.is_empty() {
+            return;
+        }
+        self.push(
+            span,
+            DiagnosticSeverity::WARNING,
+            UNKNOWN_FUNCTION,
+            format!("Unknown function `{}.{}`", namespace, name),
+            Some(json!({
+                "suggestions": suggestions
+                    .iter()
+                    .map(|s| format!("{}.{}", namespace, s))
+                    .collect::<Vec<_>>()
+            })),
+        );
+    }
+
+    /// The IDs a comparison with `loc`, `loc.id`, `foe` or `foe.id` is about
+    fn compared_ids(&self, expression: &Expression) -> Option<GameIds> {
+        let name = match expression {
+            Expression::Identifier(name, _) => name,
+            Expression::Property {
+                object, property, ..
+            } if property == "id" => match object.as_ref() {
+                Expression::Identifier(name, _) => name,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if self.declared.contains(name) {
+            return None;
+        }
+        match name.as_str() {
+            "loc" => Some(GameIds::Location),
+            "foe" => Some(GameIds::Foe),
+            _ => None,
+        }
+    }
+
+    /// The IDs taken by the first argument of `music.Play()` and
+    /// `item.GetCooldown()`
+    fn id_parameter(&self, object: &Expression, name: &str) -> Option<GameIds> {
+        let Expression::Identifier(namespace, _) = object else {
+            return None;
+        };
+        if self.declared.contains(namespace) {
+            return None;
+        }
+        match (namespace.as_str(), name) {
+            ("music", "Play") => Some(GameIds::Music),
+            ("item", "GetCooldown") => Some(GameIds::Ability),
+            _ => None,
+        }
+    }
+
+    /// `?loc = deadwod`: bare words are Strings, so the expected IDs come
+    /// from what the word is compared with or passed to. Variables and
+    /// computed values are left alone.
+    fn check_game_id(&mut self, expression: &Expression, domain: GameIds) {
+        match expression {
+            Expression::Identifier(name, span) if !self.declared.contains(name) => {
+                self.check_id(name, *span, domain)
+            }
+            Expression::String(text, span) => {
+                // Point inside the quotes so a suggestion replaces the text only
+                let quoted = span.start.line == span.end.line
+                    && span.end.column - span.start.column == text.chars().count() + 2;
+                let span = if quoted {
+                    Span::new(
+                        AstPosition::new(span.start.line, span.start.column + 1),
+                        AstPosition::new(span.end.line, span.end.column - 1),
+                    )
+                } else {
+                    *span
+                };
+                self.check_id(text, span, domain)
+            }
+            _ => {}
+        }
+    }
+
+    /// Only reported when a known ID is a likely fix, since most ID tables
+    /// don't list everything in the game
+    fn check_id(&mut self, id: &str, span: Span, domain: GameIds) {
+        if domain.is_known(id) {
+            return;
+        }
+        let suggestions = suggest(id, domain.ids());
+        if suggestions.is_empty() && domain != GameIds::Ingredient {
+            return;
+        }
+        self.push(
+            span,
+            DiagnosticSeverity::WARNING,
+            UNKNOWN_GAME_ID,
+            format!("Unknown {} `{}`", domain.noun(), id),
+            Some(json!({ "suggestions": suggestions })),
+        );
+    }
+
+    /// `equip sword *12`, `brew tar+bronz`, `loadout two`
+    fn check_command_arguments(&mut self, name: &str, structured: &CommandArgs, span: Span) {
+        let (values, expected, domain) = match structured {
+            CommandArgs::Equip(criteria) => {
+                for criterion in criteria {
+                    self.check_item_criterion(criterion);
+                }
+                return;
+            }
+            CommandArgs::Loadout(values) => (values, "one loadout number", None),
+            CommandArgs::Brew(values) => (
+                values,
+                "ingredients joined by `+`",
+                Some(GameIds::Ingredient),
+            ),
+            CommandArgs::Activate(values) => {
+                (values, "one ability or item", Some(GameIds::Activation))
+            }
+        };
+
+        let count_matches = match structured {
+            CommandArgs::Brew(_) => !values.is_empty(),
+            _ => values.len() == 1,
+        };
+        if !count_matches {
+            let span = values
+                .iter()
+                .map(CommandValue::span)
+                .reduce(|first, last| Span::new(first.start, last.end))
+                .unwrap_or(span);
+            self.push(
+                span,
+                DiagnosticSeverity::WARNING,
+                INVALID_COMMAND_ARGUMENT,
+                format!("`{}` takes {}", name, expected),
+                None,
+            );
+            return;
+        }
+
+        for value in values {
+            let CommandValue::Word(word, span) = value else {
+                continue;
+            };
+            match domain {
+                Some(domain) => self.check_id(word, *span, domain),
+                None => self.push(
+                    *span,
+                    DiagnosticSeverity::WARNING,
+                    INVALID_COMMAND_ARGUMENT,
+                    format!("`{}` is not a loadout number", word),
+                    None,
+                ),
+            }
+        }
+    }
+
+    /// `*12` beyond the highest star level, or `*x` that isn't one
+    fn check_item_criterion(&mut self, criterion: &ItemCriterion) {
+        let message = match criterion {
+            ItemCriterion::Stars(stars, _) if *stars > MAX_STAR_LEVEL => format!(
+                "Star level {} is out of range; items have 0 to {} stars",
+                stars, MAX_STAR_LEVEL
+            ),
+            ItemCriterion::Enchant(enchant, _) if *enchant > MAX_ENCHANT_LEVEL => format!(
+                "Enchantment +{} is out of range; items are enchanted up to +{}",
+                enchant, MAX_ENCHANT_LEVEL
+            ),
+            ItemCriterion::Value(CommandValue::Word(word, _)) if word.starts_with('*') => {
+                format!("`{}` is not a star level such as `*5`", word)
+            }
+            ItemCriterion::Value(CommandValue::Word(word, _)) if word.starts_with('+') => {
+                format!("`{}` is not an enchantment level such as `+3`", word)
+            }
+            _ => return,
+        };
+        self.push(
+            criterion.span(),
+            DiagnosticSeverity::WARNING,
+            INVALID_COMMAND_ARGUMENT,
+            message,
+            None,
+        );
+    }
+
+    /// Calls of user functions: arity against the definition, or a warning
+    /// when no definition can be found
+    fn check_call(&mut self, name: &str, name_span: Span, arg_count: usize, call_span: Span) {
+        // The parser reads an anonymous `func(...)` as a call of `func`
+        if name == "func" {
+            return;
+        }
+        let parameters = self
+            .scope
+            .find_function(name)
+            .or_else(|| {
+                self.modules
+                    .iter()
+                    .find_map(|module| module.scope.find_function(name))
+            })
+            .map(|function| function.parameters.clone());
+
+        if let Some(parameters) = parameters {
+            let expected = parameters.len();
+            if arg_count != expected {
+                let missing = parameters.get(arg_count..).unwrap_or_default();
+                self.push(
+                    call_span,
+                    DiagnosticSeverity::WARNING,
+                    WRONG_ARGUMENT_COUNT,
+                    format!(
+                        "`{}` takes {} argument{} but {} {} given",
+                        name,
+                        expected,
+                        if expected == 1 { "" } else { "s" },
+                        arg_count,
+                        if arg_count == 1 { "was" } else { "were" }
+                    ),
+                    Some(json!({ "missing": missing, "given": arg_count })),
+                );
+            }
+            return;
+        }
+
+        if !self.imports_resolved
+            || self.declared.contains(name)
+            || native_functions::get_function(name).is_some()
+            // Scripts call `type(x)` as often as `Type(x)`
+            || native_functions::GLOBAL_FUNCTIONS
+                .iter()
+                .any(|f| f.name.eq_ignore_ascii_case(name))
+        {
+            return;
+        }
+
+        let mut candidates: Vec<String> = self
+            .scope
+            .get_functions()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        for module in &self.modules {
+            candidates.extend(module.scope.get_functions().into_iter().map(|f| f.name));
+        }
+        let suggestions = suggest(name, candidates.iter().map(String::as_str));
+        self.push(
+            name_span,
+            DiagnosticSeverity::WARNING,
+            UNKNOWN_FUNCTION,
+            format!("Unknown function `{}`", name),
+            Some(json!({ "name": name, "suggestions": suggestions })),
+        );
+    }
+
+    /// `?loc.id = 3` compares text with a number, and `loc.begin + 1` does
+    /// arithmetic on a Bool; StoneScript coerces both without complaint
+    fn check_operand_types(
+        &mut self,
+        left: &Expression,
+        op: BinaryOperator,
+        right: &Expression,
+        span: Span,
+    ) {
+        let left_type = self.type_of(left);
+        let right_type = self.type_of(right);
+
+        match op {
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual => {
+                // Text literals are left alone: the parser also falls back to
+                // them for words it can't otherwise make sense of
+                let is_text = |expression: &Expression, typ: &Type| {
+                    *typ == Type::String && !matches!(expression, Expression::String(..))
+                };
+                let text = if is_text(left, &left_type) && right_type.is_numeric() {
+                    left
+                } else if is_text(right, &right_type) && left_type.is_numeric() {
+                    right
+                } else {
+                    return;
+                };
+                self.push(
+                    span,
+                    DiagnosticSeverity::WARNING,
+                    STRING_NUMBER_COMPARISON,
+                    match describe(text) {
+                        Some(name) => {
+                            format!("`{}` is a String but is compared with a number", name)
+                        }
+                        None => "A String is compared with a number".to_string(),
+                    },
+                    None,
+                );
+            }
+            BinaryOperator::Add
+            | BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo => {
+                // `+` joins a Bool onto text just fine
+                if op == BinaryOperator::Add
+                    && (left_type == Type::String || right_type == Type::String)
+                {
+                    return;
+                }
+                let operand = if left_type == Type::Bool {
+                    left
+                } else if right_type == Type::Bool {
+                    right
+                } else {
+                    return;
+                };
+                self.push(
+                    operand.span(),
+                    DiagnosticSeverity::WARNING,
+                    BOOL_ARITHMETIC,
+                    match describe(operand) {
+                        Some(name) => format!("Arithmetic on `{}`, which is a Bool", name),
+                        None => "Arithmetic on a Bool".to_string(),
+                    },
+                    None,
+                );
+            }
+            BinaryOperator::And | BinaryOperator::Or => {}
+        }
+    }
+
+    /// `string.Size(count)` with `count` holding a number
+    fn check_string_arguments(&mut self, object: &Expression, name: &str, args: &[Expression]) {
+        if !matches!(object, Expression::Identifier(namespace, _) if namespace == "string")
+            || self.declared.contains("string")
+        {
+            return;
+        }
+        let Some(function) = get_function_in_namespace("string", name) else {
+            return;
+        };
+
+        for (parameter, arg) in function.parameters.iter().zip(args) {
+            let Expression::Identifier(variable, span) = arg else {
+                continue;
+            };
+            let typ = self.type_of(arg);
+            if parameter.typ == Type::String && typ.is_numeric() {
+                self.push(
+                    *span,
+                    DiagnosticSeverity::WARNING,
+                    STRING_FUNCTION_NUMBER,
+                    format!(
+                        "`string.{}` expects a String but `{}` is {}",
+                        name,
+                        variable,
+                        if typ == Type::Int {
+                            "an Int"
+                        } else {
+                            "a Float"
+                        }
+                    ),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// A variable assigned a String and used as a number elsewhere, reported
+    /// once at its declaration. Scripts that test the variable with `type`
+    /// store both on purpose.
+    fn check_string_assignments(&mut self) {
+        let mut reports: Vec<(Span, String, Span, Span)> = self
+            .assignments
+            .strings
+            .iter()
+            .filter(|(key, _)| !self.assignments.type_tested.contains(*key))
+            .filter_map(|((name, declaration), string_span)| {
+                let use_span = self.numeric_uses.get(&(name.clone(), *declaration))?;
+                Some(((*declaration)?, name.clone(), *string_span, *use_span))
+            })
+            .collect();
+        reports.sort_by_key(|(declaration, ..)| (declaration.start.line, declaration.start.column));
+
+        for (declaration, name, string_span, use_span) in reports {
+            let mut diagnostic = diagnostic(
+                declaration,
+                DiagnosticSeverity::WARNING,
+                STRING_ASSIGNED_TO_NUMBER,
+                format!("`{}` is assigned a String but is used as a number", name),
+                None,
+            );
+            diagnostic.related_information = Some(vec![
+                DiagnosticRelatedInformation {
+                    location: Location::new(self.uri.clone(), to_range(&string_span)),
+                    message: format!("`{}` is assigned a String here", name),
+                },
+                DiagnosticRelatedInformation {
+                    location: Location::new(self.uri.clone(), to_range(&use_span)),
+                    message: format!("`{}` is used as a number here", name),
+                },
+            ]);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn type_of(&self, expression: &Expression) -> Type {
+        type_of(self.scope, &self.assignments.types, expression)
+    }
+
+    /// `var`s declared in a function body that are never read there
+    fn check_unused_locals(&mut self, body: &[Statement]) {
+        let mut locals = Locals(Vec::new());
+        locals.visit_block(body);
+        let locals = locals.0;
+        if locals.is_empty() {
+            return;
+        }
+
+        let reads = VariableUsage::of_statements(body).read;
+        for (name, span) in locals {
+            if !reads.contains(&name) {
+                let mut diagnostic = diagnostic(
+                    span,
+                    DiagnosticSeverity::WARNING,
+                    UNUSED_VARIABLE,
+                    format!("Variable `{}` is never used", name),
+                    None,
+                );
+                diagnostic.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        span: Span,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+        data: Option<serde_json::Value>,
+    ) {
+        self.diagnostics
+            .push(diagnostic(span, severity, code, message, data));
+    }
+}
+
+pub(crate) fn diagnostic(
+    span: Span,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+    data: Option<serde_json::Value>,
+) -> Diagnostic {
+    Diagnostic {
+        range: to_range(&span),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_string())),
+        message,
+        data,
+        ..Default::default()
     }
 }
 
+/// A variable told apart from others of the same name by its declaration
+type VariableKey = (String, Option<Span>);
+
+fn variable_key(scope: &ScopeAnalyzer, name: &str, position: AstPosition) -> VariableKey {
+    let definition = scope
+        .find_variable_at(name, position)
+        .and_then(|variable| variable.definition_span);
+    (name.to_string(), definition)
+}
+
+/// Type of an expression. A variable has the type every assignment to it
+/// agrees on, since it holds whatever it was last assigned whichever way the
+/// script went.
+fn type_of(
+    scope: &ScopeAnalyzer,
+    types: &HashMap<VariableKey, Type>,
+    expression: &Expression,
+) -> Type {
+    match expression {
+        Expression::Identifier(name, span)
+            if scope.find_variable_at(name, span.start).is_some() =>
+        {
+            types
+                .get(&variable_key(scope, name, span.start))
+                .cloned()
+                .unwrap_or(Type::Unknown)
+        }
+        _ => value_type(scope, expression),
+    }
+}
+
+/// Type of a value as it is written, looking variables up in the scope they
+/// are used in. Array elements are typed after the first one, so anything
+/// indexing an array counts as unknown.
+fn value_type(scope: &ScopeAnalyzer, expression: &Expression) -> Type {
+    let mut finder = IndexFinder(false);
+    finder.visit_expression(expression);
+    if finder.0 {
+        return Type::Unknown;
+    }
+
+    match expression {
+        Expression::Identifier(name, span) => match scope.find_variable_at(name, span.start) {
+            Some(variable) => variable.inferred_type.clone(),
+            None => infer_type_with_scope(expression, Some(scope)),
+        },
+        _ => infer_type_with_scope(expression, Some(scope)),
+    }
+}
+
+/// What each variable is assigned with `var` and `=` anywhere in the document
+struct Assignments<'a> {
+    scope: &'a ScopeAnalyzer,
+    /// The type every assignment agrees on, or Unknown
+    types: HashMap<VariableKey, Type>,
+    /// The first String assigned to each variable that is assigned one
+    strings: HashMap<VariableKey, Span>,
+    /// Variables the script tests with `type(...)`
+    type_tested: HashSet<VariableKey>,
+}
+
+impl Assignments<'_> {
+    fn assign(&mut self, key: VariableKey, typ: Type) {
+        self.types
+            .entry(key)
+            .and_modify(|agreed| {
+                if *agreed != typ {
+                    *agreed = Type::Unknown;
+                }
+            })
+            .or_insert(typ);
+    }
+
+    fn assign_value(&mut self, name: &str, position: AstPosition, value: &Expression) {
+        let key = variable_key(self.scope, name, position);
+        let typ = value_type(self.scope, value);
+        if typ == Type::String {
+            self.strings.entry(key.clone()).or_insert(value.span());
+        }
+        self.assign(key, typ);
+    }
+}
+
+impl Visitor for Assignments<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer,
+                ..
+            } => match initializer {
+                Some(value) => self.assign_value(name, name_span.start, value),
+                None => self.assign(
+                    variable_key(self.scope, name, name_span.start),
+                    Type::Unknown,
+                ),
+            },
+            Statement::Assignment {
+                target: Expression::Identifier(name, span),
+                op: AssignmentOperator::Assign,
+                value,
+                ..
+            } => self.assign_value(name, span.start, value),
+            // Callers pass parameters whatever they like
+            Statement::FunctionDefinition {
+                params,
+                param_spans,
+                ..
+            } => {
+                for (param, span) in params.iter().zip(param_spans) {
+                    self.assign((param.clone(), Some(*span)), Type::Unknown);
+                }
+            }
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::FunctionCall { function, args, .. } = expression {
+            if let (Expression::Identifier(function, _), [Expression::Identifier(name, span)]) =
+                (function.as_ref(), args.as_slice())
+            {
+                if function == "type" {
+                    self.type_tested
+                        .insert(variable_key(self.scope, name, span.start));
+                }
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// Tells whether an expression indexes anything
+struct IndexFinder(bool);
+
+impl Visitor for IndexFinder {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if matches!(expression, Expression::IndexAccess { .. }) {
+            self.0 = true;
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// `loc.id` for names and property accesses, to quote in messages
+fn describe(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Identifier(name, _) => Some(name.clone()),
+        Expression::Property {
+            object, property, ..
+        } => Some(format!("{}.{}", describe(object)?, property)),
+        _ => None,
+    }
+}
+
+/// Finds where variables are used as numbers: operands of arithmetic other
+/// than `+`, ordered comparisons with a number, `-x`, `x++`, compound
+/// assignments other than `+=`, and `for` ranges
+struct NumericUses<'a> {
+    scope: &'a ScopeAnalyzer,
+    types: &'a HashMap<VariableKey, Type>,
+    uses: HashMap<VariableKey, Span>,
+}
+
+impl NumericUses<'_> {
+    fn record(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name, span) = expression {
+            self.uses
+                .entry(variable_key(self.scope, name, span.start))
+                .or_insert(*span);
+        }
+    }
+}
+
+impl Visitor for NumericUses<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Assignment { target, op, .. }
+                if !matches!(
+                    op,
+                    AssignmentOperator::Assign | AssignmentOperator::AddAssign
+                ) =>
+            {
+                self.record(target)
+            }
+            Statement::For { range, .. } => {
+                self.record(&range.0);
+                self.record(&range.1);
+            }
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::BinaryOp {
+                left, op, right, ..
+            } => match op {
+                BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo => {
+                    self.record(left);
+                    self.record(right);
+                }
+                BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual => {
+                    if type_of(self.scope, self.types, right).is_numeric() {
+                        self.record(left);
+                    }
+                    if type_of(self.scope, self.types, left).is_numeric() {
+                        self.record(right);
+                    }
+                }
+                _ => {}
+            },
+            Expression::UnaryOp {
+                op: UnaryOperator::Negate | UnaryOperator::Increment | UnaryOperator::Decrement,
+                operand,
+                ..
+            } => self.record(operand),
+            _ => {}
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// The ID tables a word can be checked against
+#[derive(Clone, Copy, PartialEq)]
+enum GameIds {
+    Location,
+    Foe,
+    Ability,
+    Sound,
+    Music,
+    /// `brew` ingredients, the only complete table
+    Ingredient,
+    /// Abilities and the item slots `activate` takes
+    Activation,
+}
+
+impl GameIds {
+    fn ids(self) -> Vec<&'static str> {
+        match self {
+            GameIds::Location => LOCATIONS.to_vec(),
+            GameIds::Foe => FOES.to_vec(),
+            GameIds::Ability => ABILITY_IDS.to_vec(),
+            GameIds::Sound => SOUND_EFFECTS.to_vec(),
+            GameIds::Music => MUSIC_TRACKS.to_vec(),
+            GameIds::Ingredient => BREW_INGREDIENTS.iter().map(|(id, _)| *id).collect(),
+            GameIds::Activation => ACTIVATE_SLOTS
+                .iter()
+                .map(|(id, _)| *id)
+                .chain(ABILITY_IDS.iter().copied())
+                .collect(),
+        }
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            GameIds::Location => "location",
+            GameIds::Foe => "foe",
+            GameIds::Ability | GameIds::Activation => "ability",
+            GameIds::Sound => "sound",
+            GameIds::Music => "music track",
+            GameIds::Ingredient => "ingredient",
+        }
+    }
+
+    fn is_known(self, id: &str) -> bool {
+        let id = id.to_lowercase();
+        // `?loc = hall` and `?foe = chest` match on part of the ID, and the
+        // tables don't always use the game's exact spelling (`caves`)
+        let overlaps = |known: &str| id.contains(known) || known.contains(id.as_str());
+        match self {
+            GameIds::Location => LOCATIONS.iter().any(|loc| overlaps(loc)),
+            // Foes can also be tested for their name and their filters
+            GameIds::Foe => {
+                FOES.iter().any(|foe| overlaps(foe))
+                    || FOE_NAMES
+                        .iter()
+                        .any(|(_, name)| overlaps(&name.to_lowercase()))
+                    || ALL_FILTERS.contains(&id.as_str())
+            }
+            _ => self
+                .ids()
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(&id)),
+        }
+    }
+}
+
+pub(crate) fn to_range(span: &Span) -> Range {
+    Range {
+        start: Position {
+            line: span.start.line as u32,
+            character: span.start.column as u32,
+        },
+        end: Position {
+            line: span.end.line as u32,
+            character: span.end.column as u32,
+        },
+    }
+}
+
+/// Call `f` on each statement, descending into nested blocks
+pub(crate) fn for_each_statement(statements: &[Statement], f: &mut impl FnMut(&Statement)) {
+    struct Statements<F>(F);
+
+    impl<F: FnMut(&Statement)> Visitor for Statements<F> {
+        fn visit_statement(&mut self, statement: &Statement) {
+            (self.0)(statement);
+            walk_statement(self, statement);
+        }
+
+        fn visit_expression(&mut self, _expression: &Expression) {}
+    }
+
+    Statements(f).visit_block(statements);
+}
+
+/// Names brought into scope anywhere in the document
+struct Declarations(HashSet<String>);
+
+impl Visitor for Declarations {
+    fn visit_var_declaration(&mut self, name: &str, _initializer: &Option<Expression>) {
+        self.0.insert(name.to_string());
+    }
+
+    fn visit_function_definition(&mut self, name: &str, params: &[String], body: &[Statement]) {
+        self.0.insert(name.to_string());
+        self.0.extend(params.iter().cloned());
+        self.visit_block(body);
+    }
+
+    fn visit_for(&mut self, variable: &str, _range: &(Expression, Expression), body: &[Statement]) {
+        self.0.insert(variable.to_string());
+        self.visit_block(body);
+    }
+
+    fn visit_for_in(&mut self, variable: &str, _collection: &Expression, body: &[Statement]) {
+        self.0.insert(variable.to_string());
+        self.visit_block(body);
+    }
+
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
+
+/// `var`s declared in a function body, leaving out those of nested functions
+struct Locals(Vec<(String, Span)>);
+
+impl Visitor for Locals {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration {
+                name, name_span, ..
+            } => self.0.push((name.clone(), *name_span)),
+            Statement::FunctionDefinition { .. } => {}
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use stonescript_parser::parse_source;
+
+    fn codes(source: &str) -> Vec<String> {
+        let ast = parse_source(source).unwrap();
+        let mut scope = ScopeAnalyzer::new();
+        scope.analyze_ast(&ast);
+        let uri = Url::parse("file:///scripts/Test.txt").unwrap();
+        DiagnosticsProvider::new()
+            .provide_diagnostics(&ast, source, &scope, &uri, &Workspace::new())
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => Some(code),
+                _ => None,
+            })
+            .collect()
+    }
 
     #[test]
     fn test_diagnostics_provider() {
+        assert!(codes("var x = foe.hp\n?x < 5\n  >low").is_empty());
+        assert_eq!(codes("?foe.hpp < 5\n  >low"), vec![UNKNOWN_PROPERTY]);
+        assert_eq!(codes("count = 1"), vec![UNDECLARED_VARIABLE]);
+        assert_eq!(codes("Draw()"), vec![UNKNOWN_FUNCTION]);
+        assert_eq!(codes("var s = math.Sing(2)"), vec![UNKNOWN_FUNCTION]);
+        assert!(codes("var s = math.Sign(-2) + math.Atan2(1, 2)").is_empty());
+        assert!(codes("var t = type(1) + Type(2)").is_empty());
+        assert!(codes("var f = func(a)\n  return a\nf(1)")
+            .iter()
+            .all(|code| code != UNKNOWN_FUNCTION));
+        // Namespaces the tables only cover in part
+        assert!(codes("var s = string.ToUpper(\"a\")").is_empty());
+        assert_eq!(
+            codes("func Add(a, b)\n  var unused = 1\n  return a + b\nAdd(1)"),
+            vec![UNUSED_VARIABLE, WRONG_ARGUMENT_COUNT]
+        );
+    }
+
+    #[test]
+    fn test_type_mismatches() {
+        assert_eq!(codes("?loc.id = 3\n  >a"), vec![STRING_NUMBER_COMPARISON]);
+        assert!(codes("?loc.id = \"rocky\" & loc.stars > 3\n  >a").is_empty());
+
+        assert_eq!(codes("var x = loc.begin + 1"), vec![BOOL_ARITHMETIC]);
+        assert!(codes("var x = \"begin: \" + loc.begin").is_empty());
+
+        assert_eq!(
+            codes("var count = 3\nvar n = string.Size(count)"),
+            vec![STRING_FUNCTION_NUMBER]
+        );
+        assert!(codes("var text = \"abc\"\nvar n = string.Size(text)").is_empty());
+
+        assert_eq!(
+            codes("var timer = 0\ntimer = \"done\"\n?timer > 5\n  timer--"),
+            vec![STRING_ASSIGNED_TO_NUMBER]
+        );
+        assert!(codes("var label = 0\nlabel = \"done\"\n>@label@").is_empty());
+        // Reported once, at the declaration
+        assert_eq!(
+            codes("var timer = 0\ntimer = \"a\"\ntimer = \"b\"\ntimer = timer - 1"),
+            vec![STRING_ASSIGNED_TO_NUMBER]
+        );
+        // Scripts that test the type store both on purpose
+        assert!(codes(
+            "var mode = \"stop\"\nmode = 3\n?type(mode) = int\n  var speed = 4 - mode\n>@speed@"
+        )
+        .is_empty());
+
+        // A variable holds whatever it was last assigned, so a String
+        // assigned on one path does not make it a String on every path
+        assert!(codes("func F(name)\n  ?name = \"a\" | name = 0\n    >b").is_empty());
+        assert!(codes("var lvl = loc.id\n?lvl = \"rocky\"\n  lvl = 1\n?lvl = 1\n  >a").is_empty());
+        assert_eq!(
+            codes("var lvl = loc.id\nlvl = \"rocky\"\n?lvl = 1\n  >a"),
+            vec![STRING_NUMBER_COMPARISON]
+        );
+    }
+
+    #[test]
+    fn test_unknown_game_ids() {
+        assert_eq!(codes("?loc = deadwod\n  >a"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("?loc.id ! \"rocy\"\n  >a"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("?foe = bolsh\n  >a"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("play levl_up"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("music.Play(\"vilage\")"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(
+            codes("?item.GetCooldown(\"bardich\") <= 0\n  >a"),
+            vec![UNKNOWN_GAME_ID]
+        );
+        assert_eq!(
+            codes("?cooldown.hatchett <= 0\n  >a"),
+            vec![UNKNOWN_GAME_ID]
+        );
+
+        // Known IDs, foe name parts and filters, and variables
+        assert!(codes("?loc = deadwood & foe = boss\n  >a").is_empty());
+        assert!(codes("?foe = guardian | foe = phase2\n  >a").is_empty());
+        assert!(codes("play level_up 200").is_empty());
+        assert!(codes("var deadwod = 1\n?loc = deadwod\n  >a").is_empty());
+        // Too far from anything known to be a typo
+        assert!(codes("play temple_gong_echo").is_empty());
+    }
+
+    #[test]
+    fn test_command_arguments() {
+        assert!(
+            codes("equip vigor crossbow *8 +5\nloadout 2\nbrew tar+bronze\nactivate R").is_empty()
+        );
+        assert!(codes("var n = 2\nloadout @n@\nactivate Voidweaver\nequipL @n@ *10").is_empty());
+
+        assert_eq!(codes("equip sword *12"), vec![INVALID_COMMAND_ARGUMENT]);
+        assert_eq!(codes("equip sword +11"), vec![INVALID_COMMAND_ARGUMENT]);
+        assert_eq!(codes("equipR shield *x"), vec![INVALID_COMMAND_ARGUMENT]);
+        assert_eq!(codes("loadout two"), vec![INVALID_COMMAND_ARGUMENT]);
+        assert_eq!(codes("loadout 1 2"), vec![INVALID_COMMAND_ARGUMENT]);
+        assert_eq!(codes("activate R L"), vec![INVALID_COMMAND_ARGUMENT]);
+
+        // Every ingredient is known, so even distant words are reported
+        assert_eq!(codes("brew tar+bronz"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("brew tar+gold"), vec![UNKNOWN_GAME_ID]);
+        assert_eq!(codes("activate potoin"), vec![UNKNOWN_GAME_ID]);
+    }
+
+    #[test]
+    fn test_lints() {
+        assert_eq!(
+            codes("func F()\n  return 1\n  >a\n  >b"),
+            vec![UNREACHABLE_CODE]
+        );
+        assert_eq!(codes("?1 = 2\n  >a"), vec![CONSTANT_CONDITION]);
+        assert_eq!(codes("?hp < 5 & false\n  >a"), vec![CONSTANT_CONDITION]);
+        assert_eq!(
+            codes("?hp < 5\n  >a\n:?hp > 9\n  >b\n:?hp < 5\n  >c"),
+            vec![DUPLICATE_CONDITION]
+        );
+        assert_eq!(codes("for i = 5..1\n  >@i@"), vec![REVERSED_RANGE]);
+
+        // Functions defined after a return, and the script's own top level
+        assert!(codes("func F()\n  return\n  func G()\n    >a").is_empty());
+        assert!(codes("return\n>a").is_empty());
+        // Conditions that may change between branches
+        assert!(codes("?rng < 5\n  >a\n:?rng < 5\n  >b").is_empty());
+        assert!(codes("?hp < 5 & hp > 1\n  >a").is_empty());
+        assert!(codes("for i = 0..3\n  >@i@").is_empty());
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let source = "var x = loc.begin + 1\n?loc.id = 3\n  >a";
+        let ast = parse_source(source).unwrap();
+        let mut scope = ScopeAnalyzer::new();
+        scope.analyze_ast(&ast);
+        let uri = Url::parse("file:///scripts/Test.txt").unwrap();
+
         let provider = DiagnosticsProvider::new();
-        let _ = provider;
+        provider.set_severities(HashMap::from([
+            (BOOL_ARITHMETIC.to_string(), DiagnosticLevel::Off),
+            (STRING_NUMBER_COMPARISON.to_string(), DiagnosticLevel::Error),
+        ]));
+        let diagnostics =
+            provider.provide_diagnostics(&ast, source, &scope, &uri, &Workspace::new());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
     }
 }
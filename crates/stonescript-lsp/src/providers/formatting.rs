@@ -1,17 +1,408 @@
 //! Formatting provider
+//!
+//! Formatting only changes whitespace: lines are indented by how deeply they
+//! nest under `?`, `:`, `for` and `func`, measured the way the parser measures
+//! blocks, and trailing spaces and repeated blank lines are dropped. `ascii`
+//! art, block comments and `^` continuation lines are kept exactly as
+//! written. A script that would parse differently once formatted is left as
+//! it is.
 
+use crate::config::{FormattingSettings, IndentStyle};
+use std::sync::RwLock;
+use stonescript_parser::{parse_source, Statement, VisitorMut};
 use tower_lsp::lsp_types::*;
 
-pub struct FormattingProvider;
+/// One level of indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+impl Indent {
+    /// The configured indentation, falling back to the editor's tab options
+    /// and then to two spaces
+    pub fn resolve(settings: &FormattingSettings, editor: Option<&FormattingOptions>) -> Self {
+        let style = settings.indent_style.unwrap_or(match editor {
+            Some(options) if !options.insert_spaces => IndentStyle::Tabs,
+            _ => IndentStyle::Spaces,
+        });
+        match style {
+            IndentStyle::Tabs => Indent::Tabs,
+            IndentStyle::Spaces => Indent::Spaces(
+                settings
+                    .indent_width
+                    .or(editor.map(|options| options.tab_size))
+                    .unwrap_or(2) as usize,
+            ),
+        }
+    }
+
+    fn text(self, level: usize) -> String {
+        match self {
+            Indent::Spaces(width) => " ".repeat(width * level),
+            Indent::Tabs => "\t".repeat(level),
+        }
+    }
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+pub struct FormattingProvider {
+    settings: RwLock<FormattingSettings>,
+}
 
 impl FormattingProvider {
     pub fn new() -> Self {
-        Self
+        Self {
+            settings: RwLock::new(FormattingSettings::default()),
+        }
+    }
+
+    /// Replace the formatting settings loaded from the user's configuration
+    pub fn set_settings(&self, settings: FormattingSettings) {
+        if let Ok(mut current) = self.settings.write() {
+            *current = settings;
+        }
+    }
+
+    /// A single edit replacing the document, or none if it is already
+    /// formatted
+    pub fn provide_formatting(&self, source: &str, options: &FormattingOptions) -> Vec<TextEdit> {
+        let settings = self.settings.read().map(|s| *s).unwrap_or_default();
+        let formatted = format_source(source, Indent::resolve(&settings, Some(options)));
+        if formatted == source {
+            return vec![];
+        }
+
+        let last_line = source.split('\n').count() - 1;
+        let last_length = source
+            .rsplit('\n')
+            .next()
+            .unwrap_or("")
+            .encode_utf16()
+            .count();
+        vec![TextEdit {
+            range: Range::new(
+                Position::new(0, 0),
+                Position::new(last_line as u32, last_length as u32),
+            ),
+            new_text: formatted,
+        }]
+    }
+}
+
+impl Default for FormattingProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Format a whole script, or return it unchanged when the formatted text
+/// would not parse to the same statements
+pub fn format_source(source: &str, indent: Indent) -> String {
+    let formatted = reindent(source, indent);
+    let statements = |text: &str| {
+        parse_source(text).map(|mut program| {
+            BlankLines.visit_program_mut(&mut program);
+            program
+        })
+    };
+    match (statements(source), statements(&formatted)) {
+        (Ok(before), Ok(after)) if after.eq_ignoring_spans(&before) => formatted,
+        _ => source.to_string(),
+    }
+}
+
+/// Drops the statements the parser makes of some blank lines, which
+/// formatting is free to remove
+struct BlankLines;
+
+impl VisitorMut for BlankLines {
+    fn visit_block_mut(&mut self, statements: &mut Vec<Statement>) {
+        statements.retain(|statement| !matches!(statement, Statement::Empty));
+        for statement in statements {
+            self.visit_statement_mut(statement);
+        }
     }
+}
+
+fn reindent(source: &str, indent: Indent) -> String {
+    let (bom, text) = match source.strip_prefix('\u{FEFF}') {
+        Some(rest) => ("\u{FEFF}", rest),
+        None => ("", source),
+    };
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut out: Vec<String> = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    // The marker ending an `ascii` block or block comment being copied
+    let mut verbatim_until: Option<&str> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(end) = verbatim_until {
+            out.push(line.to_string());
+            // `asciiend,ascii` ends one piece of art and starts the next
+            if let Some(at) = line.find(end) {
+                verbatim_until = verbatim_start(&line[at + end.len()..]);
+            }
+            continue;
+        }
+
+        let content = line.trim_start_matches([' ', '\t']);
+        // The parser counts a tab as one column, like a space
+        let width = line.len() - content.len();
+        if content.trim().is_empty() {
+            // The parser reads the line after a blank first line differently
+            if out.last().is_none_or(|previous| !previous.is_empty()) {
+                out.push(String::new());
+            }
+            continue;
+        }
+        // Joined onto the previous line, so its spaces may be part of a string
+        if content.starts_with('^') {
+            out.push(line.to_string());
+            verbatim_until = verbatim_start(content);
+            continue;
+        }
+        let continued = lines
+            .get(index + 1)
+            .is_some_and(|next| next.trim_start().starts_with('^'));
+        // Output text and comments keep their trailing spaces
+        let content = if continued
+            || content.starts_with('>')
+            || content.contains("//")
+            || content.contains("/*")
+        {
+            content
+        } else {
+            content.trim_end()
+        };
+
+        let level = if content.starts_with("//") || content.starts_with("/*") {
+            // The game skips comments, so they never close a block, but the
+            // parser measures a block from one that comes first
+            if let Some(block) = blocks.last_mut().filter(|block| block.base.is_none()) {
+                if width > 0 {
+                    block.base = Some(width);
+                }
+            }
+            blocks
+                .iter()
+                .take_while(|block| block.base.map_or(width > 0, |base| width >= base))
+                .count()
+        } else {
+            // A block's first line sets its indentation, and it lasts until
+            // a line indented less, or a first line that is not indented.
+            // Only then may `:` or `:?` continue the condition it ended;
+            // inside a block they are read as text.
+            let mut branch = None;
+            while let Some(block) = blocks.last_mut() {
+                match block.base {
+                    None if width > 0 => {
+                        block.base = Some(width);
+                        break;
+                    }
+                    Some(base) if width >= base => break,
+                    _ => {
+                        let ended = blocks.pop();
+                        if let Some(ended) = ended.filter(|ended| {
+                            ended.condition && ended.header <= width && content.starts_with(':')
+                        }) {
+                            branch = Some(ended.header);
+                            break;
+                        }
+                    }
+                }
+            }
+            let level = blocks.len();
+            if let Some(header) = branch {
+                blocks.push(Block {
+                    header,
+                    base: None,
+                    // Only one `:` can end a chain
+                    condition: content[1..].trim_start().starts_with('?'),
+                });
+            } else if opens_block(content) && !content.starts_with(':') {
+                blocks.push(Block {
+                    header: width,
+                    base: None,
+                    condition: content.starts_with('?'),
+                });
+            }
+            level
+        };
+        out.push(format!("{}{}", indent.text(level), content));
+        verbatim_until = verbatim_start(content);
+    }
+
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    if out.is_empty() {
+        return bom.to_string();
+    }
+    format!("{}{}{}", bom, out.join(newline), newline)
+}
+
+/// A block opened by `?`, `:`, `for` or `func`, with indentation as the
+/// parser measures it
+struct Block {
+    /// Indentation of the line that opened it
+    header: usize,
+    /// Indentation of its first line, once there is one
+    base: Option<usize>,
+    /// Whether a `:` or `:?` may still follow it
+    condition: bool,
+}
+
+fn opens_block(content: &str) -> bool {
+    let keyword = |word: &str| {
+        content
+            .strip_prefix(word)
+            .is_some_and(|rest| rest.starts_with([' ', '\t']))
+    };
+    content.starts_with('?') || content.starts_with(':') || keyword("for") || keyword("func")
+}
+
+/// The marker to copy lines up to, when this line starts an `ascii` block
+/// or block comment it does not close
+fn verbatim_start(content: &str) -> Option<&'static str> {
+    let code = match content.find("//") {
+        Some(comment) => &content[..comment],
+        None => content,
+    };
+    if let Some(start) = code.find("/*") {
+        if !content[start + 2..].contains("*/") {
+            return Some("*/");
+        }
+    }
+
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let ascii = code.rmatch_indices("ascii").find(|&(at, _)| {
+        let before = code[..at].chars().next_back();
+        let after = code[at + "ascii".len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    });
+    match ascii {
+        Some((at, _)) if !content[at..].contains("asciiend") => Some("asciiend"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_source(source, Indent::default())
+    }
+
+    #[test]
+    fn test_reindents_blocks() {
+        assert_eq!(
+            format("?hp < 5\n    activate potion\n    ?foe = boss\n        >run\n:\n  >ok\n"),
+            "?hp < 5\n  activate potion\n  ?foe = boss\n    >run\n:\n  >ok\n"
+        );
+        assert_eq!(
+            format_source("func F(x)\n  for i = 0..x\n    >@i@\n", Indent::Tabs),
+            "func F(x)\n\tfor i = 0..x\n\t\t>@i@\n"
+        );
+        // Over-indented lines are still siblings
+        assert_eq!(format("?a\n  >b\n     >c\n>d\n"), "?a\n  >b\n  >c\n>d\n");
+    }
+
+    #[test]
+    fn test_whitespace_cleanup() {
+        assert_eq!(
+            format("var a = 1   \n\n\n\nvar b = 2\n>text  \n\n"),
+            "var a = 1\n\nvar b = 2\n>text  \n"
+        );
+        // One blank line is kept ahead of the script
+        assert_eq!(format("\n\n\nvar a = 1\n"), "\nvar a = 1\n");
+        assert_eq!(
+            format("var a = 1\r\n?a\r\n    >b"),
+            "var a = 1\r\n?a\r\n  >b\r\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_keep_blocks_open() {
+        assert_eq!(
+            format("func F()\n    var a = 1\n// note\n    return a\n"),
+            "func F()\n  var a = 1\n// note\n  return a\n"
+        );
+    }
+
+    #[test]
+    fn test_indentation_as_the_parser_measures_it() {
+        // A tab is one column, so both lines are in the same block
+        assert_eq!(format("?a\n\t\tinitUI()\n  >b\n"), "?a\n  initUI()\n  >b\n");
+        // `:` only continues a condition whose block it ends
+        assert_eq!(format("?a\n    >b\n  :\n    >c\n"), "?a\n  >b\n:\n  >c\n");
+        let text = "?a\n  >b\n  :\n";
+        assert_eq!(format(text), text);
+    }
+
+    #[test]
+    fn test_scripts_that_would_change_are_left_alone() {
+        // The indented `for` is a command at the top level
+        let source = "  for i = 0..2\n>@i@\n";
+        assert_eq!(format(source), source);
+    }
+
+    #[test]
+    fn test_verbatim_lines() {
+        let art = "var a = ascii\n  /\\\n /  \\   \nasciiend\n";
+        assert_eq!(format(art), art);
+
+        let source = "?a\n    >o0,0,ascii\n  ##  \n    asciiend\n    >b\n";
+        assert_eq!(
+            format(source),
+            "?a\n  >o0,0,ascii\n  ##  \n    asciiend\n  >b\n"
+        );
+
+        let continued = "var s = \"a \n    ^b\"\n";
+        assert_eq!(format(continued), continued);
+
+        let continued = "var art = [\n^ascii\n  ##  \n asciiend,\n^ascii\n #\nasciiend]\n";
+        assert_eq!(format(continued), continued);
+
+        let chained = "var a = [ascii\n  #  \nasciiend,ascii\n  ##  \nasciiend]\n";
+        assert_eq!(format(chained), chained);
+
+        let comment = "/*//////\n    note   \n*/\n?a\n    >b\n";
+        assert_eq!(format(comment), "/*//////\n    note   \n*/\n?a\n  >b\n");
+    }
+
+    #[test]
+    fn test_provide_formatting() {
+        let provider = FormattingProvider::new();
+        let options = FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+            ..Default::default()
+        };
+        let edits = provider.provide_formatting("?a\n  >b", &options);
+        assert_eq!(edits[0].new_text, "?a\n    >b\n");
+        assert_eq!(edits[0].range.end, Position::new(1, 4));
+        assert!(provider
+            .provide_formatting("?a\n    >b\n", &options)
+            .is_empty());
 
-    pub fn provide_formatting(&self, _source: &str) -> Vec<TextEdit> {
-        // Basic formatting: ensure consistent indentation
-        // For now, return empty - full formatting would need more work
-        vec![]
+        // Configured settings win over the editor's
+        provider.set_settings(FormattingSettings {
+            indent_style: Some(IndentStyle::Spaces),
+            indent_width: Some(2),
+        });
+        assert_eq!(
+            provider.provide_formatting("?a\n    >b\n", &options)[0].new_text,
+            "?a\n  >b\n"
+        );
     }
 }
@@ -1,8 +1,12 @@
 //! Hover provider with type information
 
 use crate::data::*;
-use crate::utils::{infer_type_with_scope, ScopeAnalyzer, FunctionStub};
-use stonescript_parser::ast::{Expression, Position as AstPosition, Program, Statement};
+use crate::providers::diagnostics::for_each_statement;
+use crate::utils::{infer_type_with_scope, FunctionStub, ScopeAnalyzer};
+use stonescript_parser::ast::{
+    CommandArgs, CommandValue, Expression, ItemCriterion, OutputKind, Position as AstPosition,
+    Program, Statement,
+};
 use tower_lsp::lsp_types::*;
 
 pub struct HoverProvider {
@@ -28,6 +32,22 @@ impl HoverProvider {
         // Convert LSP position to AST position
         let ast_pos = AstPosition::new(position.line as usize, position.character as usize);
 
+        // The `>` of a print statement and its variant character
+        if let Some(kind) = output_kind_at(&ast.statements, ast_pos) {
+            return self.hover_for_identifier(&format!(">{}", kind.prefix()));
+        }
+
+        // Words given to `equip`, `brew` and the like
+        if let Some(value) = command_argument_at(&ast.statements, ast_pos) {
+            return Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            });
+        }
+
         // Try to find expression at position
         if let Some(expr) = self.find_expression_at_position(ast, ast_pos) {
             return self.hover_for_expression(expr, scope);
@@ -200,7 +220,7 @@ impl HoverProvider {
 
                 // UI properties
                 if *obj_name == "Panel" || *obj_name == "UI" {
-                    if UI_PROPERTIES.iter().any(|&p| p == property) {
+                    if UI_PROPERTIES.iter().any(|(p, _)| *p == property) {
                         return Some(Hover {
                             contents: HoverContents::Markup(MarkupContent {
                                 kind: MarkupKind::Markdown,
@@ -214,7 +234,7 @@ impl HoverProvider {
                     }
 
                     // UI methods
-                    if UI_METHODS.iter().any(|&m| m == property) {
+                    if UI_METHODS.iter().any(|(m, _)| *m == property) {
                         return Some(Hover {
                             contents: HoverContents::Markup(MarkupContent {
                                 kind: MarkupKind::Markdown,
@@ -403,6 +423,17 @@ impl HoverProvider {
                 }
                 None
             }
+            Statement::VarDeclaration {
+                initializer: Some(value),
+                span,
+                ..
+            } => {
+                if span.contains_position(pos) && value.span().contains_position(pos) {
+                    self.find_deepest_expression(value, pos)
+                } else {
+                    None
+                }
+            }
             Statement::Assignment {
                 target,
                 value,
@@ -591,6 +622,132 @@ impl HoverProvider {
     }
 }
 
+/// Variant of the print statement whose `>` prefix is at `pos`
+fn output_kind_at(statements: &[Statement], pos: AstPosition) -> Option<OutputKind> {
+    let statement = statements
+        .iter()
+        .find(|stmt| stmt.span().is_some_and(|span| span.contains_position(pos)))?;
+
+    match statement {
+        Statement::Output { kind, span, .. } => {
+            let prefix_end = span.start.column + 1 + kind.prefix().len();
+            (pos.line == span.start.line && pos.column < prefix_end).then_some(*kind)
+        }
+        Statement::Condition {
+            then_block,
+            else_ifs,
+            else_block,
+            ..
+        } => output_kind_at(then_block, pos)
+            .or_else(|| {
+                else_ifs
+                    .iter()
+                    .find_map(|else_if| output_kind_at(&else_if.block, pos))
+            })
+            .or_else(|| {
+                else_block
+                    .as_ref()
+                    .and_then(|block| output_kind_at(block, pos))
+            }),
+        Statement::FunctionDefinition { body, .. }
+        | Statement::For { body, .. }
+        | Statement::ForIn { body, .. }
+        | Statement::While { body, .. } => output_kind_at(body, pos),
+        _ => None,
+    }
+}
+
+/// Explanation of the command argument at `pos`, for the commands whose
+/// arguments are words rather than expressions
+fn command_argument_at(statements: &[Statement], pos: AstPosition) -> Option<String> {
+    let mut found = None;
+    for_each_statement(statements, &mut |statement| {
+        if let Statement::Command {
+            structured: Some(structured),
+            span,
+            ..
+        } = statement
+        {
+            if found.is_none() && span.contains_position(pos) {
+                found = describe_command_argument(structured, pos);
+            }
+        }
+    });
+    found
+}
+
+fn describe_command_argument(structured: &CommandArgs, pos: AstPosition) -> Option<String> {
+    let word = |value: &CommandValue| match value {
+        CommandValue::Word(word, span) if span.contains_position(pos) => Some(word.clone()),
+        CommandValue::Number(number, span) if span.contains_position(pos) => {
+            Some(number.to_string())
+        }
+        _ => None,
+    };
+
+    let (word, description) = match structured {
+        CommandArgs::Equip(criteria) => {
+            let criterion = criteria
+                .iter()
+                .find(|criterion| criterion.span().contains_position(pos))?;
+            match criterion {
+                ItemCriterion::Stars(stars, _) => (
+                    format!("*{}", stars),
+                    format!("Star level: equips an item with {} stars", stars),
+                ),
+                ItemCriterion::Enchant(enchant, _) => (
+                    format!("+{}", enchant),
+                    format!("Enchantment: equips an item enchanted to +{}", enchant),
+                ),
+                ItemCriterion::Value(value) => {
+                    let word = word(value)?;
+                    let description = if ITEM_FILTERS.contains(&word.to_lowercase().as_str()) {
+                        format!("Element: equips an item of the {} element", word)
+                    } else {
+                        format!("Item name: equips an item whose name contains `{}`", word)
+                    };
+                    (word, description)
+                }
+            }
+        }
+        CommandArgs::Loadout(values) => {
+            let word = values.iter().find_map(word)?;
+            let description = format!("Loadout number: equips loadout {}", word);
+            (word, description)
+        }
+        CommandArgs::Brew(values) => {
+            let word = values.iter().find_map(word)?;
+            let (_, name) = BREW_INGREDIENTS
+                .iter()
+                .find(|(id, _)| id.eq_ignore_ascii_case(&word))?;
+            let description = format!("Ingredient: refills the potion with {}", name);
+            (word, description)
+        }
+        CommandArgs::Activate(values) => {
+            let word = values.iter().find_map(word)?;
+            let description = if let Some((_, slot)) = ACTIVATE_SLOTS
+                .iter()
+                .find(|(id, _)| id.eq_ignore_ascii_case(&word))
+            {
+                slot.to_string()
+            } else if ABILITY_IDS.iter().any(|id| id.eq_ignore_ascii_case(&word)) {
+                format!("Ability: activates the `{}` ability", word)
+            } else {
+                return None;
+            };
+            (word, description)
+        }
+    };
+    Some(format!(
+        "```stonescript
+{}
+```
+
+{}",
+        word, description
+    ))
+}
+
 impl Default for HoverProvider {
     fn default() -> Self {
         Self::new()
@@ -607,6 +764,57 @@ mod tests {
         assert!(!provider.keywords.is_empty());
     }
 
+    #[test]
+    fn test_hover_for_output_variant() {
+        let source = "?hp < 5\n  >c0,-5,#red,Low health\n";
+        let ast = stonescript_parser::parse_source(source).unwrap();
+        let hover = HoverProvider::new()
+            .provide_hover(
+                &ast,
+                Position {
+                    line: 1,
+                    character: 3,
+                },
+                source,
+                &ScopeAnalyzer::new(),
+            )
+            .expect("Should describe the print variant");
+
+        match hover.contents {
+            HoverContents::Markup(content) => {
+                assert!(content.value.contains("center of the screen"))
+            }
+            other => panic!("Expected markup, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_hover_for_command_arguments() {
+        let hover_text = |source: &str, character: u32| {
+            let ast = stonescript_parser::parse_source(source).unwrap();
+            let hover = HoverProvider::new().provide_hover(
+                &ast,
+                Position { line: 0, character },
+                source,
+                &ScopeAnalyzer::new(),
+            );
+            match hover.map(|hover| hover.contents) {
+                Some(HoverContents::Markup(content)) => content.value,
+                other => panic!("Expected markup, got {:?}", other),
+            }
+        };
+
+        let source = "equip vigor crossbow *8 +5\n";
+        assert!(hover_text(source, 7).contains("vigor element"));
+        assert!(hover_text(source, 14).contains("name contains `crossbow`"));
+        assert!(hover_text(source, 22).contains("with 8 stars"));
+        assert!(hover_text(source, 25).contains("enchanted to +5"));
+        assert!(hover_text("brew tar+bronze\n", 11).contains("with Bronze"));
+        assert!(hover_text("activate R\n", 9).contains("right hand item"));
+        // The command itself keeps its keyword documentation
+        assert!(hover_text(source, 2).contains("**Usage:**"));
+    }
+
     #[test]
     fn test_extract_word() {
         let provider = HoverProvider::new();
@@ -0,0 +1,272 @@
+//! Diagnostics that follow from the shape of the AST alone: code after a
+//! `return`, conditions with a known value, repeated `:?` conditions and
+//! `for` loops over a reversed range
+
+use crate::providers::diagnostics::{
+    diagnostic, CONSTANT_CONDITION, DUPLICATE_CONDITION, REVERSED_RANGE, UNREACHABLE_CODE,
+};
+use std::cmp::Ordering;
+use stonescript_parser::{
+    walk_expression, BinaryOperator, ElseIf, Expression, Program, Span, Statement, UnaryOperator,
+    Visitor,
+};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+
+/// Run every pass over the program
+pub(crate) fn lint(program: &Program) -> Vec<Diagnostic> {
+    let mut unreachable = UnreachableCode(Vec::new());
+    unreachable.visit_program(program);
+    let mut conditions = Conditions(Vec::new());
+    conditions.visit_program(program);
+    let mut ranges = ReversedRanges(Vec::new());
+    ranges.visit_program(program);
+
+    let mut diagnostics = unreachable.0;
+    diagnostics.extend(conditions.0);
+    diagnostics.extend(ranges.0);
+    diagnostics
+}
+
+/// Statements following a `return` in the same block
+struct UnreachableCode(Vec<Diagnostic>);
+
+impl Visitor for UnreachableCode {
+    // The parser picks up at the top level after a line it cannot read, so
+    // a `return` there is usually the tail of a function it lost track of
+    fn visit_program(&mut self, program: &Program) {
+        for statement in &program.statements {
+            self.visit_statement(statement);
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Statement]) {
+        let after_return = statements
+            .iter()
+            .position(|statement| matches!(statement, Statement::Return { .. }))
+            .map(|index| &statements[index + 1..])
+            .unwrap_or_default();
+        // Functions are defined wherever they appear in the block
+        let spans: Vec<Span> = after_return
+            .iter()
+            .filter(|statement| {
+                !matches!(
+                    statement,
+                    Statement::Comment(..)
+                        | Statement::Empty
+                        | Statement::FunctionDefinition { .. }
+                )
+            })
+            .filter_map(Statement::span)
+            .collect();
+
+        if let (Some(first), Some(last)) = (spans.first(), spans.last()) {
+            let mut unreachable = diagnostic(
+                Span::new(first.start, last.end),
+                DiagnosticSeverity::WARNING,
+                UNREACHABLE_CODE,
+                "Unreachable code after `return`".to_string(),
+                None,
+            );
+            unreachable.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+            self.0.push(unreachable);
+        }
+
+        for statement in statements {
+            self.visit_statement(statement);
+        }
+    }
+}
+
+/// `?1 = 2` and `?false`, and `:?` branches shadowed by an earlier branch
+/// with the same condition
+struct Conditions(Vec<Diagnostic>);
+
+impl Conditions {
+    fn check_constant(&mut self, condition: &Expression) {
+        if let Some(Constant::Bool(value)) = constant(condition) {
+            self.0.push(diagnostic(
+                condition.span(),
+                DiagnosticSeverity::WARNING,
+                CONSTANT_CONDITION,
+                format!("Condition is always {}", value),
+                None,
+            ));
+        }
+    }
+}
+
+impl Visitor for Conditions {
+    fn visit_condition(
+        &mut self,
+        condition: &Expression,
+        then_block: &[Statement],
+        else_ifs: &[ElseIf],
+        else_block: &Option<Vec<Statement>>,
+    ) {
+        self.check_constant(condition);
+        for (index, else_if) in else_ifs.iter().enumerate() {
+            self.check_constant(&else_if.condition);
+
+            let mut earlier = std::iter::once(condition)
+                .chain(else_ifs[..index].iter().map(|earlier| &earlier.condition));
+            if is_stable(&else_if.condition)
+                && earlier.any(|earlier| earlier.eq_ignoring_spans(&else_if.condition))
+            {
+                self.0.push(diagnostic(
+                    else_if.condition.span(),
+                    DiagnosticSeverity::WARNING,
+                    DUPLICATE_CONDITION,
+                    "An earlier branch has the same condition, so this one never runs".to_string(),
+                    None,
+                ));
+            }
+        }
+
+        self.visit_block(then_block);
+        for else_if in else_ifs {
+            self.visit_block(&else_if.block);
+        }
+        if let Some(block) = else_block {
+            self.visit_block(block);
+        }
+    }
+}
+
+/// `for i = 5..1` with literal bounds. Scripts do count down on purpose, so
+/// this is only a hint
+struct ReversedRanges(Vec<Diagnostic>);
+
+impl Visitor for ReversedRanges {
+    fn visit_for(&mut self, _variable: &str, range: &(Expression, Expression), body: &[Statement]) {
+        if let (Some(Constant::Int(start)), Some(Constant::Int(end))) =
+            (constant(&range.0), constant(&range.1))
+        {
+            if start > end {
+                self.0.push(diagnostic(
+                    Span::new(range.0.span().start, range.1.span().end),
+                    DiagnosticSeverity::HINT,
+                    REVERSED_RANGE,
+                    format!(
+                        "The range {}..{} counts down; write {}..{} to count up",
+                        start, end, end, start
+                    ),
+                    None,
+                ));
+            }
+        }
+        self.visit_block(body);
+    }
+}
+
+/// Whether evaluating the expression twice gives the same value: no calls,
+/// which may have side effects, and no random numbers
+fn is_stable(expression: &Expression) -> bool {
+    struct Unstable(bool);
+
+    impl Visitor for Unstable {
+        fn visit_expression(&mut self, expression: &Expression) {
+            match expression {
+                Expression::FunctionCall { .. } => self.0 = true,
+                Expression::Identifier(name, _) if name == "rng" || name == "rngf" => self.0 = true,
+                _ => walk_expression(self, expression),
+            }
+        }
+    }
+
+    let mut unstable = Unstable(false);
+    unstable.visit_expression(expression);
+    !unstable.0
+}
+
+/// A value known without running the script
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Constant {
+    fn as_float(self) -> Option<f64> {
+        match self {
+            Constant::Int(value) => Some(value as f64),
+            Constant::Float(value) => Some(value),
+            Constant::Bool(_) => None,
+        }
+    }
+}
+
+fn constant(expression: &Expression) -> Option<Constant> {
+    match expression {
+        Expression::Integer(value, _) => Some(Constant::Int(*value)),
+        Expression::Float(value, _) => Some(Constant::Float(*value)),
+        Expression::Boolean(value, _) => Some(Constant::Bool(*value)),
+        Expression::UnaryOp { op, operand, .. } => match (op, constant(operand)?) {
+            (UnaryOperator::Not, Constant::Bool(value)) => Some(Constant::Bool(!value)),
+            (UnaryOperator::Negate, Constant::Int(value)) => value.checked_neg().map(Constant::Int),
+            (UnaryOperator::Negate, Constant::Float(value)) => Some(Constant::Float(-value)),
+            _ => None,
+        },
+        Expression::BinaryOp {
+            left, op, right, ..
+        } => {
+            let (left, right) = (constant(left), constant(right));
+            // One known side can settle `&` and `|` on its own
+            match (op, left, right) {
+                (BinaryOperator::And, Some(Constant::Bool(false)), _)
+                | (BinaryOperator::And, _, Some(Constant::Bool(false))) => {
+                    Some(Constant::Bool(false))
+                }
+                (BinaryOperator::Or, Some(Constant::Bool(true)), _)
+                | (BinaryOperator::Or, _, Some(Constant::Bool(true))) => Some(Constant::Bool(true)),
+                _ => binary(*op, left?, right?),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn binary(op: BinaryOperator, left: Constant, right: Constant) -> Option<Constant> {
+    match (left, right) {
+        (Constant::Bool(a), Constant::Bool(b)) => match op {
+            BinaryOperator::And => Some(Constant::Bool(a && b)),
+            BinaryOperator::Or => Some(Constant::Bool(a || b)),
+            BinaryOperator::Equal => Some(Constant::Bool(a == b)),
+            BinaryOperator::NotEqual => Some(Constant::Bool(a != b)),
+            _ => None,
+        },
+        // Integer arithmetic truncates, as in the game
+        (Constant::Int(a), Constant::Int(b)) => match op {
+            BinaryOperator::Add => a.checked_add(b).map(Constant::Int),
+            BinaryOperator::Subtract => a.checked_sub(b).map(Constant::Int),
+            BinaryOperator::Multiply => a.checked_mul(b).map(Constant::Int),
+            BinaryOperator::Divide => a.checked_div(b).map(Constant::Int),
+            BinaryOperator::Modulo => a.checked_rem(b).map(Constant::Int),
+            _ => compare(op, a.cmp(&b)),
+        },
+        _ => {
+            let (a, b) = (left.as_float()?, right.as_float()?);
+            match op {
+                BinaryOperator::Add => Some(Constant::Float(a + b)),
+                BinaryOperator::Subtract => Some(Constant::Float(a - b)),
+                BinaryOperator::Multiply => Some(Constant::Float(a * b)),
+                BinaryOperator::Divide if b != 0.0 => Some(Constant::Float(a / b)),
+                BinaryOperator::Modulo if b != 0.0 => Some(Constant::Float(a % b)),
+                _ => compare(op, a.partial_cmp(&b)?),
+            }
+        }
+    }
+}
+
+fn compare(op: BinaryOperator, ordering: Ordering) -> Option<Constant> {
+    let result = match op {
+        BinaryOperator::Equal => ordering == Ordering::Equal,
+        BinaryOperator::NotEqual => ordering != Ordering::Equal,
+        BinaryOperator::Less => ordering == Ordering::Less,
+        BinaryOperator::LessEqual => ordering != Ordering::Greater,
+        BinaryOperator::Greater => ordering == Ordering::Greater,
+        BinaryOperator::GreaterEqual => ordering != Ordering::Less,
+        _ => return None,
+    };
+    Some(Constant::Bool(result))
+}
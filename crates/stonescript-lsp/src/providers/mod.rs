@@ -8,6 +8,10 @@ pub mod definition;
 pub mod symbols;
 pub mod formatting;
 pub mod semantic_tokens;
+pub mod code_action;
+pub mod refactoring;
+pub mod conditions;
+mod lints;
 
 pub use completion::CompletionProvider;
 pub use hover::HoverProvider;
@@ -17,3 +21,4 @@ pub use definition::DefinitionProvider;
 pub use symbols::SymbolsProvider;
 pub use formatting::FormattingProvider;
 pub use semantic_tokens::SemanticTokensProvider;
+pub use code_action::CodeActionProvider;
@@ -0,0 +1,765 @@
+//! Refactoring code actions: extract function, extract variable and inline variable
+
+use crate::providers::diagnostics::{for_each_statement, to_range};
+use crate::utils::{ScopeAnalyzer, VariableUsage};
+use std::collections::HashMap;
+use stonescript_parser::ast::{InterpolationPart, Position as AstPosition, UnaryOperator};
+use stonescript_parser::{
+    walk_expression, walk_statement, Expression, Program, Span, Statement, Visitor,
+};
+use tower_lsp::lsp_types::*;
+
+/// Move the selected statements into a new `func`.
+///
+/// Local variables the statements use become parameters, and a variable they
+/// declare or assign that is still read afterwards becomes the return value.
+/// Selections that would need several return values are not offered.
+pub fn extract_function(
+    ast: &Program,
+    source: &str,
+    scope: &ScopeAnalyzer,
+    uri: &Url,
+    range: Range,
+) -> Option<CodeAction> {
+    if range.start == range.end {
+        return None;
+    }
+    let start_line = range.start.line as usize;
+    let mut end_line = range.end.line as usize;
+    if range.end.character == 0 && end_line > start_line {
+        end_line -= 1;
+    }
+
+    let mut selection = StatementSelection {
+        start: start_line,
+        end: end_line,
+        enclosing: Vec::new(),
+        selected: None,
+        function_body: None,
+    };
+    selection.visit_block(&ast.statements);
+    let selected = selection.selected?;
+    let mut extractable = true;
+    let mut has_code = false;
+    for_each_statement(&selected, &mut |statement| match statement {
+        Statement::Return { .. }
+        | Statement::FunctionDefinition { .. }
+        | Statement::Import { .. } => extractable = false,
+        Statement::Comment(_, _) | Statement::Empty => {}
+        _ => has_code = true,
+    });
+    if !extractable || !has_code {
+        return None;
+    }
+
+    let first_line = line_range(selected.first()?)?.0;
+    let last_line = selected.iter().rev().find_map(line_range)?.1;
+    let selection_start = selected.iter().find_map(Statement::span)?.start;
+    let usage = VariableUsage::of_statements(&selected);
+    let is_local = |name: &str| {
+        scope
+            .find_variable_at(name, selection_start)
+            .is_some_and(|var| var.scope_id != 0)
+    };
+
+    // Outer locals are passed in; globals stay reachable from the function
+    let params: Vec<&String> = usage
+        .read
+        .iter()
+        .chain(&usage.written)
+        .filter(|name| !usage.declared.contains(name) && is_local(name))
+        .fold(Vec::new(), |mut params, name| {
+            if !params.contains(&name) {
+                params.push(name);
+            }
+            params
+        });
+
+    let mut declared = Vec::new();
+    for_each_statement(&selected, &mut |statement| {
+        if let Statement::VarDeclaration { name, .. } = statement {
+            declared.push(name.clone());
+        }
+    });
+
+    // Variables the rest of the enclosing function, or script, still reads
+    let function_body = selection.function_body.as_deref();
+    let mut read_after = Vec::new();
+    collect_reads_after(
+        function_body.unwrap_or(&ast.statements),
+        last_line,
+        &mut read_after,
+    );
+    if function_body.is_none() {
+        for statement in &ast.statements {
+            if let Statement::FunctionDefinition { .. } = statement {
+                read_after
+                    .extend(VariableUsage::of_statements(std::slice::from_ref(statement)).read);
+            }
+        }
+    }
+    let outputs: Vec<&String> = declared
+        .iter()
+        .chain(
+            params
+                .iter()
+                .copied()
+                .filter(|name| usage.written.contains(name)),
+        )
+        .filter(|name| read_after.contains(name))
+        .collect();
+    if outputs.len() > 1 {
+        return None;
+    }
+    let output = outputs.first().copied();
+
+    let name = unique_name("NewFunction", scope);
+    let lines: Vec<&str> = source.lines().collect();
+    let indent = indentation(lines.get(first_line)?);
+    let params = params
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut function = format!("func {}({})\n", name, params);
+    for line in &lines[first_line..=last_line] {
+        if line.trim().is_empty() {
+            function.push('\n');
+        } else {
+            let line = line.strip_prefix(indent).unwrap_or(line.trim_start());
+            function.push_str(&format!("  {}\n", line));
+        }
+    }
+    if let Some(output) = output {
+        function.push_str(&format!("  return {}\n", output));
+    }
+    function.push('\n');
+
+    let call = format!("{}({})", name, params);
+    let call = match output {
+        Some(output) if declared.contains(output) => format!("var {} = {}", output, call),
+        Some(output) => format!("{} = {}", output, call),
+        None => call,
+    };
+
+    let replaced = Range::new(
+        Position::new(first_line as u32, indent.chars().count() as u32),
+        Position::new(last_line as u32, lines[last_line].chars().count() as u32),
+    );
+    let edits = match selection.enclosing.first() {
+        // Inside a block: define the function above the top-level statement
+        Some(&(outermost, _)) => {
+            let line = definition_line(&lines, outermost);
+            let position = Position::new(line as u32, 0);
+            vec![
+                TextEdit::new(Range::new(position, position), function),
+                TextEdit::new(replaced, call),
+            ]
+        }
+        None => vec![TextEdit::new(
+            Range::new(Position::new(first_line as u32, 0), replaced.end),
+            format!("{}{}{}", function, indent, call),
+        )],
+    };
+
+    Some(refactor(
+        "Extract into function",
+        CodeActionKind::REFACTOR_EXTRACT,
+        uri,
+        edits,
+    ))
+}
+
+/// Move the selected expression into a `var` declared just above its statement
+pub fn extract_variable(
+    ast: &Program,
+    source: &str,
+    scope: &ScopeAnalyzer,
+    uri: &Url,
+    range: Range,
+) -> Option<CodeAction> {
+    let range = trim_range(source, range)?;
+    let span = to_span(range);
+    let mut finder = Extractable { span, found: None };
+    finder.visit_program(ast);
+    let (statement_line, expression_span) = finder.found?;
+
+    let lines: Vec<&str> = source.lines().collect();
+    let indent = indentation(lines.get(statement_line)?);
+    let name = unique_name("value", scope);
+    let declaration = format!(
+        "{}var {} = {}\n",
+        indent,
+        name,
+        text_of(&lines, &expression_span)
+    );
+    let position = Position::new(statement_line as u32, 0);
+    let edits = if range.start == position {
+        vec![TextEdit::new(range, format!("{}{}", declaration, name))]
+    } else {
+        vec![
+            TextEdit::new(Range::new(position, position), declaration),
+            TextEdit::new(range, name),
+        ]
+    };
+
+    Some(refactor(
+        "Extract into variable",
+        CodeActionKind::REFACTOR_EXTRACT,
+        uri,
+        edits,
+    ))
+}
+
+/// Replace the uses of a `var` with its initializer and remove the declaration.
+///
+/// Only offered when that keeps the meaning: the variable and everything its
+/// initializer reads are never reassigned, the initializer calls nothing,
+/// and script-level variables, which are initialized only once, hold a
+/// literal.
+pub fn inline_variable(
+    ast: &Program,
+    source: &str,
+    scope: &ScopeAnalyzer,
+    uri: &Url,
+    range: Range,
+) -> Option<CodeAction> {
+    let position = AstPosition::new(range.start.line as usize, range.start.character as usize);
+    let declaration_span = declaration_at(ast, scope, position)?;
+    let mut finder = DeclarationFinder {
+        name_span: declaration_span,
+        found: None,
+    };
+    finder.visit_program(ast);
+    let Declaration {
+        name,
+        initializer,
+        span,
+        function_body,
+    } = finder.found?;
+
+    let inlinable = match &function_body {
+        Some(_) => !matches!(
+            initializer,
+            Expression::Array { .. } | Expression::New { .. } | Expression::Interpolation(..)
+        ),
+        None => is_literal(&initializer),
+    };
+    if !inlinable || contains_call(&initializer) {
+        return None;
+    }
+
+    let container = function_body.as_deref().unwrap_or(&ast.statements);
+    let container_usage = VariableUsage::of_statements(container);
+    let dependencies = VariableUsage::of_expression(&initializer).read;
+    if container_usage.written.contains(&name)
+        || dependencies
+            .iter()
+            .any(|dependency| container_usage.written.contains(dependency))
+    {
+        return None;
+    }
+    let mut declarations = 0;
+    for_each_statement(container, &mut |statement| {
+        if matches!(statement, Statement::VarDeclaration { name: n, .. } if *n == name) {
+            declarations += 1;
+        }
+    });
+    if declarations != 1 {
+        return None;
+    }
+
+    // Uses resolve to this declaration through the scope analysis
+    let mut collector = IdentifierSpans {
+        name: &name,
+        spans: Vec::new(),
+    };
+    collector.visit_program(ast);
+    let lines: Vec<&str> = source.lines().collect();
+    let value = text_of(&lines, &initializer.span());
+    // `a-v` must not become `a--1`, nor `v.x` become `!a.x`
+    let atomic = match initializer {
+        Expression::BinaryOp { .. } | Expression::UnaryOp { .. } => false,
+        Expression::Integer(n, _) => n >= 0,
+        Expression::Float(n, _) => n >= 0.0,
+        _ => true,
+    };
+    let value = if atomic {
+        value
+    } else {
+        format!("({})", value)
+    };
+
+    let mut edits: Vec<TextEdit> = collector
+        .spans
+        .iter()
+        .filter(|use_span| {
+            scope
+                .find_variable_at(&name, use_span.start)
+                .and_then(|var| var.definition_span)
+                == Some(declaration_span)
+        })
+        .map(|use_span| TextEdit::new(to_range(use_span), value.clone()))
+        .collect();
+    edits.push(TextEdit::new(
+        Range::new(
+            Position::new(span.start.line as u32, 0),
+            Position::new(span.end.line as u32 + 1, 0),
+        ),
+        String::new(),
+    ));
+
+    Some(refactor(
+        &format!("Inline variable `{}`", name),
+        CodeActionKind::REFACTOR_INLINE,
+        uri,
+        edits,
+    ))
+}
+
+/// Whether evaluating an expression calls a function, which may have side effects
+pub fn contains_call(expression: &Expression) -> bool {
+    match expression {
+        Expression::FunctionCall { .. } => true,
+        Expression::Property { object, .. } => contains_call(object),
+        Expression::BinaryOp { left, right, .. } => contains_call(left) || contains_call(right),
+        Expression::UnaryOp { operand, .. } => contains_call(operand),
+        Expression::Interpolation(parts, _) => parts.iter().any(|part| match part {
+            InterpolationPart::Expression(expr) => contains_call(expr),
+            InterpolationPart::Text(_, _) => false,
+        }),
+        Expression::Array { elements, .. } => elements.iter().any(contains_call),
+        Expression::IndexAccess { object, index, .. } => {
+            contains_call(object) || contains_call(index)
+        }
+        Expression::Integer(_, _)
+        | Expression::Float(_, _)
+        | Expression::Boolean(_, _)
+        | Expression::String(_, _)
+        | Expression::Identifier(_, _)
+        | Expression::New { .. } => false,
+    }
+}
+
+pub(crate) fn refactor(
+    title: &str,
+    kind: CodeActionKind,
+    uri: &Url,
+    edits: Vec<TextEdit>,
+) -> CodeAction {
+    CodeAction {
+        title: title.to_string(),
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// First and last line a statement occupies
+pub(crate) fn line_range(statement: &Statement) -> Option<(usize, usize)> {
+    let span = statement.span()?;
+    let end = if span.end.column == 0 && span.end.line > span.start.line {
+        span.end.line - 1
+    } else {
+        span.end.line
+    };
+    Some((span.start.line, end))
+}
+
+/// Finds the whole statements of one block covering the lines
+/// `start..=end`, with the line ranges of the statements enclosing that
+/// block, outermost first, and the body of the innermost enclosing function
+struct StatementSelection {
+    start: usize,
+    end: usize,
+    enclosing: Vec<(usize, usize)>,
+    selected: Option<Vec<Statement>>,
+    function_body: Option<Vec<Statement>>,
+}
+
+impl Visitor for StatementSelection {
+    fn visit_block(&mut self, block: &[Statement]) {
+        if self.selected.is_some() {
+            return;
+        }
+        // The selection may not reach into the line opening a nested block
+        let opening = block.iter().find_map(line_range).map(|(line, _)| line);
+        if !self.enclosing.is_empty() && opening.is_none_or(|line| self.start < line) {
+            return;
+        }
+
+        let mut first = None;
+        let mut last = None;
+        for (i, statement) in block.iter().enumerate() {
+            let Some((a, b)) = line_range(statement) else {
+                continue;
+            };
+            if a >= self.start && b <= self.end {
+                first.get_or_insert(i);
+                last = Some(i);
+            } else if a <= self.start && b >= self.end && first.is_none() {
+                self.enclosing.push((a, b));
+                walk_statement(self, statement);
+                if self.selected.is_none() {
+                    self.enclosing.pop();
+                } else if let Statement::FunctionDefinition { body, .. } = statement {
+                    // Inner functions finish first, so the innermost one is kept
+                    self.function_body.get_or_insert_with(|| body.clone());
+                }
+                return;
+            } else if a <= self.end && b >= self.start {
+                // Only part of this statement is selected
+                return;
+            }
+        }
+
+        if let (Some(first), Some(last)) = (first, last) {
+            self.selected = Some(block[first..=last].to_vec());
+        }
+    }
+}
+
+/// Names read by statements after `line`. Loops running across that line
+/// count entirely, since their next iteration reads from the top.
+fn collect_reads_after(statements: &[Statement], line: usize, reads: &mut Vec<String>) {
+    for statement in statements {
+        let Some((start, end)) = line_range(statement) else {
+            continue;
+        };
+        if start > line {
+            reads.extend(VariableUsage::of_statements(std::slice::from_ref(statement)).read);
+        } else if end > line {
+            match statement {
+                Statement::Condition {
+                    then_block,
+                    else_ifs,
+                    else_block,
+                    ..
+                } => {
+                    collect_reads_after(then_block, line, reads);
+                    for else_if in else_ifs {
+                        if else_if.span.start.line > line {
+                            reads.extend(VariableUsage::of_expression(&else_if.condition).read);
+                        }
+                        collect_reads_after(&else_if.block, line, reads);
+                    }
+                    if let Some(block) = else_block {
+                        collect_reads_after(block, line, reads);
+                    }
+                }
+                Statement::For { .. } | Statement::ForIn { .. } | Statement::While { .. } => {
+                    reads
+                        .extend(VariableUsage::of_statements(std::slice::from_ref(statement)).read);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Line to insert a new function at, above the statement starting at
+/// `line` and the comments documenting it
+fn definition_line(lines: &[&str], mut line: usize) -> usize {
+    while line > 0 && lines[line - 1].trim_start().starts_with("//") {
+        line -= 1;
+    }
+    line
+}
+
+pub(crate) fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// `base`, or `base2`, `base3`... if the name is already taken
+fn unique_name(base: &str, scope: &ScopeAnalyzer) -> String {
+    let taken = |name: &str| scope.has_variable(name) || scope.find_function(name).is_some();
+    let mut name = base.to_string();
+    let mut counter = 2;
+    while taken(&name) {
+        name = format!("{}{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+fn to_span(range: Range) -> Span {
+    Span::new(
+        AstPosition::new(range.start.line as usize, range.start.character as usize),
+        AstPosition::new(range.end.line as usize, range.end.character as usize),
+    )
+}
+
+/// Source text covered by a span
+pub(crate) fn text_of(lines: &[&str], span: &Span) -> String {
+    let mut text = String::new();
+    for line in span.start.line..=span.end.line {
+        let Some(content) = lines.get(line) else {
+            break;
+        };
+        let from = if line == span.start.line {
+            span.start.column
+        } else {
+            0
+        };
+        let to = if line == span.end.line {
+            span.end.column
+        } else {
+            content.chars().count()
+        };
+        if line > span.start.line {
+            text.push('\n');
+        }
+        text.extend(content.chars().skip(from).take(to.saturating_sub(from)));
+    }
+    text
+}
+
+/// A non-empty selection on one line, without surrounding whitespace
+fn trim_range(source: &str, range: Range) -> Option<Range> {
+    if range.start.line != range.end.line || range.start.character >= range.end.character {
+        return None;
+    }
+    let line = source.lines().nth(range.start.line as usize)?;
+    let selected: Vec<char> = line
+        .chars()
+        .skip(range.start.character as usize)
+        .take((range.end.character - range.start.character) as usize)
+        .collect();
+    let leading = selected.iter().take_while(|c| c.is_whitespace()).count();
+    let trailing = selected
+        .iter()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .count();
+    if leading == selected.len() {
+        return None;
+    }
+
+    let mut range = range;
+    range.start.character += leading as u32;
+    range.end.character -= trailing as u32;
+    Some(range)
+}
+
+/// Finds the first line of the statement and the span of the expression
+/// matching a selection, if the expression can be evaluated just before its
+/// statement
+struct Extractable {
+    span: Span,
+    found: Option<(usize, Span)>,
+}
+
+impl Visitor for Extractable {
+    fn visit_statement(&mut self, statement: &Statement) {
+        let Some(statement_span) = statement.span() else {
+            return;
+        };
+        if self.found.is_some() || !statement_span.contains_position(self.span.start) {
+            return;
+        }
+        // Statements in nested blocks first
+        walk_statement(self, statement);
+        if self.found.is_some() {
+            return;
+        }
+
+        // Expressions evaluated once, before anything else in the statement
+        // runs; else-if and while conditions are evaluated later or repeatedly
+        let heads: Vec<(&Expression, bool)> = match statement {
+            Statement::Condition { condition, .. } => vec![(condition, true)],
+            Statement::Command { args, .. } => args.iter().map(|arg| (arg, true)).collect(),
+            Statement::VarDeclaration { initializer, .. } => {
+                initializer.iter().map(|value| (value, true)).collect()
+            }
+            Statement::Assignment { target, value, .. } => vec![(target, false), (value, true)],
+            Statement::Output {
+                position,
+                color,
+                text,
+                ..
+            } => {
+                let mut heads = Vec::new();
+                if let Some((x, y)) = position {
+                    heads.push((x, true));
+                    heads.push((y, true));
+                }
+                heads.extend(color.iter().map(|color| (color, true)));
+                heads.push((text, true));
+                heads
+            }
+            Statement::ExpressionStatement { expression, .. } => vec![(expression, false)],
+            Statement::Return { value, .. } => value.iter().map(|value| (value, true)).collect(),
+            Statement::For { range, .. } => vec![(&range.0, true), (&range.1, true)],
+            Statement::ForIn { collection, .. } => vec![(collection, true)],
+            _ => Vec::new(),
+        };
+        self.found = heads
+            .into_iter()
+            .find_map(|(head, allowed)| find_expression(head, self.span, allowed))
+            .map(|expression| (statement_span.start.line, expression.span()));
+    }
+}
+
+fn find_expression(expression: &Expression, span: Span, allowed: bool) -> Option<&Expression> {
+    if allowed && expression.span() == span && is_extractable(expression) {
+        return Some(expression);
+    }
+
+    match expression {
+        Expression::Property { object, .. } => find_expression(object, span, true),
+        Expression::FunctionCall { function, args, .. } => {
+            // The called function itself can't be held in a variable
+            find_expression(function, span, false)
+                .or_else(|| args.iter().find_map(|arg| find_expression(arg, span, true)))
+        }
+        Expression::BinaryOp { left, right, .. } => {
+            find_expression(left, span, true).or_else(|| find_expression(right, span, true))
+        }
+        Expression::UnaryOp { op, operand, .. } => find_expression(
+            operand,
+            span,
+            !matches!(op, UnaryOperator::Increment | UnaryOperator::Decrement),
+        ),
+        Expression::Interpolation(parts, _) => parts.iter().find_map(|part| match part {
+            InterpolationPart::Expression(expr) => find_expression(expr, span, true),
+            InterpolationPart::Text(_, _) => None,
+        }),
+        Expression::Array { elements, .. } => elements
+            .iter()
+            .find_map(|element| find_expression(element, span, true)),
+        Expression::IndexAccess { object, index, .. } => {
+            find_expression(object, span, true).or_else(|| find_expression(index, span, true))
+        }
+        _ => None,
+    }
+}
+
+/// Plain names and output text gain nothing from being held in a variable
+fn is_extractable(expression: &Expression) -> bool {
+    !matches!(
+        expression,
+        Expression::Identifier(..) | Expression::Interpolation(..)
+    )
+}
+
+fn is_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::Integer(..)
+        | Expression::Float(..)
+        | Expression::Boolean(..)
+        | Expression::String(..) => true,
+        Expression::UnaryOp {
+            op: UnaryOperator::Negate,
+            operand,
+            ..
+        } => is_literal(operand),
+        _ => false,
+    }
+}
+
+/// Name span of the declaration at `position`, either on the declared name
+/// or on a use of the variable
+fn declaration_at(ast: &Program, scope: &ScopeAnalyzer, position: AstPosition) -> Option<Span> {
+    let mut found = None;
+    for_each_statement(&ast.statements, &mut |statement| {
+        if let Statement::VarDeclaration { name_span, .. } = statement {
+            if name_span.contains_position(position) {
+                found = Some(*name_span);
+            }
+        }
+    });
+    if found.is_some() {
+        return found;
+    }
+
+    let mut finder = IdentifierAt {
+        position,
+        found: None,
+    };
+    finder.visit_program(ast);
+    let name = finder.found?;
+    scope.find_variable_at(&name, position)?.definition_span
+}
+
+/// Finds the declaration with an initializer whose name is at `name_span`
+struct DeclarationFinder {
+    name_span: Span,
+    found: Option<Declaration>,
+}
+
+struct Declaration {
+    name: String,
+    initializer: Expression,
+    span: Span,
+    /// Body of the function declaring the variable
+    function_body: Option<Vec<Statement>>,
+}
+
+impl Visitor for DeclarationFinder {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if self.found.is_some() {
+            return;
+        }
+        match statement {
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer: Some(initializer),
+                span,
+            } if *name_span == self.name_span => {
+                self.found = Some(Declaration {
+                    name: name.clone(),
+                    initializer: initializer.clone(),
+                    span: *span,
+                    function_body: None,
+                });
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    fn visit_function_definition(&mut self, _name: &str, _params: &[String], body: &[Statement]) {
+        self.visit_block(body);
+        if let Some(found) = &mut self.found {
+            found.function_body.get_or_insert_with(|| body.to_vec());
+        }
+    }
+}
+
+/// Spans of every identifier expression with a given name
+struct IdentifierSpans<'a> {
+    name: &'a str,
+    spans: Vec<Span>,
+}
+
+impl Visitor for IdentifierSpans<'_> {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name, span) = expression {
+            if name == self.name {
+                self.spans.push(*span);
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// Name of the identifier expression at a position
+struct IdentifierAt {
+    position: AstPosition,
+    found: Option<String>,
+}
+
+impl Visitor for IdentifierAt {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name, span) = expression {
+            if span.contains_position(self.position) {
+                self.found = Some(name.clone());
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
@@ -1,6 +1,6 @@
 //! Semantic tokens provider
 
-use stonescript_parser::{Expression, Program, Statement};
+use stonescript_parser::{Expression, Program, Span, Statement};
 use tower_lsp::lsp_types::*;
 
 pub struct SemanticTokensProvider {
@@ -89,7 +89,9 @@ impl SemanticTokensProvider {
                 }
             }
 
-            Statement::Command { name, args, span } => {
+            Statement::Command {
+                name, args, span, ..
+            } => {
                 // Command name as keyword
                 tokens.push(Token {
                     line: span.start.line as u32,
@@ -103,6 +105,32 @@ impl SemanticTokensProvider {
                 }
             }
 
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer,
+                span,
+                ..
+            } => {
+                // 'var' keyword
+                tokens.push(Token {
+                    line: span.start.line as u32,
+                    start: span.start.column as u32,
+                    length: 3,
+                    token_type: 2, // KEYWORD
+                });
+                tokens.push(Token {
+                    line: name_span.start.line as u32,
+                    start: name_span.start.column as u32,
+                    length: name.len() as u32,
+                    token_type: 0, // VARIABLE
+                });
+
+                if let Some(value) = initializer {
+                    self.collect_expression_tokens(value, tokens);
+                }
+            }
+
             Statement::Assignment {
                 target,
                 op: _,
@@ -114,15 +142,18 @@ impl SemanticTokensProvider {
             }
 
             Statement::Output {
+                kind,
                 position,
+                color,
                 text,
                 span,
+                ..
             } => {
-                // Output operator '>' as keyword
+                // Output operator '>' and its variant character as keyword
                 tokens.push(Token {
                     line: span.start.line as u32,
                     start: span.start.column as u32,
-                    length: 1,
+                    length: 1 + kind.prefix().len() as u32,
                     token_type: 2, // KEYWORD
                 });
 
@@ -130,6 +161,9 @@ impl SemanticTokensProvider {
                     self.collect_expression_tokens(x, tokens);
                     self.collect_expression_tokens(y, tokens);
                 }
+                if let Some(color) = color {
+                    self.collect_expression_tokens(color, tokens);
+                }
                 self.collect_expression_tokens(text, tokens);
             }
 
@@ -141,21 +175,26 @@ impl SemanticTokensProvider {
             }
 
             Statement::FunctionDefinition {
-                name,
-                params: _,
+                name_span,
+                param_spans,
                 body,
                 span,
+                ..
             } => {
-                // Function name
+                // 'func' keyword
                 tokens.push(Token {
                     line: span.start.line as u32,
                     start: span.start.column as u32,
-                    length: name.len() as u32,
-                    token_type: 1, // FUNCTION
+                    length: 4,     // "func".len()
+                    token_type: 2, // KEYWORD
                 });
 
-                // Parameters would need position info from parser
-                // For now we skip them as we don't have their exact positions
+                // Function name
+                tokens.push(span_token(name_span, 1)); // FUNCTION
+
+                for param_span in param_spans {
+                    tokens.push(span_token(param_span, 7)); // PARAMETER
+                }
 
                 for stmt in body {
                     self.collect_statement_tokens(stmt, tokens);
@@ -177,10 +216,11 @@ impl SemanticTokensProvider {
             }
 
             Statement::For {
-                variable: _,
+                variable_span,
                 range,
                 body,
                 span,
+                ..
             } => {
                 // 'for' keyword
                 tokens.push(Token {
@@ -189,6 +229,7 @@ impl SemanticTokensProvider {
                     length: 3,     // "for".len()
                     token_type: 2, // KEYWORD
                 });
+                tokens.push(span_token(variable_span, 0)); // VARIABLE
 
                 self.collect_expression_tokens(&range.0, tokens);
                 self.collect_expression_tokens(&range.1, tokens);
@@ -199,10 +240,11 @@ impl SemanticTokensProvider {
             }
 
             Statement::ForIn {
-                variable: _,
+                variable_span,
                 collection,
                 body,
                 span,
+                ..
             } => {
                 // 'for' keyword
                 tokens.push(Token {
@@ -211,6 +253,7 @@ impl SemanticTokensProvider {
                     length: 3,
                     token_type: 2, // KEYWORD
                 });
+                tokens.push(span_token(variable_span, 0)); // VARIABLE
 
                 self.collect_expression_tokens(collection, tokens);
 
@@ -239,7 +282,11 @@ impl SemanticTokensProvider {
                 }
             }
 
-            Statement::Import { path: _, span } => {
+            Statement::Import {
+                segment_spans,
+                span,
+                ..
+            } => {
                 // 'import' keyword
                 tokens.push(Token {
                     line: span.start.line as u32,
@@ -248,8 +295,10 @@ impl SemanticTokensProvider {
                     token_type: 2, // KEYWORD
                 });
 
-                // Import path as string
-                // We'd need better position tracking for the path itself
+                // Import path segments as strings
+                for segment_span in segment_spans {
+                    tokens.push(span_token(segment_span, 5)); // STRING
+                }
             }
 
             Statement::Comment(_text, span) => {
@@ -394,7 +443,11 @@ impl SemanticTokensProvider {
                 });
             }
 
-            Expression::New { path: _, span } => {
+            Expression::New {
+                segment_spans,
+                span,
+                ..
+            } => {
                 // 'new' keyword
                 tokens.push(Token {
                     line: span.start.line as u32,
@@ -402,6 +455,10 @@ impl SemanticTokensProvider {
                     length: 3,     // "new".len()
                     token_type: 2, // KEYWORD
                 });
+
+                for segment_span in segment_spans {
+                    tokens.push(span_token(segment_span, 5)); // STRING
+                }
             }
 
             Expression::Array { elements, span: _ } => {
@@ -457,3 +514,13 @@ struct Token {
     length: u32,
     token_type: u32,
 }
+
+/// Token covering a single-line span such as an identifier
+fn span_token(span: &Span, token_type: u32) -> Token {
+    Token {
+        line: span.start.line as u32,
+        start: span.start.column as u32,
+        length: (span.end.column - span.start.column) as u32,
+        token_type,
+    }
+}
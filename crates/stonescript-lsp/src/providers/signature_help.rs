@@ -1,12 +1,29 @@
 //! Signature help provider with comprehensive function signature information
 
 use crate::data::*;
-use crate::utils::ScopeAnalyzer;
+use crate::utils::{FunctionStub, ScopeAnalyzer, Workspace};
 use stonescript_parser::ast::{Expression, Position as AstPosition, Program, Statement};
 use tower_lsp::lsp_types::*;
 
 pub struct SignatureHelpProvider;
 
+/// Everything needed to resolve a callee name to its definition
+struct Lookup<'a> {
+    ast: &'a Program,
+    scope: &'a ScopeAnalyzer,
+    uri: &'a Url,
+    workspace: &'a Workspace,
+}
+
+/// A user-defined function together with where it was found
+struct UserFunction {
+    stub: FunctionStub,
+    /// Receiver shown in the label, e.g. `Vector` for a `new Components/Vector` method
+    owner: Option<String>,
+    /// Module path when the function lives in another file
+    module: Option<String>,
+}
+
 impl SignatureHelpProvider {
     pub fn new() -> Self {
         Self
@@ -18,27 +35,35 @@ impl SignatureHelpProvider {
         position: Position,
         source: &str,
         scope: &ScopeAnalyzer,
+        uri: &Url,
+        workspace: &Workspace,
     ) -> Option<SignatureHelp> {
+        let lookup = Lookup {
+            ast,
+            scope,
+            uri,
+            workspace,
+        };
+
         // Try AST-based approach first
         let ast_pos = AstPosition::new(position.line as usize, position.character as usize);
-        if let Some(help) = self.find_signature_from_ast(ast, ast_pos, source, scope) {
+        if let Some(help) = self.find_signature_from_ast(ast_pos, source, &lookup) {
             return Some(help);
         }
 
         // Fallback to text-based approach
-        self.find_signature_from_text(position, source, scope)
+        self.find_signature_from_text(position, source, &lookup)
     }
 
     fn find_signature_from_ast(
         &self,
-        ast: &Program,
         pos: AstPosition,
         source: &str,
-        scope: &ScopeAnalyzer,
+        lookup: &Lookup,
     ) -> Option<SignatureHelp> {
         // Find function call at position
-        for stmt in &ast.statements {
-            if let Some(help) = self.find_signature_in_statement(stmt, pos, source, scope) {
+        for stmt in &lookup.ast.statements {
+            if let Some(help) = self.find_signature_in_statement(stmt, pos, source, lookup) {
                 return Some(help);
             }
         }
@@ -50,14 +75,18 @@ impl SignatureHelpProvider {
         stmt: &Statement,
         pos: AstPosition,
         source: &str,
-        scope: &ScopeAnalyzer,
+        lookup: &Lookup,
     ) -> Option<SignatureHelp> {
         match stmt {
             Statement::ExpressionStatement { expression, .. } => {
-                self.find_signature_in_expression(expression, pos, source, scope)
+                self.find_signature_in_expression(expression, pos, source, lookup)
             }
-            Statement::Assignment { value, .. } => {
-                self.find_signature_in_expression(value, pos, source, scope)
+            Statement::VarDeclaration {
+                initializer: Some(value),
+                ..
+            }
+            | Statement::Assignment { value, .. } => {
+                self.find_signature_in_expression(value, pos, source, lookup)
             }
             Statement::Condition {
                 condition,
@@ -66,23 +95,24 @@ impl SignatureHelpProvider {
                 else_block,
                 ..
             } => {
-                if let Some(help) = self.find_signature_in_expression(condition, pos, source, scope)
+                if let Some(help) =
+                    self.find_signature_in_expression(condition, pos, source, lookup)
                 {
                     return Some(help);
                 }
                 for s in then_block {
-                    if let Some(help) = self.find_signature_in_statement(s, pos, source, scope) {
+                    if let Some(help) = self.find_signature_in_statement(s, pos, source, lookup) {
                         return Some(help);
                     }
                 }
                 for elif in else_ifs {
                     if let Some(help) =
-                        self.find_signature_in_expression(&elif.condition, pos, source, scope)
+                        self.find_signature_in_expression(&elif.condition, pos, source, lookup)
                     {
                         return Some(help);
                     }
                     for s in &elif.block {
-                        if let Some(help) = self.find_signature_in_statement(s, pos, source, scope)
+                        if let Some(help) = self.find_signature_in_statement(s, pos, source, lookup)
                         {
                             return Some(help);
                         }
@@ -90,7 +120,7 @@ impl SignatureHelpProvider {
                 }
                 if let Some(else_stmts) = else_block {
                     for s in else_stmts {
-                        if let Some(help) = self.find_signature_in_statement(s, pos, source, scope)
+                        if let Some(help) = self.find_signature_in_statement(s, pos, source, lookup)
                         {
                             return Some(help);
                         }
@@ -100,22 +130,22 @@ impl SignatureHelpProvider {
             }
             Statement::Return { value, .. } => {
                 if let Some(expr) = value {
-                    self.find_signature_in_expression(expr, pos, source, scope)
+                    self.find_signature_in_expression(expr, pos, source, lookup)
                 } else {
                     None
                 }
             }
             Statement::For { range, body, .. } => {
-                if let Some(help) = self.find_signature_in_expression(&range.0, pos, source, scope)
+                if let Some(help) = self.find_signature_in_expression(&range.0, pos, source, lookup)
                 {
                     return Some(help);
                 }
-                if let Some(help) = self.find_signature_in_expression(&range.1, pos, source, scope)
+                if let Some(help) = self.find_signature_in_expression(&range.1, pos, source, lookup)
                 {
                     return Some(help);
                 }
                 for s in body {
-                    if let Some(help) = self.find_signature_in_statement(s, pos, source, scope) {
+                    if let Some(help) = self.find_signature_in_statement(s, pos, source, lookup) {
                         return Some(help);
                     }
                 }
@@ -123,7 +153,18 @@ impl SignatureHelpProvider {
             }
             Statement::Command { args, .. } => {
                 for arg in args {
-                    if let Some(help) = self.find_signature_in_expression(arg, pos, source, scope) {
+                    if let Some(help) = self.find_signature_in_expression(arg, pos, source, lookup)
+                    {
+                        return Some(help);
+                    }
+                }
+                None
+            }
+            Statement::FunctionDefinition { body, .. }
+            | Statement::ForIn { body, .. }
+            | Statement::While { body, .. } => {
+                for s in body {
+                    if let Some(help) = self.find_signature_in_statement(s, pos, source, lookup) {
                         return Some(help);
                     }
                 }
@@ -138,7 +179,7 @@ impl SignatureHelpProvider {
         expr: &Expression,
         pos: AstPosition,
         source: &str,
-        scope: &ScopeAnalyzer,
+        lookup: &Lookup,
     ) -> Option<SignatureHelp> {
         match expr {
             Expression::FunctionCall {
@@ -148,48 +189,58 @@ impl SignatureHelpProvider {
             } => {
                 // Check if cursor is inside this function call
                 if span.contains_position(pos) {
+                    // A call nested in the arguments is closer to the cursor
+                    for arg in args {
+                        if let Some(help) =
+                            self.find_signature_in_expression(arg, pos, source, lookup)
+                        {
+                            return Some(help);
+                        }
+                    }
+
                     // Determine active parameter
                     let active_param = self.calculate_active_parameter(args, pos, source);
 
                     // Get function signature
-                    return self.get_signature_for_function(function, scope, active_param);
+                    return self.get_signature_for_function(function, lookup, active_param);
                 }
 
                 // Check nested expressions
-                if let Some(help) = self.find_signature_in_expression(function, pos, source, scope)
+                if let Some(help) = self.find_signature_in_expression(function, pos, source, lookup)
                 {
                     return Some(help);
                 }
                 for arg in args {
-                    if let Some(help) = self.find_signature_in_expression(arg, pos, source, scope) {
+                    if let Some(help) = self.find_signature_in_expression(arg, pos, source, lookup)
+                    {
                         return Some(help);
                     }
                 }
                 None
             }
             Expression::BinaryOp { left, right, .. } => {
-                if let Some(help) = self.find_signature_in_expression(left, pos, source, scope) {
+                if let Some(help) = self.find_signature_in_expression(left, pos, source, lookup) {
                     Some(help)
                 } else {
-                    self.find_signature_in_expression(right, pos, source, scope)
+                    self.find_signature_in_expression(right, pos, source, lookup)
                 }
             }
             Expression::UnaryOp { operand, .. } => {
-                self.find_signature_in_expression(operand, pos, source, scope)
+                self.find_signature_in_expression(operand, pos, source, lookup)
             }
             Expression::Property { object, .. } => {
-                self.find_signature_in_expression(object, pos, source, scope)
+                self.find_signature_in_expression(object, pos, source, lookup)
             }
             Expression::IndexAccess { object, index, .. } => {
-                if let Some(help) = self.find_signature_in_expression(object, pos, source, scope) {
+                if let Some(help) = self.find_signature_in_expression(object, pos, source, lookup) {
                     Some(help)
                 } else {
-                    self.find_signature_in_expression(index, pos, source, scope)
+                    self.find_signature_in_expression(index, pos, source, lookup)
                 }
             }
             Expression::Array { elements, .. } => {
                 for elem in elements {
-                    if let Some(help) = self.find_signature_in_expression(elem, pos, source, scope)
+                    if let Some(help) = self.find_signature_in_expression(elem, pos, source, lookup)
                     {
                         return Some(help);
                     }
@@ -230,7 +281,7 @@ impl SignatureHelpProvider {
     fn get_signature_for_function(
         &self,
         function: &Expression,
-        _scope: &ScopeAnalyzer,
+        lookup: &Lookup,
         active_param: Option<u32>,
     ) -> Option<SignatureHelp> {
         match function {
@@ -239,47 +290,158 @@ impl SignatureHelpProvider {
             } => {
                 // Try to identify namespace.function pattern
                 if let Expression::Identifier(namespace, _) = object.as_ref() {
-                    // Check built-in functions
-                    if let Some(func) = get_function(property) {
-                        if func.namespace == namespace {
-                            return Some(self.create_signature_help(func, active_param));
-                        }
-                    }
-
-                    // Check all namespaces
-                    let all_funcs = [
-                        MATH_FUNCTIONS,
-                        STRING_FUNCTIONS,
-                        STORAGE_FUNCTIONS,
-                        MUSIC_FUNCTIONS,
-                        UI_FUNCTIONS,
-                    ]
-                    .concat();
-
-                    for func in &all_funcs {
-                        if func.namespace == namespace && func.name == property {
-                            return Some(self.create_signature_help(func, active_param));
-                        }
-                    }
-
-                    // Check UI methods
-                    // Note: UI_METHODS is just a list of strings, not full signatures
-                    // Full UI method signatures would need to be defined separately
+                    return self.resolve_signature(Some(namespace), property, lookup, active_param);
                 }
                 None
             }
             Expression::Identifier(name, _) => {
-                // Check built-in functions without namespace
-                if let Some(func) = get_function(name) {
-                    return Some(self.create_signature_help(func, active_param));
-                }
-
-                None
+                self.resolve_signature(None, name, lookup, active_param)
             }
             _ => None,
         }
     }
 
+    /// Resolve `name` or `receiver.name` to a signature: user-defined functions
+    /// first, since they shadow built-ins, then native functions
+    fn resolve_signature(
+        &self,
+        receiver: Option<&str>,
+        name: &str,
+        lookup: &Lookup,
+        active_param: Option<u32>,
+    ) -> Option<SignatureHelp> {
+        if let Some(func) = self.find_user_function(receiver, name, lookup) {
+            return Some(self.create_user_signature_help(&func, active_param));
+        }
+
+        match receiver {
+            Some(namespace) => {
+                // Check all namespaces
+                let all_funcs = [
+                    MATH_FUNCTIONS,
+                    STRING_FUNCTIONS,
+                    STORAGE_FUNCTIONS,
+                    MUSIC_FUNCTIONS,
+                    UI_FUNCTIONS,
+                ]
+                .concat();
+
+                // Note: UI_METHODS is just a list of strings, not full signatures
+                // Full UI method signatures would need to be defined separately
+                all_funcs
+                    .iter()
+                    .find(|func| func.namespace == namespace && func.name == name)
+                    .map(|func| self.create_signature_help(func, active_param))
+            }
+            // Check built-in functions without namespace
+            None => get_function(name).map(|func| self.create_signature_help(func, active_param)),
+        }
+    }
+
+    fn find_user_function(
+        &self,
+        receiver: Option<&str>,
+        name: &str,
+        lookup: &Lookup,
+    ) -> Option<UserFunction> {
+        let Some(receiver) = receiver else {
+            // Functions declared in this file
+            if let Some(stub) = lookup.scope.find_function(name) {
+                return Some(UserFunction {
+                    stub: stub.clone(),
+                    owner: None,
+                    module: None,
+                });
+            }
+
+            // Functions brought in by `import`
+            return lookup
+                .workspace
+                .imported_modules(lookup.uri, lookup.ast)
+                .iter()
+                .find_map(|module| {
+                    let stub = module.scope.find_function(name)?;
+                    Some(UserFunction {
+                        stub: stub.clone(),
+                        owner: None,
+                        module: Some(module.name.clone()),
+                    })
+                });
+        };
+
+        // Methods of objects created with `new`
+        let module =
+            lookup
+                .workspace
+                .module_for_variable(lookup.uri, lookup.ast, lookup.scope, receiver)?;
+        let stub = module.scope.find_function(name)?;
+        let owner = module.name.rsplit('/').next().unwrap_or(&module.name);
+
+        Some(UserFunction {
+            stub: stub.clone(),
+            owner: Some(owner.to_string()),
+            module: Some(module.name.clone()),
+        })
+    }
+
+    fn create_user_signature_help(
+        &self,
+        func: &UserFunction,
+        active_param: Option<u32>,
+    ) -> SignatureHelp {
+        let stub = &func.stub;
+        let param_labels: Vec<String> = stub
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| match stub.parameter_types.get(idx) {
+                Some(typ) if !matches!(typ, Type::Unknown) => format!("{}: {}", name, typ),
+                _ => name.clone(),
+            })
+            .collect();
+
+        let qualified_name = match &func.owner {
+            Some(owner) => format!("{}.{}", owner, stub.name),
+            None => stub.name.clone(),
+        };
+        let mut label = format!("func {}({})", qualified_name, param_labels.join(", "));
+        if !matches!(stub.return_type, Type::Unknown) {
+            label.push_str(&format!(" -> {}", stub.return_type));
+        }
+
+        let mut documentation = stub.doc.clone().unwrap_or_default();
+        if let Some(module) = &func.module {
+            if !documentation.is_empty() {
+                documentation.push_str("\n\n");
+            }
+            documentation.push_str(&format!("Defined in `{}`", module));
+        }
+
+        let params = param_labels
+            .into_iter()
+            .map(|label| ParameterInformation {
+                label: ParameterLabel::Simple(label),
+                documentation: None,
+            })
+            .collect();
+
+        SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label,
+                documentation: (!documentation.is_empty()).then_some(Documentation::MarkupContent(
+                    MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: documentation,
+                    },
+                )),
+                parameters: Some(params),
+                active_parameter: None,
+            }],
+            active_signature: Some(0),
+            active_parameter: active_param,
+        }
+    }
+
     fn create_signature_help(
         &self,
         func: &FunctionSignature,
@@ -350,7 +512,7 @@ impl SignatureHelpProvider {
         &self,
         position: Position,
         source: &str,
-        _scope: &ScopeAnalyzer,
+        lookup: &Lookup,
     ) -> Option<SignatureHelp> {
         let line = source.lines().nth(position.line as usize)?;
         let text_before = &line[..position.character.min(line.len() as u32) as usize];
@@ -369,35 +531,18 @@ impl SignatureHelpProvider {
             let namespace = caps.get(1)?.as_str();
             let func_name = caps.get(2)?.as_str();
 
-            // Try to find the function
-            let all_funcs = [
-                MATH_FUNCTIONS,
-                STRING_FUNCTIONS,
-                STORAGE_FUNCTIONS,
-                MUSIC_FUNCTIONS,
-                UI_FUNCTIONS,
-            ]
-            .concat();
-
-            for func in &all_funcs {
-                if func.namespace == namespace && func.name == func_name {
-                    return Some(self.create_signature_help(func, Some(active_param)));
-                }
+            if let Some(help) =
+                self.resolve_signature(Some(namespace), func_name, lookup, Some(active_param))
+            {
+                return Some(help);
             }
-
-            // Note: UI_METHODS is just a list of strings, not full signatures
-            // Full UI method signatures would need to be defined separately
         }
 
         // Look for simple function call
         let simple_pattern = regex::Regex::new(r"(\w+)\s*\([^)]*$").ok()?;
         if let Some(caps) = simple_pattern.captures(text_before) {
             let func_name = caps.get(1)?.as_str();
-
-            // Check built-in functions
-            if let Some(func) = get_function(func_name) {
-                return Some(self.create_signature_help(func, Some(active_param)));
-            }
+            return self.resolve_signature(None, func_name, lookup, Some(active_param));
         }
 
         None
@@ -413,23 +558,31 @@ impl Default for SignatureHelpProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use stonescript_parser::parse_source;
+
+    fn text_help(source: &str, position: Position) -> Option<SignatureHelp> {
+        let ast = parse_source("").unwrap();
+        let scope = ScopeAnalyzer::new();
+        let uri = Url::parse("file:///test.txt").unwrap();
+        let workspace = Workspace::new();
+        let lookup = Lookup {
+            ast: &ast,
+            scope: &scope,
+            uri: &uri,
+            workspace: &workspace,
+        };
+        SignatureHelpProvider::new().find_signature_from_text(position, source, &lookup)
+    }
 
     #[test]
     fn test_signature_help_provider() {
-        let provider = SignatureHelpProvider::new();
-        assert!(provider
-            .find_signature_from_text(Position::new(0, 10), "math.Abs(", &ScopeAnalyzer::new())
-            .is_some());
+        assert!(text_help("math.Abs(", Position::new(0, 10)).is_some());
     }
 
     #[test]
     fn test_active_parameter_counting() {
-        let provider = SignatureHelpProvider::new();
-        let scope = ScopeAnalyzer::new();
-
         // Test with one comma (second parameter)
-        let result =
-            provider.find_signature_from_text(Position::new(0, 15), "math.Pow(2, ", &scope);
+        let result = text_help("math.Pow(2, ", Position::new(0, 15));
         assert!(result.is_some());
         if let Some(help) = result {
             assert_eq!(help.active_parameter, Some(1));
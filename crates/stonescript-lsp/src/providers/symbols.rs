@@ -1,8 +1,8 @@
 //! Symbols provider (document outline)
 
-use tower_lsp::lsp_types::*;
 use crate::utils::ScopeAnalyzer;
-use stonescript_parser::Program;
+use stonescript_parser::{Program, Span, Statement};
+use tower_lsp::lsp_types::*;
 
 pub struct SymbolsProvider;
 
@@ -13,37 +13,74 @@ impl SymbolsProvider {
 
     pub fn provide_symbols(
         &self,
-        _ast: &Program,
+        ast: &Program,
         scope: &ScopeAnalyzer,
         _source: &str,
     ) -> Vec<DocumentSymbol> {
         let mut symbols = Vec::new();
 
+        // Add top-level functions, selecting just their name
+        for stmt in &ast.statements {
+            if let Statement::FunctionDefinition {
+                name,
+                name_span,
+                params,
+                span,
+                ..
+            } = stmt
+            {
+                #[allow(deprecated)]
+                symbols.push(DocumentSymbol {
+                    name: name.clone(),
+                    detail: Some(format!("func {}({})", name, params.join(", "))),
+                    kind: SymbolKind::FUNCTION,
+                    range: to_range(span),
+                    selection_range: to_range(name_span),
+                    children: None,
+                    tags: None,
+                    deprecated: None,
+                });
+            }
+        }
+
         // Add variables from scope
-        // Note: Without source position tracking in AST, we use placeholder ranges
+        // Note: Variables without a `var` declaration get placeholder ranges
         for var in scope.get_all_variables() {
+            if scope.find_function(&var.name).is_some() {
+                continue;
+            }
+            let range = var
+                .definition_span
+                .as_ref()
+                .map(to_range)
+                .unwrap_or_default();
+
             #[allow(deprecated)]
             symbols.push(DocumentSymbol {
                 name: var.name.clone(),
                 detail: Some("variable".to_string()),
                 kind: SymbolKind::VARIABLE,
-                range: Range {
-                    start: Position { line: 0, character: 0 },
-                    end: Position { line: 0, character: 0 },
-                },
-                selection_range: Range {
-                    start: Position { line: 0, character: 0 },
-                    end: Position { line: 0, character: 0 },
-                },
+                range,
+                selection_range: range,
                 children: None,
                 tags: None,
                 deprecated: None,
             });
         }
 
-        // Note: StoneScript doesn't have user-defined functions
-        // Only variables are shown in the symbol outline
-
         symbols
     }
 }
+
+fn to_range(span: &Span) -> Range {
+    Range {
+        start: Position {
+            line: span.start.line as u32,
+            character: span.start.column as u32,
+        },
+        end: Position {
+            line: span.end.line as u32,
+            character: span.end.column as u32,
+        },
+    }
+}
@@ -7,8 +7,9 @@ use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
+use crate::config::Settings;
 use crate::providers::*;
-use crate::utils::ScopeAnalyzer;
+use crate::utils::{ScopeAnalyzer, Workspace};
 
 /// Document information
 struct Document {
@@ -21,6 +22,7 @@ struct Document {
 pub struct Backend {
     client: Client,
     documents: DashMap<String, Document>,
+    workspace: Workspace,
 
     // Providers
     completion: CompletionProvider,
@@ -31,6 +33,7 @@ pub struct Backend {
     symbols: SymbolsProvider,
     formatting: FormattingProvider,
     semantic_tokens: SemanticTokensProvider,
+    code_action: CodeActionProvider,
 }
 
 impl Backend {
@@ -38,6 +41,7 @@ impl Backend {
         Self {
             client,
             documents: DashMap::new(),
+            workspace: Workspace::new(),
             completion: CompletionProvider::new(),
             hover: HoverProvider::new(),
             diagnostics: DiagnosticsProvider::new(),
@@ -46,9 +50,18 @@ impl Backend {
             symbols: SymbolsProvider::new(),
             formatting: FormattingProvider::new(),
             semantic_tokens: SemanticTokensProvider::new(),
+            code_action: CodeActionProvider::new(),
         }
     }
 
+    /// Hand client settings to the providers that use them
+    fn apply_settings(&self, settings: Settings) {
+        self.completion.set_user_snippets(settings.snippets);
+        self.diagnostics
+            .set_severities(settings.diagnostic_severities);
+        self.formatting.set_settings(settings.formatting);
+    }
+
     fn analyze_document(&self, uri: &str, text: &str, version: i32) {
         let rope = Rope::from_str(text);
 
@@ -105,11 +118,15 @@ impl Backend {
 
         // Publish diagnostics
         if let Some(doc) = self.documents.get(uri) {
-            let diagnostics = self
-                .diagnostics
-                .provide_diagnostics(&doc.ast, text, &doc.scope);
-
             let uri_parsed = Url::parse(uri).unwrap();
+            let diagnostics = self.diagnostics.provide_diagnostics(
+                &doc.ast,
+                text,
+                &doc.scope,
+                &uri_parsed,
+                &self.workspace,
+            );
+
             let client = self.client.clone();
             tokio::spawn(async move {
                 client
@@ -122,7 +139,22 @@ impl Backend {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let mut roots: Vec<_> = params
+            .workspace_folders
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect();
+        #[allow(deprecated)]
+        if let Some(root) = params.root_uri.and_then(|uri| uri.to_file_path().ok()) {
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+        self.workspace.set_roots(roots);
+        self.apply_settings(Settings::from_value(params.initialization_options));
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
@@ -150,6 +182,17 @@ impl LanguageServer for Backend {
                         },
                     ),
                 ),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR_EXTRACT,
+                            CodeActionKind::REFACTOR_INLINE,
+                            CodeActionKind::REFACTOR_REWRITE,
+                        ]),
+                        ..Default::default()
+                    },
+                )),
                 ..Default::default()
             },
             ..Default::default()
@@ -160,6 +203,10 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.apply_settings(Settings::from_value(Some(params.settings)));
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let uri = params.text_document.uri.to_string();
         let text = params.text_document.text;
@@ -200,14 +247,19 @@ impl LanguageServer for Backend {
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let uri = params.text_document_position.text_document.uri.to_string();
+        let uri = params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
 
-        if let Some(doc) = self.documents.get(&uri) {
+        if let Some(doc) = self.documents.get(&uri.to_string()) {
             let text = doc.rope.to_string();
-            let items = self
-                .completion
-                .provide_completion(&doc.ast, position, &text, &doc.scope);
+            let items = self.completion.provide_completion(
+                &doc.ast,
+                position,
+                &text,
+                &doc.scope,
+                &uri,
+                &self.workspace,
+            );
             Ok(Some(CompletionResponse::Array(items)))
         } else {
             Ok(None)
@@ -237,14 +289,19 @@ impl LanguageServer for Backend {
             .text_document_position_params
             .text_document
             .uri
-            .to_string();
+            .clone();
         let position = params.text_document_position_params.position;
 
-        if let Some(doc) = self.documents.get(&uri) {
+        if let Some(doc) = self.documents.get(&uri.to_string()) {
             let text = doc.rope.to_string();
-            Ok(self
-                .signature_help
-                .provide_signature_help(&doc.ast, position, &text, &doc.scope))
+            Ok(self.signature_help.provide_signature_help(
+                &doc.ast,
+                position,
+                &text,
+                &doc.scope,
+                &uri,
+                &self.workspace,
+            ))
         } else {
             Ok(None)
         }
@@ -291,7 +348,7 @@ impl LanguageServer for Backend {
 
         if let Some(doc) = self.documents.get(&uri) {
             let text = doc.rope.to_string();
-            let edits = self.formatting.provide_formatting(&text);
+            let edits = self.formatting.provide_formatting(&text, &params.options);
             Ok(Some(edits))
         } else {
             Ok(None)
@@ -314,4 +371,24 @@ impl LanguageServer for Backend {
             Ok(None)
         }
     }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+
+        if let Some(doc) = self.documents.get(&uri.to_string()) {
+            let text = doc.rope.to_string();
+            let actions = self.code_action.provide_code_actions(
+                &doc.ast,
+                &text,
+                &doc.scope,
+                &uri,
+                params.range,
+                &params.context.diagnostics,
+                &self.workspace,
+            );
+            Ok(Some(actions))
+        } else {
+            Ok(None)
+        }
+    }
 }
@@ -0,0 +1,500 @@
+//! Completion context derived from the AST at the cursor
+//!
+//! The document usually does not parse while the user is typing, so the
+//! statement under the cursor is recovered on its own: the text before the
+//! cursor is cut at the word being typed, a placeholder identifier is spliced
+//! in, and any open brackets, strings and interpolations are closed. The
+//! position of the placeholder in the resulting AST tells what is expected.
+
+use stonescript_parser::ast::{
+    BinaryOperator, CommandValue, Expression, InterpolationPart, Statement,
+};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::Position;
+
+/// Placeholder identifier spliced in at the cursor
+pub const CURSOR_MARKER: &str = "__cursor__";
+
+/// What kind of code is expected at the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompletionContext {
+    /// Start of a statement
+    Statement,
+    /// Any expression position
+    Expression,
+    /// After a dot; holds the object expression, e.g. `ui.root`
+    MemberAccess(Expression),
+    /// Argument `index` of a call to `function`
+    Argument { function: Expression, index: usize },
+    /// Right-hand side of a comparison; holds the left-hand side
+    Comparison(Expression),
+    /// Argument `index` of a command such as `equip` or `play`
+    CommandArgument { command: String, index: usize },
+    /// Module path after `import`; holds the part before the segment being typed
+    ImportPath(String),
+    /// Module path after `new`; holds the part before the segment being typed
+    NewPath(String),
+    /// Plain text, comments and new names: nothing to complete
+    NoCompletion,
+}
+
+/// Determine the completion context at a position
+pub fn completion_context(source: &str, position: Position) -> CompletionContext {
+    let lines: Vec<&str> = source.lines().collect();
+    let line_index = position.line as usize;
+    let line = lines.get(line_index).copied().unwrap_or("");
+
+    if in_ascii_block(&lines[..line_index.min(lines.len())]) {
+        return CompletionContext::NoCompletion;
+    }
+
+    let Some(statement) = statement_before_cursor(&lines, line_index, line, position.character)
+    else {
+        return CompletionContext::NoCompletion;
+    };
+
+    // Replace the word being typed with the marker
+    let head = statement.trim_end_matches(is_word_char);
+    let Some(closers) = closing_delimiters(head) else {
+        return CompletionContext::NoCompletion;
+    };
+    let snippet = format!("{}{}{}", head, CURSOR_MARKER, closers);
+
+    match parse_source(&snippet) {
+        Ok(program) => {
+            find_in_statements(&program.statements).unwrap_or(CompletionContext::NoCompletion)
+        }
+        Err(_) => context_from_text(head),
+    }
+}
+
+/// Context worked out from the text alone, for statements that do not
+/// parse even with the placeholder: a statement start, an expression after
+/// an operator, `(`, `[` or `,`, and otherwise nothing
+fn context_from_text(head: &str) -> CompletionContext {
+    let head = head.trim_end();
+    if head.is_empty() {
+        return CompletionContext::Statement;
+    }
+    if head.starts_with("//") || head.starts_with("/*") {
+        return CompletionContext::NoCompletion;
+    }
+    match head.chars().last() {
+        Some(
+            '+' | '-' | '*' | '/' | '%' | '=' | '<' | '>' | '!' | '&' | '|' | '?' | '(' | '[' | ',',
+        ) => CompletionContext::Expression,
+        _ => CompletionContext::NoCompletion,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Text of the statement from its start up to the cursor, with `^`
+/// continuations joined and a leading `:` of else branches removed
+fn statement_before_cursor(
+    lines: &[&str],
+    line_index: usize,
+    line: &str,
+    character: u32,
+) -> Option<String> {
+    let mut statement = line[..byte_offset(line, character)]
+        .trim_start()
+        .to_string();
+
+    let mut index = line_index;
+    while let Some(continuation) = statement.strip_prefix('^') {
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+        let previous = lines.get(index)?.trim_start();
+        if previous.starts_with("//") {
+            break;
+        }
+        statement = format!("{}{}", previous, continuation);
+    }
+
+    if let Some(rest) = statement.strip_prefix(':') {
+        statement = rest.trim_start().to_string();
+    }
+
+    Some(statement)
+}
+
+/// Convert a UTF-16 column to a byte offset within the line
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character as usize {
+            return offset;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// Whether the lines end inside an unterminated `ascii` block
+fn in_ascii_block(lines: &[&str]) -> bool {
+    let mut inside = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if inside {
+            if trimmed.contains("asciiend") {
+                inside = false;
+            }
+        } else if trimmed.ends_with("ascii") && !trimmed.starts_with("//") {
+            inside = true;
+        }
+    }
+    inside
+}
+
+/// Delimiters that close everything left open in `head`, or `None` when
+/// the cursor is in plain text (output text or a string literal, which
+/// unlike output text has no interpolation)
+fn closing_delimiters(head: &str) -> Option<String> {
+    let mut open = Vec::new();
+    let mut chars = head.chars();
+
+    // Everything after `>` is text, apart from `@...@` interpolations
+    if head.starts_with('>') {
+        chars.next();
+        open.push('>');
+    }
+
+    for c in chars {
+        match open.last() {
+            Some('>') if c == '@' => open.push('@'),
+            Some('"') if c == '"' => {
+                open.pop();
+            }
+            Some('>') | Some('"') => {}
+            top => match c {
+                '@' if top == Some(&'@') => {
+                    open.pop();
+                }
+                '"' | '(' | '[' | '@' => open.push(c),
+                ')' | ']' => {
+                    open.pop();
+                }
+                _ => {}
+            },
+        }
+    }
+
+    if matches!(open.last(), Some('>') | Some('"')) {
+        return None;
+    }
+
+    Some(
+        open.iter()
+            .rev()
+            .filter_map(|c| match c {
+                '(' => Some(')'),
+                '[' => Some(']'),
+                '@' => Some('@'),
+                '"' => Some('"'),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+fn is_marker(expr: &Expression) -> bool {
+    matches!(expr, Expression::Identifier(name, _) if name == CURSOR_MARKER)
+}
+
+fn find_in_statements(statements: &[Statement]) -> Option<CompletionContext> {
+    statements.iter().find_map(find_in_statement)
+}
+
+fn find_in_statement(statement: &Statement) -> Option<CompletionContext> {
+    match statement {
+        Statement::ExpressionStatement { expression, .. } => {
+            if is_marker(expression) {
+                Some(CompletionContext::Statement)
+            } else {
+                find_in_expression(expression)
+            }
+        }
+        // `var x` names a new variable
+        Statement::VarDeclaration {
+            name, initializer, ..
+        } => {
+            if name.ends_with(CURSOR_MARKER) {
+                return Some(CompletionContext::NoCompletion);
+            }
+            initializer.as_ref().and_then(find_in_expression)
+        }
+        Statement::Assignment { target, value, .. } => {
+            if is_marker(target) {
+                return Some(CompletionContext::Expression);
+            }
+            find_in_expression(target).or_else(|| find_in_expression(value))
+        }
+        Statement::Condition {
+            condition,
+            then_block,
+            ..
+        } => find_in_expression(condition).or_else(|| find_in_statements(then_block)),
+        // `equip`, `brew` and the like take words, and their `args` only
+        // hold the `@expr@` interpolations
+        Statement::Command {
+            name,
+            structured: Some(structured),
+            args,
+            ..
+        } => structured
+            .values()
+            .iter()
+            .position(|value| matches!(value, CommandValue::Word(word, _) if word == CURSOR_MARKER))
+            .map(|index| CompletionContext::CommandArgument {
+                command: name.clone(),
+                index,
+            })
+            .or_else(|| args.iter().find_map(find_in_expression)),
+        Statement::Command { name, args, .. } => args.iter().enumerate().find_map(|(i, arg)| {
+            if is_marker(arg) {
+                Some(CompletionContext::CommandArgument {
+                    command: name.clone(),
+                    index: i,
+                })
+            } else {
+                find_in_expression(arg)
+            }
+        }),
+        Statement::Output {
+            position,
+            color,
+            text,
+            ..
+        } => position
+            .iter()
+            .find_map(|(x, y)| find_in_expression(x).or_else(|| find_in_expression(y)))
+            .or_else(|| color.as_ref().and_then(find_in_expression))
+            .or_else(|| find_in_expression(text)),
+        Statement::Return { value, .. } => value.as_ref().and_then(find_in_expression),
+        Statement::For { range, .. } => {
+            find_in_expression(&range.0).or_else(|| find_in_expression(&range.1))
+        }
+        Statement::ForIn { collection, .. } => find_in_expression(collection),
+        Statement::While { condition, .. } => find_in_expression(condition),
+        Statement::Import { path, .. } => path
+            .strip_suffix(CURSOR_MARKER)
+            .map(|prefix| CompletionContext::ImportPath(prefix.to_string())),
+        // Function names and parameters are new names
+        Statement::FunctionDefinition { .. } => Some(CompletionContext::NoCompletion),
+        Statement::Comment(..) | Statement::Empty => None,
+    }
+}
+
+fn find_in_expression(expr: &Expression) -> Option<CompletionContext> {
+    if is_marker(expr) {
+        return Some(CompletionContext::Expression);
+    }
+
+    match expr {
+        Expression::Property {
+            object, property, ..
+        } => {
+            if property == CURSOR_MARKER {
+                Some(CompletionContext::MemberAccess((**object).clone()))
+            } else {
+                find_in_expression(object)
+            }
+        }
+        Expression::FunctionCall { function, args, .. } => args
+            .iter()
+            .enumerate()
+            .find_map(|(i, arg)| {
+                if is_marker(arg) {
+                    Some(CompletionContext::Argument {
+                        function: (**function).clone(),
+                        index: i,
+                    })
+                } else {
+                    find_in_expression(arg)
+                }
+            })
+            .or_else(|| find_in_expression(function)),
+        Expression::BinaryOp {
+            left, op, right, ..
+        } => {
+            if is_marker(right) && is_comparison(*op) {
+                Some(CompletionContext::Comparison((**left).clone()))
+            } else {
+                find_in_expression(left).or_else(|| find_in_expression(right))
+            }
+        }
+        Expression::UnaryOp { operand, .. } => find_in_expression(operand),
+        Expression::IndexAccess { object, index, .. } => {
+            find_in_expression(object).or_else(|| find_in_expression(index))
+        }
+        Expression::Array { elements, .. } => elements.iter().find_map(find_in_expression),
+        Expression::Interpolation(parts, _) => parts.iter().find_map(|part| match part {
+            InterpolationPart::Expression(expr) => find_in_expression(expr),
+            InterpolationPart::Text(text, _) => text
+                .contains(CURSOR_MARKER)
+                .then_some(CompletionContext::NoCompletion),
+        }),
+        Expression::New { path, .. } => path
+            .strip_suffix(CURSOR_MARKER)
+            .map(|prefix| CompletionContext::NewPath(prefix.to_string())),
+        Expression::Integer(..)
+        | Expression::Float(..)
+        | Expression::Boolean(..)
+        | Expression::String(..)
+        | Expression::Identifier(..) => None,
+    }
+}
+
+fn is_comparison(op: BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Context at the end of the last line of `source`
+    fn context_at_end(source: &str) -> CompletionContext {
+        let line = source.lines().count().saturating_sub(1);
+        let character = source.lines().last().unwrap_or("").encode_utf16().count();
+        completion_context(
+            source,
+            Position {
+                line: line as u32,
+                character: character as u32,
+            },
+        )
+    }
+
+    fn member_object(context: CompletionContext) -> Expression {
+        match context {
+            CompletionContext::MemberAccess(object) => object,
+            other => panic!("Expected member access, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_member_access_on_chain() {
+        let object = member_object(context_at_end("ui.root.chi"));
+        match object {
+            Expression::Property {
+                object, property, ..
+            } => {
+                assert_eq!(property, "root");
+                assert!(matches!(*object, Expression::Identifier(ref name, _) if name == "ui"));
+            }
+            other => panic!("Expected ui.root, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_argument_position() {
+        match context_at_end("x = math.Max(1, ") {
+            CompletionContext::Argument { function, index } => {
+                assert_eq!(index, 1);
+                assert!(
+                    matches!(function, Expression::Property { ref property, .. } if property == "Max")
+                );
+            }
+            other => panic!("Expected argument context, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_comparison_rhs() {
+        match context_at_end("  ?foe.id = bo") {
+            CompletionContext::Comparison(Expression::Property { property, .. }) => {
+                assert_eq!(property, "id")
+            }
+            other => panic!("Expected comparison context, got {:?}", other),
+        }
+        assert!(matches!(
+            context_at_end(":?loc ! "),
+            CompletionContext::Comparison(Expression::Identifier(ref name, _)) if name == "loc"
+        ));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert!(matches!(
+            context_at_end(">Gold: @loc."),
+            CompletionContext::MemberAccess(_)
+        ));
+        assert_eq!(context_at_end(">Gold: lo"), CompletionContext::NoCompletion);
+        assert_eq!(
+            context_at_end("var s = \"hp @h"),
+            CompletionContext::NoCompletion
+        );
+    }
+
+    #[test]
+    fn test_new_and_import_paths() {
+        assert_eq!(
+            context_at_end("import Games/Fi"),
+            CompletionContext::ImportPath("Games/".to_string())
+        );
+        assert_eq!(
+            context_at_end("var v = new Compo"),
+            CompletionContext::NewPath(String::new())
+        );
+    }
+
+    #[test]
+    fn test_statements_and_commands() {
+        assert_eq!(context_at_end("  equ"), CompletionContext::Statement);
+        assert_eq!(
+            context_at_end("equipR "),
+            CompletionContext::CommandArgument {
+                command: "equipR".to_string(),
+                index: 0
+            }
+        );
+        assert_eq!(context_at_end("var coun"), CompletionContext::NoCompletion);
+        assert_eq!(context_at_end("// loc."), CompletionContext::NoCompletion);
+        assert_eq!(
+            context_at_end(">o3,4,\nascii\n##"),
+            CompletionContext::NoCompletion
+        );
+    }
+
+    #[test]
+    fn test_context_from_text() {
+        // Statement keywords are only offered where a statement starts
+        assert_eq!(context_from_text("  "), CompletionContext::Statement);
+        assert_eq!(
+            context_from_text("x = (1 + 2)) + "),
+            CompletionContext::Expression
+        );
+        assert_eq!(
+            context_from_text("?hp < 5 & "),
+            CompletionContext::Expression
+        );
+        assert_eq!(context_from_text("Max(1, "), CompletionContext::Expression);
+        assert_eq!(
+            context_from_text("x = a b "),
+            CompletionContext::NoCompletion
+        );
+        assert_eq!(context_from_text("// a /"), CompletionContext::NoCompletion);
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        assert!(matches!(
+            context_at_end("?loc = caves\n^|loc = "),
+            CompletionContext::Comparison(_)
+        ));
+    }
+}
@@ -1,7 +1,13 @@
 //! Utility modules
 
+pub mod completion_context;
 pub mod scope_analyzer;
+pub mod suggest;
 pub mod type_inference;
+pub mod workspace;
 
-pub use scope_analyzer::{FunctionStub, ScopeAnalyzer, Variable};
+pub use completion_context::{completion_context, CompletionContext};
+pub use scope_analyzer::{FunctionStub, ScopeAnalyzer, Variable, VariableUsage};
+pub use suggest::{edit_distance, suggest};
 pub use type_inference::{infer_type, infer_type_with_scope};
+pub use workspace::{Module, ModuleEntry, Workspace};
@@ -1,8 +1,12 @@
 //! Scope analysis using nom-based AST
 
 use crate::data::Type;
+use crate::utils::type_inference::{infer_parameter_types, infer_return_type};
 use std::collections::HashMap;
-use stonescript_parser::ast::{Expression, Position, Program, Span, Statement};
+use stonescript_parser::ast::{
+    AssignmentOperator, Expression, InterpolationPart, Position, Program, Span, Statement,
+    UnaryOperator,
+};
 
 /// A variable in scope
 #[derive(Debug, Clone)]
@@ -11,6 +15,8 @@ pub struct Variable {
     pub scope_id: usize,
     pub inferred_type: Type,
     pub definition_span: Option<Span>,
+    /// Module path when the variable holds a `new` object
+    pub new_path: Option<String>,
 }
 
 /// Scope information
@@ -28,6 +34,9 @@ pub struct ScopeAnalyzer {
     next_scope_id: usize,
     current_scope: usize,
     functions: HashMap<String, FunctionStub>,
+    /// Consecutive `//` comment lines seen just before the current statement
+    pending_doc: Vec<String>,
+    pending_doc_end_line: Option<usize>,
 }
 
 impl ScopeAnalyzer {
@@ -45,6 +54,8 @@ impl ScopeAnalyzer {
             next_scope_id: 1,
             current_scope: 0,
             functions: HashMap::new(),
+            pending_doc: Vec::new(),
+            pending_doc_end_line: None,
         }
     }
 
@@ -56,27 +67,62 @@ impl ScopeAnalyzer {
     }
 
     fn analyze_statement(&mut self, statement: &Statement) {
+        let doc = self.take_doc_comment(statement);
+
         match statement {
             Statement::ForIn {
                 variable,
+                variable_span,
                 collection,
                 body,
-                ..
+                span,
             } => {
                 self.analyze_expression(collection);
-                let _scope = self.enter_scope();
+                let _scope = self.enter_scope(Some(*span));
                 self.add_variable(variable.clone());
+                self.set_definition_span(variable, *variable_span);
                 for stmt in body {
                     self.analyze_statement(stmt);
                 }
                 self.exit_scope();
             }
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer,
+                ..
+            } => {
+                let inferred_type = initializer
+                    .as_ref()
+                    .map(crate::utils::type_inference::infer_type)
+                    .unwrap_or(Type::Unknown);
+                self.add_variable_with_type(name.clone(), inferred_type);
+                self.set_definition_span(name, *name_span);
+                if let Some(Expression::New { path, .. }) = initializer {
+                    self.set_new_path(name, path.clone());
+                }
+                if let Some(value) = initializer {
+                    self.analyze_expression(value);
+                }
+            }
             Statement::Assignment { target, value, .. } => {
-                // Extract variable name from target and add to current scope
                 if let Expression::Identifier(name, _) = target {
-                    // Try to infer type from value
                     let inferred_type = crate::utils::type_inference::infer_type(value);
-                    self.add_variable_with_type(name.clone(), inferred_type);
+                    match self.find_variable_mut(name) {
+                        // A mutation only fills in a type the declaration left open
+                        Some(var) => {
+                            if matches!(var.inferred_type, Type::Unknown) {
+                                var.inferred_type = inferred_type;
+                            }
+                        }
+                        // Assigning an undeclared name still brings it into scope
+                        None => {
+                            self.add_variable_with_type(name.clone(), inferred_type);
+                            if let Expression::New { path, .. } = value {
+                                self.set_new_path(name, path.clone());
+                            }
+                        }
+                    }
                 }
                 // Analyze both target and value expressions
                 self.analyze_expression(target);
@@ -104,7 +150,7 @@ impl ScopeAnalyzer {
                 self.analyze_expression(condition);
 
                 // Analyze then block in new scope
-                let _then_scope = self.enter_scope();
+                let _then_scope = self.enter_scope(block_span(then_block));
                 for stmt in then_block {
                     self.analyze_statement(stmt);
                 }
@@ -113,7 +159,7 @@ impl ScopeAnalyzer {
                 // Analyze else-if blocks
                 for else_if in else_ifs {
                     self.analyze_expression(&else_if.condition);
-                    let _else_if_scope = self.enter_scope();
+                    let _else_if_scope = self.enter_scope(block_span(&else_if.block));
                     for stmt in &else_if.block {
                         self.analyze_statement(stmt);
                     }
@@ -122,42 +168,63 @@ impl ScopeAnalyzer {
 
                 // Analyze else block
                 if let Some(stmts) = else_block {
-                    let _else_scope = self.enter_scope();
+                    let _else_scope = self.enter_scope(block_span(stmts));
                     for stmt in stmts {
                         self.analyze_statement(stmt);
                     }
                     self.exit_scope();
                 }
             }
-            Statement::Output { position, text, .. } => {
+            Statement::Output {
+                position,
+                color,
+                text,
+                ..
+            } => {
                 if let Some((x, y)) = position {
                     self.analyze_expression(x);
                     self.analyze_expression(y);
                 }
+                if let Some(color) = color {
+                    self.analyze_expression(color);
+                }
                 self.analyze_expression(text);
             }
             Statement::ExpressionStatement { expression, .. } => {
                 self.analyze_expression(expression);
             }
             Statement::FunctionDefinition {
-                name, params, body, ..
+                name,
+                name_span,
+                params,
+                param_spans,
+                body,
+                span,
             } => {
+                self.add_variable(name.clone());
+                self.set_definition_span(name, *name_span);
+                let _scope = self.enter_scope(Some(*span));
+                let parameter_types = infer_parameter_types(params, body);
+                for ((param, typ), span) in params.iter().zip(&parameter_types).zip(param_spans) {
+                    self.add_variable_with_type(param.clone(), typ.clone());
+                    self.set_definition_span(param, *span);
+                }
+                for stmt in body {
+                    self.analyze_statement(stmt);
+                }
+                let return_type = infer_return_type(body, self);
+                self.exit_scope();
+
                 self.functions.insert(
                     name.clone(),
                     FunctionStub {
                         name: name.clone(),
                         parameters: params.clone(),
+                        parameter_types,
+                        return_type,
+                        doc,
                     },
                 );
-                self.add_variable(name.clone());
-                let _scope = self.enter_scope();
-                for param in params {
-                    self.add_variable(param.clone());
-                }
-                for stmt in body {
-                    self.analyze_statement(stmt);
-                }
-                self.exit_scope();
             }
             Statement::Return { value, .. } => {
                 if let Some(expr) = value {
@@ -166,24 +233,28 @@ impl ScopeAnalyzer {
             }
             Statement::For {
                 variable,
+                variable_span,
                 range,
                 body,
-                ..
+                span,
             } => {
                 self.analyze_expression(&range.0);
                 self.analyze_expression(&range.1);
-                let _scope = self.enter_scope();
+                let _scope = self.enter_scope(Some(*span));
                 self.add_variable(variable.clone());
+                self.set_definition_span(variable, *variable_span);
                 for stmt in body {
                     self.analyze_statement(stmt);
                 }
                 self.exit_scope();
             }
             Statement::While {
-                condition, body, ..
+                condition,
+                body,
+                span,
             } => {
                 self.analyze_expression(condition);
-                let _scope = self.enter_scope();
+                let _scope = self.enter_scope(Some(*span));
                 for stmt in body {
                     self.analyze_statement(stmt);
                 }
@@ -198,6 +269,40 @@ impl ScopeAnalyzer {
         }
     }
 
+    /// Track `//` comment lines and hand them over as documentation when the
+    /// statement directly below them is a function definition
+    fn take_doc_comment(&mut self, statement: &Statement) -> Option<String> {
+        match statement {
+            Statement::Comment(text, span) => {
+                let adjacent = self
+                    .pending_doc_end_line
+                    .is_some_and(|line| line + 1 == span.start.line);
+                if !adjacent {
+                    self.pending_doc.clear();
+                }
+                self.pending_doc.push(text.trim().to_string());
+                self.pending_doc_end_line = Some(span.end.line);
+                None
+            }
+            Statement::Empty => None,
+            _ => {
+                let doc_lines = std::mem::take(&mut self.pending_doc);
+                let doc_end = self.pending_doc_end_line.take();
+                let is_function = matches!(statement, Statement::FunctionDefinition { .. });
+                let adjacent = match (doc_end, statement.span()) {
+                    (Some(line), Some(span)) => line + 1 == span.start.line,
+                    _ => false,
+                };
+
+                if is_function && adjacent && !doc_lines.is_empty() {
+                    Some(doc_lines.join("\n"))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
     fn analyze_expression(&mut self, expression: &Expression) {
         match expression {
             Expression::Boolean(_, _) => {
@@ -260,6 +365,7 @@ impl ScopeAnalyzer {
             scope_id: self.current_scope,
             inferred_type,
             definition_span: None,
+            new_path: None,
         };
 
         self.scopes[self.current_scope]
@@ -267,7 +373,19 @@ impl ScopeAnalyzer {
             .insert(name, variable);
     }
 
-    fn enter_scope(&mut self) -> usize {
+    fn set_definition_span(&mut self, name: &str, span: Span) {
+        if let Some(var) = self.scopes[self.current_scope].variables.get_mut(name) {
+            var.definition_span = Some(span);
+        }
+    }
+
+    fn set_new_path(&mut self, name: &str, path: String) {
+        if let Some(var) = self.scopes[self.current_scope].variables.get_mut(name) {
+            var.new_path = Some(path);
+        }
+    }
+
+    fn enter_scope(&mut self, span: Option<Span>) -> usize {
         let scope_id = self.next_scope_id;
         self.next_scope_id += 1;
 
@@ -275,7 +393,7 @@ impl ScopeAnalyzer {
             id: scope_id,
             parent: Some(self.current_scope),
             variables: HashMap::new(),
-            span: None,
+            span,
         };
 
         self.scopes.push(scope);
@@ -304,6 +422,35 @@ impl ScopeAnalyzer {
         }
     }
 
+    fn find_variable_mut(&mut self, name: &str) -> Option<&mut Variable> {
+        let mut current = self.current_scope;
+        while !self.scopes[current].variables.contains_key(name) {
+            current = self.scopes[current].parent?;
+        }
+        self.scopes[current].variables.get_mut(name)
+    }
+
+    /// Find a variable visible at a position, starting from the innermost
+    /// scope whose statements contain it
+    pub fn find_variable_at(&self, name: &str, position: Position) -> Option<&Variable> {
+        let mut current = self
+            .scopes
+            .iter()
+            .rev()
+            .find(|scope| {
+                scope
+                    .span
+                    .is_some_and(|span| span.contains_position(position))
+            })
+            .map_or(0, |scope| scope.id);
+        loop {
+            if let Some(var) = self.scopes[current].variables.get(name) {
+                return Some(var);
+            }
+            current = self.scopes[current].parent?;
+        }
+    }
+
     /// Get all variables in current scope
     pub fn get_variables_in_scope(&self, scope_id: usize) -> Vec<&Variable> {
         let mut variables = Vec::new();
@@ -345,9 +492,7 @@ impl ScopeAnalyzer {
         self.get_all_variables()
     }
 
-    /// Get user-defined functions (compatibility method)
-    /// Note: StoneScript doesn't support user-defined functions, returns empty vec
-    /// This is a compatibility shim for code migrating from tree-sitter
+    /// Get user-defined functions declared with `func`
     pub fn get_functions(&self) -> Vec<FunctionStub> {
         self.functions.values().cloned().collect()
     }
@@ -357,12 +502,227 @@ impl ScopeAnalyzer {
     }
 }
 
-/// Stub type for function compatibility
-/// StoneScript doesn't have user-defined functions, but some code still references them
+/// A user-defined function declared with `func`
 #[derive(Debug, Clone)]
 pub struct FunctionStub {
     pub name: String,
     pub parameters: Vec<String>,
+    /// Parameter types inferred from how the body uses them
+    pub parameter_types: Vec<Type>,
+    /// Type of the first `return` value whose type could be inferred
+    pub return_type: Type,
+    /// `//` comment lines directly above the definition
+    pub doc: Option<String>,
+}
+
+/// Span from the first to the last statement of a block
+fn block_span(statements: &[Statement]) -> Option<Span> {
+    let mut spans = statements.iter().filter_map(Statement::span);
+    let first = spans.next()?;
+    let last = spans.next_back().unwrap_or(first);
+    Some(Span::new(first.start, last.end))
+}
+
+/// Variables a run of statements declares, reads and assigns, each listed
+/// once in order of first appearance.
+///
+/// Names called as functions are not reads, and a nested function only
+/// contributes the names it uses from outside its own scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableUsage {
+    pub declared: Vec<String>,
+    pub read: Vec<String>,
+    pub written: Vec<String>,
+}
+
+impl VariableUsage {
+    pub fn of_statements(statements: &[Statement]) -> Self {
+        let mut usage = Self::default();
+        for statement in statements {
+            usage.add_statement(statement);
+        }
+        usage
+    }
+
+    pub fn of_expression(expression: &Expression) -> Self {
+        let mut usage = Self::default();
+        usage.add_expression(expression);
+        usage
+    }
+
+    fn add_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Condition {
+                condition,
+                then_block,
+                else_ifs,
+                else_block,
+                ..
+            } => {
+                self.add_expression(condition);
+                self.add_statements(then_block);
+                for else_if in else_ifs {
+                    self.add_expression(&else_if.condition);
+                    self.add_statements(&else_if.block);
+                }
+                if let Some(block) = else_block {
+                    self.add_statements(block);
+                }
+            }
+            Statement::Command { args, .. } => {
+                for arg in args {
+                    self.add_expression(arg);
+                }
+            }
+            Statement::VarDeclaration {
+                name, initializer, ..
+            } => {
+                if let Some(value) = initializer {
+                    self.add_expression(value);
+                }
+                push_unique(&mut self.declared, name);
+            }
+            Statement::Assignment {
+                target, op, value, ..
+            } => {
+                self.add_expression(value);
+                match target {
+                    Expression::Identifier(name, _) => {
+                        if *op != AssignmentOperator::Assign {
+                            push_unique(&mut self.read, name);
+                        }
+                        push_unique(&mut self.written, name);
+                    }
+                    _ => self.add_expression(target),
+                }
+            }
+            Statement::Output {
+                position,
+                color,
+                text,
+                ..
+            } => {
+                if let Some((x, y)) = position {
+                    self.add_expression(x);
+                    self.add_expression(y);
+                }
+                if let Some(color) = color {
+                    self.add_expression(color);
+                }
+                self.add_expression(text);
+            }
+            Statement::ExpressionStatement { expression, .. } => self.add_expression(expression),
+            Statement::FunctionDefinition {
+                name, params, body, ..
+            } => {
+                push_unique(&mut self.declared, name);
+                let inner = Self::of_statements(body);
+                let is_outer =
+                    |var: &&String| !params.contains(var) && !inner.declared.contains(var);
+                for var in inner.read.iter().filter(is_outer) {
+                    push_unique(&mut self.read, var);
+                }
+                for var in inner.written.iter().filter(is_outer) {
+                    push_unique(&mut self.written, var);
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.add_expression(value);
+                }
+            }
+            Statement::For {
+                variable,
+                range,
+                body,
+                ..
+            } => {
+                self.add_expression(&range.0);
+                self.add_expression(&range.1);
+                push_unique(&mut self.declared, variable);
+                self.add_statements(body);
+            }
+            Statement::ForIn {
+                variable,
+                collection,
+                body,
+                ..
+            } => {
+                self.add_expression(collection);
+                push_unique(&mut self.declared, variable);
+                self.add_statements(body);
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.add_expression(condition);
+                self.add_statements(body);
+            }
+            Statement::Import { .. } | Statement::Comment(_, _) | Statement::Empty => {}
+        }
+    }
+
+    fn add_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.add_statement(statement);
+        }
+    }
+
+    fn add_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name, _) => push_unique(&mut self.read, name),
+            Expression::Property { object, .. } => self.add_expression(object),
+            Expression::FunctionCall { function, args, .. } => {
+                if !matches!(function.as_ref(), Expression::Identifier(_, _)) {
+                    self.add_expression(function);
+                }
+                for arg in args {
+                    self.add_expression(arg);
+                }
+            }
+            Expression::BinaryOp { left, right, .. } => {
+                self.add_expression(left);
+                self.add_expression(right);
+            }
+            Expression::UnaryOp { op, operand, .. } => {
+                self.add_expression(operand);
+                if let (
+                    UnaryOperator::Increment | UnaryOperator::Decrement,
+                    Expression::Identifier(name, _),
+                ) = (op, operand.as_ref())
+                {
+                    push_unique(&mut self.written, name);
+                }
+            }
+            Expression::Interpolation(parts, _) => {
+                for part in parts {
+                    if let InterpolationPart::Expression(expr) = part {
+                        self.add_expression(expr);
+                    }
+                }
+            }
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.add_expression(element);
+                }
+            }
+            Expression::IndexAccess { object, index, .. } => {
+                self.add_expression(object);
+                self.add_expression(index);
+            }
+            Expression::Integer(_, _)
+            | Expression::Float(_, _)
+            | Expression::Boolean(_, _)
+            | Expression::String(_, _)
+            | Expression::New { .. } => {}
+        }
+    }
+}
+
+fn push_unique(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_string());
+    }
 }
 
 impl Default for ScopeAnalyzer {
@@ -422,4 +782,54 @@ var a
         assert!(analyzer.has_variable("b"));
         assert!(analyzer.has_variable("c"));
     }
+
+    #[test]
+    fn test_function_stub_details() {
+        let source = r#"// Draws a box outline
+// at the given position
+func DrawBox(x, y, w, h)
+  return x + w
+
+var v = new Components/Vector
+"#;
+        let program = parse_source(source).unwrap();
+
+        let mut analyzer = ScopeAnalyzer::new();
+        analyzer.analyze_ast(&program);
+
+        let func = analyzer.find_function("DrawBox").unwrap();
+        assert_eq!(func.parameters, vec!["x", "y", "w", "h"]);
+        assert_eq!(
+            func.doc.as_deref(),
+            Some("Draws a box outline\nat the given position")
+        );
+
+        let var = analyzer.find_variable("v").unwrap();
+        assert_eq!(var.new_path.as_deref(), Some("Components/Vector"));
+    }
+
+    #[test]
+    fn test_find_variable_at() {
+        let source = "var x = 1\nfunc F(x)\n  return x\n>@x@\n";
+        let program = parse_source(source).unwrap();
+
+        let mut analyzer = ScopeAnalyzer::new();
+        analyzer.analyze_ast(&program);
+
+        let inner = analyzer.find_variable_at("x", Position::new(2, 9)).unwrap();
+        assert_ne!(inner.scope_id, 0);
+        let outer = analyzer.find_variable_at("x", Position::new(3, 2)).unwrap();
+        assert_eq!(outer.scope_id, 0);
+    }
+
+    #[test]
+    fn test_variable_usage() {
+        let source = "var a = b + c\nd += a\ne++\nfor i = 1..n\n  f(i)\n";
+        let program = parse_source(source).unwrap();
+
+        let usage = VariableUsage::of_statements(&program.statements);
+        assert_eq!(usage.declared, vec!["a", "i"]);
+        assert_eq!(usage.read, vec!["b", "c", "a", "d", "e", "n", "i"]);
+        assert_eq!(usage.written, vec!["d", "e"]);
+    }
 }
@@ -0,0 +1,75 @@
+//! "Did you mean" suggestions based on edit distance
+
+/// Maximum number of suggestions returned by [`suggest`]
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Levenshtein distance between two strings, counted in chars
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Candidates close enough to `name` to be what the user meant, closest first.
+///
+/// Names are compared case-insensitively and may be a third of their length
+/// away, so very short names only match candidates differing in case.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let lowered = name.to_lowercase();
+    let max_distance = name.chars().count() / 3;
+
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| {
+            (
+                edit_distance(&lowered, &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.dedup_by(|a, b| a.1 == b.1);
+
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("hp", "hp"), 0);
+        assert_eq!(edit_distance("hpp", "hp"), 1);
+        assert_eq!(edit_distance("maxhp", "mxhp"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let candidates = ["hp", "maxhp", "armor", "distance"];
+        assert_eq!(suggest("hpp", candidates), vec!["hp"]);
+        assert_eq!(suggest("distanse", candidates), vec!["distance"]);
+        assert_eq!(suggest("HP", candidates), vec!["hp"]);
+        assert!(suggest("x", candidates).is_empty());
+        assert!(suggest("level", candidates).is_empty());
+    }
+}
@@ -1,11 +1,14 @@
 //! Type inference from AST
 
+use crate::data::native_functions::get_function_in_namespace;
 use crate::data::{
     get_function, get_game_state, Type, MATH_FUNCTIONS, MUSIC_FUNCTIONS, STORAGE_FUNCTIONS,
     STRING_FUNCTIONS, UI_FUNCTIONS,
 };
 use crate::utils::ScopeAnalyzer;
-use stonescript_parser::ast::{BinaryOperator, Expression, Statement, UnaryOperator};
+use stonescript_parser::ast::{
+    BinaryOperator, Expression, InterpolationPart, Statement, UnaryOperator,
+};
 
 /// Infer type from an AST expression
 pub fn infer_type(expression: &Expression) -> Type {
@@ -51,7 +54,11 @@ pub fn infer_type_with_scope(expression: &Expression, scope: Option<&ScopeAnalyz
                 }
             }
 
-            Type::Unknown
+            // Game state objects are also used without `?`, e.g. `loc.id`
+            match get_game_state(name) {
+                Some(query) => query.return_type.clone(),
+                None => Type::Unknown,
+            }
         }
 
         // Property access
@@ -155,6 +162,16 @@ fn infer_function_return_type(
         Expression::Property {
             object, property, ..
         } => {
+            // Namespace function: ui.AddButton(), string.Size(s)
+            if let Expression::Identifier(namespace, _) = object.as_ref() {
+                let is_variable = scope.is_some_and(|s| s.find_variable(namespace).is_some());
+                if !is_variable {
+                    if let Some(func) = get_function_in_namespace(namespace, property) {
+                        return func.return_type.clone();
+                    }
+                }
+            }
+
             let obj_type = infer_type_with_scope(object, scope);
 
             // Check for method on specific object types
@@ -324,7 +341,11 @@ pub fn infer_type_from_statement(statement: &Statement, scope: Option<&ScopeAnal
                 Type::Unknown
             }
         }
-        Statement::Assignment { value, .. } => infer_type_with_scope(value, scope),
+        Statement::VarDeclaration {
+            initializer: Some(value),
+            ..
+        }
+        | Statement::Assignment { value, .. } => infer_type_with_scope(value, scope),
         Statement::ExpressionStatement { expression, .. } => {
             infer_type_with_scope(expression, scope)
         }
@@ -332,6 +353,247 @@ pub fn infer_type_from_statement(statement: &Statement, scope: Option<&ScopeAnal
     }
 }
 
+/// Infer the return type of a function body from its `return` statements
+pub fn infer_return_type(body: &[Statement], scope: &ScopeAnalyzer) -> Type {
+    let mut collector = ReturnCollector::default();
+    collector.visit_block(body);
+
+    collector
+        .values
+        .iter()
+        .map(|expr| infer_type_with_scope(expr, Some(scope)))
+        .find(|typ| !matches!(typ, Type::Unknown))
+        .unwrap_or(Type::Unknown)
+}
+
+/// Infer parameter types from how a function body uses them
+///
+/// StoneScript parameters are untyped, so this only looks for usages that pin
+/// a type down: arithmetic or comparisons against a typed operand, use as a
+/// loop bound and use as an index.
+pub fn infer_parameter_types(params: &[String], body: &[Statement]) -> Vec<Type> {
+    let mut collector = ParameterUsageCollector {
+        params,
+        types: vec![Type::Unknown; params.len()],
+    };
+    collector.visit_block(body);
+    collector.types
+}
+
+#[derive(Default)]
+struct ReturnCollector<'a> {
+    values: Vec<&'a Expression>,
+}
+
+impl<'a> ReturnCollector<'a> {
+    fn visit_block(&mut self, statements: &'a [Statement]) {
+        for stmt in statements {
+            match stmt {
+                Statement::Return {
+                    value: Some(value), ..
+                } => self.values.push(value),
+                Statement::Condition {
+                    then_block,
+                    else_ifs,
+                    else_block,
+                    ..
+                } => {
+                    self.visit_block(then_block);
+                    for else_if in else_ifs {
+                        self.visit_block(&else_if.block);
+                    }
+                    if let Some(block) = else_block {
+                        self.visit_block(block);
+                    }
+                }
+                // Nested functions return to their own callers
+                Statement::For { body, .. }
+                | Statement::ForIn { body, .. }
+                | Statement::While { body, .. } => self.visit_block(body),
+                _ => {}
+            }
+        }
+    }
+}
+
+struct ParameterUsageCollector<'a> {
+    params: &'a [String],
+    types: Vec<Type>,
+}
+
+impl ParameterUsageCollector<'_> {
+    fn param_index(&self, expr: &Expression) -> Option<usize> {
+        match expr {
+            Expression::Identifier(name, _) => self.params.iter().position(|p| p == name),
+            _ => None,
+        }
+    }
+
+    fn record(&mut self, expr: &Expression, typ: Type) {
+        if matches!(typ, Type::Unknown) {
+            return;
+        }
+        if let Some(idx) = self.param_index(expr) {
+            if matches!(self.types[idx], Type::Unknown) {
+                self.types[idx] = typ;
+            }
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            match stmt {
+                Statement::Condition {
+                    condition,
+                    then_block,
+                    else_ifs,
+                    else_block,
+                    ..
+                } => {
+                    self.visit_expression(condition);
+                    self.visit_block(then_block);
+                    for else_if in else_ifs {
+                        self.visit_expression(&else_if.condition);
+                        self.visit_block(&else_if.block);
+                    }
+                    if let Some(block) = else_block {
+                        self.visit_block(block);
+                    }
+                }
+                Statement::VarDeclaration {
+                    initializer: Some(value),
+                    ..
+                } => self.visit_expression(value),
+                Statement::Assignment { target, value, .. } => {
+                    self.visit_expression(target);
+                    self.visit_expression(value);
+                }
+                Statement::ExpressionStatement { expression, .. } => {
+                    self.visit_expression(expression)
+                }
+                Statement::Return {
+                    value: Some(value), ..
+                } => self.visit_expression(value),
+                Statement::Output {
+                    position,
+                    color,
+                    text,
+                    ..
+                } => {
+                    if let Some((x, y)) = position {
+                        self.record(x, Type::Int);
+                        self.record(y, Type::Int);
+                        self.visit_expression(x);
+                        self.visit_expression(y);
+                    }
+                    if let Some(color) = color {
+                        self.record(color, Type::String);
+                        self.visit_expression(color);
+                    }
+                    self.visit_expression(text);
+                }
+                Statement::For { range, body, .. } => {
+                    self.record(&range.0, Type::Int);
+                    self.record(&range.1, Type::Int);
+                    self.visit_expression(&range.0);
+                    self.visit_expression(&range.1);
+                    self.visit_block(body);
+                }
+                Statement::ForIn {
+                    collection, body, ..
+                } => {
+                    self.visit_expression(collection);
+                    self.visit_block(body);
+                }
+                Statement::While {
+                    condition, body, ..
+                } => {
+                    self.visit_expression(condition);
+                    self.visit_block(body);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::BinaryOp {
+                left, op, right, ..
+            } => {
+                let numeric_only = matches!(
+                    op,
+                    BinaryOperator::Subtract
+                        | BinaryOperator::Multiply
+                        | BinaryOperator::Divide
+                        | BinaryOperator::Modulo
+                        | BinaryOperator::Less
+                        | BinaryOperator::LessEqual
+                        | BinaryOperator::Greater
+                        | BinaryOperator::GreaterEqual
+                );
+                let left_type = infer_type(left);
+                let right_type = infer_type(right);
+
+                if numeric_only {
+                    let numeric = |t: &Type| if t.is_numeric() { t.clone() } else { Type::Int };
+                    self.record(left, numeric(&right_type));
+                    self.record(right, numeric(&left_type));
+                } else if !matches!(op, BinaryOperator::And | BinaryOperator::Or) {
+                    self.record(left, right_type);
+                    self.record(right, left_type);
+                }
+
+                self.visit_expression(left);
+                self.visit_expression(right);
+            }
+            Expression::UnaryOp { op, operand, .. } => {
+                if !matches!(op, UnaryOperator::Not) {
+                    self.record(operand, Type::Int);
+                }
+                self.visit_expression(operand);
+            }
+            Expression::IndexAccess { object, index, .. } => {
+                self.record(index, Type::Int);
+                self.visit_expression(object);
+                self.visit_expression(index);
+            }
+            Expression::FunctionCall { function, args, .. } => {
+                if let Expression::Property {
+                    object, property, ..
+                } = function.as_ref()
+                {
+                    if let Expression::Identifier(namespace, _) = object.as_ref() {
+                        if let Some(func) = get_function_in_namespace(namespace, property) {
+                            for (arg, param) in args.iter().zip(func.parameters) {
+                                self.record(arg, param.typ.clone());
+                            }
+                        }
+                    }
+                }
+                self.visit_expression(function);
+                for arg in args {
+                    self.visit_expression(arg);
+                }
+            }
+            Expression::Property { object, .. } => self.visit_expression(object),
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.visit_expression(element);
+                }
+            }
+            Expression::Interpolation(parts, _) => {
+                for part in parts {
+                    if let InterpolationPart::Expression(inner) = part {
+                        self.visit_expression(inner);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -401,4 +663,16 @@ mod tests {
         let result = infer_binary_op_type(&str_expr, BinaryOperator::Add, &int_expr, None);
         assert_eq!(result, Type::String);
     }
+
+    #[test]
+    fn test_infer_parameter_types_from_usage() {
+        let source = "func DrawBox(x, y, label)\n  >`@x@,@y - 1@,@label@\n  ?label = \"x\"\n    return x * 2";
+        let program = stonescript_parser::parse_source(source).unwrap();
+        let Statement::FunctionDefinition { params, body, .. } = &program.statements[0] else {
+            panic!("Expected function definition");
+        };
+
+        let types = infer_parameter_types(params, body);
+        assert_eq!(types, vec![Type::Int, Type::Int, Type::String]);
+    }
 }
@@ -0,0 +1,472 @@
+//! Workspace module resolution for `import` and `new` paths
+
+use crate::utils::ScopeAnalyzer;
+use dashmap::DashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use stonescript_parser::ast::{Expression, Program, Statement};
+use stonescript_parser::{parse_source, Visitor};
+use tower_lsp::lsp_types::Url;
+
+/// A parsed script file reachable through `import` or `new`
+pub struct Module {
+    /// Module path with `/` separators, e.g. `Components/Vector`
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub ast: Program,
+    pub scope: ScopeAnalyzer,
+}
+
+struct CachedModule {
+    modified: Option<SystemTime>,
+    module: Arc<Module>,
+}
+
+/// Names a script defines with `func`, as of when it was last modified
+struct CachedFunctions {
+    modified: Option<SystemTime>,
+    names: Arc<Vec<String>>,
+}
+
+/// A folder or script file offered while typing an `import` or `new` path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleEntry {
+    Folder(String),
+    /// Script file, named without its `.txt` suffix
+    File {
+        name: String,
+        path: PathBuf,
+    },
+}
+
+/// Resolves module paths against the workspace roots and caches parsed modules
+pub struct Workspace {
+    roots: RwLock<Vec<PathBuf>>,
+    modules: DashMap<PathBuf, CachedModule>,
+    functions: DashMap<PathBuf, CachedFunctions>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self {
+            roots: RwLock::new(Vec::new()),
+            modules: DashMap::new(),
+            functions: DashMap::new(),
+        }
+    }
+
+    /// Replace the configured workspace roots
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        if let Ok(mut current) = self.roots.write() {
+            *current = roots;
+        }
+    }
+
+    /// Directories a module path may be relative to, in lookup order.
+    ///
+    /// StoneScript resolves paths against the game's script folder, which is
+    /// usually the workspace root. A document also resolves against its own
+    /// folder and the folders between it and the workspace root containing
+    /// it, but never above that root, where unrelated scripts could match.
+    pub fn search_roots(&self, document: &Url) -> Vec<PathBuf> {
+        let mut roots = self.roots.read().map(|r| r.clone()).unwrap_or_default();
+
+        if let Some(dir) = document
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+        {
+            // Outside every root, only the document's own folder
+            let inside = roots.iter().any(|root| dir.starts_with(root));
+            let folders: Vec<PathBuf> = dir
+                .ancestors()
+                .take_while(|ancestor| !roots.iter().any(|root| root == ancestor))
+                .take(if inside { usize::MAX } else { 1 })
+                .map(Path::to_path_buf)
+                .collect();
+            roots.extend(folders);
+        }
+
+        roots
+    }
+
+    /// Folders and script files inside a partially typed module path such
+    /// as `Games/` or `Games\\`, folders first
+    pub fn list_modules(&self, document: &Url, prefix: &str) -> Vec<ModuleEntry> {
+        let relative: PathBuf = module_segments(prefix).collect();
+        let roots = if relative.as_os_str().is_empty() {
+            // Listing every ancestor of the document would offer unrelated
+            // folders, so only the workspace roots and the document's folder
+            let mut roots = self.roots.read().map(|r| r.clone()).unwrap_or_default();
+            if let Some(dir) = document
+                .to_file_path()
+                .ok()
+                .and_then(|path| path.parent().map(Path::to_path_buf))
+            {
+                roots.push(dir);
+            }
+            roots
+        } else {
+            self.search_roots(document)
+        };
+
+        let mut folders = Vec::new();
+        let mut files = Vec::new();
+        for root in roots {
+            let Ok(entries) = std::fs::read_dir(root.join(&relative)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if name.starts_with('.') {
+                    continue;
+                }
+                if path.is_dir() {
+                    folders.push(ModuleEntry::Folder(name.to_string()));
+                } else if path.extension().is_some_and(|ext| ext == "txt") {
+                    files.push(ModuleEntry::File {
+                        name: name.to_string(),
+                        path,
+                    });
+                }
+            }
+        }
+
+        folders.sort_by(|a, b| entry_name(a).cmp(entry_name(b)));
+        files.sort_by(|a, b| entry_name(a).cmp(entry_name(b)));
+        let mut entries: Vec<ModuleEntry> = folders.into_iter().chain(files).collect();
+        // The same folder may be reachable from several roots, but a folder
+        // and a script may share a name, e.g. `Games/Blackjack(.txt)`
+        entries.dedup_by(|a, b| {
+            matches!(
+                (a, b),
+                (ModuleEntry::Folder(x), ModuleEntry::Folder(y))
+                    | (ModuleEntry::File { name: x, .. }, ModuleEntry::File { name: y, .. })
+                    if x == y
+            )
+        });
+        entries
+    }
+
+    /// Resolve a module path such as `Games/Fishing/FishingGame` to a file
+    pub fn resolve_path(&self, document: &Url, module_path: &str) -> Option<PathBuf> {
+        let relative = module_file_name(module_path);
+        self.search_roots(document)
+            .into_iter()
+            .map(|root| root.join(&relative))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Resolve and parse a module, reusing the cached parse while the file is unchanged
+    pub fn load(&self, document: &Url, module_path: &str) -> Option<Arc<Module>> {
+        let path = self.resolve_path(document, module_path)?;
+        self.load_file(&path, &module_name(module_path))
+    }
+
+    /// Parse a module file, reusing the cached parse while the file is unchanged
+    pub fn load_file(&self, path: &Path, name: &str) -> Option<Arc<Module>> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if let Some(cached) = self.modules.get(path) {
+            if cached.modified == modified {
+                return Some(cached.module.clone());
+            }
+        }
+
+        let source = std::fs::read_to_string(path).ok()?;
+        let ast = parse_source(&source).ok()?;
+        let mut scope = ScopeAnalyzer::new();
+        scope.analyze_ast(&ast);
+
+        let module = Arc::new(Module {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            source,
+            ast,
+            scope,
+        });
+        self.modules.insert(
+            path.to_path_buf(),
+            CachedModule {
+                modified,
+                module: module.clone(),
+            },
+        );
+        Some(module)
+    }
+
+    /// Modules imported by a program, following nested imports transitively
+    pub fn imported_modules(&self, document: &Url, ast: &Program) -> Vec<Arc<Module>> {
+        let mut modules: Vec<Arc<Module>> = Vec::new();
+        let mut pending = import_paths(&ast.statements);
+
+        while let Some(module_path) = pending.pop() {
+            let Some(module) = self.load(document, &module_path) else {
+                continue;
+            };
+            if modules.iter().any(|m| m.path == module.path) {
+                continue;
+            }
+            pending.extend(import_paths(&module.ast.statements));
+            modules.push(module);
+        }
+
+        modules
+    }
+
+    /// Modules under the workspace roots, or the document's folder when there
+    /// are none, that define `func <function>`.
+    ///
+    /// Each script's function names are indexed from its `func` lines, so a
+    /// script is only read again once it has changed, and never parsed.
+    pub fn modules_defining(&self, document: &Url, function: &str) -> Vec<String> {
+        let own_path = document.to_file_path().ok();
+        let mut roots = self.roots.read().map(|r| r.clone()).unwrap_or_default();
+        if roots.is_empty() {
+            roots.extend(
+                own_path
+                    .as_ref()
+                    .and_then(|path| path.parent().map(Path::to_path_buf)),
+            );
+        }
+
+        let mut names = Vec::new();
+        for root in roots {
+            let mut files = Vec::new();
+            collect_scripts(&root, &mut files);
+            for path in files {
+                if own_path.as_ref() == Some(&path) {
+                    continue;
+                }
+                if !self.functions_in(&path).iter().any(|f| f == function) {
+                    continue;
+                }
+                let stem = path.with_extension("");
+                if let Ok(relative) = stem.strip_prefix(&root) {
+                    let name = module_name(&relative.to_string_lossy());
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+
+        names.sort();
+        names
+    }
+
+    /// Names of the functions a script defines, reusing the index entry
+    /// while the file is unchanged
+    fn functions_in(&self, path: &Path) -> Arc<Vec<String>> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if let Some(cached) = self.functions.get(path) {
+            if cached.modified == modified {
+                return cached.names.clone();
+            }
+        }
+
+        let source = std::fs::read_to_string(path).unwrap_or_default();
+        let names = Arc::new(function_names(&source));
+        self.functions.insert(
+            path.to_path_buf(),
+            CachedFunctions {
+                modified,
+                names: names.clone(),
+            },
+        );
+        names
+    }
+
+    /// Module a variable was instantiated from with `new`, looking in the
+    /// document's own scope first and then in imported modules
+    pub fn module_for_variable(
+        &self,
+        document: &Url,
+        ast: &Program,
+        scope: &ScopeAnalyzer,
+        variable: &str,
+    ) -> Option<Arc<Module>> {
+        let new_path = new_path_in_scope(scope, variable).or_else(|| {
+            self.imported_modules(document, ast)
+                .iter()
+                .find_map(|module| new_path_in_scope(&module.scope, variable))
+        })?;
+        self.load(document, &new_path)
+    }
+}
+
+/// `new` path of a variable, preferring the innermost scope
+fn new_path_in_scope(scope: &ScopeAnalyzer, variable: &str) -> Option<String> {
+    scope
+        .find_variable(variable)
+        .and_then(|var| var.new_path.clone())
+        .or_else(|| {
+            scope
+                .get_all_variables()
+                .into_iter()
+                .filter(|var| var.name == variable)
+                .find_map(|var| var.new_path.clone())
+        })
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// All `.txt` scripts below a folder, skipping hidden entries
+fn collect_scripts(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_scripts(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "txt") {
+            files.push(path);
+        }
+    }
+}
+
+/// Names declared by the `func` lines of a script
+fn function_names(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("func"))
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .map(|rest| {
+            rest.trim_start()
+                .chars()
+                .take_while(|&c| c.is_alphanumeric() || c == '_')
+                .collect::<String>()
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn entry_name(entry: &ModuleEntry) -> &str {
+    match entry {
+        ModuleEntry::Folder(name) | ModuleEntry::File { name, .. } => name,
+    }
+}
+
+/// Leading comment lines of a script, which usually describe the module
+pub fn module_preview(path: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(path).ok()?;
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_block = false;
+
+    for line in source.lines().map(str::trim) {
+        if in_block {
+            match line.find("*/") {
+                Some(end) => {
+                    in_block = false;
+                    lines.push(line[..end].trim());
+                }
+                None => lines.push(line),
+            }
+        } else if let Some(comment) = line.strip_prefix("//") {
+            lines.push(comment.trim());
+        } else if let Some(comment) = line.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => lines.push(comment[..end].trim()),
+                None => {
+                    in_block = true;
+                    lines.push(comment.trim());
+                }
+            }
+        } else if !line.is_empty() || !lines.is_empty() {
+            break;
+        }
+
+        if lines.len() >= PREVIEW_LINES {
+            break;
+        }
+    }
+
+    let preview = lines.join("\n").trim().to_string();
+    (!preview.is_empty()).then_some(preview)
+}
+
+/// Maximum number of comment lines shown by [`module_preview`]
+const PREVIEW_LINES: usize = 8;
+
+/// Split a module path into its segments, accepting both `/` and `\`.
+/// Segments that would leave the search root, such as `..` or a drive
+/// prefix, are dropped.
+pub fn module_segments(module_path: &str) -> impl Iterator<Item = &str> {
+    module_path.split(['/', '\\']).filter(|segment| {
+        let mut components = Path::new(segment).components();
+        matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+    })
+}
+
+/// Normalize a module path to `/` separators
+pub fn module_name(module_path: &str) -> String {
+    module_segments(module_path).collect::<Vec<_>>().join("/")
+}
+
+/// Convert a module path to a relative file name
+pub fn module_file_name(module_path: &str) -> PathBuf {
+    let mut path: PathBuf = module_segments(module_path).collect();
+    path.set_extension("txt");
+    path
+}
+
+/// Collect the paths of all `import` statements, including nested ones
+pub fn import_paths(statements: &[Statement]) -> Vec<String> {
+    let mut imports = Imports(Vec::new());
+    imports.visit_block(statements);
+    imports.0
+}
+
+struct Imports(Vec<String>);
+
+impl Visitor for Imports {
+    fn visit_import(&mut self, path: &str) {
+        self.0.push(path.to_string());
+    }
+
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_file_name_accepts_both_separators() {
+        assert_eq!(
+            module_file_name("Games/Fishing/FishingGame"),
+            PathBuf::from("Games")
+                .join("Fishing")
+                .join("FishingGame.txt")
+        );
+        assert_eq!(
+            module_file_name("Games\\StoneBoxman\\StoneBoxmanMain"),
+            PathBuf::from("Games")
+                .join("StoneBoxman")
+                .join("StoneBoxmanMain.txt")
+        );
+    }
+
+    #[test]
+    fn test_module_paths_stay_inside_the_root() {
+        assert_eq!(
+            module_file_name("../../Games/./Fishing"),
+            PathBuf::from("Games").join("Fishing.txt")
+        );
+        assert_eq!(module_name("/UI\\..\\Menu"), "UI/Menu");
+    }
+}
@@ -0,0 +1,193 @@
+//! Test quick fixes offered for diagnostics
+
+use std::path::PathBuf;
+use stonescript_lsp::providers::{CodeActionProvider, DiagnosticsProvider};
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{CodeAction, CodeActionOrCommand, Range, TextEdit, Url};
+
+fn test_scripts() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.pop();
+    path.pop();
+    path.push("test_scripts");
+    path
+}
+
+/// Quick fixes for all diagnostics of an (unsaved) script in test_scripts
+fn quick_fixes(source: &str) -> Vec<CodeAction> {
+    let ast = parse_source(source).expect("Failed to parse");
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+    let uri = Url::from_file_path(test_scripts().join("CodeActionTest.txt")).unwrap();
+    let workspace = Workspace::new();
+    workspace.set_roots(vec![test_scripts()]);
+
+    let diagnostics =
+        DiagnosticsProvider::new().provide_diagnostics(&ast, source, &scope, &uri, &workspace);
+    CodeActionProvider::new()
+        .provide_code_actions(
+            &ast,
+            source,
+            &scope,
+            &uri,
+            Range::default(),
+            &diagnostics,
+            &workspace,
+        )
+        .into_iter()
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => action,
+            CodeActionOrCommand::Command(command) => panic!("Unexpected command {:?}", command),
+        })
+        .collect()
+}
+
+/// Apply the action titled `title` and return the edited source
+fn apply(source: &str, title: &str) -> String {
+    let actions = quick_fixes(source);
+    let action = actions
+        .iter()
+        .find(|action| action.title == title)
+        .unwrap_or_else(|| {
+            let titles: Vec<_> = actions.iter().map(|a| a.title.as_str()).collect();
+            panic!("No action {:?} among {:?}", title, titles)
+        });
+    let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    assert_eq!(changes.len(), 1);
+    let mut edits: Vec<&TextEdit> = changes.values().next().unwrap().iter().collect();
+
+    // Apply from the end so earlier positions stay valid
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut lines: Vec<String> = source.split('\n').map(str::to_string).collect();
+    for edit in edits {
+        let offset = |line: u32, character: u32| -> usize {
+            lines[..line as usize]
+                .iter()
+                .map(|l| l.len() + 1)
+                .sum::<usize>()
+                + character as usize
+        };
+        let mut text = lines.join("\n");
+        let start = offset(edit.range.start.line, edit.range.start.character);
+        let end = offset(edit.range.end.line, edit.range.end.character);
+        text.replace_range(start..end, &edit.new_text);
+        lines = text.split('\n').map(str::to_string).collect();
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn test_did_you_mean_property() {
+    let source = "?foe.hpp < 5\n  >low\n";
+    assert_eq!(
+        apply(source, "Did you mean `foe.hp`?"),
+        "?foe.hp < 5\n  >low\n"
+    );
+
+    let actions = quick_fixes(source);
+    assert_eq!(actions[0].is_preferred, Some(true));
+    assert_eq!(actions[0].diagnostics.as_ref().unwrap().len(), 1);
+}
+
+#[test]
+fn test_did_you_mean_function() {
+    assert_eq!(
+        apply("var b = ui.AddButon()\n", "Did you mean `ui.AddButton`?"),
+        "var b = ui.AddButton()\n"
+    );
+    assert_eq!(
+        apply(
+            "func DrawBox(x)\n  return x\nDrawBx(1)\n",
+            "Did you mean `DrawBox`?"
+        ),
+        "func DrawBox(x)\n  return x\nDrawBox(1)\n"
+    );
+}
+
+#[test]
+fn test_did_you_mean_game_id() {
+    assert_eq!(
+        apply("?loc = deadwod\n  >a\n", "Did you mean `deadwood`?"),
+        "?loc = deadwood\n  >a\n"
+    );
+    // Only the text inside the quotes is replaced
+    assert_eq!(
+        apply(
+            "?item.GetCooldown(\"bardich\") <= 0\n  >a\n",
+            "Did you mean `bardiche`?"
+        ),
+        "?item.GetCooldown(\"bardiche\") <= 0\n  >a\n"
+    );
+    assert_eq!(
+        apply("?cooldown.hatchett <= 0\n  >a\n", "Did you mean `hatchet`?"),
+        "?cooldown.hatchet <= 0\n  >a\n"
+    );
+}
+
+#[test]
+fn test_declare_variable() {
+    assert_eq!(
+        apply("count = 1\n>@count@\n", "Declare variable with `var`"),
+        "var count = 1\n>@count@\n"
+    );
+
+    // Compound assignments can't become declarations
+    assert!(quick_fixes("var a = 1\ncount += a\n").is_empty());
+}
+
+#[test]
+fn test_remove_unused_variable() {
+    assert_eq!(
+        apply(
+            "func F()\n  var unused = 1\n  return 2\n",
+            "Remove unused variable `unused`"
+        ),
+        "func F()\n  return 2\n"
+    );
+
+    // A call in the initializer is kept for its side effects
+    assert_eq!(
+        apply(
+            "func F()\n  var b = ui.AddButton()\n  return 2\n",
+            "Remove unused variable `b`"
+        ),
+        "func F()\n  ui.AddButton()\n  return 2\n"
+    );
+}
+
+#[test]
+fn test_add_missing_argument() {
+    let defs = "func Add(a, b)\n  return a + b\n";
+    assert_eq!(
+        apply(&format!("{}Add(1)\n", defs), "Add missing argument"),
+        format!("{}Add(1, b)\n", defs)
+    );
+    assert_eq!(
+        apply(&format!("{}var x = Add()\n", defs), "Add missing arguments"),
+        format!("{}var x = Add(a, b)\n", defs)
+    );
+    // The `)` is found in the text, wherever the call ends
+    assert_eq!(
+        apply(&format!("{}Add(1\n^)\n", defs), "Add missing argument"),
+        format!("{}Add(1\n^, b)\n", defs)
+    );
+    assert_eq!(
+        apply(
+            &format!("{}Add((1) ) // sum\n", defs),
+            "Add missing argument"
+        ),
+        format!("{}Add((1) , b) // sum\n", defs)
+    );
+}
+
+#[test]
+fn test_add_import() {
+    assert_eq!(
+        apply(
+            "import Components/Vector\nDrawBorder(1, 2)\n",
+            "Add `import Games/Fishing/FishingShop`"
+        ),
+        "import Components/Vector\nimport Games/Fishing/FishingShop\nDrawBorder(1, 2)\n"
+    );
+}
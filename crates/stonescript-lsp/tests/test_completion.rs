@@ -0,0 +1,284 @@
+//! Test completion contexts
+
+use std::path::PathBuf;
+use stonescript_lsp::config::UserSnippet;
+use stonescript_lsp::data::SNIPPETS;
+use stonescript_lsp::providers::completion::CompletionProvider;
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, InsertTextFormat, Position, Url,
+};
+
+/// URI of an (unsaved) script in the test_scripts folder, so that `new`
+/// paths resolve against it
+fn script_uri() -> Url {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.pop();
+    path.pop();
+    path.push("test_scripts");
+    path.push("CompletionTest.txt");
+    Url::from_file_path(path).unwrap()
+}
+
+/// Complete at the end of `line`, typed after the already parsed `defs`
+fn complete(defs: &str, line: &str) -> Vec<CompletionItem> {
+    let ast = parse_source(defs).expect("Failed to parse");
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+
+    let source = format!("{}{}", defs, line);
+    let position = Position {
+        line: defs.lines().count() as u32,
+        character: line.encode_utf16().count() as u32,
+    };
+
+    CompletionProvider::new().provide_completion(
+        &ast,
+        position,
+        &source,
+        &scope,
+        &script_uri(),
+        &Workspace::new(),
+    )
+}
+
+fn labels(items: &[CompletionItem]) -> Vec<&str> {
+    items.iter().map(|item| item.label.as_str()).collect()
+}
+
+fn labels_contain(items: &[CompletionItem], label: &str) -> bool {
+    items.iter().any(|item| item.label == label)
+}
+
+#[test]
+fn test_completion_after_location_comparison() {
+    let items = complete("", "?loc = ro");
+    let labels = labels(&items);
+    assert!(labels.contains(&"rocky"), "Should suggest locations");
+    assert!(!labels.contains(&"var"), "Should not suggest keywords");
+
+    // Same on an indented else-if branch comparing the id property
+    let items = complete("?hp < 5\n  >low\n", ":?loc.id ! ");
+    assert!(labels_contain(&items, "rocky"));
+}
+
+#[test]
+fn test_member_completion_on_chain() {
+    let items = complete("", "ui.root.");
+    assert!(
+        labels_contain(&items, "visible"),
+        "Should suggest Panel properties"
+    );
+    assert!(
+        labels_contain(&items, "Add"),
+        "Should suggest Panel methods"
+    );
+
+    let items = complete("var b = ui.AddButton()\n", "b.");
+    assert!(labels_contain(&items, "SetPressed"));
+}
+
+#[test]
+fn test_member_completion_filtered_by_component_kind() {
+    let items = complete("var t = ui.AddText()\n", "t.");
+    assert!(labels_contain(&items, "align"));
+    assert!(
+        labels_contain(&items, "x"),
+        "Should include shared properties"
+    );
+    assert!(!labels_contain(&items, "SetPressed"));
+    assert!(!labels_contain(&items, "children"));
+
+    // Components created inside a function body
+    let items = complete(
+        "func Make()\n  var a = ui.AddAnim(\"x\")\n  return a\n",
+        "  a.",
+    );
+    assert!(labels_contain(&items, "Play"));
+    assert!(!labels_contain(&items, "Add"));
+}
+
+#[test]
+fn test_member_completion_for_arrays_and_strings() {
+    let items = complete("var list = [1, 2]\n", "?list.");
+    assert!(labels_contain(&items, "Add"));
+    assert!(labels_contain(&items, "Count"));
+    assert!(labels_contain(&items, "Contains"));
+
+    let items = complete("var name = \"abc\"\n", ">@name.");
+    assert!(labels_contain(&items, "Size"));
+    assert!(!labels_contain(&items, "Count"));
+}
+
+#[test]
+fn test_member_completion_for_new_objects() {
+    let items = complete("var v = new Components/Vector\n", "v.");
+    assert!(labels_contain(&items, "Init"));
+    assert!(labels_contain(&items, "GetDistance"));
+    assert!(
+        labels_contain(&items, "x"),
+        "Should include module variables"
+    );
+    assert!(
+        !labels_contain(&items, "deltaX"),
+        "Should not include function locals"
+    );
+}
+
+#[test]
+fn test_member_completion_in_interpolation() {
+    let items = complete("", ">Gold @loc.");
+    assert!(labels_contain(&items, "gp"));
+    assert!(labels_contain(&items, "stars"));
+
+    // Plain output text has nothing to complete
+    assert!(complete("", ">Gold lo").is_empty());
+}
+
+#[test]
+fn test_argument_completion() {
+    let items = complete("", "music.Play(");
+    assert!(labels_contain(&items, "deadwood"));
+
+    // Arguments of other functions take any expression
+    let items = complete("var count = 3\n", "x = math.Max(1, co");
+    assert!(labels_contain(&items, "count"));
+    assert!(labels_contain(&items, "math"));
+    assert!(!labels_contain(&items, "deadwood"));
+}
+
+#[test]
+fn test_command_argument_completion() {
+    let items = complete("", "equipL ");
+    assert!(labels_contain(&items, "poison"));
+
+    let items = complete("", "  activate ");
+    assert!(labels_contain(&items, "potion"));
+}
+
+#[test]
+fn test_statement_completion_includes_user_functions() {
+    let items = complete("func DrawBox(x, y)\n  return x\n", "Dr");
+    assert!(labels_contain(&items, "DrawBox"));
+    assert!(labels_contain(&items, "var"));
+}
+
+#[test]
+fn test_import_path_completion() {
+    let items = complete("", "import Ga");
+    assert!(labels_contain(&items, "Games"));
+    assert!(
+        labels_contain(&items, "Fishing"),
+        "Should list script files"
+    );
+    assert!(!labels_contain(&items, "Fishing.txt"));
+
+    let games = items.iter().find(|item| item.label == "Games").unwrap();
+    assert_eq!(games.insert_text.as_deref(), Some("Games/"));
+}
+
+#[test]
+fn test_new_path_completion_folder_by_folder() {
+    let items = complete("", "var game = new Games/Fishing/");
+    let fishing_game = items
+        .iter()
+        .find(|item| item.label == "FishingGame")
+        .expect("Should list files of Games/Fishing");
+    match &fishing_game.documentation {
+        Some(Documentation::MarkupContent(content)) => {
+            assert!(content.value.starts_with("Deadwood Fishing"))
+        }
+        other => panic!("Expected a preview of the file comments, got {:?}", other),
+    }
+
+    // Backslash separators, as accepted by the game
+    let items = complete("", "import Games\\StoneBoxman\\StoneBox");
+    assert!(labels_contain(&items, "StoneBoxmanMain"));
+    let items = complete("", "import Games\\");
+    let folder = items.iter().find(|item| item.label == "Fishing").unwrap();
+    assert_eq!(folder.insert_text.as_deref(), Some("Fishing\\"));
+
+    // A folder and a script of the same name are both offered
+    let blackjack = items.iter().filter(|item| item.label == "Blackjack");
+    assert_eq!(blackjack.count(), 2);
+}
+
+fn snippets(items: &[CompletionItem]) -> Vec<&CompletionItem> {
+    items
+        .iter()
+        .filter(|item| item.kind == Some(CompletionItemKind::SNIPPET))
+        .collect()
+}
+
+#[test]
+fn test_snippets_offered_where_statements_start() {
+    let items = complete("?hp < 5\n", "  fo");
+    let snippets = snippets(&items);
+    let for_range = snippets
+        .iter()
+        .find(|item| item.label == "for")
+        .expect("Should offer the for snippet");
+    assert_eq!(
+        for_range.insert_text_format,
+        Some(InsertTextFormat::SNIPPET)
+    );
+    assert!(snippets.iter().any(|item| item.label == "ui.AddButton"));
+
+    // Not inside expressions
+    assert!(complete("", "var x = fo")
+        .iter()
+        .all(|item| item.kind != Some(CompletionItemKind::SNIPPET)));
+    assert!(complete("", "?loc = ro")
+        .iter()
+        .all(|item| item.kind != Some(CompletionItemKind::SNIPPET)));
+}
+
+#[test]
+fn test_builtin_snippets_expand_to_valid_scripts() {
+    let tab_stop = regex::Regex::new(r"\$\{\d+:([^}]*)\}|\$\{\d+\}|\$\d+").unwrap();
+    for snippet in SNIPPETS {
+        let expanded = tab_stop.replace_all(snippet.body, "$1");
+        let expanded = expanded.replace("condition", "hp < 5");
+        assert!(
+            parse_source(&expanded).is_ok(),
+            "Snippet {} should expand to a valid script:\n{}",
+            snippet.label,
+            expanded
+        );
+    }
+}
+
+#[test]
+fn test_user_snippets_from_configuration() {
+    let ast = parse_source("").unwrap();
+    let scope = ScopeAnalyzer::new();
+    let provider = CompletionProvider::new();
+    provider.set_user_snippets(vec![UserSnippet {
+        label: "lowhp".to_string(),
+        body: "?hp < ${1:5}\n  activate potion".to_string(),
+        description: Some("Drink a potion when low".to_string()),
+    }]);
+
+    let items = provider.provide_completion(
+        &ast,
+        Position {
+            line: 0,
+            character: 3,
+        },
+        "low",
+        &scope,
+        &script_uri(),
+        &Workspace::new(),
+    );
+    let item = items
+        .iter()
+        .find(|item| item.label == "lowhp")
+        .expect("Should offer the user snippet");
+    assert_eq!(item.kind, Some(CompletionItemKind::SNIPPET));
+    assert_eq!(item.detail.as_deref(), Some("Drink a potion when low"));
+    assert_eq!(
+        item.insert_text.as_deref(),
+        Some("?hp < ${1:5}\n  activate potion")
+    );
+}
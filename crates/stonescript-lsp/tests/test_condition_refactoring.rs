@@ -0,0 +1,152 @@
+//! Test rewriting conditions: chains, nesting, inverting and merging
+
+use stonescript_lsp::providers::CodeActionProvider;
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{CodeAction, CodeActionOrCommand, Position, Range, TextEdit, Url};
+
+/// Refactorings offered with the cursor at the start of `line`
+fn refactorings(source: &str, line: u32) -> Vec<CodeAction> {
+    let ast = parse_source(source).expect("Failed to parse");
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+    let uri = Url::parse("file:///scripts/Refactor.txt").unwrap();
+    let range = Range::new(Position::new(line, 0), Position::new(line, 0));
+
+    CodeActionProvider::new()
+        .provide_code_actions(&ast, source, &scope, &uri, range, &[], &Workspace::new())
+        .into_iter()
+        .filter_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => Some(action),
+            CodeActionOrCommand::Command(_) => None,
+        })
+        .collect()
+}
+
+fn find<'a>(actions: &'a [CodeAction], title: &str) -> Option<&'a CodeAction> {
+    actions.iter().find(|action| action.title == title)
+}
+
+/// Apply the action titled `title` and return the edited source
+fn apply(source: &str, line: u32, title: &str) -> String {
+    let actions = refactorings(source, line);
+    let action = find(&actions, title).unwrap_or_else(|| {
+        let titles: Vec<_> = actions.iter().map(|a| a.title.as_str()).collect();
+        panic!("No action {:?} among {:?}", title, titles)
+    });
+    let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    let mut edits: Vec<&TextEdit> = changes.values().next().unwrap().iter().collect();
+
+    // Apply from the end so earlier positions stay valid
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut text = source.to_string();
+    for edit in edits {
+        let offset = |position: Position| -> usize {
+            let line_start: usize = text
+                .split('\n')
+                .take(position.line as usize)
+                .map(|line| line.len() + 1)
+                .sum();
+            line_start + position.character as usize
+        };
+        let (start, end) = (offset(edit.range.start), offset(edit.range.end));
+        text.replace_range(start..end, &edit.new_text);
+    }
+
+    assert!(
+        parse_source(&text).is_ok(),
+        "Refactored script should parse:\n{}",
+        text
+    );
+    text
+}
+
+#[test]
+fn test_chain_to_nested_and_back() {
+    let chain = "\
+func Report()
+  ?hp < 5
+    >low
+  :?hp < 10 // getting there
+    >mid
+  :
+    >high
+";
+    let nested = "\
+func Report()
+  ?hp < 5
+    >low
+  :
+    ?hp < 10 // getting there
+      >mid
+    :
+      >high
+";
+    assert_eq!(apply(chain, 1, "Convert to nested conditions"), nested);
+    assert_eq!(apply(chain, 3, "Convert to nested conditions"), nested);
+    assert_eq!(apply(nested, 1, "Convert to else-if chain"), chain);
+
+    // Only the else branch holding a single condition can be flattened
+    let source = "?hp < 5\n  >low\n:\n  >ok\n  ?hp < 10\n    >mid\n";
+    assert!(find(&refactorings(source, 0), "Convert to else-if chain").is_none());
+}
+
+#[test]
+fn test_invert_condition() {
+    assert_eq!(
+        apply(
+            "?hp < 10 & foe.hp > 5\n  >a\n:\n  >b\n",
+            0,
+            "Invert condition"
+        ),
+        "?hp >= 10 | foe.hp <= 5\n  >b\n:\n  >a\n"
+    );
+    assert_eq!(
+        apply(
+            "?loc = caves & (foe = boss | foe.count > 2)\n  >a\n:\n  >b\n",
+            0,
+            "Invert condition"
+        ),
+        "?loc != caves | foe != boss & foe.count <= 2\n  >b\n:\n  >a\n"
+    );
+    assert_eq!(
+        apply(
+            "?!(a | b) // both off\n  >a\n:\n  >b\n",
+            0,
+            "Invert condition"
+        ),
+        "?a | b // both off\n  >b\n:\n  >a\n"
+    );
+    assert_eq!(
+        apply("?ready\n  >a\n:\n  >b\n", 0, "Invert condition"),
+        "?!ready\n  >b\n:\n  >a\n"
+    );
+
+    // Without an else branch there is nothing to swap with
+    assert!(find(&refactorings("?ready\n  >a\n", 0), "Invert condition").is_none());
+}
+
+#[test]
+fn test_merge_nested_conditions() {
+    assert_eq!(
+        apply(
+            "?loc = caves\n  ?hp < 5 | foe.count > 3\n    >run\n",
+            0,
+            "Merge nested conditions"
+        ),
+        "?loc = caves & (hp < 5 | foe.count > 3)\n  >run\n"
+    );
+
+    // Else branches would change meaning
+    let source = "?loc = caves\n  ?hp < 5\n    >run\n  :\n    >stay\n";
+    assert!(find(&refactorings(source, 0), "Merge nested conditions").is_none());
+    // So would other statements next to the inner condition
+    let source = "?loc = caves\n  >here\n  ?hp < 5\n    >run\n";
+    assert!(find(&refactorings(source, 0), "Merge nested conditions").is_none());
+}
+
+#[test]
+fn test_condition_refactorings_need_condition_line() {
+    let source = "?hp < 5\n  >low\n:?hp < 10\n  >mid\n";
+    assert!(refactorings(source, 1).is_empty());
+}
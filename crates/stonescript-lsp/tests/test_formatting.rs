@@ -0,0 +1,108 @@
+//! Formatting a script gives text that parses to the same statements
+
+use std::fs;
+use std::path::PathBuf;
+use stonescript_lsp::providers::formatting::{format_source, Indent};
+use stonescript_parser::{parse_source, Program, Statement, VisitorMut};
+use walkdir::WalkDir;
+
+/// Scripts with `ascii` art, block comments and `^` continuations, which
+/// must be reindented rather than left as they are
+const SAMPLE: &[&str] = &[
+    "Games/SwordGame.txt",
+    "Pets/Dragon.txt",
+    "Pets/SnowBunny.txt",
+    "UI/FoeStateTracker.txt",
+    "UI/InfoFloat.txt",
+];
+
+fn scripts_dir() -> PathBuf {
+    let mut test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_dir.pop();
+    test_dir.pop();
+    test_dir.push("test_scripts");
+    test_dir
+}
+
+/// Parse a script, leaving out the statements the parser makes of blank
+/// lines
+fn statements(source: &str) -> Option<Program> {
+    struct BlankLines;
+    impl VisitorMut for BlankLines {
+        fn visit_block_mut(&mut self, statements: &mut Vec<Statement>) {
+            statements.retain(|s| !matches!(s, Statement::Empty));
+            for statement in statements {
+                self.visit_statement_mut(statement);
+            }
+        }
+    }
+    let mut program = parse_source(source).ok()?;
+    BlankLines.visit_program_mut(&mut program);
+    Some(program)
+}
+
+/// Format each script with tabs, returning those left as they are, those
+/// formatted differently when formatted again and those that parse
+/// differently once formatted
+fn format_scripts(
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let test_dir = scripts_dir();
+    let mut unformatted = Vec::new();
+    let mut unstable = Vec::new();
+    let mut changed = Vec::new();
+    for path in paths {
+        let name = path.strip_prefix(&test_dir).unwrap().display().to_string();
+        let content = fs::read_to_string(&path).unwrap();
+        let formatted = format_source(&content, Indent::Tabs);
+        if formatted == content {
+            unformatted.push(name);
+            continue;
+        }
+        if format_source(&formatted, Indent::Tabs) != formatted {
+            unstable.push(name.clone());
+        }
+        let same = match (statements(&content), statements(&formatted)) {
+            (Some(before), Some(after)) => after.eq_ignoring_spans(&before),
+            _ => false,
+        };
+        if !same {
+            changed.push(name);
+        }
+    }
+    (unformatted, unstable, changed)
+}
+
+#[test]
+fn test_sample_scripts_format() {
+    let (unformatted, unstable, changed) =
+        format_scripts(SAMPLE.iter().map(|script| scripts_dir().join(script)));
+    assert!(
+        unformatted.is_empty(),
+        "left as they are: {:?}",
+        unformatted
+    );
+    assert!(
+        unstable.is_empty(),
+        "formatted differently twice: {:?}",
+        unstable
+    );
+    assert!(changed.is_empty(), "parse differently: {:?}", changed);
+}
+
+/// Formats every script, which takes minutes in a debug build; run it with
+/// `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn test_all_scripts_parse_the_same_once_formatted() {
+    let scripts = WalkDir::new(scripts_dir())
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("txt"))
+        .map(|e| e.into_path());
+    // Scripts the parser would read differently once reindented are left as
+    // they are, and a few whose blocks the parser does not see settle only
+    // after a second pass, so neither is checked here
+    let (_, _, changed) = format_scripts(scripts);
+    assert!(changed.is_empty(), "parse differently: {:?}", changed);
+}
@@ -0,0 +1,258 @@
+//! Test extract function, extract variable and inline variable
+
+use stonescript_lsp::providers::CodeActionProvider;
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{CodeAction, CodeActionOrCommand, Position, Range, TextEdit, Url};
+
+/// Refactorings offered for a selection given as (line, character) pairs
+fn refactorings(source: &str, start: (u32, u32), end: (u32, u32)) -> Vec<CodeAction> {
+    let ast = parse_source(source).expect("Failed to parse");
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+    let uri = Url::parse("file:///scripts/Refactor.txt").unwrap();
+    let range = Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1));
+
+    CodeActionProvider::new()
+        .provide_code_actions(&ast, source, &scope, &uri, range, &[], &Workspace::new())
+        .into_iter()
+        .filter_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => Some(action),
+            CodeActionOrCommand::Command(_) => None,
+        })
+        .collect()
+}
+
+fn find<'a>(actions: &'a [CodeAction], title: &str) -> Option<&'a CodeAction> {
+    actions.iter().find(|action| action.title == title)
+}
+
+/// Apply the action titled `title` and return the edited source
+fn apply(source: &str, start: (u32, u32), end: (u32, u32), title: &str) -> String {
+    let actions = refactorings(source, start, end);
+    let action = find(&actions, title).unwrap_or_else(|| {
+        let titles: Vec<_> = actions.iter().map(|a| a.title.as_str()).collect();
+        panic!("No action {:?} among {:?}", title, titles)
+    });
+    let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    let mut edits: Vec<&TextEdit> = changes.values().next().unwrap().iter().collect();
+
+    // Apply from the end so earlier positions stay valid
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut text = source.to_string();
+    for edit in edits {
+        let offset = |position: Position| -> usize {
+            let line_start: usize = text
+                .split('\n')
+                .take(position.line as usize)
+                .map(|line| line.len() + 1)
+                .sum();
+            line_start + position.character as usize
+        };
+        let (start, end) = (offset(edit.range.start), offset(edit.range.end));
+        text.replace_range(start..end, &edit.new_text);
+    }
+
+    assert!(
+        parse_source(&text).is_ok(),
+        "Refactored script should parse:\n{}",
+        text
+    );
+    text
+}
+
+#[test]
+fn test_extract_function_with_parameters_and_return() {
+    let source = "\
+func Label(x, y)
+  var t = ui.AddText()
+  t.x = x
+  t.y = y
+  t.text = \"hi\"
+  return t
+";
+    assert_eq!(
+        apply(source, (1, 0), (4, 0), "Extract into function"),
+        "\
+func NewFunction(x, y)
+  var t = ui.AddText()
+  t.x = x
+  t.y = y
+  return t
+
+func Label(x, y)
+  var t = NewFunction(x, y)
+  t.text = \"hi\"
+  return t
+"
+    );
+}
+
+#[test]
+fn test_extract_function_at_top_level() {
+    let source = "var count = 0\n?hp < 5\n  count++\n  >low\n";
+    assert_eq!(
+        apply(source, (1, 0), (3, 6), "Extract into function"),
+        "var count = 0\nfunc NewFunction()\n  ?hp < 5\n    count++\n    >low\n\nNewFunction()\n"
+    );
+}
+
+#[test]
+fn test_extract_function_assigning_outer_local() {
+    let source = "\
+func Sum(list)
+  var total = 0
+  for e : list
+    total = total + e
+  return total
+";
+    assert_eq!(
+        apply(source, (2, 0), (3, 21), "Extract into function"),
+        "\
+func NewFunction(list, total)
+  for e : list
+    total = total + e
+  return total
+
+func Sum(list)
+  var total = 0
+  total = NewFunction(list, total)
+  return total
+"
+    );
+}
+
+#[test]
+fn test_extract_function_not_offered() {
+    // Two variables would have to be returned
+    let source = "func F()\n  var a = 1\n  var b = 2\n  return a + b\n";
+    assert!(find(
+        &refactorings(source, (1, 0), (2, 11)),
+        "Extract into function"
+    )
+    .is_none());
+
+    // Returning from the selection
+    let source = "func F()\n  var a = 1\n  return a\n";
+    assert!(find(
+        &refactorings(source, (1, 0), (2, 10)),
+        "Extract into function"
+    )
+    .is_none());
+
+    // Part of a statement
+    let source = "?hp < 5\n  >low\n  >lower\n";
+    assert!(find(
+        &refactorings(source, (0, 0), (1, 6)),
+        "Extract into function"
+    )
+    .is_none());
+}
+
+#[test]
+fn test_extract_variable() {
+    let source = "?foe.hp * 2 < foe.maxhp\n  >low\n";
+    assert_eq!(
+        apply(source, (0, 1), (0, 11), "Extract into variable"),
+        "var value = foe.hp * 2\n?value < foe.maxhp\n  >low\n"
+    );
+
+    // Indented like the statement, named apart from existing variables
+    let source = "var value = 1\nfunc F()\n  >@foe.hp * 2@ \n";
+    assert_eq!(
+        apply(source, (2, 4), (2, 14), "Extract into variable"),
+        "var value = 1\nfunc F()\n  var value2 = foe.hp * 2\n  >@value2@ \n"
+    );
+}
+
+#[test]
+fn test_extract_variable_not_offered() {
+    let extract = |source: &str, start, end| {
+        find(&refactorings(source, start, end), "Extract into variable").is_some()
+    };
+
+    // Else-if conditions are only evaluated when the first one fails
+    assert!(!extract("?hp < 5\n  >a\n:?hp < 10\n  >b\n", (2, 2), (2, 8)));
+    // A name on its own, and a called function
+    assert!(!extract("var a = hp\n?a < 5\n  >a\n", (1, 1), (1, 2)));
+    assert!(!extract("var b = ui.AddButton()\n", (0, 8), (0, 20)));
+}
+
+#[test]
+fn test_inline_variable() {
+    let source = "\
+func F()
+  var limit = foe.maxhp / 2
+  ?foe.hp < limit
+    >@limit@
+";
+    let expected = "\
+func F()
+  ?foe.hp < (foe.maxhp / 2)
+    >@(foe.maxhp / 2)@
+";
+    assert_eq!(
+        apply(source, (1, 7), (1, 7), "Inline variable `limit`"),
+        expected
+    );
+    // Also from one of its uses
+    assert_eq!(
+        apply(source, (2, 13), (2, 13), "Inline variable `limit`"),
+        expected
+    );
+
+    // Operators and negative numbers keep their own parentheses
+    assert_eq!(
+        apply(
+            "func F(a)\n  var v = -1\n  return a-v\n",
+            (1, 6),
+            (1, 6),
+            "Inline variable `v`"
+        ),
+        "func F(a)\n  return a-(-1)\n"
+    );
+    assert_eq!(
+        apply(
+            "func F(a)\n  var v = !a\n  return v.x\n",
+            (1, 6),
+            (1, 6),
+            "Inline variable `v`"
+        ),
+        "func F(a)\n  return (!a).x\n"
+    );
+
+    // Script-level constants reach into functions, but not shadowed names
+    let source =
+        "var speed = 3\nfunc Move(x)\n  return x + speed\nfunc Other(speed)\n  return speed\n";
+    assert_eq!(
+        apply(source, (0, 5), (0, 5), "Inline variable `speed`"),
+        "func Move(x)\n  return x + 3\nfunc Other(speed)\n  return speed\n"
+    );
+}
+
+#[test]
+fn test_inline_variable_not_offered() {
+    let inline = |source: &str, position| {
+        refactorings(source, position, position)
+            .iter()
+            .any(|action| action.title.starts_with("Inline variable"))
+    };
+
+    // Reassigned
+    assert!(!inline(
+        "func F()\n  var a = 1\n  a = 2\n  return a\n",
+        (1, 6)
+    ));
+    // Reads something reassigned later
+    assert!(!inline(
+        "func F(x)\n  var a = x\n  x = 2\n  return a\n",
+        (1, 6)
+    ));
+    // Calls a function
+    assert!(!inline(
+        "func F()\n  var b = ui.AddButton()\n  return b\n",
+        (1, 6)
+    ));
+    // Script-level variables are initialized once
+    assert!(!inline("var start = totaltime\n>@start@\n", (0, 5)));
+}
@@ -0,0 +1,167 @@
+//! Test signature help for user-defined and imported functions
+
+use std::path::PathBuf;
+use stonescript_lsp::providers::signature_help::SignatureHelpProvider;
+use stonescript_lsp::utils::{ScopeAnalyzer, Workspace};
+use stonescript_parser::parse_source;
+use tower_lsp::lsp_types::{Documentation, Position, SignatureHelp, Url};
+
+/// URI of an (unsaved) script placed in the test_scripts folder, so that
+/// `import` and `new` paths resolve against it
+fn script_uri() -> Url {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.pop();
+    path.pop();
+    path.push("test_scripts");
+    path.push("SignatureHelpTest.txt");
+    Url::from_file_path(path).unwrap()
+}
+
+fn signature_help(source: &str, position: Position) -> Option<SignatureHelp> {
+    let ast = parse_source(source).expect("Failed to parse");
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+
+    SignatureHelpProvider::new().provide_signature_help(
+        &ast,
+        position,
+        source,
+        &scope,
+        &script_uri(),
+        &Workspace::new(),
+    )
+}
+
+#[test]
+fn test_signature_help_for_local_function() {
+    let source = r#"// Draw a box outline
+func DrawBox(x, y, w, h)
+  ?x * y > w - h
+    >inside
+
+DrawBox(1, 2, 3, 4)
+"#;
+
+    let help = signature_help(
+        source,
+        Position {
+            line: 5,
+            character: 11,
+        },
+    )
+    .expect("Should have signature help for DrawBox");
+
+    let signature = &help.signatures[0];
+    assert_eq!(
+        signature.label,
+        "func DrawBox(x: Int, y: Int, w: Int, h: Int)"
+    );
+    assert_eq!(help.active_parameter, Some(1));
+    match &signature.documentation {
+        Some(Documentation::MarkupContent(content)) => {
+            assert_eq!(content.value, "Draw a box outline")
+        }
+        other => panic!("Expected doc comment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_signature_help_while_typing_local_call() {
+    // The document no longer parses while the call is being typed, so the
+    // last good AST and the text before the cursor are used instead
+    let source = "func DrawBox(x, y, w, h)\n  return x\n";
+    let typing = "func DrawBox(x, y, w, h)\n  return x\nDrawBox(";
+
+    let ast = parse_source(source).unwrap();
+    let mut scope = ScopeAnalyzer::new();
+    scope.analyze_ast(&ast);
+
+    let help = SignatureHelpProvider::new()
+        .provide_signature_help(
+            &ast,
+            Position {
+                line: 2,
+                character: 8,
+            },
+            typing,
+            &scope,
+            &script_uri(),
+            &Workspace::new(),
+        )
+        .expect("Should have signature help for DrawBox");
+
+    assert!(help.signatures[0].label.starts_with("func DrawBox(x"));
+    assert_eq!(help.active_parameter, Some(0));
+}
+
+#[test]
+fn test_signature_help_for_new_object_method() {
+    let source =
+        "var vec = new Components/Vector\nvar other = new Components/Vector\nvec.Add(other)\n";
+
+    let help = signature_help(
+        source,
+        Position {
+            line: 2,
+            character: 9,
+        },
+    )
+    .expect("Should have signature help for Vector.Add");
+
+    let signature = &help.signatures[0];
+    assert_eq!(signature.label, "func Vector.Add(otherVect)");
+    match &signature.documentation {
+        Some(Documentation::MarkupContent(content)) => {
+            assert!(content.value.contains("Components/Vector"))
+        }
+        other => panic!("Expected module documentation, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_signature_help_for_imported_function() {
+    let source = "import Components/Vector\nInit(1, 2)\n";
+
+    let help = signature_help(
+        source,
+        Position {
+            line: 1,
+            character: 8,
+        },
+    )
+    .expect("Should have signature help for imported Init");
+
+    assert_eq!(help.signatures[0].label, "func Init(_x, _y)");
+    assert_eq!(help.active_parameter, Some(1));
+}
+
+#[test]
+fn test_module_paths_stay_inside_the_workspace() {
+    let scripts = script_uri().to_file_path().unwrap();
+    let scripts = scripts.parent().unwrap();
+    let games = scripts.join("Games");
+    let document = Url::from_file_path(games.join("Fishing").join("Test.txt")).unwrap();
+
+    // Folders from the document's up to the root containing it
+    let workspace = Workspace::new();
+    workspace.set_roots(vec![games.clone()]);
+    assert_eq!(
+        workspace.search_roots(&document),
+        vec![games.clone(), games.join("Fishing")]
+    );
+    // `Components` is above the root, so it is out of reach
+    assert_eq!(workspace.resolve_path(&document, "Components/Vector"), None);
+
+    workspace.set_roots(vec![scripts.to_path_buf()]);
+    assert_eq!(
+        workspace.resolve_path(&document, "Components/Vector"),
+        Some(scripts.join("Components").join("Vector.txt"))
+    );
+
+    // With no roots, only the document's own folder
+    let workspace = Workspace::new();
+    assert_eq!(
+        workspace.search_roots(&document),
+        vec![games.join("Fishing")]
+    );
+}
@@ -3,7 +3,7 @@
 use std::fmt;
 
 /// Position in source code (line and column, 0-indexed)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
@@ -16,7 +16,7 @@ impl Position {
 }
 
 /// Span representing a range in source code
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -42,6 +42,26 @@ pub struct Program {
     pub span: Span,
 }
 
+impl Program {
+    /// Structural equality that ignores where in the source the
+    /// statements are
+    pub fn eq_ignoring_spans(&self, other: &Program) -> bool {
+        blocks_eq_ignoring_spans(&self.statements, &other.statements)
+    }
+}
+
+fn blocks_eq_ignoring_spans(a: &[Statement], b: &[Statement]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignoring_spans(b))
+}
+
+fn options_eq_ignoring_spans(a: Option<&Expression>, b: Option<&Expression>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignoring_spans(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
 /// A single statement in StoneScript
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
@@ -56,10 +76,23 @@ pub enum Statement {
     /// Command statement (e.g., equip, loadout, activate)
     Command {
         name: String,
+        /// Expressions evaluated by the command; for commands with
+        /// `structured` arguments, only the `@expr@` interpolations
         args: Vec<Expression>,
+        /// Arguments of `equip`, `equipL`, `equipR`, `loadout`, `brew` and
+        /// `activate`, which take words rather than expressions
+        structured: Option<CommandArgs>,
         span: Span,
     },
-    /// Variable declaration or assignment
+    /// Variable declaration: var name [= initializer]
+    VarDeclaration {
+        name: String,
+        /// Span of the declared identifier
+        name_span: Span,
+        initializer: Option<Expression>,
+        span: Span,
+    },
+    /// Assignment to a variable, property or array element
     Assignment {
         target: Expression,
         op: AssignmentOperator,
@@ -68,8 +101,14 @@ pub enum Statement {
     },
     /// Output statement (>)
     Output {
+        kind: OutputKind,
+        /// Offset given to the advanced print variants: `>oX,Y,text`
         position: Option<(Expression, Expression)>,
+        /// Optional `#color` or `@var@` argument after the position
+        color: Option<Expression>,
         text: Expression,
+        /// Whether the text contains `@expr@` interpolations
+        interpolated: bool,
         span: Span,
     },
     /// Expression used as statement (e.g., function call)
@@ -77,7 +116,10 @@ pub enum Statement {
     /// Function definition
     FunctionDefinition {
         name: String,
+        name_span: Span,
         params: Vec<String>,
+        /// Span of each parameter name, parallel to `params`
+        param_spans: Vec<Span>,
         body: Vec<Statement>,
         span: Span,
     },
@@ -89,6 +131,7 @@ pub enum Statement {
     /// For loop with range
     For {
         variable: String,
+        variable_span: Span,
         range: (Expression, Expression),
         body: Vec<Statement>,
         span: Span,
@@ -96,6 +139,7 @@ pub enum Statement {
     /// For-in loop (iterate over collection)
     ForIn {
         variable: String,
+        variable_span: Span,
         collection: Expression,
         body: Vec<Statement>,
         span: Span,
@@ -109,6 +153,8 @@ pub enum Statement {
     /// Import statement
     Import {
         path: String, // e.g., "Cosmetics/TrainAdventure/Main"
+        /// Span of each folder or file name in the path
+        segment_spans: Vec<Span>,
         span: Span,
     },
     /// Comment (// or /* */)
@@ -122,6 +168,7 @@ impl Statement {
         match self {
             Statement::Condition { span, .. } => Some(*span),
             Statement::Command { span, .. } => Some(*span),
+            Statement::VarDeclaration { span, .. } => Some(*span),
             Statement::Assignment { span, .. } => Some(*span),
             Statement::Output { span, .. } => Some(*span),
             Statement::ExpressionStatement { span, .. } => Some(*span),
@@ -135,6 +182,222 @@ impl Statement {
             Statement::Empty => None,
         }
     }
+
+    /// Structural equality that ignores where in the source the
+    /// statements are
+    pub fn eq_ignoring_spans(&self, other: &Statement) -> bool {
+        match (self, other) {
+            (
+                Statement::Condition {
+                    condition: c,
+                    then_block: t,
+                    else_ifs: i,
+                    else_block: e,
+                    ..
+                },
+                Statement::Condition {
+                    condition: d,
+                    then_block: u,
+                    else_ifs: j,
+                    else_block: f,
+                    ..
+                },
+            ) => {
+                c.eq_ignoring_spans(d)
+                    && blocks_eq_ignoring_spans(t, u)
+                    && i.len() == j.len()
+                    && i.iter().zip(j).all(|(a, b)| {
+                        a.condition.eq_ignoring_spans(&b.condition)
+                            && blocks_eq_ignoring_spans(&a.block, &b.block)
+                    })
+                    && match (e, f) {
+                        (Some(a), Some(b)) => blocks_eq_ignoring_spans(a, b),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            (
+                Statement::Command {
+                    name: n,
+                    args: a,
+                    structured: s,
+                    ..
+                },
+                Statement::Command {
+                    name: m,
+                    args: b,
+                    structured: t,
+                    ..
+                },
+            ) => {
+                n == m
+                    && a.len() == b.len()
+                    && a.iter().zip(b).all(|(a, b)| a.eq_ignoring_spans(b))
+                    && match (s, t) {
+                        (Some(s), Some(t)) => s.eq_ignoring_spans(t),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            (
+                Statement::VarDeclaration {
+                    name: n,
+                    initializer: a,
+                    ..
+                },
+                Statement::VarDeclaration {
+                    name: m,
+                    initializer: b,
+                    ..
+                },
+            ) => n == m && options_eq_ignoring_spans(a.as_ref(), b.as_ref()),
+            (
+                Statement::Assignment {
+                    target: t,
+                    op: o,
+                    value: v,
+                    ..
+                },
+                Statement::Assignment {
+                    target: u,
+                    op: p,
+                    value: w,
+                    ..
+                },
+            ) => o == p && t.eq_ignoring_spans(u) && v.eq_ignoring_spans(w),
+            (
+                Statement::Output {
+                    kind: k,
+                    position: p,
+                    color: c,
+                    text: t,
+                    interpolated: i,
+                    ..
+                },
+                Statement::Output {
+                    kind: l,
+                    position: q,
+                    color: d,
+                    text: u,
+                    interpolated: j,
+                    ..
+                },
+            ) => {
+                k == l
+                    && i == j
+                    && match (p, q) {
+                        (Some((x, y)), Some((a, b))) => {
+                            x.eq_ignoring_spans(a) && y.eq_ignoring_spans(b)
+                        }
+                        (None, None) => true,
+                        _ => false,
+                    }
+                    && options_eq_ignoring_spans(c.as_ref(), d.as_ref())
+                    && t.eq_ignoring_spans(u)
+            }
+            (
+                Statement::ExpressionStatement { expression: a, .. },
+                Statement::ExpressionStatement { expression: b, .. },
+            ) => a.eq_ignoring_spans(b),
+            (
+                Statement::FunctionDefinition {
+                    name: n,
+                    params: p,
+                    body: a,
+                    ..
+                },
+                Statement::FunctionDefinition {
+                    name: m,
+                    params: q,
+                    body: b,
+                    ..
+                },
+            ) => n == m && p == q && blocks_eq_ignoring_spans(a, b),
+            (Statement::Return { value: a, .. }, Statement::Return { value: b, .. }) => {
+                options_eq_ignoring_spans(a.as_ref(), b.as_ref())
+            }
+            (
+                Statement::For {
+                    variable: v,
+                    range: (s, e),
+                    body: a,
+                    ..
+                },
+                Statement::For {
+                    variable: w,
+                    range: (t, f),
+                    body: b,
+                    ..
+                },
+            ) => {
+                v == w
+                    && s.eq_ignoring_spans(t)
+                    && e.eq_ignoring_spans(f)
+                    && blocks_eq_ignoring_spans(a, b)
+            }
+            (
+                Statement::ForIn {
+                    variable: v,
+                    collection: c,
+                    body: a,
+                    ..
+                },
+                Statement::ForIn {
+                    variable: w,
+                    collection: d,
+                    body: b,
+                    ..
+                },
+            ) => v == w && c.eq_ignoring_spans(d) && blocks_eq_ignoring_spans(a, b),
+            (
+                Statement::While {
+                    condition: c,
+                    body: a,
+                    ..
+                },
+                Statement::While {
+                    condition: d,
+                    body: b,
+                    ..
+                },
+            ) => c.eq_ignoring_spans(d) && blocks_eq_ignoring_spans(a, b),
+            (Statement::Import { path: a, .. }, Statement::Import { path: b, .. }) => a == b,
+            (Statement::Comment(a, _), Statement::Comment(b, _)) => a == b,
+            (Statement::Empty, Statement::Empty) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Print statement variants, selected by the character after `>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    /// `>text`, printed at the top of the screen
+    Plain,
+    /// `>oX,Y,text`, relative to the player
+    Overlay,
+    /// `>hX,Y,text`, relative to the player on the HUD layer
+    Hud,
+    /// `>cX,Y,text`, relative to the center of the screen
+    Center,
+    /// `>fX,Y,text`, relative to the targeted foe
+    Foe,
+    /// `` >`X,Y,text ``, relative to the top-left corner of the screen
+    Screen,
+}
+
+impl OutputKind {
+    /// Prefix written after `>` for this variant
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            OutputKind::Plain => "",
+            OutputKind::Overlay => "o",
+            OutputKind::Hud => "h",
+            OutputKind::Center => "c",
+            OutputKind::Foe => "f",
+            OutputKind::Screen => "`",
+        }
+    }
 }
 
 /// Else-if branch
@@ -145,6 +408,114 @@ pub struct ElseIf {
     pub span: Span,
 }
 
+/// Arguments of the commands that take words rather than expressions
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandArgs {
+    /// `equip vigor crossbow *8 +5`: what the item has to match
+    Equip(Vec<ItemCriterion>),
+    /// `loadout 2`
+    Loadout(Vec<CommandValue>),
+    /// `brew tar+bronze`: the ingredients between the `+`s
+    Brew(Vec<CommandValue>),
+    /// `activate R`: the ability or item to use
+    Activate(Vec<CommandValue>),
+}
+
+impl CommandArgs {
+    /// Structural equality that ignores where in the source the arguments
+    /// are
+    pub fn eq_ignoring_spans(&self, other: &CommandArgs) -> bool {
+        fn values_eq(a: &[CommandValue], b: &[CommandValue]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignoring_spans(b))
+        }
+
+        match (self, other) {
+            (CommandArgs::Equip(a), CommandArgs::Equip(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|criteria| match criteria {
+                        (ItemCriterion::Stars(a, _), ItemCriterion::Stars(b, _))
+                        | (ItemCriterion::Enchant(a, _), ItemCriterion::Enchant(b, _)) => a == b,
+                        (ItemCriterion::Value(a), ItemCriterion::Value(b)) => {
+                            a.eq_ignoring_spans(b)
+                        }
+                        _ => false,
+                    })
+            }
+            (CommandArgs::Loadout(a), CommandArgs::Loadout(b))
+            | (CommandArgs::Brew(a), CommandArgs::Brew(b))
+            | (CommandArgs::Activate(a), CommandArgs::Activate(b)) => values_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// The words given to the command, leaving out `equip`'s star and
+    /// enchantment levels
+    pub fn values(&self) -> Vec<&CommandValue> {
+        match self {
+            CommandArgs::Equip(criteria) => criteria
+                .iter()
+                .filter_map(|criterion| match criterion {
+                    ItemCriterion::Value(value) => Some(value),
+                    _ => None,
+                })
+                .collect(),
+            CommandArgs::Loadout(values)
+            | CommandArgs::Brew(values)
+            | CommandArgs::Activate(values) => values.iter().collect(),
+        }
+    }
+}
+
+/// One criterion of an `equip` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemCriterion {
+    /// `*7`: the star level
+    Stars(i64, Span),
+    /// `+5`: the enchantment level
+    Enchant(i64, Span),
+    /// A word of the item's name, an element or another filter
+    Value(CommandValue),
+}
+
+impl ItemCriterion {
+    pub fn span(&self) -> Span {
+        match self {
+            ItemCriterion::Stars(_, span) | ItemCriterion::Enchant(_, span) => *span,
+            ItemCriterion::Value(value) => value.span(),
+        }
+    }
+}
+
+/// A word of a command's arguments
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandValue {
+    Number(i64, Span),
+    Word(String, Span),
+    /// `@expr@`, evaluated when the command runs
+    Interpolated(Expression),
+}
+
+impl CommandValue {
+    pub fn span(&self) -> Span {
+        match self {
+            CommandValue::Number(_, span) | CommandValue::Word(_, span) => *span,
+            CommandValue::Interpolated(expression) => expression.span(),
+        }
+    }
+
+    /// Structural equality that ignores where in the source the values are
+    pub fn eq_ignoring_spans(&self, other: &CommandValue) -> bool {
+        match (self, other) {
+            (CommandValue::Number(a, _), CommandValue::Number(b, _)) => a == b,
+            (CommandValue::Word(a, _), CommandValue::Word(b, _)) => a == b,
+            (CommandValue::Interpolated(a), CommandValue::Interpolated(b)) => {
+                a.eq_ignoring_spans(b)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Expression types
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
@@ -188,6 +559,8 @@ pub enum Expression {
     /// Object instantiation: new path
     New {
         path: String, // e.g., "Games/Fishing/FishingGame"
+        /// Span of each folder or file name in the path
+        segment_spans: Vec<Span>,
         span: Span,
     },
     /// Array literal (e.g., [], [1, 2, 3])
@@ -221,6 +594,95 @@ impl Expression {
             Expression::IndexAccess { span, .. } => *span,
         }
     }
+
+    /// Structural equality that ignores where in the source the
+    /// expressions are
+    pub fn eq_ignoring_spans(&self, other: &Expression) -> bool {
+        fn all_eq(a: &[Expression], b: &[Expression]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignoring_spans(b))
+        }
+
+        match (self, other) {
+            (Expression::Integer(a, _), Expression::Integer(b, _)) => a == b,
+            (Expression::Float(a, _), Expression::Float(b, _)) => a == b,
+            (Expression::Boolean(a, _), Expression::Boolean(b, _)) => a == b,
+            (Expression::String(a, _), Expression::String(b, _))
+            | (Expression::Identifier(a, _), Expression::Identifier(b, _)) => a == b,
+            (
+                Expression::Property {
+                    object: a,
+                    property: p,
+                    ..
+                },
+                Expression::Property {
+                    object: b,
+                    property: q,
+                    ..
+                },
+            ) => p == q && a.eq_ignoring_spans(b),
+            (
+                Expression::FunctionCall {
+                    function: f,
+                    args: a,
+                    ..
+                },
+                Expression::FunctionCall {
+                    function: g,
+                    args: b,
+                    ..
+                },
+            ) => f.eq_ignoring_spans(g) && all_eq(a, b),
+            (
+                Expression::BinaryOp {
+                    left: l,
+                    op: o,
+                    right: r,
+                    ..
+                },
+                Expression::BinaryOp {
+                    left: m,
+                    op: p,
+                    right: s,
+                    ..
+                },
+            ) => o == p && l.eq_ignoring_spans(m) && r.eq_ignoring_spans(s),
+            (
+                Expression::UnaryOp {
+                    op: o, operand: a, ..
+                },
+                Expression::UnaryOp {
+                    op: p, operand: b, ..
+                },
+            ) => o == p && a.eq_ignoring_spans(b),
+            (Expression::Interpolation(a, _), Expression::Interpolation(b, _)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|parts| match parts {
+                        (InterpolationPart::Text(a, _), InterpolationPart::Text(b, _)) => a == b,
+                        (InterpolationPart::Expression(a), InterpolationPart::Expression(b)) => {
+                            a.eq_ignoring_spans(b)
+                        }
+                        _ => false,
+                    })
+            }
+            (Expression::New { path: a, .. }, Expression::New { path: b, .. }) => a == b,
+            (Expression::Array { elements: a, .. }, Expression::Array { elements: b, .. }) => {
+                all_eq(a, b)
+            }
+            (
+                Expression::IndexAccess {
+                    object: a,
+                    index: i,
+                    ..
+                },
+                Expression::IndexAccess {
+                    object: b,
+                    index: j,
+                    ..
+                },
+            ) => a.eq_ignoring_spans(b) && i.eq_ignoring_spans(j),
+            _ => false,
+        }
+    }
 }
 
 /// Parts of string interpolation
@@ -273,6 +735,25 @@ pub enum AssignmentOperator {
     ModuloAssign,   // %=
 }
 
+impl BinaryOperator {
+    /// Binding strength; higher binds tighter. Operators of equal
+    /// precedence associate to the left.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual => 3,
+            BinaryOperator::Add | BinaryOperator::Subtract => 4,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 5,
+        }
+    }
+}
+
 impl fmt::Display for BinaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -303,3 +784,16 @@ impl fmt::Display for UnaryOperator {
         }
     }
 }
+
+impl fmt::Display for AssignmentOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignmentOperator::Assign => write!(f, "="),
+            AssignmentOperator::AddAssign => write!(f, "+="),
+            AssignmentOperator::SubtractAssign => write!(f, "-="),
+            AssignmentOperator::MultiplyAssign => write!(f, "*="),
+            AssignmentOperator::DivideAssign => write!(f, "/="),
+            AssignmentOperator::ModuloAssign => write!(f, "%="),
+        }
+    }
+}
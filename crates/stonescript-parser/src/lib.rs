@@ -4,13 +4,16 @@
 
 pub mod ast;
 pub mod parser;
+pub mod printer;
 
 // Re-export main types and functions
 pub use ast::{
-    AssignmentOperator, BinaryOperator, ElseIf, Expression, InterpolationPart, Position, Program,
-    Span, Statement, UnaryOperator,
+    AssignmentOperator, BinaryOperator, CommandArgs, CommandValue, ElseIf, Expression,
+    InterpolationPart, ItemCriterion, OutputKind, Position, Program, Span, Statement,
+    UnaryOperator,
 };
 pub use parser::parse;
+pub use printer::Printer;
 
 /// Parse StoneScript source code and return a Program AST
 pub fn parse_source(source: &str) -> Result<Program, String> {
@@ -22,74 +25,19 @@ pub fn parse_source(source: &str) -> Result<Program, String> {
 /// Visitor trait for traversing the AST
 pub trait Visitor {
     fn visit_program(&mut self, program: &Program) {
-        for statement in &program.statements {
+        self.visit_block(&program.statements);
+    }
+
+    /// A sequence of statements: the program, or the body of a condition
+    /// branch, function or loop
+    fn visit_block(&mut self, statements: &[Statement]) {
+        for statement in statements {
             self.visit_statement(statement);
         }
     }
 
     fn visit_statement(&mut self, statement: &Statement) {
-        match statement {
-            Statement::Condition {
-                condition,
-                then_block,
-                else_ifs,
-                else_block,
-                ..
-            } => {
-                self.visit_condition(condition, then_block, else_ifs, else_block);
-            }
-            Statement::Command { name, args, .. } => {
-                self.visit_command(name, args);
-            }
-            Statement::Assignment { target, value, .. } => {
-                self.visit_assignment(target, value);
-            }
-            Statement::Output { position, text, .. } => {
-                self.visit_output(position, text);
-            }
-
-            Statement::ExpressionStatement { expression, .. } => {
-                self.visit_expression(expression);
-            }
-            Statement::FunctionDefinition {
-                name, params, body, ..
-            } => {
-                self.visit_function_definition(name, params, body);
-            }
-            Statement::Return { value, .. } => {
-                self.visit_return(value);
-            }
-            Statement::For {
-                variable,
-                range,
-                body,
-                ..
-            } => {
-                self.visit_for(variable, range, body);
-            }
-            Statement::ForIn {
-                variable,
-                collection,
-                body,
-                ..
-            } => {
-                self.visit_for_in(variable, collection, body);
-            }
-            Statement::While {
-                condition, body, ..
-            } => {
-                self.visit_while(condition, body);
-            }
-            Statement::Import { path, .. } => {
-                self.visit_import(path);
-            }
-            Statement::Comment(text, _) => {
-                self.visit_comment(text);
-            }
-            Statement::Empty => {
-                self.visit_empty();
-            }
-        }
+        walk_statement(self, statement);
     }
 
     fn visit_condition(
@@ -100,19 +48,13 @@ pub trait Visitor {
         else_block: &Option<Vec<Statement>>,
     ) {
         self.visit_expression(condition);
-        for stmt in then_block {
-            self.visit_statement(stmt);
-        }
+        self.visit_block(then_block);
         for else_if in else_ifs {
             self.visit_expression(&else_if.condition);
-            for stmt in &else_if.block {
-                self.visit_statement(stmt);
-            }
+            self.visit_block(&else_if.block);
         }
         if let Some(stmts) = else_block {
-            for stmt in stmts {
-                self.visit_statement(stmt);
-            }
+            self.visit_block(stmts);
         }
     }
 
@@ -122,16 +64,31 @@ pub trait Visitor {
         }
     }
 
+    fn visit_var_declaration(&mut self, _name: &str, initializer: &Option<Expression>) {
+        if let Some(value) = initializer {
+            self.visit_expression(value);
+        }
+    }
+
     fn visit_assignment(&mut self, target: &Expression, value: &Expression) {
         self.visit_expression(target);
         self.visit_expression(value);
     }
 
-    fn visit_output(&mut self, position: &Option<(Expression, Expression)>, text: &Expression) {
+    fn visit_output(
+        &mut self,
+        _kind: OutputKind,
+        position: &Option<(Expression, Expression)>,
+        color: &Option<Expression>,
+        text: &Expression,
+    ) {
         if let Some((x, y)) = position {
             self.visit_expression(x);
             self.visit_expression(y);
         }
+        if let Some(color) = color {
+            self.visit_expression(color);
+        }
         self.visit_expression(text);
     }
 
@@ -140,9 +97,7 @@ pub trait Visitor {
     fn visit_import(&mut self, _path: &str) {}
 
     fn visit_function_definition(&mut self, _name: &str, _params: &[String], body: &[Statement]) {
-        for statement in body {
-            self.visit_statement(statement);
-        }
+        self.visit_block(body);
     }
 
     fn visit_return(&mut self, value: &Option<Expression>) {
@@ -154,68 +109,318 @@ pub trait Visitor {
     fn visit_for(&mut self, _variable: &str, range: &(Expression, Expression), body: &[Statement]) {
         self.visit_expression(&range.0);
         self.visit_expression(&range.1);
-        for statement in body {
-            self.visit_statement(statement);
-        }
+        self.visit_block(body);
     }
 
     fn visit_for_in(&mut self, _variable: &str, collection: &Expression, body: &[Statement]) {
         self.visit_expression(collection);
-        for statement in body {
-            self.visit_statement(statement);
-        }
+        self.visit_block(body);
     }
 
     fn visit_while(&mut self, condition: &Expression, body: &[Statement]) {
         self.visit_expression(condition);
-        for statement in body {
-            self.visit_statement(statement);
-        }
+        self.visit_block(body);
     }
 
     fn visit_empty(&mut self) {}
 
     fn visit_expression(&mut self, expression: &Expression) {
-        match expression {
-            Expression::Integer(..) => {}
-            Expression::Float(..) => {}
-            Expression::Boolean(..) => {}
-            Expression::String(..) => {}
-            Expression::Identifier(..) => {}
-            Expression::New { .. } => {}
-            Expression::Property { object, .. } => {
-                self.visit_expression(object);
+        walk_expression(self, expression);
+    }
+}
+
+/// Dispatch a statement to the matching `visit_*` method; the default
+/// behavior of [`Visitor::visit_statement`], for overrides that still want
+/// to descend
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Condition {
+            condition,
+            then_block,
+            else_ifs,
+            else_block,
+            ..
+        } => {
+            visitor.visit_condition(condition, then_block, else_ifs, else_block);
+        }
+        Statement::Command { name, args, .. } => {
+            visitor.visit_command(name, args);
+        }
+        Statement::VarDeclaration {
+            name, initializer, ..
+        } => {
+            visitor.visit_var_declaration(name, initializer);
+        }
+        Statement::Assignment { target, value, .. } => {
+            visitor.visit_assignment(target, value);
+        }
+        Statement::Output {
+            kind,
+            position,
+            color,
+            text,
+            ..
+        } => {
+            visitor.visit_output(*kind, position, color, text);
+        }
+
+        Statement::ExpressionStatement { expression, .. } => {
+            visitor.visit_expression(expression);
+        }
+        Statement::FunctionDefinition {
+            name, params, body, ..
+        } => {
+            visitor.visit_function_definition(name, params, body);
+        }
+        Statement::Return { value, .. } => {
+            visitor.visit_return(value);
+        }
+        Statement::For {
+            variable,
+            range,
+            body,
+            ..
+        } => {
+            visitor.visit_for(variable, range, body);
+        }
+        Statement::ForIn {
+            variable,
+            collection,
+            body,
+            ..
+        } => {
+            visitor.visit_for_in(variable, collection, body);
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            visitor.visit_while(condition, body);
+        }
+        Statement::Import { path, .. } => {
+            visitor.visit_import(path);
+        }
+        Statement::Comment(text, _) => {
+            visitor.visit_comment(text);
+        }
+        Statement::Empty => {
+            visitor.visit_empty();
+        }
+    }
+}
+
+/// Visit the sub-expressions of an expression; the default behavior of
+/// [`Visitor::visit_expression`], for overrides that still want to descend
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Integer(..) => {}
+        Expression::Float(..) => {}
+        Expression::Boolean(..) => {}
+        Expression::String(..) => {}
+        Expression::Identifier(..) => {}
+        Expression::New { .. } => {}
+        Expression::Property { object, .. } => {
+            visitor.visit_expression(object);
+        }
+        Expression::FunctionCall { function, args, .. } => {
+            visitor.visit_expression(function);
+            for arg in args {
+                visitor.visit_expression(arg);
             }
-            Expression::FunctionCall { function, args, .. } => {
-                self.visit_expression(function);
-                for arg in args {
-                    self.visit_expression(arg);
+        }
+        Expression::BinaryOp { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::UnaryOp { operand, .. } => {
+            visitor.visit_expression(operand);
+        }
+        Expression::Interpolation(parts, _) => {
+            for part in parts {
+                if let InterpolationPart::Expression(expr) = part {
+                    visitor.visit_expression(expr);
                 }
             }
-            Expression::BinaryOp { left, right, .. } => {
-                self.visit_expression(left);
-                self.visit_expression(right);
+        }
+        Expression::Array { elements, .. } => {
+            for elem in elements {
+                visitor.visit_expression(elem);
+            }
+        }
+        Expression::IndexAccess { object, index, .. } => {
+            visitor.visit_expression(object);
+            visitor.visit_expression(index);
+        }
+    }
+}
+
+/// Counterpart of [`Visitor`] for passes that change the tree in place
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        self.visit_block_mut(&mut program.statements);
+    }
+
+    /// A sequence of statements, which a pass may also add to or take from
+    fn visit_block_mut(&mut self, statements: &mut Vec<Statement>) {
+        for statement in statements {
+            self.visit_statement_mut(statement);
+        }
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+}
+
+/// Visit the expressions and nested blocks of a statement; the default
+/// behavior of [`VisitorMut::visit_statement_mut`]
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::Condition {
+            condition,
+            then_block,
+            else_ifs,
+            else_block,
+            ..
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_block_mut(then_block);
+            for else_if in else_ifs {
+                visitor.visit_expression_mut(&mut else_if.condition);
+                visitor.visit_block_mut(&mut else_if.block);
             }
-            Expression::UnaryOp { operand, .. } => {
-                self.visit_expression(operand);
+            if let Some(block) = else_block {
+                visitor.visit_block_mut(block);
             }
-            Expression::Interpolation(parts, _) => {
-                for part in parts {
-                    if let InterpolationPart::Expression(expr) = part {
-                        self.visit_expression(expr);
-                    }
+        }
+        Statement::Command {
+            args, structured, ..
+        } => {
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+            // The structured arguments hold the interpolations again
+            let values: Vec<&mut CommandValue> = match structured {
+                Some(CommandArgs::Equip(criteria)) => criteria
+                    .iter_mut()
+                    .filter_map(|criterion| match criterion {
+                        ItemCriterion::Value(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect(),
+                Some(
+                    CommandArgs::Loadout(values)
+                    | CommandArgs::Brew(values)
+                    | CommandArgs::Activate(values),
+                ) => values.iter_mut().collect(),
+                None => Vec::new(),
+            };
+            for value in values {
+                if let CommandValue::Interpolated(expression) = value {
+                    visitor.visit_expression_mut(expression);
                 }
             }
-            Expression::Array { elements, .. } => {
-                for elem in elements {
-                    self.visit_expression(elem);
+        }
+        Statement::VarDeclaration { initializer, .. } => {
+            if let Some(value) = initializer {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        Statement::Assignment { target, value, .. } => {
+            visitor.visit_expression_mut(target);
+            visitor.visit_expression_mut(value);
+        }
+        Statement::Output {
+            position,
+            color,
+            text,
+            ..
+        } => {
+            if let Some((x, y)) = position {
+                visitor.visit_expression_mut(x);
+                visitor.visit_expression_mut(y);
+            }
+            if let Some(color) = color {
+                visitor.visit_expression_mut(color);
+            }
+            visitor.visit_expression_mut(text);
+        }
+        Statement::ExpressionStatement { expression, .. } => {
+            visitor.visit_expression_mut(expression);
+        }
+        Statement::FunctionDefinition { body, .. } => {
+            visitor.visit_block_mut(body);
+        }
+        Statement::Return { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        Statement::For { range, body, .. } => {
+            visitor.visit_expression_mut(&mut range.0);
+            visitor.visit_expression_mut(&mut range.1);
+            visitor.visit_block_mut(body);
+        }
+        Statement::ForIn {
+            collection, body, ..
+        } => {
+            visitor.visit_expression_mut(collection);
+            visitor.visit_block_mut(body);
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_block_mut(body);
+        }
+        Statement::Import { .. } | Statement::Comment(..) | Statement::Empty => {}
+    }
+}
+
+/// Visit the sub-expressions of an expression; the default behavior of
+/// [`VisitorMut::visit_expression_mut`]
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Integer(..)
+        | Expression::Float(..)
+        | Expression::Boolean(..)
+        | Expression::String(..)
+        | Expression::Identifier(..)
+        | Expression::New { .. } => {}
+        Expression::Property { object, .. } => {
+            visitor.visit_expression_mut(object);
+        }
+        Expression::FunctionCall { function, args, .. } => {
+            visitor.visit_expression_mut(function);
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::BinaryOp { left, right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        Expression::UnaryOp { operand, .. } => {
+            visitor.visit_expression_mut(operand);
+        }
+        Expression::Interpolation(parts, _) => {
+            for part in parts {
+                if let InterpolationPart::Expression(expression) = part {
+                    visitor.visit_expression_mut(expression);
                 }
             }
-            Expression::IndexAccess { object, index, .. } => {
-                self.visit_expression(object);
-                self.visit_expression(index);
+        }
+        Expression::Array { elements, .. } => {
+            for element in elements {
+                visitor.visit_expression_mut(element);
             }
-            _ => {}
+        }
+        Expression::IndexAccess { object, index, .. } => {
+            visitor.visit_expression_mut(object);
+            visitor.visit_expression_mut(index);
         }
     }
 }
@@ -246,6 +451,15 @@ impl Default for IdentifierCollector {
 }
 
 impl Visitor for IdentifierCollector {
+    fn visit_var_declaration(&mut self, name: &str, initializer: &Option<Expression>) {
+        if !self.identifiers.contains(&name.to_string()) {
+            self.identifiers.push(name.to_string());
+        }
+        if let Some(value) = initializer {
+            self.visit_expression(value);
+        }
+    }
+
     fn visit_assignment(&mut self, target: &Expression, value: &Expression) {
         // Extract identifier from target if it's a simple identifier
         if let Expression::Identifier(name, _) = target {
@@ -356,6 +570,51 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_var_declaration() {
+        let program = parse_source("var x\n  var total = [\n^1, 2]\ntotal = 3").unwrap();
+        assert_eq!(program.statements.len(), 3);
+
+        match &program.statements[0] {
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer,
+                ..
+            } => {
+                assert_eq!(name, "x");
+                assert_eq!(
+                    *name_span,
+                    Span::new(Position::new(0, 4), Position::new(0, 5))
+                );
+                assert!(initializer.is_none());
+            }
+            other => panic!("Expected VarDeclaration, got {:?}", other),
+        }
+
+        // The name span stays on the identifier when the value spans lines
+        match &program.statements[1] {
+            Statement::VarDeclaration {
+                name,
+                name_span,
+                initializer: Some(Expression::Array { .. }),
+                ..
+            } => {
+                assert_eq!(name, "total");
+                assert_eq!(
+                    *name_span,
+                    Span::new(Position::new(1, 6), Position::new(1, 11))
+                );
+            }
+            other => panic!("Expected VarDeclaration with array, got {:?}", other),
+        }
+
+        assert!(matches!(
+            &program.statements[2],
+            Statement::Assignment { .. }
+        ));
+    }
+
     #[test]
     fn test_parse_very_simple_function_call() {
         let result = parse_source("clamp(1,2,3)");
@@ -614,9 +873,14 @@ mod tests {
         if let Ok(program) = result {
             assert_eq!(program.statements.len(), 1);
             match &program.statements[0] {
-                Statement::Command { name, args, .. } => {
+                Statement::Command {
+                    name, structured, ..
+                } => {
                     assert_eq!(name, "equip");
-                    assert_eq!(args.len(), 1);
+                    match structured {
+                        Some(CommandArgs::Equip(criteria)) => assert_eq!(criteria.len(), 1),
+                        other => panic!("Expected equip arguments, got {:?}", other),
+                    }
                 }
                 _ => panic!("Expected command statement"),
             }
@@ -780,8 +1044,14 @@ mod tests {
                     Statement::Assignment { target, value, .. } => {
                         self.visit_assignment(target, value);
                     }
-                    Statement::Output { position, text, .. } => {
-                        self.visit_output(position, text);
+                    Statement::Output {
+                        kind,
+                        position,
+                        color,
+                        text,
+                        ..
+                    } => {
+                        self.visit_output(*kind, position, color, text);
                     }
                     _ => {}
                 }
@@ -816,6 +1086,32 @@ mod tests {
         assert!(visitor.expression_count > 0);
     }
 
+    #[test]
+    fn test_visitor_mut() {
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn visit_block_mut(&mut self, statements: &mut Vec<Statement>) {
+                statements.retain(|statement| !matches!(statement, Statement::Comment(..)));
+                for statement in statements {
+                    self.visit_statement_mut(statement);
+                }
+            }
+
+            fn visit_expression_mut(&mut self, expression: &mut Expression) {
+                if let Expression::Identifier(name, _) = expression {
+                    name.make_ascii_uppercase();
+                }
+                walk_expression_mut(self, expression);
+            }
+        }
+
+        let mut program = parse_source("?hp < x\n  // heal\n  equip @y@").unwrap();
+        Rename.visit_program_mut(&mut program);
+        let expected = parse_source("?HP < X\n  equip @Y@").unwrap();
+        assert!(program.eq_ignoring_spans(&expected), "{:#?}", program);
+    }
+
     #[test]
     fn test_utf8_bom_handling() {
         let source_with_bom = "\u{FEFF}equip sword";
@@ -195,15 +195,35 @@ fn bare_string<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str, E
 }
 
 /// Parse a path string (for new/import): Games/Fishing/FishingGame
-fn path_string(input: &str) -> IResult<&str, String> {
-    recognize(separated_list1(
-        char('/'),
-        recognize(pair(
-            alt((alpha1, tag("_"))),
-            many0(alt((alphanumeric1, tag("_")))),
-        )),
-    ))(input)
-    .map(|(i, s)| (i, s.to_string()))
+/// The game also accepts backslashes: Games\StoneBoxman\StoneBoxmanMain
+/// Returns the path along with the span of each segment
+fn path_string<'a>(
+    input: &'a str,
+    ctx: &ParseContext<'a>,
+) -> IResult<&'a str, (String, Vec<Span>)> {
+    let (rest, segments) = separated_list1(alt((char('/'), char('\\'))), |i: &'a str| {
+        spanned(i, ctx, |i| {
+            recognize(pair(
+                alt((alpha1, tag("_"))),
+                many0(alt((alphanumeric1, tag("_")))),
+            ))(i)
+        })
+    })(input)?;
+
+    let path = &input[..input.len() - rest.len()];
+    let spans = segments.into_iter().map(|(_, span)| span).collect();
+    Ok((rest, (path.to_string(), spans)))
+}
+
+/// Run `parser` and pair its output with the span it consumed
+fn spanned<'a, O>(
+    input: &'a str,
+    ctx: &ParseContext<'a>,
+    mut parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> IResult<&'a str, (O, Span)> {
+    let start = ctx.offset(input);
+    let (input, output) = parser(input)?;
+    Ok((input, (output, ctx.make_span(start, ctx.offset(input)))))
 }
 
 /// Parse new expression: new Games/Fishing/FishingGame
@@ -211,12 +231,13 @@ fn new_expression<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str
     let start = ctx.offset(input);
     let (input, _) = tag("new")(input)?;
     let (input, _) = ws1(input)?;
-    let (input, path) = path_string(input)?;
+    let (input, (path, segment_spans)) = path_string(input, ctx)?;
     let end = ctx.offset(input);
     Ok((
         input,
         Expression::New {
             path,
+            segment_spans,
             span: ctx.make_span(start, end),
         },
     ))
@@ -227,12 +248,13 @@ fn import_statement<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a s
     let start = ctx.offset(input);
     let (input, _) = tag("import")(input)?;
     let (input, _) = ws1(input)?;
-    let (input, path) = path_string(input)?;
+    let (input, (path, segment_spans)) = path_string(input, ctx)?;
     let end = ctx.offset(input);
     Ok((
         input,
         Statement::Import {
             path,
+            segment_spans,
             span: ctx.make_span(start, end),
         },
     ))
@@ -360,15 +382,22 @@ fn lvalue_expression<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a
 /// Parse boolean literal (true or false)
 fn boolean_literal<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str, Expression> {
     let start = ctx.offset(input);
+    // `trueAnswer` is an identifier, not `true` followed by `Answer`
+    let ends_word = |rest: &str| {
+        !rest
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    };
 
     // Try "true"
-    if let Ok((rest, _)) = tag::<_, _, nom::error::Error<&str>>("true")(input) {
+    if let Some(rest) = input.strip_prefix("true").filter(|rest| ends_word(rest)) {
         let end = ctx.offset(rest);
         return Ok((rest, Expression::Boolean(true, ctx.make_span(start, end))));
     }
 
     // Try "false"
-    if let Ok((rest, _)) = tag::<_, _, nom::error::Error<&str>>("false")(input) {
+    if let Some(rest) = input.strip_prefix("false").filter(|rest| ends_word(rest)) {
         let end = ctx.offset(rest);
         return Ok((rest, Expression::Boolean(false, ctx.make_span(start, end))));
     }
@@ -652,15 +681,21 @@ fn array_literal<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str,
 
     let (input, _) = ws_multi(input)?;
 
-    let (input, elements) = separated_list0(
-        |i| {
-            let (i, _) = ws_multi(i)?;
-            let (i, _) = char(',')(i)?;
-            let (i, _) = ws_multi(i)?;
-            Ok((i, ()))
-        },
-        |i| expression(i, ctx),
-    )(input)?;
+    // An empty array is checked for first, as the bare string fallback
+    // would take the closing bracket for an element
+    let (input, elements) = if input.starts_with(']') || input.starts_with('］') {
+        (input, Vec::new())
+    } else {
+        separated_list0(
+            |i| {
+                let (i, _) = ws_multi(i)?;
+                let (i, _) = char(',')(i)?;
+                let (i, _) = ws_multi(i)?;
+                Ok((i, ()))
+            },
+            |i| expression(i, ctx),
+        )(input)?
+    };
 
     let (input, _) = ws_multi(input)?;
 
@@ -743,33 +778,77 @@ fn simple_expression<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a
     unary_expression(input, ctx)
 }
 
-/// Parse binary expression
-/// Parse binary expression (handles chained operators like a + b + c)
+/// Parse binary expression (handles chained operators like a + b * c).
+///
+/// Operands and operators are read left to right, then combined by
+/// precedence: `|`, `&`, comparisons, `+ -`, then `* / %` binding tightest.
 fn binary_expression<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str, Expression> {
-    let start = ctx.offset(input);
-    let (mut input, mut left) = simple_expression(input, ctx)?;
+    let mut start = ctx.offset(input);
+    let (mut input, first) = simple_expression(input, ctx)?;
+    let mut operands = vec![(first, start, ctx.offset(input))];
+    let mut operators = Vec::new();
 
     loop {
         let (next_input, _) = ws0(input)?;
 
         if let Ok((next_input, op)) = binary_operator(next_input) {
             let (next_input, _) = ws0(next_input)?;
+            start = ctx.offset(next_input);
             let (next_input, right) = simple_expression(next_input, ctx)?;
-            let end = ctx.offset(next_input);
+            operators.push(op);
+            operands.push((right, start, ctx.offset(next_input)));
+            input = next_input;
+        } else {
+            break;
+        }
+    }
+
+    Ok((input, combine_operands(operands, operators, ctx)))
+}
 
-            left = Expression::BinaryOp {
+/// Build the expression tree for `operands[0] op[0] operands[1] ...`, each
+/// operand given with its start and end offsets
+fn combine_operands<'a>(
+    operands: Vec<(Expression, usize, usize)>,
+    operators: Vec<BinaryOperator>,
+    ctx: &ParseContext<'a>,
+) -> Expression {
+    let mut output: Vec<(Expression, usize, usize)> = Vec::new();
+    let mut pending: Vec<BinaryOperator> = Vec::new();
+
+    let reduce = |output: &mut Vec<(Expression, usize, usize)>, op: BinaryOperator| {
+        let (right, _, end) = output.pop().expect("right operand");
+        let (left, start, _) = output.pop().expect("left operand");
+        output.push((
+            Expression::BinaryOp {
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
                 span: ctx.make_span(start, end),
-            };
-            input = next_input;
-        } else {
-            break;
+            },
+            start,
+            end,
+        ));
+    };
+
+    let mut operands = operands.into_iter();
+    output.extend(operands.next());
+    for (op, operand) in operators.into_iter().zip(operands) {
+        while let Some(&top) = pending.last() {
+            if top.precedence() < op.precedence() {
+                break;
+            }
+            pending.pop();
+            reduce(&mut output, top);
         }
+        pending.push(op);
+        output.push(operand);
+    }
+    while let Some(op) = pending.pop() {
+        reduce(&mut output, op);
     }
 
-    Ok((input, left))
+    output.pop().expect("expression").0
 }
 
 /// Parse any expression
@@ -848,102 +927,108 @@ fn interpolated_string<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'
 fn output_statement<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a str, Statement> {
     let start = ctx.offset(input);
     let (input, _) = char('>')(input)?;
-    // Optional 'o' prefix (e.g. >o x,y,ascii)
-    let (input, _) = opt(char('o'))(input)?;
-    let (input, _) = ws0(input)?;
-
-    // Unified position parsing:
-    // Supports:
-    // >x,y,ascii
-    // >`x,y,ascii
-    // >x,y,color,ascii
-    // >`x,y,color,ascii
-    // >`x,y,text
 
-    let (input, position) = opt(|input| {
-        // Optional backtick
-        let (input, _) = opt(char('`'))(input)?;
-
-        // Parse x
-        let (input, x) = expression(input, ctx)?;
-        let (input, _) = tuple((ws0, char(','), ws0))(input)?;
-
-        // Parse y
-        let (input, y) = expression(input, ctx)?;
-        let (input, _) = tuple((ws0, char(','), ws0))(input)?;
-
-        // Check for optional color
-        let peek = input.trim_start();
-        let (input, _) = if peek.starts_with('#') || peek.starts_with('@') {
-            // Parse color
-            let (input, _) = expression(input, ctx)?;
-            let (input, _) = tuple((ws0, char(','), ws0))(input)?;
-            (input, ())
-        } else {
-            (input, ())
-        };
-
-        Ok((input, (x, y)))
-    })(input)?;
+    // Advanced print variants:
+    // >oX,Y,text
+    // >hX,Y,#color,text
+    // >`X,Y,@color@,ascii
+    // A variant character only counts when a position follows it, so that
+    // `>hello` still prints "hello"
+    let (input, kind, position, color) = match output_position(input, ctx) {
+        Ok((input, (kind, x, y, color))) => (input, kind, Some((x, y)), color),
+        Err(_) => (input, OutputKind::Plain, None, None),
+    };
 
     let (input, _) = ws0(input)?;
 
     // Try to parse ASCII block first, then fall back to interpolated string
     let (input, text) = alt((|i| ascii_block(i, ctx), |i| interpolated_string(i, ctx)))(input)?;
+    let interpolated = matches!(
+        &text,
+        Expression::Interpolation(parts, _)
+            if parts.iter().any(|part| matches!(part, InterpolationPart::Expression(_)))
+    );
 
     let end = ctx.offset(input);
     Ok((
         input,
         Statement::Output {
+            kind,
             position,
+            color,
             text,
+            interpolated,
             span: ctx.make_span(start, end),
         },
     ))
 }
 
-/// Parse variable declaration or assignment
-fn var_assignment<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, Statement> {
+/// Parse the variant character, position and optional color of an
+/// advanced print, up to and including the comma before its text
+fn output_position<'a>(
+    input: &'a str,
+    ctx: &ParseContext<'a>,
+) -> IResult<&'a str, (OutputKind, Expression, Expression, Option<Expression>)> {
+    let (input, kind) = alt((
+        value(OutputKind::Overlay, char('o')),
+        value(OutputKind::Hud, char('h')),
+        value(OutputKind::Center, char('c')),
+        value(OutputKind::Foe, char('f')),
+        value(OutputKind::Screen, char('`')),
+    ))(input)?;
+    let (input, _) = ws0(input)?;
+
+    let (input, x) = expression(input, ctx)?;
+    let (input, _) = tuple((ws0, char(','), ws0))(input)?;
+    let (input, y) = expression(input, ctx)?;
+    let (input, _) = tuple((ws0, char(','), ws0))(input)?;
+
+    // A color is followed by another comma, otherwise `@var@` starts the text
+    let (input, color) = opt(|input: &'a str| {
+        if !input.starts_with('#') && !input.starts_with('@') {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Char,
+            )));
+        }
+        let (input, color) = expression(input, ctx)?;
+        let (input, _) = tuple((ws0, char(','), ws0))(input)?;
+        Ok((input, color))
+    })(input)?;
+
+    Ok((input, (kind, x, y, color)))
+}
+
+/// Parse variable declaration
+fn var_declaration<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, Statement> {
     let start = ctx.offset(input);
     let (input, _) = tag("var")(input)?;
     let (input, _) = ws1(input)?;
+    let name_start = ctx.offset(input);
     let (input, name) = identifier(input)?;
+    let name_span = ctx.make_span(name_start, ctx.offset(input));
     let (input, _) = ws0(input)?;
 
-    // Check if we have an assignment
-    if let Ok((next_input, _)) = char::<_, nom::error::Error<&str>>('=')(input) {
-        // We have an equals sign, so we MUST parse an expression
-        let (input, _) = ws_multi(next_input)?;
-        match expression(input, ctx) {
-            Ok((input, value)) => {
-                let end = ctx.offset(input);
-                let name_span = ctx.make_span(start, ctx.offset(&input) - value.span().end.column);
-                Ok((
-                    input,
-                    Statement::Assignment {
-                        target: Expression::Identifier(name.clone(), name_span),
-                        op: AssignmentOperator::Assign,
-                        value,
-                        span: ctx.make_span(start, end),
-                    },
-                ))
-            }
-            Err(e) => Err(e),
+    // An equals sign MUST be followed by an initializer expression
+    let (input, initializer) = match char::<_, nom::error::Error<&str>>('=')(input) {
+        Ok((input, _)) => {
+            let (input, _) = ws_multi(input)?;
+            let (input, value) = expression(input, ctx)?;
+            (input, Some(value))
         }
-    } else {
-        // No assignment, just declaration
-        let end = ctx.offset(input);
-        let name_span = ctx.make_span(start, end);
-        Ok((
-            input,
-            Statement::Assignment {
-                target: Expression::Identifier(name.clone(), name_span),
-                op: AssignmentOperator::Assign,
-                value: Expression::Integer(0, ctx.make_span(end, end)), // Default value
-                span: ctx.make_span(start, end),
-            },
-        ))
-    }
+        Err(_) => (input, None),
+    };
+
+    let end = ctx.offset(input);
+    Ok((
+        input,
+        Statement::VarDeclaration {
+            name,
+            name_span,
+            initializer,
+            span: ctx.make_span(start, end),
+        },
+    ))
 }
 
 /// Parse variable assignment
@@ -1009,7 +1094,7 @@ fn for_loop<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, S
     let start = ctx.offset(input);
     let (input, _) = tag("for")(input)?;
     let (input, _) = ws1(input)?;
-    let (input, variable) = identifier(input)?;
+    let (input, (variable, variable_span)) = spanned(input, ctx, identifier)?;
     let (input, _) = ws0(input)?;
 
     // Check if it's a range-based loop (=) or collection-based loop (:)
@@ -1036,6 +1121,7 @@ fn for_loop<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, S
             input,
             Statement::For {
                 variable,
+                variable_span,
                 range: (start_expr, end_expr),
                 body,
                 span: ctx.make_span(start, end),
@@ -1055,6 +1141,7 @@ fn for_loop<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, S
             input,
             Statement::ForIn {
                 variable,
+                variable_span,
                 collection,
                 body,
                 span: ctx.make_span(start, end),
@@ -1071,13 +1158,16 @@ fn function_definition<'a>(
     let start = ctx.offset(input);
     let (input, _) = tag("func")(input)?;
     let (input, _) = ws1(input)?;
-    let (input, name) = identifier(input)?;
+    let (input, (name, name_span)) = spanned(input, ctx, identifier)?;
     let (input, _) = ws0(input)?;
     let (input, _) = char('(')(input)?;
     let (input, _) = ws0(input)?;
 
     // Parse parameters
-    let (input, params) = separated_list0(delimited(ws0, char(','), ws0), identifier)(input)?;
+    let (input, params) = separated_list0(delimited(ws0, char(','), ws0), |i| {
+        spanned(i, ctx, identifier)
+    })(input)?;
+    let (params, param_spans) = params.into_iter().unzip();
 
     let (input, _) = ws0(input)?;
     let (input, _) = char(')')(input)?;
@@ -1097,7 +1187,9 @@ fn function_definition<'a>(
         input,
         Statement::FunctionDefinition {
             name,
+            name_span,
             params,
+            param_spans,
             body,
             span: ctx.make_span(start, end),
         },
@@ -1123,7 +1215,24 @@ fn command_statement<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a
     let start = ctx.offset(input);
     let (input, name) = identifier(input)?;
     let (input, _) = ws1(input)?;
-    let (input, args) = separated_list0(ws1, |i| simple_expression(i, ctx))(input)?;
+
+    let (input, args, structured) = match command_arguments(&name, input, ctx) {
+        Some((input, structured)) => {
+            let args = structured
+                .values()
+                .into_iter()
+                .filter_map(|value| match value {
+                    CommandValue::Interpolated(expression) => Some(expression.clone()),
+                    _ => None,
+                })
+                .collect();
+            (input, args, Some(structured))
+        }
+        None => {
+            let (input, args) = separated_list0(ws1, |i| simple_expression(i, ctx))(input)?;
+            (input, args, None)
+        }
+    };
 
     let end = ctx.offset(input);
     Ok((
@@ -1131,11 +1240,98 @@ fn command_statement<'a>(input: &'a str, ctx: &ParseContext<'a>) -> IResult<&'a
         Statement::Command {
             name,
             args,
+            structured,
             span: ctx.make_span(start, end),
         },
     ))
 }
 
+/// Parse the words after the commands that don't take expressions
+fn command_arguments<'a>(
+    name: &str,
+    input: &'a str,
+    ctx: &ParseContext<'a>,
+) -> Option<(&'a str, CommandArgs)> {
+    let structured = match name {
+        "equip" | "equipL" | "equipR" => {
+            let (input, values) = command_values(input, ctx, None);
+            let criteria = values.into_iter().map(item_criterion).collect();
+            (input, CommandArgs::Equip(criteria))
+        }
+        "loadout" => {
+            let (input, values) = command_values(input, ctx, None);
+            (input, CommandArgs::Loadout(values))
+        }
+        "brew" => {
+            let (input, values) = command_values(input, ctx, Some('+'));
+            (input, CommandArgs::Brew(values))
+        }
+        "activate" => {
+            let (input, values) = command_values(input, ctx, None);
+            (input, CommandArgs::Activate(values))
+        }
+        _ => return None,
+    };
+    Some(structured)
+}
+
+/// Words up to the end of the line or a comment, split at whitespace and
+/// `separator`; `@expr@` interpolations are kept whole
+fn command_values<'a>(
+    mut input: &'a str,
+    ctx: &ParseContext<'a>,
+    separator: Option<char>,
+) -> (&'a str, Vec<CommandValue>) {
+    let is_gap = |c: char| c == ' ' || c == '\t' || Some(c) == separator;
+    let mut values = Vec::new();
+
+    loop {
+        let rest = input.trim_start_matches(is_gap);
+        if rest.is_empty() || rest.starts_with(['\n', '\r']) || rest.starts_with("//") {
+            return (rest, values);
+        }
+        if let Ok((next, expression)) = interpolated_expression(rest, ctx) {
+            values.push(CommandValue::Interpolated(expression));
+            input = next;
+            continue;
+        }
+
+        let length = rest
+            .find(|c: char| c.is_whitespace() || Some(c) == separator)
+            .unwrap_or(rest.len());
+        let (word, next) = rest.split_at(length);
+        let span = ctx.make_span(ctx.offset(rest), ctx.offset(next));
+        let number = word
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| word.parse().ok())
+            .flatten();
+        values.push(match number {
+            Some(number) => CommandValue::Number(number, span),
+            None => CommandValue::Word(word.to_string(), span),
+        });
+        input = next;
+    }
+}
+
+/// `*7` and `+5` set the star and enchantment level of an equipped item
+fn item_criterion(value: CommandValue) -> ItemCriterion {
+    if let CommandValue::Word(word, span) = &value {
+        let level = |prefix: char| {
+            word.strip_prefix(prefix)
+                .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|digits| digits.parse().ok())
+        };
+        if let Some(stars) = level('*') {
+            return ItemCriterion::Stars(stars, *span);
+        }
+        if let Some(enchant) = level('+') {
+            return ItemCriterion::Enchant(enchant, *span);
+        }
+    }
+    ItemCriterion::Value(value)
+}
+
 /// Parse a single line statement (without indentation handling)
 fn single_statement<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'a str, Statement> {
     preceded(
@@ -1147,7 +1343,7 @@ fn single_statement<'a>(input: &'a str, ctx: &'a ParseContext<'a>) -> IResult<&'
             |i| output_statement(i, ctx),
             map(line_ending, |_| Statement::Empty),
             // Try var assignment first (var x = value)
-            |i| var_assignment(i, ctx),
+            |i| var_declaration(i, ctx),
             // Try regular assignment
             |i| assignment_statement(i, ctx),
             |i| command_statement(i, ctx),
@@ -1166,6 +1362,16 @@ fn indented_block<'a>(
     let mut remaining = input;
     let mut base_indent: Option<usize> = None;
 
+    // A comment on the header line, as in `func F()//note`, belongs to the block
+    if !ctx.source[..ctx.offset(input)].ends_with(['\n', '\r']) {
+        let (after_spaces, _) = ws0(input)?;
+        if let Ok((next, header_comment)) = comment(after_spaces, ctx) {
+            statements.push(header_comment);
+            let (next, _) = opt(line_ending)(next)?;
+            remaining = next;
+        }
+    }
+
     loop {
         // Skip empty lines (including lines with only whitespace)
         loop {
@@ -1314,6 +1520,14 @@ fn condition_statement<'a>(
     let (input, else_ifs) = many0(|i| {
         let start = ctx.offset(i);
         let (i, _) = opt(line_ending)(i)?;
+        // As with the else below, a `:?` indented less belongs to an outer condition
+        let indent = i.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        if indent < condition_indent {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            )));
+        }
         let (i, _) = ws0(i)?;
         let (i, _) = tag(":?")(i)?;
         let (i, _) = ws0(i)?;
@@ -1545,6 +1759,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_backslash_module_paths() {
+        let input = "import UI\\BossHealthBar\nvar map = new Games\\StoneBoxman\\StoneBoxmanCore\n";
+        let program = parse(input).expect("Failed to parse backslash paths");
+        match &program.statements[0] {
+            Statement::Import { path, .. } => assert_eq!(path, "UI\\BossHealthBar"),
+            other => panic!("Expected import, got {:?}", other),
+        }
+        match &program.statements[1] {
+            Statement::VarDeclaration {
+                initializer: Some(Expression::New { path, .. }),
+                ..
+            } => assert_eq!(path, "Games\\StoneBoxman\\StoneBoxmanCore"),
+            other => panic!("Expected new expression, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_line_continuation() {
         let input = "?loc = icy_ridge\n^|loc = cross_bridge\n  x = 1\n";
@@ -1583,13 +1814,11 @@ mod tests {
         // First, print ALL top-level statements to understand structure
         for (idx, stmt) in ast.statements.iter().enumerate() {
             match stmt {
-                Statement::Assignment { target, span, .. } => {
-                    if let Expression::Identifier(var_name, _) = target {
-                        println!(
-                            "[{}] Top-level assignment: {} at span {:?}",
-                            idx, var_name, span
-                        );
-                    }
+                Statement::VarDeclaration { name, span, .. } => {
+                    println!(
+                        "[{}] Top-level declaration: {} at span {:?}",
+                        idx, name, span
+                    );
                 }
                 Statement::FunctionDefinition { name, span, .. } => {
                     println!("[{}] Function: {} at span {:?}", idx, name, span);
@@ -1617,19 +1846,14 @@ mod tests {
 
                     for (idx, func_stmt) in body.iter().enumerate() {
                         match func_stmt {
-                            Statement::Assignment { target, span, .. } => {
-                                if let Expression::Identifier(var_name, _) = target {
-                                    println!(
-                                        "  [{}] Assignment to {} at span {:?}",
-                                        idx, var_name, span
-                                    );
-                                    if var_name == "LoadAllLayers" {
-                                        load_all_in_function = true;
-                                        println!("    !!! LoadAllLayers INCORRECTLY found in function body !!!");
-                                    }
-                                    if var_name == "SLButton" {
-                                        slbutton_in_function = true;
-                                    }
+                            Statement::VarDeclaration { name, span, .. } => {
+                                println!("  [{}] Declaration of {} at span {:?}", idx, name, span);
+                                if name == "LoadAllLayers" {
+                                    load_all_in_function = true;
+                                    println!("    !!! LoadAllLayers INCORRECTLY found in function body !!!");
+                                }
+                                if name == "SLButton" {
+                                    slbutton_in_function = true;
                                 }
                             }
                             _ => {
@@ -1681,12 +1905,10 @@ var AfterLogo = "test"
         // Find AfterLogo assignment
         let mut after_logo_span = None;
         for stmt in &ast.statements {
-            if let Statement::Assignment { target, span, .. } = stmt {
-                if let Expression::Identifier(name, _) = target {
-                    if name == "AfterLogo" {
-                        after_logo_span = Some(*span);
-                        break;
-                    }
+            if let Statement::VarDeclaration { name, span, .. } = stmt {
+                if name == "AfterLogo" {
+                    after_logo_span = Some(*span);
+                    break;
                 }
             }
         }
@@ -1870,15 +2092,15 @@ var AfterLogo = "test"
         let ast = result.unwrap();
         assert_eq!(ast.statements.len(), 2, "Should have 2 statements");
 
-        // First should be assignment with function call
+        // First should be a declaration with function call
         match &ast.statements[0] {
-            Statement::Assignment { value, .. } => match value {
-                Expression::FunctionCall { args, .. } => {
+            Statement::VarDeclaration { initializer, .. } => match initializer {
+                Some(Expression::FunctionCall { args, .. }) => {
                     assert_eq!(args.len(), 0, "Should have 0 arguments");
                 }
                 _ => panic!("Expected FunctionCall expression"),
             },
-            _ => panic!("Expected Assignment statement"),
+            _ => panic!("Expected VarDeclaration statement"),
         }
     }
 
@@ -1987,12 +2209,7 @@ var LoadAllLayers = ui.AddButton()
 
                 // Verify SLButton is in function body
                 let slbutton_in_body = body.iter().any(|stmt| {
-                    if let Statement::Assignment { target, .. } = stmt {
-                        if let Expression::Identifier(name, _) = target {
-                            return name == "SLButton";
-                        }
-                    }
-                    false
+                    matches!(stmt, Statement::VarDeclaration { name, .. } if name == "SLButton")
                 });
                 assert!(slbutton_in_body, "SLButton should be in function body");
             }
@@ -2001,17 +2218,13 @@ var LoadAllLayers = ui.AddButton()
 
         // Check LoadAllLayers is top-level
         match &ast.statements[1] {
-            Statement::Assignment { target, .. } => {
-                if let Expression::Identifier(name, _) = target {
-                    assert_eq!(
-                        name, "LoadAllLayers",
-                        "Second statement should be LoadAllLayers"
-                    );
-                } else {
-                    panic!("Expected identifier in assignment");
-                }
+            Statement::VarDeclaration { name, .. } => {
+                assert_eq!(
+                    name, "LoadAllLayers",
+                    "Second statement should be LoadAllLayers"
+                );
             }
-            _ => panic!("Expected Assignment for LoadAllLayers"),
+            _ => panic!("Expected VarDeclaration for LoadAllLayers"),
         }
     }
 
@@ -2068,4 +2281,57 @@ var outside = 3
             _ => panic!("Expected FunctionDefinition"),
         }
     }
+
+    #[test]
+    fn test_comment_after_block_header() {
+        let source = "func Roll(bins)//0 to bins - 1\n    return 1\nvar after = 2\n";
+        let ast = parse(source).expect("Parse failed");
+        assert_eq!(ast.statements.len(), 2, "Should have function + var");
+        match &ast.statements[0] {
+            Statement::FunctionDefinition { body, .. } => {
+                assert!(matches!(body[0], Statement::Comment(..)));
+                assert!(matches!(body[1], Statement::Return { .. }));
+            }
+            _ => panic!("Expected FunctionDefinition"),
+        }
+    }
+
+    #[test]
+    fn test_else_if_belongs_to_its_indentation() {
+        let source = "?a\n  ?b\n    >b\n  :?c\n    >c\n:?d\n  >d\n";
+        let ast = parse(source).expect("Parse failed");
+        match &ast.statements[0] {
+            Statement::Condition {
+                then_block,
+                else_ifs,
+                ..
+            } => {
+                assert_eq!(else_ifs.len(), 1, "`:?d` should follow `?a`");
+                match &then_block[0] {
+                    Statement::Condition { else_ifs, .. } => assert_eq!(else_ifs.len(), 1),
+                    _ => panic!("Expected nested Condition"),
+                }
+            }
+            _ => panic!("Expected Condition"),
+        }
+    }
+
+    #[test]
+    fn test_identifier_starting_with_boolean() {
+        let ast = parse("?trueAnswer > falsey\n  >x\n").expect("Parse failed");
+        match &ast.statements[0] {
+            Statement::Condition { condition, .. } => match condition {
+                Expression::BinaryOp { left, right, .. } => {
+                    assert!(
+                        matches!(&**left, Expression::Identifier(name, _) if name == "trueAnswer")
+                    );
+                    assert!(
+                        matches!(&**right, Expression::Identifier(name, _) if name == "falsey")
+                    );
+                }
+                _ => panic!("Expected BinaryOp"),
+            },
+            _ => panic!("Expected Condition"),
+        }
+    }
 }
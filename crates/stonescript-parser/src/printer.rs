@@ -0,0 +1,591 @@
+//! Turning a syntax tree back into StoneScript source
+//!
+//! Blocks are indented by a fixed step, operators spaced and parentheses
+//! written only where the tree would otherwise read differently, so that
+//! parsing the printed text gives back the same tree, spans aside. Lines
+//! longer than the line width are split onto `^` continuation lines at the
+//! spaces after commas and operators.
+//!
+//! Strings spanning lines print as `ascii` blocks, colors as `#name` and
+//! other strings quoted. Fullwidth brackets and quotes print as their ASCII
+//! forms, which the parser reads the same.
+
+use crate::ast::*;
+use std::fmt;
+
+/// Binding strength of a prefix `!` or `-`
+const PREFIX: u8 = 6;
+/// Binding strength of a postfix `++` or `--`
+const POSTFIX: u8 = 7;
+/// Binding strength of literals, names, calls, property and index access
+const PRIMARY: u8 = 8;
+
+/// Prints programs, statements and expressions as source text
+#[derive(Debug, Clone)]
+pub struct Printer {
+    indent: usize,
+    line_width: Option<usize>,
+}
+
+impl Printer {
+    /// Two spaces a level, continuing lines longer than 100 characters
+    pub fn new() -> Self {
+        Self {
+            indent: 2,
+            line_width: Some(100),
+        }
+    }
+
+    /// Spaces to indent each level of nesting by
+    pub fn set_indent(&mut self, indent: usize) {
+        self.indent = indent.max(1);
+    }
+
+    /// Characters a line may take before it is continued on a `^` line, or
+    /// `None` to never split lines
+    pub fn set_line_width(&mut self, line_width: Option<usize>) {
+        self.line_width = line_width;
+    }
+
+    pub fn print(&self, program: &Program) -> String {
+        let mut out = String::new();
+        self.block(&program.statements, 0, &mut out);
+        out
+    }
+
+    pub fn print_statement(&self, statement: &Statement) -> String {
+        let mut out = String::new();
+        self.statement(statement, 0, &mut out);
+        out
+    }
+
+    pub fn print_expression(&self, expression: &Expression) -> String {
+        let mut line = Line::default();
+        self.expression(expression, 0, &mut line);
+        line.text
+    }
+
+    fn block(&self, statements: &[Statement], depth: usize, out: &mut String) {
+        let mut after_block = false;
+        for statement in statements {
+            match statement {
+                // At the top level a `//` line after a block is taken as the
+                // trailing comment of the block's last line, which a block
+                // comment is not
+                Statement::Comment(text, _)
+                    if depth == 0 && after_block && !text.contains(['\n', '\r']) =>
+                {
+                    if text.contains("*/") {
+                        out.push_str(&format!("//\n//{}\n", text));
+                    } else {
+                        out.push_str(&format!("/*{}*/\n", text));
+                    }
+                }
+                // A blank line after a block is skipped with it, unless a
+                // comment ends the block first
+                Statement::Empty if depth == 0 && after_block => {
+                    out.push_str("//\n");
+                    self.statement(statement, depth, out);
+                }
+                _ => self.statement(statement, depth, out),
+            }
+            after_block = matches!(
+                statement,
+                Statement::Condition { .. }
+                    | Statement::FunctionDefinition { .. }
+                    | Statement::For { .. }
+                    | Statement::ForIn { .. }
+                    | Statement::While { .. }
+            );
+        }
+    }
+
+    fn statement(&self, statement: &Statement, depth: usize, out: &mut String) {
+        let indent = " ".repeat(depth * self.indent);
+        let mut line = Line::default();
+        match statement {
+            Statement::Condition {
+                condition,
+                then_block,
+                else_ifs,
+                else_block,
+                ..
+            } => {
+                line.push("?");
+                self.expression(condition, 0, &mut line);
+                self.wrap(&indent, line, out);
+                self.block(then_block, depth + 1, out);
+                for else_if in else_ifs {
+                    let mut line = Line::default();
+                    line.push(":?");
+                    self.expression(&else_if.condition, 0, &mut line);
+                    self.wrap(&indent, line, out);
+                    self.block(&else_if.block, depth + 1, out);
+                }
+                if let Some(block) = else_block {
+                    self.wrap(&indent, Line::from(":"), out);
+                    self.block(block, depth + 1, out);
+                }
+                return;
+            }
+            Statement::Command {
+                name,
+                args,
+                structured,
+                ..
+            } => {
+                // The space is what makes a command of a lone word
+                line.push(name);
+                line.push(" ");
+                match structured {
+                    Some(structured) => self.command_args(structured, &mut line),
+                    None => {
+                        for (index, arg) in args.iter().enumerate() {
+                            if index > 0 {
+                                line.push(" ");
+                            }
+                            // `func F()` would define a function
+                            let strength = if name == "func" { u8::MAX } else { PREFIX };
+                            self.expression(arg, strength, &mut line);
+                        }
+                    }
+                }
+            }
+            Statement::VarDeclaration {
+                name, initializer, ..
+            } => {
+                line.push("var ");
+                line.push(name);
+                if let Some(value) = initializer {
+                    line.push(" = ");
+                    self.expression(value, 0, &mut line);
+                }
+            }
+            Statement::Assignment {
+                target, op, value, ..
+            } => {
+                self.expression(target, PRIMARY, &mut line);
+                line.push(&format!(" {} ", op));
+                self.expression(value, 0, &mut line);
+            }
+            Statement::Output {
+                kind,
+                position,
+                color,
+                text,
+                ..
+            } => {
+                // Output text runs to the end of the line, so it is never split
+                out.push_str(&indent);
+                out.push('>');
+                out.push_str(kind.prefix());
+                if let Some((x, y)) = position {
+                    out.push_str(&self.print_expression(x));
+                    out.push(',');
+                    out.push_str(&self.print_expression(y));
+                    out.push(',');
+                }
+                if let Some(color) = color {
+                    match color {
+                        Expression::String(color, _) if is_color(color) => out.push_str(color),
+                        _ => out.push_str(&format!("@{}@", self.print_expression(color))),
+                    }
+                    out.push(',');
+                }
+                match text {
+                    Expression::String(text, _) => out.push_str(&ascii(text)),
+                    // Text opening with `@expr@,` would be read as a color, so
+                    // the first `@` is kept from closing the expression there
+                    Expression::Interpolation(parts, _)
+                        if position.is_some() && color.is_none() && opens_like_color(parts) =>
+                    {
+                        if let Some(InterpolationPart::Expression(expression)) = parts.first() {
+                            out.push_str(&format!("@{} @", self.print_expression(expression)));
+                        }
+                        self.interpolation(&parts[1..], out);
+                    }
+                    Expression::Interpolation(parts, _) => self.interpolation(parts, out),
+                    _ => out.push_str(&format!("@{}@", self.print_expression(text))),
+                }
+                out.push('\n');
+                return;
+            }
+            Statement::ExpressionStatement { expression, .. } => {
+                // `a = b` on its own would assign
+                self.expression(expression, PREFIX, &mut line);
+            }
+            Statement::FunctionDefinition {
+                name, params, body, ..
+            } => {
+                line.push("func ");
+                line.push(name);
+                line.push("(");
+                for (index, param) in params.iter().enumerate() {
+                    if index > 0 {
+                        line.comma();
+                    }
+                    line.push(param);
+                }
+                line.push(")");
+                self.wrap(&indent, line, out);
+                self.block(body, depth + 1, out);
+                return;
+            }
+            Statement::Return { value, .. } => {
+                line.push("return");
+                if let Some(value) = value {
+                    line.push(" ");
+                    self.expression(value, 0, &mut line);
+                }
+            }
+            Statement::For {
+                variable,
+                range,
+                body,
+                ..
+            } => {
+                line.push(&format!("for {} = ", variable));
+                self.expression(&range.0, 0, &mut line);
+                line.push("..");
+                self.expression(&range.1, 0, &mut line);
+                self.wrap(&indent, line, out);
+                self.block(body, depth + 1, out);
+                return;
+            }
+            Statement::ForIn {
+                variable,
+                collection,
+                body,
+                ..
+            } => {
+                line.push(&format!("for {} : ", variable));
+                self.expression(collection, 0, &mut line);
+                self.wrap(&indent, line, out);
+                self.block(body, depth + 1, out);
+                return;
+            }
+            // StoneScript has no loop of this kind to print it as
+            Statement::While {
+                condition, body, ..
+            } => {
+                line.push("while ");
+                self.expression(condition, 0, &mut line);
+                self.wrap(&indent, line, out);
+                self.block(body, depth + 1, out);
+                return;
+            }
+            Statement::Import { path, .. } => {
+                line.push("import ");
+                line.push(path);
+            }
+            Statement::Comment(text, _) => {
+                // A `^` line after a `//` comment starts a new line, so
+                // comments are never split
+                out.push_str(&indent);
+                if text.contains(['\n', '\r']) {
+                    out.push_str(&format!("/*{}*/\n", text));
+                } else {
+                    out.push_str(&format!("//{}\n", text));
+                }
+                return;
+            }
+            // A blank line is skipped, while one holding only spaces is read
+            // as an empty statement. The blank line after it keeps a comment
+            // on the next line from being taken as the statement's own.
+            Statement::Empty => {
+                out.push_str(&indent);
+                out.push_str(" \n\n");
+                return;
+            }
+        }
+        self.wrap(&indent, line, out);
+    }
+
+    fn command_args(&self, args: &CommandArgs, line: &mut Line) {
+        let (values, separator) = match args {
+            CommandArgs::Equip(criteria) => {
+                let words: Vec<String> = criteria
+                    .iter()
+                    .map(|criterion| match criterion {
+                        ItemCriterion::Stars(stars, _) => format!("*{}", stars),
+                        ItemCriterion::Enchant(enchant, _) => format!("+{}", enchant),
+                        ItemCriterion::Value(value) => self.command_value(value),
+                    })
+                    .collect();
+                line.push(&words.join(" "));
+                return;
+            }
+            CommandArgs::Loadout(values) | CommandArgs::Activate(values) => (values, " "),
+            CommandArgs::Brew(values) => (values, "+"),
+        };
+        let words: Vec<String> = values
+            .iter()
+            .map(|value| self.command_value(value))
+            .collect();
+        line.push(&words.join(separator));
+    }
+
+    fn command_value(&self, value: &CommandValue) -> String {
+        match value {
+            CommandValue::Number(number, _) => number.to_string(),
+            CommandValue::Word(word, _) => word.clone(),
+            CommandValue::Interpolated(expression) => {
+                format!("@{}@", self.print_expression(expression))
+            }
+        }
+    }
+
+    fn interpolation(&self, parts: &[InterpolationPart], out: &mut String) {
+        for part in parts {
+            match part {
+                InterpolationPart::Text(text, _) => out.push_str(text),
+                InterpolationPart::Expression(expression) => {
+                    out.push_str(&format!("@{}@", self.print_expression(expression)))
+                }
+            }
+        }
+    }
+
+    /// Print `expression`, in parentheses when it binds looser than
+    /// `strength`
+    fn expression(&self, expression: &Expression, strength: u8, line: &mut Line) {
+        if binding(expression) < strength {
+            line.push("(");
+            self.expression(expression, 0, line);
+            line.push(")");
+            return;
+        }
+        match expression {
+            Expression::Integer(value, _) => line.push(&value.to_string()),
+            Expression::Float(value, _) => {
+                // `1.0` has to keep its point to stay a float
+                let text = value.to_string();
+                line.push(&text);
+                if !text.contains('.') {
+                    line.push(".0");
+                }
+            }
+            Expression::Boolean(value, _) => line.push(&value.to_string()),
+            Expression::String(text, _) => {
+                if text.contains('\n') && !text.contains("asciiend") {
+                    line.push(&ascii(text));
+                } else if is_color(text) {
+                    line.push(text);
+                } else {
+                    line.push(&quote(text));
+                }
+            }
+            Expression::Identifier(name, _) => line.push(name),
+            Expression::Property {
+                object, property, ..
+            } => {
+                self.expression(object, PRIMARY, line);
+                line.push(".");
+                line.push(property);
+            }
+            Expression::FunctionCall { function, args, .. } => {
+                self.expression(function, PRIMARY, line);
+                line.push("(");
+                self.list(args, line);
+                line.push(")");
+            }
+            Expression::BinaryOp {
+                left, op, right, ..
+            } => {
+                // Operators of equal strength group to the left
+                let strength = op.precedence();
+                self.expression(left, strength, line);
+                line.push(&format!(" {}", op));
+                line.space();
+                self.expression(right, strength + 1, line);
+            }
+            Expression::UnaryOp { op, operand, .. } => match op {
+                UnaryOperator::Not | UnaryOperator::Negate => {
+                    line.push(&op.to_string());
+                    self.expression(operand, PREFIX, line);
+                }
+                UnaryOperator::Increment | UnaryOperator::Decrement => {
+                    self.expression(operand, PRIMARY, line);
+                    line.push(&op.to_string());
+                }
+            },
+            Expression::Interpolation(parts, _) => {
+                let mut text = String::new();
+                self.interpolation(parts, &mut text);
+                line.push(&text);
+            }
+            Expression::New { path, .. } => {
+                line.push("new ");
+                line.push(path);
+            }
+            Expression::Array { elements, .. } => {
+                line.push("[");
+                self.list(elements, line);
+                line.push("]");
+            }
+            Expression::IndexAccess { object, index, .. } => {
+                self.expression(object, PRIMARY, line);
+                line.push("[");
+                self.expression(index, 0, line);
+                line.push("]");
+            }
+        }
+    }
+
+    fn list(&self, expressions: &[Expression], line: &mut Line) {
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                line.comma();
+            }
+            self.expression(expression, 0, line);
+        }
+    }
+
+    /// Write `line` at `indent`, split onto `^` lines where it is too long
+    fn wrap(&self, indent: &str, line: Line, out: &mut String) {
+        let mut start = 0;
+        let mut prefix = indent.to_string();
+        // Lines running through an `ascii` block keep their breaks
+        if let Some(width) = self.line_width.filter(|_| !line.text.contains('\n')) {
+            let continued = format!("{}{}^", indent, " ".repeat(self.indent));
+            let columns = |text: &str| text.chars().count();
+            while columns(&prefix) + columns(&line.text[start..]) > width {
+                let mut breaks = line.breaks.iter().copied().filter(|&at| at > start);
+                let first = breaks.clone().next();
+                let Some(at) = breaks
+                    .rfind(|&at| columns(&prefix) + columns(&line.text[start..at]) <= width)
+                    .or(first)
+                else {
+                    break;
+                };
+                out.push_str(&prefix);
+                out.push_str(&line.text[start..at]);
+                out.push('\n');
+                // The space split at is left out, as joining the lines puts
+                // nothing between them
+                start = at + 1;
+                prefix = continued.clone();
+            }
+        }
+        out.push_str(&prefix);
+        out.push_str(&line.text[start..]);
+        out.push('\n');
+    }
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A statement's text, with the spaces it may be split at
+#[derive(Default)]
+struct Line {
+    text: String,
+    breaks: Vec<usize>,
+}
+
+impl Line {
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// A space the parser skips, which a `^` line can stand in for
+    fn space(&mut self) {
+        self.breaks.push(self.text.len());
+        self.text.push(' ');
+    }
+
+    fn comma(&mut self) {
+        self.text.push(',');
+        self.space();
+    }
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            breaks: Vec::new(),
+        }
+    }
+}
+
+/// How tightly `expression` holds together, against the strength an
+/// operand needs to go without parentheses
+fn binding(expression: &Expression) -> u8 {
+    match expression {
+        Expression::BinaryOp { op, .. } => op.precedence(),
+        Expression::UnaryOp {
+            op: UnaryOperator::Not | UnaryOperator::Negate,
+            ..
+        } => PREFIX,
+        Expression::UnaryOp { .. } => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+/// Whether output text starts with an `@expr@` and then a comma
+fn opens_like_color(parts: &[InterpolationPart]) -> bool {
+    matches!(
+        parts,
+        [InterpolationPart::Expression(_), InterpolationPart::Text(text, _), ..]
+            if text.trim_start_matches([' ', '\t']).starts_with(',')
+    )
+}
+
+fn ascii(text: &str) -> String {
+    // The parser skips one line break after `ascii`
+    format!("ascii\n{}asciiend", text)
+}
+
+/// Whether `text` reads back whole as a color literal: `#` and then six or
+/// three hex digits or a name
+fn is_color(text: &str) -> bool {
+    let Some(name) = text.strip_prefix('#') else {
+        return false;
+    };
+    let hex = name.chars().take_while(char::is_ascii_hexdigit).count();
+    let length = if hex >= 6 {
+        6
+    } else if hex >= 3 {
+        3
+    } else {
+        name.chars().take_while(char::is_ascii_alphabetic).count()
+    };
+    length > 0 && length == name.len()
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Printer::new().print(self))
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Printer::new().print_statement(self))
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Printer::new().print_expression(self))
+    }
+}
@@ -0,0 +1,102 @@
+use stonescript_parser::ast::{CommandArgs, CommandValue, Expression, ItemCriterion, Statement};
+use stonescript_parser::parse_source;
+
+fn command(source: &str) -> (Vec<Expression>, CommandArgs) {
+    let program = parse_source(source).expect("Failed to parse");
+    assert_eq!(program.statements.len(), 1, "{:?}", program.statements);
+    match &program.statements[0] {
+        Statement::Command {
+            args,
+            structured: Some(structured),
+            ..
+        } => (args.clone(), structured.clone()),
+        other => panic!("Expected structured command, got {:?}", other),
+    }
+}
+
+/// Render the arguments compactly, ignoring spans
+fn words(structured: &CommandArgs) -> Vec<String> {
+    let value = |value: &CommandValue| match value {
+        CommandValue::Number(number, _) => format!("#{}", number),
+        CommandValue::Word(word, _) => word.clone(),
+        CommandValue::Interpolated(_) => "@".to_string(),
+    };
+    match structured {
+        CommandArgs::Equip(criteria) => criteria
+            .iter()
+            .map(|criterion| match criterion {
+                ItemCriterion::Stars(stars, _) => format!("stars {}", stars),
+                ItemCriterion::Enchant(enchant, _) => format!("enchant {}", enchant),
+                ItemCriterion::Value(v) => value(v),
+            })
+            .collect(),
+        CommandArgs::Loadout(values)
+        | CommandArgs::Brew(values)
+        | CommandArgs::Activate(values) => values.iter().map(value).collect(),
+    }
+}
+
+#[test]
+fn test_equip_criteria() {
+    let (args, structured) = command("equip vigor crossbow *8 +5\n");
+    assert!(args.is_empty());
+    assert!(matches!(structured, CommandArgs::Equip(_)));
+    assert_eq!(
+        words(&structured),
+        vec!["vigor", "crossbow", "stars 8", "enchant 5"]
+    );
+
+    // Malformed levels stay words
+    let (_, structured) = command("equipR shield *x +\n");
+    assert_eq!(words(&structured), vec!["shield", "*x", "+"]);
+}
+
+#[test]
+fn test_interpolated_arguments() {
+    let (args, structured) = command("equipL @ToolList[i][0]@ *@stars@\n");
+    assert_eq!(words(&structured), vec!["@", "*@stars@"]);
+    assert_eq!(args.len(), 1);
+    assert!(matches!(args[0], Expression::IndexAccess { .. }));
+}
+
+#[test]
+fn test_other_commands() {
+    let (_, structured) = command("loadout 2\n");
+    assert!(matches!(structured, CommandArgs::Loadout(_)));
+    assert_eq!(words(&structured), vec!["#2"]);
+
+    let (_, structured) = command("brew tar+bronze\n");
+    assert!(matches!(structured, CommandArgs::Brew(_)));
+    assert_eq!(words(&structured), vec!["tar", "bronze"]);
+    let (_, structured) = command("brew stone + wood\n");
+    assert_eq!(words(&structured), vec!["stone", "wood"]);
+
+    let (_, structured) = command("activate R\n");
+    assert!(matches!(structured, CommandArgs::Activate(_)));
+    assert_eq!(words(&structured), vec!["R"]);
+}
+
+#[test]
+fn test_argument_spans() {
+    let (_, structured) = command("brew tar+bronze\n");
+    let CommandArgs::Brew(values) = structured else {
+        unreachable!()
+    };
+    let columns: Vec<_> = values
+        .iter()
+        .map(|value| (value.span().start.column, value.span().end.column))
+        .collect();
+    assert_eq!(columns, vec![(5, 8), (9, 15)]);
+}
+
+#[test]
+fn test_trailing_comment() {
+    let program = parse_source("equip sword // main weapon\n").expect("Failed to parse");
+    match &program.statements[0] {
+        Statement::Command {
+            structured: Some(structured),
+            ..
+        } => assert_eq!(words(structured), vec!["sword"]),
+        other => panic!("Expected structured command, got {:?}", other),
+    }
+}
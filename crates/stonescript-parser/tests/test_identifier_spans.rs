@@ -0,0 +1,66 @@
+//! Test spans of names inside definitions, loops and module paths
+
+use stonescript_parser::{parse_source, Expression, Position, Span, Statement};
+
+fn span(line: usize, start: usize, end: usize) -> Span {
+    Span::new(Position::new(line, start), Position::new(line, end))
+}
+
+#[test]
+fn test_function_name_and_parameter_spans() {
+    let program = parse_source("func DrawBox(x, y , w)\n  return x\n").unwrap();
+    match &program.statements[0] {
+        Statement::FunctionDefinition {
+            name_span,
+            params,
+            param_spans,
+            ..
+        } => {
+            assert_eq!(*name_span, span(0, 5, 12));
+            assert_eq!(params.len(), param_spans.len());
+            assert_eq!(
+                param_spans,
+                &vec![span(0, 13, 14), span(0, 16, 17), span(0, 20, 21)]
+            );
+        }
+        other => panic!("Expected function, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_loop_variable_spans() {
+    let program = parse_source("for i = 0..9\n  >@i@\nfor  item : list\n  >@item@\n").unwrap();
+    match &program.statements[0] {
+        Statement::For { variable_span, .. } => assert_eq!(*variable_span, span(0, 4, 5)),
+        other => panic!("Expected for loop, got {:?}", other),
+    }
+    match &program.statements[1] {
+        Statement::ForIn { variable_span, .. } => assert_eq!(*variable_span, span(2, 5, 9)),
+        other => panic!("Expected for-in loop, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_module_path_segment_spans() {
+    let program =
+        parse_source("import UI/BossHealthBar\nvar game = new Games\\Fishing\\FishingGame\n")
+            .unwrap();
+    match &program.statements[0] {
+        Statement::Import { segment_spans, .. } => {
+            assert_eq!(segment_spans, &vec![span(0, 7, 9), span(0, 10, 23)]);
+        }
+        other => panic!("Expected import, got {:?}", other),
+    }
+    match &program.statements[1] {
+        Statement::VarDeclaration {
+            initializer: Some(Expression::New { segment_spans, .. }),
+            ..
+        } => {
+            assert_eq!(
+                segment_spans,
+                &vec![span(1, 15, 20), span(1, 21, 28), span(1, 29, 40)]
+            );
+        }
+        other => panic!("Expected new expression, got {:?}", other),
+    }
+}
@@ -32,12 +32,8 @@ fn test_mech_file_parse() {
 
             // Check for specific variables that use array of ASCII blocks
             let has_mech_wlk = program.statements.iter().any(|stmt| {
-                if let stonescript_parser::Statement::Assignment { target, .. } = stmt {
-                    if let stonescript_parser::Expression::Identifier(name, _) = target {
-                        name == "MechWlkR"
-                    } else {
-                        false
-                    }
+                if let stonescript_parser::Statement::VarDeclaration { name, .. } = stmt {
+                    name == "MechWlkR"
                 } else {
                     false
                 }
@@ -0,0 +1,66 @@
+use stonescript_parser::ast::{Expression, Statement};
+use stonescript_parser::parse_source;
+
+fn condition(source: &str) -> Expression {
+    let program = parse_source(source).expect("Failed to parse");
+    match &program.statements[0] {
+        Statement::Condition { condition, .. } => condition.clone(),
+        other => panic!("Expected condition, got {:?}", other),
+    }
+}
+
+/// Render with explicit parentheses to show the grouping
+fn grouped(expression: &Expression) -> String {
+    match expression {
+        Expression::BinaryOp {
+            left, op, right, ..
+        } => format!("({} {} {})", grouped(left), op, grouped(right)),
+        Expression::Identifier(name, _) => name.clone(),
+        Expression::Integer(value, _) => value.to_string(),
+        Expression::Property {
+            object, property, ..
+        } => format!("{}.{}", grouped(object), property),
+        other => panic!("Unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_comparison_binds_tighter_than_logic() {
+    assert_eq!(
+        grouped(&condition("?hp < 10 & foe.hp > 5\n  >a\n")),
+        "((hp < 10) & (foe.hp > 5))"
+    );
+    assert_eq!(
+        grouped(&condition("?a = 1 | b = 2 & c = 3\n  >a\n")),
+        "((a = 1) | ((b = 2) & (c = 3)))"
+    );
+}
+
+#[test]
+fn test_arithmetic_precedence() {
+    assert_eq!(
+        grouped(&condition("?a + b * c - d < 4\n  >a\n")),
+        "(((a + (b * c)) - d) < 4)"
+    );
+    assert_eq!(
+        grouped(&condition("?(a + b) * c > 1\n  >a\n")),
+        "(((a + b) * c) > 1)"
+    );
+}
+
+#[test]
+fn test_binary_spans_cover_operands() {
+    let expression = condition("?hp < 10 & (foe.hp > 5)\n  >a\n");
+    let Expression::BinaryOp {
+        left, right, span, ..
+    } = &expression
+    else {
+        panic!("Expected binary operation");
+    };
+    assert_eq!((span.start.column, span.end.column), (1, 23));
+    assert_eq!((left.span().start.column, left.span().end.column), (1, 8));
+    assert_eq!(
+        (right.span().start.column, right.span().end.column),
+        (12, 22)
+    );
+}
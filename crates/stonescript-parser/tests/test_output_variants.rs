@@ -0,0 +1,93 @@
+//! Test print statement variants, colors and interpolation
+
+use stonescript_parser::{parse_source, Expression, OutputKind, Statement};
+
+fn output(source: &str) -> Statement {
+    let program = parse_source(source).expect("Failed to parse");
+    program.statements.into_iter().next().unwrap()
+}
+
+#[test]
+fn test_output_kinds() {
+    let cases = [
+        (">Hello", OutputKind::Plain),
+        (">o-6,3,Hi", OutputKind::Overlay),
+        (">h-4,-3,Hi", OutputKind::Hud),
+        (">c0,0,Hi", OutputKind::Center),
+        (">f0,-2,Hi", OutputKind::Foe),
+        (">`0,1,Hi", OutputKind::Screen),
+    ];
+
+    for (source, expected) in cases {
+        match output(source) {
+            Statement::Output { kind, position, .. } => {
+                assert_eq!(kind, expected, "{}", source);
+                assert_eq!(position.is_some(), expected != OutputKind::Plain);
+            }
+            other => panic!("Expected output for {}, got {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_variant_character_needs_a_position() {
+    // Plain text that happens to start with a variant character
+    for source in [">hello", ">effects", ">ok then", ">craft, then go"] {
+        match output(source) {
+            Statement::Output {
+                kind: OutputKind::Plain,
+                position: None,
+                text: Expression::Interpolation(..),
+                ..
+            } => {}
+            other => panic!("Expected plain output for {}, got {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_output_color() {
+    match output(">o0,0,#red,Danger") {
+        Statement::Output { color, .. } => {
+            assert!(matches!(color, Some(Expression::String(c, _)) if c == "#red"));
+        }
+        other => panic!("Expected output, got {:?}", other),
+    }
+
+    match output(">h0,0,@c@,ascii\n##\nasciiend") {
+        Statement::Output {
+            color,
+            interpolated,
+            ..
+        } => {
+            assert!(matches!(color, Some(Expression::Identifier(c, _)) if c == "c"));
+            assert!(!interpolated);
+        }
+        other => panic!("Expected output, got {:?}", other),
+    }
+
+    // Without a following comma, `@var@` is the start of the text
+    match output(">o0,0,@name@ says hi") {
+        Statement::Output {
+            color,
+            interpolated,
+            ..
+        } => {
+            assert_eq!(color, None);
+            assert!(interpolated);
+        }
+        other => panic!("Expected output, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_output_interpolation_flag() {
+    match output(">Gold: @loc.gp@") {
+        Statement::Output { interpolated, .. } => assert!(interpolated),
+        other => panic!("Expected output, got {:?}", other),
+    }
+    match output(">Gold") {
+        Statement::Output { interpolated, .. } => assert!(!interpolated),
+        other => panic!("Expected output, got {:?}", other),
+    }
+}
@@ -1,6 +1,6 @@
 //! Test cases for parsing issues found in test_scripts
 
-use stonescript_parser::parse_source;
+use stonescript_parser::{parse_source, Expression, Statement};
 
 #[test]
 fn test_fullwidth_quotes() {
@@ -190,10 +190,17 @@ fn test_output_with_interpolation() {
 
 #[test]
 fn test_empty_array() {
-    // Issue: Empty array assignment
-    let input = r#"var arr = []"#;
-    let result = parse_source(input);
-    assert!(result.is_ok(), "Should parse empty array: {:?}", result);
+    // Issue: Empty array assignment, which was read as the bare string "[]"
+    for input in ["var arr = []", "var arr = [ ]", "var arr = ［］"] {
+        let program = parse_source(input).unwrap();
+        match &program.statements[0] {
+            Statement::VarDeclaration {
+                initializer: Some(Expression::Array { elements, .. }),
+                ..
+            } => assert!(elements.is_empty(), "{}", input),
+            other => panic!("{}: expected an empty array, got {:?}", input, other),
+        }
+    }
 }
 
 #[test]
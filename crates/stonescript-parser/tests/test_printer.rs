@@ -0,0 +1,221 @@
+//! Printing a syntax tree gives source that parses back to the same tree
+
+use std::fs;
+use std::path::PathBuf;
+use stonescript_parser::{parse_source, Printer};
+use walkdir::WalkDir;
+
+/// Parse `source`, print it and check the printed text parses to the same
+/// tree; returns the printed text
+fn round_trip(source: &str) -> String {
+    let program = parse_source(source).unwrap();
+    let printed = program.to_string();
+    let reparsed = parse_source(&printed)
+        .unwrap_or_else(|e| panic!("printed source does not parse: {}\n{}", e, printed));
+    assert!(
+        reparsed.eq_ignoring_spans(&program),
+        "printed source parses differently:\n{}",
+        printed
+    );
+    printed
+}
+
+#[test]
+fn test_print_statements() {
+    let printed = round_trip(
+        "\
+import Games/Fishing
+var  count=0
+func Add( a ,b )
+    return a+b
+?loc.begin|loc.loop
+    count+=Add(1 , 2)
+    for i=0 .. 3
+        >o-2,i,#red,@i@ fish
+:?foe.count > 0
+    activate R
+    equip vigor crossbow *8 +5
+    brew tar + bronze
+:
+    >`0,0,@count@
+    i++
+",
+    );
+    assert_eq!(
+        printed,
+        "\
+import Games/Fishing
+var count = 0
+func Add(a, b)
+  return a + b
+?loc.begin | loc.loop
+  count += Add(1, 2)
+  for i = 0..3
+    >o-2,i,#red,@i@ fish
+:?foe.count > 0
+  activate R
+  equip vigor crossbow *8 +5
+  brew tar+bronze
+:
+  >`0,0,@count@
+  i++
+"
+    );
+}
+
+#[test]
+fn test_minimal_parentheses() {
+    let printed = round_trip(
+        "\
+var a = ((1 + 2) * 3) - (4 - 5)
+var b = (1 * 2) + (3 / (4 % 5))
+var c = !(x & y) | -(z)
+var d = (n++).ToString()
+var e = [1, (2 + 3)][0]
+(x = 1)
+",
+    );
+    assert_eq!(
+        printed,
+        "\
+var a = (1 + 2) * 3 - (4 - 5)
+var b = 1 * 2 + 3 / (4 % 5)
+var c = !(x & y) | -z
+var d = (n++).ToString()
+var e = [1, 2 + 3][0]
+(x = 1)
+"
+    );
+}
+
+#[test]
+fn test_print_strings() {
+    let printed = round_trip(
+        "\
+var a = \"say \\\"hi\\\"\"
+var b = ＂wide＂
+var c = ［#fff, #red］
+var d = ██
+var art = ascii
+ /\\_/\\
+( o.o )
+asciiend
+>`0,0,ascii
+#
+asciiend
+",
+    );
+    assert_eq!(
+        printed,
+        "\
+var a = \"say \\\"hi\\\"\"
+var b = \"wide\"
+var c = [#fff, #red]
+var d = \"██\"
+var art = ascii
+ /\\_/\\
+( o.o )
+asciiend
+>`0,0,ascii
+#
+asciiend
+"
+    );
+}
+
+#[test]
+fn test_comments_and_empty_lines() {
+    round_trip("\n// note\n/* block\n   comment */\nvar a = 1\n  \n?a // why\n  a = 2\n");
+}
+
+#[test]
+fn test_long_lines_continue() {
+    let source = "\
+var names = [\"first name\", \"second name\", \"third name\", \"fourth name\"]
+?foe.count > 0 & foe.hp <= foe.maxhp * 0.25 & foe.hp != 0 & loc.stars > 5
+  >too long to split, it is printed as it is written here
+";
+    let mut printer = Printer::new();
+    printer.set_line_width(Some(40));
+    let printed = printer.print(&parse_source(source).unwrap());
+    assert_eq!(
+        printed,
+        "\
+var names = [\"first name\",
+  ^\"second name\", \"third name\",
+  ^\"fourth name\"]
+?foe.count > 0 & foe.hp <= foe.maxhp *
+  ^0.25 & foe.hp != 0 & loc.stars > 5
+  >too long to split, it is printed as it is written here
+"
+    );
+    assert!(parse_source(&printed)
+        .unwrap()
+        .eq_ignoring_spans(&parse_source(source).unwrap()));
+}
+
+/// Scripts with `ascii` art, `^` continuations, fullwidth brackets and
+/// interpolation, round tripped on every run
+const SAMPLE: &[&str] = &[
+    "Cosmetics/ConfettiHead.txt",
+    "Fishing.txt",
+    "Games/SwordGame.txt",
+    "Hats/ReindeerHead.txt",
+    "Pets/SnowBunny.txt",
+    "UI/InfoFloat.txt",
+    "Weapons/ArmorGenerator.txt",
+];
+
+fn scripts_dir() -> PathBuf {
+    let mut test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_dir.pop();
+    test_dir.pop();
+    test_dir.push("test_scripts");
+    test_dir
+}
+
+/// Round trip each script, returning those that do not parse and those
+/// printed differently
+fn round_trip_scripts(paths: impl IntoIterator<Item = PathBuf>) -> (Vec<String>, Vec<String>) {
+    let test_dir = scripts_dir();
+    let mut unparsed = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+        let name = path.strip_prefix(&test_dir).unwrap().display().to_string();
+        let content = fs::read_to_string(&path).unwrap();
+        let Ok(program) = parse_source(&content) else {
+            unparsed.push(name);
+            continue;
+        };
+        let same = parse_source(&program.to_string())
+            .is_ok_and(|reparsed| reparsed.eq_ignoring_spans(&program));
+        if !same {
+            failed.push(name);
+        }
+    }
+    (unparsed, failed)
+}
+
+#[test]
+fn test_sample_scripts_round_trip() {
+    let (unparsed, failed) =
+        round_trip_scripts(SAMPLE.iter().map(|script| scripts_dir().join(script)));
+    assert!(unparsed.is_empty(), "do not parse: {:?}", unparsed);
+    assert!(failed.is_empty(), "printed differently: {:?}", failed);
+}
+
+/// Parses every script, which takes minutes in a debug build; run it with
+/// `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn test_all_scripts_round_trip() {
+    let scripts = WalkDir::new(scripts_dir())
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("txt"))
+        .map(|e| e.into_path());
+    let (unparsed, failed) = round_trip_scripts(scripts);
+    // A script that does not parse would otherwise go unchecked
+    assert!(unparsed.is_empty(), "do not parse: {:?}", unparsed);
+    assert!(failed.is_empty(), "printed differently: {:?}", failed);
+}